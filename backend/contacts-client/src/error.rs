@@ -0,0 +1,64 @@
+// backend/contacts-client/src/error.rs
+// This file defines the errors of the client: failed calls and error answers from the API.
+// It exists so callers can match on the status of an error instead of parsing messages.
+// RELEVANT FILES: backend/contacts-client/src/lib.rs, backend/src/error.rs
+
+use contacts_types::Problem;
+use reqwest::StatusCode;
+use serde_json::Value;
+use thiserror::Error;
+
+/// An error of a call to the contacts API.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The call did not get an answer, or the answer could not be read.
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The API answered with an error status.
+    #[error("The API answered {status}: {message}")]
+    Api {
+        /// The HTTP status of the answer.
+        status: StatusCode,
+        /// The message of the API, from a plain JSON string or the `detail` of a problem body.
+        message: String,
+    },
+}
+
+impl Error {
+    /// Reads an error answer of the API.
+    ///
+    /// The API sends its messages as a JSON string, or as a problem body (RFC 7807)
+    /// for unknown routes, wrong methods and timeouts.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The status of the answer.
+    /// * `body` - The body of the answer.
+    ///
+    /// # Returns
+    ///
+    /// * An `Error::Api`.
+    pub(crate) fn from_answer(status: StatusCode, body: &str) -> Self {
+        let message = if let Ok(problem) = serde_json::from_str::<Problem>(body) {
+            problem.detail
+        } else if let Ok(Value::String(message)) = serde_json::from_str::<Value>(body) {
+            message
+        } else {
+            body.to_string()
+        };
+        Error::Api { status, message }
+    }
+
+    /// Returns the HTTP status if the API answered with an error.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Http(e) => e.status(),
+            Error::Api { status, .. } => Some(*status),
+        }
+    }
+
+    /// Tells whether the error is a `404 Not Found`.
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(StatusCode::NOT_FOUND)
+    }
+}
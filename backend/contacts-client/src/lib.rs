@@ -0,0 +1,287 @@
+// backend/contacts-client/src/lib.rs
+// This file is an async client for the contacts API, with a typed method per endpoint.
+// It exists so other Rust services call the API with checked payloads instead of hand-written requests.
+// RELEVANT FILES: backend/contacts-types/src/lib.rs, backend/contacts-client/src/error.rs, backend/src/lib.rs
+
+mod error;
+
+pub use contacts_types::{BatchGet, Company, Contact, ContactFilter, NewCompany, NewContact};
+pub use error::Error;
+
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+/// The version of the API the client speaks.
+const API_PREFIX: &str = "/api/v1";
+
+/// The answer of a count.
+#[derive(Deserialize)]
+struct Count {
+    count: i64,
+}
+
+/// A client for the contacts API.
+///
+/// It is cheap to clone, and clones share their connections.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), contacts_client::Error> {
+/// use contacts_client::{Client, ContactFilter};
+///
+/// let client = Client::new("http://127.0.0.1:8081").with_token("eyJ...");
+/// let leads = client
+///     .list_contacts(&ContactFilter { status: Some("lead".into()), ..Default::default() })
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl Client {
+    /// Creates a client without a token, e.g. for the public endpoints.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_url` - The base URL of the API, e.g. `http://127.0.0.1:8081`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+        }
+    }
+
+    /// Sends the access token of the identity provider with every call.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The JWT, without the `Bearer ` prefix.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Uses an HTTP client of the caller, e.g. one with a proxy or timeouts.
+    ///
+    /// # Arguments
+    ///
+    /// * `http` - The `reqwest` client.
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Starts a request to a path of the API, with the token if there is one.
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{}{}", self.base_url, API_PREFIX, path))
+            // Lists are decoded as arrays, even on a server that wraps them by default
+            .query(&[("envelope", "false")]);
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Sends a request and turns an error status into an `Error::Api`.
+    async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(Error::from_answer(status, &body))
+    }
+
+    /// Sends a request and reads the JSON body of the answer.
+    async fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, Error> {
+        Ok(self.send(request).await?.json().await?)
+    }
+
+    /// Lists the contacts that match a filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The filters to apply. `ContactFilter::default()` lists every contact.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(contacts)` with the contacts the user may see.
+    pub async fn list_contacts(&self, filter: &ContactFilter) -> Result<Vec<Contact>, Error> {
+        self.json(self.request(Method::GET, "/contacts").query(filter))
+            .await
+    }
+
+    /// Counts the contacts that match a filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The filters to apply.
+    pub async fn count_contacts(&self, filter: &ContactFilter) -> Result<i64, Error> {
+        let count: Count = self
+            .json(self.request(Method::GET, "/contacts/count").query(filter))
+            .await?;
+        Ok(count.count)
+    }
+
+    /// Reads a contact.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the contact.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(contact)` if it exists. A missing contact gives an error for which `is_not_found` is true.
+    pub async fn get_contact(&self, id: i32) -> Result<Contact, Error> {
+        self.json(self.request(Method::GET, &format!("/contacts/{}", id)))
+            .await
+    }
+
+    /// Checks whether a contact exists, without reading it.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the contact.
+    pub async fn contact_exists(&self, id: i32) -> Result<bool, Error> {
+        match self
+            .send(self.request(Method::HEAD, &format!("/contacts/{}", id)))
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if e.is_not_found() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads several contacts in one call.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - The IDs of the contacts. The API reads at most 100 at once.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(batch)` with the contacts that were found and the IDs that were not.
+    pub async fn batch_get_contacts(&self, ids: &[i32]) -> Result<BatchGet, Error> {
+        self.json(
+            self.request(Method::POST, "/contacts/batch-get")
+                .json(&serde_json::json!({ "ids": ids })),
+        )
+        .await
+    }
+
+    /// Creates a contact, or updates the one with the same `uuid`.
+    ///
+    /// # Arguments
+    ///
+    /// * `contact` - The new contact.
+    pub async fn create_contact(&self, contact: &NewContact) -> Result<(), Error> {
+        self.send(self.request(Method::POST, "/contacts").json(contact))
+            .await?;
+        Ok(())
+    }
+
+    /// Replaces the values of a contact.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the contact.
+    /// * `contact` - The new values.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(true)` if the change was saved, `Ok(false)` if it waits for approval (`202 Accepted`).
+    pub async fn update_contact(&self, id: i32, contact: &NewContact) -> Result<bool, Error> {
+        let response = self
+            .send(
+                self.request(Method::PUT, &format!("/contacts/{}", id))
+                    .json(contact),
+            )
+            .await?;
+        Ok(response.status() != StatusCode::ACCEPTED)
+    }
+
+    /// Deletes a contact.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the contact.
+    pub async fn delete_contact(&self, id: i32) -> Result<(), Error> {
+        self.send(self.request(Method::DELETE, &format!("/contacts/{}", id)))
+            .await?;
+        Ok(())
+    }
+
+    /// Lists every company.
+    pub async fn list_companies(&self) -> Result<Vec<Company>, Error> {
+        self.json(self.request(Method::GET, "/companies")).await
+    }
+
+    /// Reads a company.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the company.
+    pub async fn get_company(&self, id: i32) -> Result<Company, Error> {
+        self.json(self.request(Method::GET, &format!("/companies/{}", id)))
+            .await
+    }
+
+    /// Lists the contacts of a company.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the company.
+    pub async fn company_contacts(&self, id: i32) -> Result<Vec<Contact>, Error> {
+        self.json(self.request(Method::GET, &format!("/companies/{}/contacts", id)))
+            .await
+    }
+
+    /// Creates a company.
+    ///
+    /// # Arguments
+    ///
+    /// * `company` - The new company.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(company)` with the created company and its ID.
+    pub async fn create_company(&self, company: &NewCompany) -> Result<Company, Error> {
+        self.json(self.request(Method::POST, "/companies").json(company))
+            .await
+    }
+
+    /// Replaces the values of a company.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the company.
+    /// * `company` - The new values.
+    pub async fn update_company(&self, id: i32, company: &NewCompany) -> Result<Company, Error> {
+        self.json(
+            self.request(Method::PUT, &format!("/companies/{}", id))
+                .json(company),
+        )
+        .await
+    }
+
+    /// Deletes a company.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the company.
+    pub async fn delete_company(&self, id: i32) -> Result<(), Error> {
+        self.send(self.request(Method::DELETE, &format!("/companies/{}", id)))
+            .await?;
+        Ok(())
+    }
+}
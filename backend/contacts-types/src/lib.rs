@@ -0,0 +1,247 @@
+// backend/contacts-types/src/lib.rs
+// This file defines the payloads of the contacts API: contacts, companies, filters and error bodies.
+// It exists so the server, the Rust client and the frontend (through wasm32) use one definition and stop drifting.
+// RELEVANT FILES: backend/src/models.rs, backend/contacts-types/src/schema.rs, backend/contacts-client/src/lib.rs, backend/src/error.rs
+
+use serde::{Deserialize, Serialize};
+
+/// The tables of the server's database, as the Diesel CLI prints them.
+#[cfg(feature = "diesel")]
+pub mod schema;
+
+/// The lifecycle statuses a contact can have, in pipeline order.
+pub const CONTACT_STATUSES: [&str; 4] = ["lead", "prospect", "customer", "inactive"];
+
+/// A contact as the API returns it.
+///
+/// With the `diesel` feature, the server reads it straight from the `contacts` table,
+/// so the fields must stay in the order of the columns.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "diesel", derive(diesel::Queryable))]
+pub struct Contact {
+    /// The unique identifier for the contact.
+    pub id: i32,
+    /// The first name of the contact.
+    pub first_name: String,
+    /// The last name of the contact.
+    pub last_name: String,
+    /// The email address of the contact.
+    pub email: String,
+    /// The phone number of the contact.
+    pub phone_number: String,
+    /// The ID of the company the contact works for, if any.
+    pub company_id: Option<i32>,
+    /// The lifecycle status: lead, prospect, customer or inactive.
+    pub status: String,
+    /// Where the contact came from (e.g. "website" or "referral").
+    pub source: Option<String>,
+    /// The person responsible for the contact.
+    pub owner: Option<String>,
+    /// Whether the contact agreed to receive email.
+    pub email_opt_in: bool,
+    /// Whether the contact agreed to receive text messages.
+    pub sms_opt_in: bool,
+    /// Where the last consent change came from (e.g. "signup form").
+    pub consent_source: Option<String>,
+    /// When consent last changed, as a Unix timestamp in seconds.
+    pub consent_timestamp: Option<i64>,
+    /// The result of the last email check: unverified, valid, invalid or unknown.
+    pub email_status: String,
+    /// When the email was last found valid, as a Unix timestamp in seconds.
+    pub email_verified_at: Option<i64>,
+    /// The globally unique ID of the contact, which clients may choose themselves.
+    pub uuid: Option<String>,
+    /// The ID of the contact in the system named by `source`, for contacts synced from elsewhere.
+    pub external_id: Option<String>,
+    /// When the contact was archived, as a Unix timestamp in seconds. Archived contacts are left out of lists.
+    #[serde(default)]
+    pub archived_at: Option<i64>,
+    /// How complete the contact is, from 0 to 100. The server computes it from the other fields.
+    #[serde(default)]
+    pub quality_score: i32,
+}
+
+/// A new contact, or the new values of an existing one.
+///
+/// With the `diesel` feature, the server inserts it and updates contacts with it.
+/// Fields that are `None` are left as they are on updates.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::Insertable, diesel::AsChangeset),
+    diesel(table_name = crate::schema::contacts)
+)]
+pub struct NewContact {
+    /// The first name of the contact.
+    pub first_name: String,
+    /// The last name of the contact.
+    pub last_name: String,
+    /// The email address of the contact.
+    pub email: String,
+    /// The phone number of the contact.
+    pub phone_number: String,
+    /// The ID of the company the contact works for, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company_id: Option<i32>,
+    /// The lifecycle status. New contacts default to "lead".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Where the contact came from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// The person responsible for the contact.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// The globally unique ID of the contact. The server creates one when it is not sent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// The ID of the contact in the system named by `source`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+}
+
+/// Represents the optional filters for listing contacts.
+///
+/// Every filter that is set must match. Archived contacts only match with `include_archived`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ContactFilter {
+    /// Only return contacts with this status.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Only return contacts from this source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Only return contacts with this owner.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Only return contacts whose first name starts with this, ignoring case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_name_prefix: Option<String>,
+    /// Only return contacts whose last name starts with this, ignoring case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_name_prefix: Option<String>,
+    /// Only return contacts whose email address starts with this, ignoring case, e.g. the start of the local part.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_prefix: Option<String>,
+    /// Also return archived contacts, which are left out otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_archived: Option<bool>,
+    /// Only return contacts with at least this quality score.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_quality: Option<i32>,
+}
+
+impl ContactFilter {
+    /// Checks if a contact matches the filter.
+    ///
+    /// This is used for data that does not come from the database, like demo contacts.
+    ///
+    /// # Arguments
+    ///
+    /// * `contact` - The contact to check.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if every filter that is set matches the contact.
+    pub fn matches(&self, contact: &Contact) -> bool {
+        self.status.as_ref().is_none_or(|s| *s == contact.status)
+            && self
+                .source
+                .as_ref()
+                .is_none_or(|s| Some(s) == contact.source.as_ref())
+            && self
+                .owner
+                .as_ref()
+                .is_none_or(|o| Some(o) == contact.owner.as_ref())
+            && starts_with(&self.first_name_prefix, &contact.first_name)
+            && starts_with(&self.last_name_prefix, &contact.last_name)
+            && starts_with(&self.email_prefix, &contact.email)
+            && (self.include_archived == Some(true) || contact.archived_at.is_none())
+            && self
+                .min_quality
+                .is_none_or(|min| contact.quality_score >= min)
+    }
+}
+
+/// Checks that a value starts with an optional prefix, ignoring case. No prefix matches everything.
+fn starts_with(prefix: &Option<String>, value: &str) -> bool {
+    prefix
+        .as_ref()
+        .is_none_or(|p| value.to_lowercase().starts_with(&p.trim().to_lowercase()))
+}
+
+/// The answer of a batch read.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BatchGet {
+    /// The contacts that were found.
+    pub contacts: Vec<Contact>,
+    /// The IDs that were not found.
+    pub missing: Vec<i32>,
+}
+
+/// A company as the API returns it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "diesel", derive(diesel::Queryable))]
+pub struct Company {
+    /// The unique identifier for the company.
+    pub id: i32,
+    /// The name of the company.
+    pub name: String,
+    /// The postal address of the company.
+    pub address: Option<String>,
+    /// The email domain of the company (e.g. "example.com").
+    pub domain: Option<String>,
+    /// The URL of the company logo.
+    pub logo_url: Option<String>,
+    /// Free-form notes about the company.
+    pub notes: Option<String>,
+    /// Whether the address can be delivered to: deliverable, undeliverable or unknown.
+    pub address_status: Option<String>,
+}
+
+/// A new company, or the new values of an existing one.
+///
+/// With the `diesel` feature, the server inserts it and updates companies with it.
+/// Fields that are `None` are cleared on updates.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::Insertable, diesel::AsChangeset),
+    diesel(table_name = crate::schema::companies, treat_none_as_null = true)
+)]
+pub struct NewCompany {
+    /// The name of the company.
+    pub name: String,
+    /// The postal address of the company.
+    pub address: Option<String>,
+    /// The email domain of the company (e.g. "example.com").
+    pub domain: Option<String>,
+    /// The URL of the company logo.
+    pub logo_url: Option<String>,
+    /// Free-form notes about the company.
+    pub notes: Option<String>,
+    /// Whether the address can be delivered to. The server sets it when it checks the address.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub address_status: Option<String>,
+}
+
+/// An error body in the problem details format of RFC 7807.
+///
+/// The API sends it for unknown routes, wrong methods and timeouts; other errors are a JSON string.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Problem {
+    /// A URI that identifies the kind of problem. `about:blank` means the status says it all.
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    /// The reason phrase of the status, e.g. `Not Found`.
+    pub title: String,
+    /// The HTTP status code.
+    pub status: u16,
+    /// An explanation for the user.
+    pub detail: String,
+    /// The path of the request the problem is about.
+    pub instance: String,
+    /// The methods the path does accept, when the method was wrong.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_methods: Option<Vec<String>>,
+}
@@ -0,0 +1,5 @@
+CREATE TABLE share_links (
+    id INTEGER PRIMARY KEY NOT NULL,
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    expires_at BIGINT NOT NULL
+);
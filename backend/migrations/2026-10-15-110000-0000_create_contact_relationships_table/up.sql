@@ -0,0 +1,10 @@
+CREATE TABLE contact_relationships (
+    id INTEGER PRIMARY KEY NOT NULL,
+    contact_a INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    contact_b INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('spouse', 'colleague', 'manager', 'assistant')),
+    CHECK (contact_a <> contact_b)
+);
+
+CREATE INDEX contact_relationships_contact_a ON contact_relationships (contact_a);
+CREATE INDEX contact_relationships_contact_b ON contact_relationships (contact_b);
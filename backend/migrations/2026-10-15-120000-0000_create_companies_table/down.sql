@@ -0,0 +1,3 @@
+DROP INDEX contacts_company_id;
+ALTER TABLE contacts DROP COLUMN company_id;
+DROP TABLE companies;
@@ -0,0 +1,14 @@
+CREATE TABLE companies (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    address TEXT,
+    domain TEXT,
+    logo_url TEXT,
+    notes TEXT
+);
+
+CREATE INDEX companies_domain ON companies (domain);
+
+ALTER TABLE contacts ADD COLUMN company_id INTEGER REFERENCES companies(id) ON DELETE SET NULL;
+
+CREATE INDEX contacts_company_id ON contacts (company_id);
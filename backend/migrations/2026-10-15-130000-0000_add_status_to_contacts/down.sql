@@ -0,0 +1,4 @@
+DROP INDEX contacts_status;
+ALTER TABLE contacts DROP COLUMN owner;
+ALTER TABLE contacts DROP COLUMN source;
+ALTER TABLE contacts DROP COLUMN status;
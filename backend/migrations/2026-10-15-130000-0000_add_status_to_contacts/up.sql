@@ -0,0 +1,6 @@
+ALTER TABLE contacts ADD COLUMN status TEXT NOT NULL DEFAULT 'lead'
+    CHECK (status IN ('lead', 'prospect', 'customer', 'inactive'));
+ALTER TABLE contacts ADD COLUMN source TEXT;
+ALTER TABLE contacts ADD COLUMN owner TEXT;
+
+CREATE INDEX contacts_status ON contacts (status);
@@ -0,0 +1,4 @@
+ALTER TABLE contacts DROP COLUMN consent_timestamp;
+ALTER TABLE contacts DROP COLUMN consent_source;
+ALTER TABLE contacts DROP COLUMN sms_opt_in;
+ALTER TABLE contacts DROP COLUMN email_opt_in;
@@ -0,0 +1,4 @@
+ALTER TABLE contacts ADD COLUMN email_opt_in BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE contacts ADD COLUMN sms_opt_in BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE contacts ADD COLUMN consent_source TEXT;
+ALTER TABLE contacts ADD COLUMN consent_timestamp BIGINT;
@@ -0,0 +1,2 @@
+ALTER TABLE contacts DROP COLUMN email_verified_at;
+ALTER TABLE contacts DROP COLUMN email_status;
@@ -0,0 +1,3 @@
+ALTER TABLE contacts ADD COLUMN email_status TEXT NOT NULL DEFAULT 'unverified'
+    CHECK (email_status IN ('unverified', 'valid', 'invalid', 'unknown'));
+ALTER TABLE contacts ADD COLUMN email_verified_at BIGINT;
@@ -0,0 +1,11 @@
+CREATE TABLE enrichments (
+    id INTEGER PRIMARY KEY NOT NULL,
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    provider TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    company_name TEXT,
+    logo_url TEXT,
+    fetched_at BIGINT NOT NULL
+);
+
+CREATE INDEX enrichments_contact_id ON enrichments (contact_id);
@@ -0,0 +1 @@
+DROP INDEX contacts_email_lower;
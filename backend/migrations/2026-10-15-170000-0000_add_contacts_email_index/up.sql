@@ -0,0 +1,2 @@
+-- Case-insensitive email lookups use lower(email), so the index is on the same expression
+CREATE INDEX contacts_email_lower ON contacts (lower(email));
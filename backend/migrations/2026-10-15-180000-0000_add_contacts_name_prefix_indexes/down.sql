@@ -0,0 +1,2 @@
+DROP INDEX contacts_last_name_lower;
+DROP INDEX contacts_first_name_lower;
@@ -0,0 +1,3 @@
+-- Autocomplete searches prefixes of lower(first_name) and lower(last_name) with range scans on these indexes
+CREATE INDEX contacts_first_name_lower ON contacts (lower(first_name));
+CREATE INDEX contacts_last_name_lower ON contacts (lower(last_name));
@@ -0,0 +1,2 @@
+DROP INDEX contacts_uuid;
+ALTER TABLE contacts DROP COLUMN uuid;
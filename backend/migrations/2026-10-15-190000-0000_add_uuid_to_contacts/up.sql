@@ -0,0 +1,12 @@
+ALTER TABLE contacts ADD COLUMN uuid TEXT;
+
+-- Give existing contacts a random version 4 UUID
+UPDATE contacts SET uuid = lower(
+    hex(randomblob(4)) || '-' ||
+    hex(randomblob(2)) || '-' ||
+    '4' || substr(hex(randomblob(2)), 2) || '-' ||
+    substr('89ab', 1 + abs(random()) % 4, 1) || substr(hex(randomblob(2)), 2) || '-' ||
+    hex(randomblob(6))
+);
+
+CREATE UNIQUE INDEX contacts_uuid ON contacts (uuid);
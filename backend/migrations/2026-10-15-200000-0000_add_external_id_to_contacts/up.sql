@@ -0,0 +1,4 @@
+ALTER TABLE contacts ADD COLUMN external_id TEXT;
+
+-- An external ID is only unique within the system it comes from
+CREATE UNIQUE INDEX contacts_source_external_id ON contacts (source, external_id);
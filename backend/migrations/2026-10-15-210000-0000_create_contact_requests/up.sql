@@ -0,0 +1,17 @@
+-- Details that people submit about themselves through the public form, waiting for an admin to decide
+CREATE TABLE contact_requests (
+    id INTEGER PRIMARY KEY NOT NULL,
+    first_name TEXT NOT NULL,
+    last_name TEXT NOT NULL,
+    email TEXT NOT NULL,
+    phone_number TEXT NOT NULL DEFAULT '',
+    message TEXT,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'rejected')),
+    created_at BIGINT NOT NULL,
+    decided_at BIGINT,
+    decided_by TEXT,
+    -- The contact created on approval
+    contact_id INTEGER REFERENCES contacts(id) ON DELETE SET NULL
+);
+
+CREATE INDEX contact_requests_status ON contact_requests (status);
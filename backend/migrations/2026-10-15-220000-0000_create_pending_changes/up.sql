@@ -0,0 +1,14 @@
+-- Contact edits that wait for a reviewer when approval is required
+CREATE TABLE pending_changes (
+    id INTEGER PRIMARY KEY NOT NULL,
+    contact_id INTEGER NOT NULL REFERENCES contacts(id) ON DELETE CASCADE,
+    -- The proposed contact data, as the JSON body of the edit
+    changes TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'rejected')),
+    requested_by TEXT NOT NULL,
+    requested_at BIGINT NOT NULL,
+    decided_by TEXT,
+    decided_at BIGINT
+);
+
+CREATE INDEX pending_changes_status ON pending_changes (status);
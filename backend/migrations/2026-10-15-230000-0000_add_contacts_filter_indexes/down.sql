@@ -0,0 +1,2 @@
+DROP INDEX contacts_email;
+DROP INDEX contacts_owner_name;
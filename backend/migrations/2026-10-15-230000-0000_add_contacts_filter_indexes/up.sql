@@ -0,0 +1,4 @@
+-- Listing by owner is sorted by name, so one index serves both the filter and the order
+CREATE INDEX contacts_owner_name ON contacts (owner, last_name, first_name);
+-- SCIM finds users by their exact email, which the index on lower(email) does not serve
+CREATE INDEX contacts_email ON contacts (email);
@@ -0,0 +1,9 @@
+-- Capabilities an admin has switched on or off for one workspace
+CREATE TABLE feature_flags (
+    workspace TEXT NOT NULL,
+    flag TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    updated_by TEXT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    PRIMARY KEY (workspace, flag)
+);
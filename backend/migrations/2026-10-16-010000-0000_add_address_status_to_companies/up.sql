@@ -0,0 +1,2 @@
+-- Whether the standardized address can be delivered to: deliverable, undeliverable or unknown
+ALTER TABLE companies ADD COLUMN address_status TEXT;
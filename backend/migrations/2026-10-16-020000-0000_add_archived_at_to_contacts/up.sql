@@ -0,0 +1,2 @@
+-- When the contact was archived. Archived contacts are kept, but left out of lists and autocomplete
+ALTER TABLE contacts ADD COLUMN archived_at BIGINT;
@@ -0,0 +1,9 @@
+-- Report definitions users saved, to run again on demand
+CREATE TABLE saved_reports (
+    id INTEGER PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    -- The definition as JSON, checked against the allowed fields when it was saved
+    definition TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
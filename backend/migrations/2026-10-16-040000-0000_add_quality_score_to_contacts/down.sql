@@ -0,0 +1,2 @@
+DROP INDEX contacts_quality_score;
+ALTER TABLE contacts DROP COLUMN quality_score;
@@ -0,0 +1,17 @@
+-- How complete a contact is, from 0 to 100. SQLite computes it from the other columns,
+-- so every write keeps it current. Keep the weights in step with src/quality.rs.
+ALTER TABLE contacts ADD COLUMN quality_score INTEGER NOT NULL GENERATED ALWAYS AS (
+    (CASE WHEN trim(first_name) <> '' AND trim(last_name) <> '' THEN 10 ELSE 0 END)
+    + (CASE
+        WHEN trim(email) = '' THEN 0
+        WHEN email_status = 'valid' THEN 30
+        WHEN email_status = 'invalid' THEN 0
+        ELSE 15
+    END)
+    + (CASE WHEN trim(phone_number) <> '' THEN 25 ELSE 0 END)
+    + (CASE WHEN company_id IS NOT NULL THEN 20 ELSE 0 END)
+    + (CASE WHEN consent_timestamp IS NOT NULL THEN 15 ELSE 0 END)
+) VIRTUAL;
+
+-- Lists filter on ?min_quality=
+CREATE INDEX contacts_quality_score ON contacts (quality_score);
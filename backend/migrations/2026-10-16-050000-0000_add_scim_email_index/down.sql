@@ -0,0 +1 @@
+DROP INDEX contacts_scim_email;
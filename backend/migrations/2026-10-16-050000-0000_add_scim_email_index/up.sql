@@ -0,0 +1,2 @@
+-- userName is unique in SCIM, and it is the email. Contacts entered by hand may still share an address
+CREATE UNIQUE INDEX contacts_scim_email ON contacts (email) WHERE source = 'scim';
@@ -0,0 +1,2 @@
+-- The workspace the link was made in. A link only works while share links are on there
+ALTER TABLE share_links ADD COLUMN workspace TEXT NOT NULL DEFAULT 'default';
@@ -0,0 +1,317 @@
+// backend/src/addresses.rs
+// This file standardizes postal addresses and flags the ones that cannot be delivered to, with a provider we can swap out.
+// It exists so company addresses are stored in one format, and forms can check an address before it is saved.
+// RELEVANT FILES: backend/src/companies.rs, backend/src/enrichment.rs, backend/src/outbound.rs
+
+use crate::auth::Principal;
+use crate::error::ApiError;
+use crate::outbound;
+use actix_web::{web, HttpResponse};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// How long the address provider may take to answer, since the user waits for it.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Words that are written in their short form, as postal services prefer.
+const ABBREVIATIONS: [(&str, &str); 12] = [
+    ("street", "St"),
+    ("avenue", "Ave"),
+    ("road", "Rd"),
+    ("boulevard", "Blvd"),
+    ("drive", "Dr"),
+    ("lane", "Ln"),
+    ("court", "Ct"),
+    ("place", "Pl"),
+    ("square", "Sq"),
+    ("highway", "Hwy"),
+    ("suite", "Ste"),
+    ("apartment", "Apt"),
+];
+
+/// Whether an address can be delivered to.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Deliverability {
+    /// The provider confirmed the address.
+    Deliverable,
+    /// The address is incomplete or the provider does not know it.
+    Undeliverable,
+    /// The provider cannot tell, e.g. the built-in one for a complete address.
+    Unknown,
+}
+
+impl Deliverability {
+    /// Returns the value stored in `companies.address_status`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Deliverability::Deliverable => "deliverable",
+            Deliverability::Undeliverable => "undeliverable",
+            Deliverability::Unknown => "unknown",
+        }
+    }
+}
+
+/// The result of checking an address.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddressCheck {
+    /// The address in its standard form, to store or to show to the user.
+    pub standardized: String,
+    /// Whether the address can be delivered to.
+    pub deliverability: Deliverability,
+    /// What is wrong with the address, for the user.
+    #[serde(default)]
+    pub messages: Vec<String>,
+}
+
+/// The future returned by `AddressProvider::check`.
+pub type CheckFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<AddressCheck, ApiError>> + Send + 'a>>;
+
+/// A service that standardizes and checks addresses.
+///
+/// Implement this trait to plug in another provider.
+pub trait AddressProvider: Send + Sync {
+    /// Returns the name of the provider, for logs.
+    fn name(&self) -> &'static str;
+
+    /// Standardizes and checks an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address, already standardized by `standardize`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AddressCheck)` with the result.
+    /// * `Err(ApiError)` if the provider could not be called.
+    fn check<'a>(&'a self, address: &'a str) -> CheckFuture<'a>;
+}
+
+/// Writes a word with a capital first letter, e.g. `main` as `Main`.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Standardizes one word of an address.
+///
+/// Street types are shortened, words with digits (house numbers and postal codes like `sw1a`)
+/// are written in capitals, and words in all lowercase get a capital first letter.
+fn standardize_word(word: &str) -> String {
+    let bare = word.trim_end_matches('.');
+    if let Some((_, short)) = ABBREVIATIONS
+        .iter()
+        .find(|(long, short)| bare.eq_ignore_ascii_case(long) || bare.eq_ignore_ascii_case(short))
+    {
+        return short.to_string();
+    }
+    if word.chars().any(|c| c.is_ascii_digit()) {
+        return word.to_uppercase();
+    }
+    if word.chars().all(|c| !c.is_uppercase()) {
+        return capitalize(word);
+    }
+    word.to_string()
+}
+
+/// Brings an address into a standard form, without calling a provider.
+///
+/// The lines (separated by commas or line breaks) are trimmed, runs of spaces are collapsed,
+/// and every word is standardized, e.g. `12 main street ,  london  sw1a 1aa` becomes
+/// `12 Main St, London SW1A 1AA`.
+///
+/// # Arguments
+///
+/// * `address` - The address as the user typed it.
+///
+/// # Returns
+///
+/// * The address in its standard form, one line with the parts separated by `, `.
+pub fn standardize(address: &str) -> String {
+    address
+        .split([',', '\n'])
+        .map(|line| {
+            line.split_whitespace()
+                .map(standardize_word)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The built-in provider, which only looks at the form of an address.
+///
+/// An address needs a street with a number and a place, so one without a digit
+/// or with a single line cannot be delivered to. Other addresses are `unknown`.
+pub struct BuiltInProvider;
+
+impl AddressProvider for BuiltInProvider {
+    fn name(&self) -> &'static str {
+        "built-in"
+    }
+
+    fn check<'a>(&'a self, address: &'a str) -> CheckFuture<'a> {
+        Box::pin(async move {
+            let mut messages = Vec::new();
+            if !address.chars().any(|c| c.is_ascii_digit()) {
+                messages.push("The address has no house number or postal code".to_string());
+            }
+            if address.split(", ").count() < 2 {
+                messages.push(
+                    "The address needs a street and a place, separated by a comma".to_string(),
+                );
+            }
+            Ok(AddressCheck {
+                standardized: address.to_string(),
+                deliverability: if messages.is_empty() {
+                    Deliverability::Unknown
+                } else {
+                    Deliverability::Undeliverable
+                },
+                messages,
+            })
+        })
+    }
+}
+
+/// A provider for address validation APIs that take `POST <url>` with `{"address": ...}`
+/// and answer with `standardized`, `deliverability` and optional `messages`.
+pub struct HttpAddressProvider {
+    client: Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+impl AddressProvider for HttpAddressProvider {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn check<'a>(&'a self, address: &'a str) -> CheckFuture<'a> {
+        Box::pin(async move {
+            let mut request = self
+                .client
+                .post(&self.url)
+                .json(&serde_json::json!({ "address": address }));
+            if let Some(api_key) = &self.api_key {
+                request = request.bearer_auth(api_key);
+            }
+            let check = async { request.send().await?.error_for_status()?.json().await }
+                .await
+                .map_err(|e: reqwest::Error| ApiError::Upstream(e.to_string()))?;
+            Ok(check)
+        })
+    }
+}
+
+/// Runs the configured address provider.
+pub struct AddressValidator {
+    provider: Box<dyn AddressProvider>,
+}
+
+impl AddressValidator {
+    /// Creates a validator from the `ADDRESS_VALIDATION_URL` and `ADDRESS_VALIDATION_API_KEY` environment variables.
+    ///
+    /// # Returns
+    ///
+    /// * An `AddressValidator` with the external provider, or the built-in one if `ADDRESS_VALIDATION_URL` is not set.
+    pub fn from_env() -> Self {
+        let provider = match std::env::var("ADDRESS_VALIDATION_URL") {
+            Ok(url) if !url.trim().is_empty() => Box::new(HttpAddressProvider {
+                client: outbound::client_builder()
+                    .timeout(CHECK_TIMEOUT)
+                    .build()
+                    .expect("Failed to build the HTTP client for address validation"),
+                url,
+                api_key: std::env::var("ADDRESS_VALIDATION_API_KEY").ok(),
+            }) as Box<dyn AddressProvider>,
+            _ => Box::new(BuiltInProvider),
+        };
+        Self { provider }
+    }
+
+    /// Standardizes and checks an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address as the user typed it.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AddressCheck)` with the result.
+    /// * `Err(ApiError::Upstream)` if the external provider could not be called.
+    pub async fn check(&self, address: &str) -> Result<AddressCheck, ApiError> {
+        self.provider.check(&standardize(address)).await
+    }
+
+    /// Standardizes and checks an address before it is stored.
+    ///
+    /// A failing provider must not block writes, so the address is then only standardized
+    /// and its deliverability is `unknown`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address as the user typed it.
+    ///
+    /// # Returns
+    ///
+    /// * The result of the check.
+    pub async fn check_for_storage(&self, address: &str) -> AddressCheck {
+        match self.check(address).await {
+            Ok(check) => check,
+            Err(e) => {
+                log::warn!(
+                    "Address check with the {} provider failed: {}",
+                    self.provider.name(),
+                    e
+                );
+                AddressCheck {
+                    standardized: standardize(address),
+                    deliverability: Deliverability::Unknown,
+                    messages: Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+/// The request body for checking an address.
+#[derive(Deserialize)]
+pub struct AddressInput {
+    /// The address as the user typed it.
+    pub address: String,
+}
+
+/// Handles checking an address, e.g. while the user fills in a form.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `input` - The address to check.
+/// * `validator` - The configured address validator.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the standardized address, its deliverability and what is wrong with it.
+/// * `Err(ApiError)` if the address is empty or the external provider could not be called.
+pub async fn validate_address(
+    _principal: Principal,
+    input: web::Json<AddressInput>,
+    validator: web::Data<AddressValidator>,
+) -> Result<HttpResponse, ApiError> {
+    if input.address.trim().is_empty() {
+        return Err(ApiError::BadRequest("The address is empty".to_string()));
+    }
+    Ok(HttpResponse::Ok().json(validator.check(&input.address).await?))
+}
@@ -0,0 +1,105 @@
+// backend/src/archive.rs
+// This file archives and unarchives contacts: archived contacts are kept, but left out of lists and autocomplete.
+// It exists for former clients we must retain but rarely touch, so they stop cluttering the address book without being deleted.
+// RELEVANT FILES: backend/src/handlers.rs, backend/src/lookup.rs, backend/src/consent.rs
+
+use crate::error::ApiError;
+use crate::field_permissions::FieldRules;
+use crate::identifiers::ContactKey;
+use crate::models::Contact;
+use crate::schema::contacts;
+use crate::transaction::Transaction;
+use crate::unix_now;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::Deserialize;
+
+/// The query parameter of lists that do not take the full contact filter, e.g. autocomplete.
+#[derive(Deserialize)]
+pub struct ArchivedQuery {
+    /// Also return archived contacts, which are left out otherwise.
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+/// Archives or unarchives a contact.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection, with the transaction of the request.
+/// * `id` - The ID of the contact.
+/// * `archive` - `true` to archive the contact, `false` to unarchive it.
+///
+/// # Returns
+///
+/// * `Ok(Contact)` with the updated contact.
+/// * `Err(ApiError)` if the contact is not found or there is a database error.
+fn set_archived(conn: &mut SqliteConnection, id: i32, archive: bool) -> Result<Contact, ApiError> {
+    let contact = contacts::table.find(id).first::<Contact>(conn)?;
+    // Archiving twice keeps the first date, so it still says since when the contact is archived
+    if contact.archived_at.is_some() == archive {
+        return Ok(contact);
+    }
+    Ok(diesel::update(contacts::table.find(id))
+        .set(contacts::archived_at.eq(archive.then(unix_now)))
+        .get_result::<Contact>(conn)?)
+}
+
+/// Handles archiving a contact.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// The contact keeps all its data and can still be read by its ID, but lists and autocomplete
+/// leave it out unless they are called with `?include_archived=true`.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `id` - The ID or UUID of the contact, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the archived contact.
+/// * `Err(ApiError::Forbidden)` if the user may not change `archived_at`.
+/// * `Err(ApiError)` if the contact is not found or there is a database error.
+pub async fn archive_contact(
+    field_rules: FieldRules,
+    id: web::Path<ContactKey>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    field_rules.check_writable("archived_at")?;
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+    let id = id.resolve(conn)?;
+    let contact = set_archived(conn, id, true)?;
+    Ok(HttpResponse::Ok().json(field_rules.masked(contact)))
+}
+
+/// Handles unarchiving a contact, which brings it back into lists and autocomplete.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `id` - The ID or UUID of the contact, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the contact.
+/// * `Err(ApiError::Forbidden)` if the user may not change `archived_at`.
+/// * `Err(ApiError)` if the contact is not found or there is a database error.
+pub async fn unarchive_contact(
+    field_rules: FieldRules,
+    id: web::Path<ContactKey>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    field_rules.check_writable("archived_at")?;
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+    let id = id.resolve(conn)?;
+    let contact = set_archived(conn, id, false)?;
+    Ok(HttpResponse::Ok().json(field_rules.masked(contact)))
+}
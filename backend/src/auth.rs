@@ -3,9 +3,13 @@
 // It fetches OIDC configuration and JWKS from an identity provider to validate tokens.
 // RELEVANT FILES: backend/src/main.rs, backend/src/handlers.rs
 
+use crate::circuit_breaker::{backoff, CircuitBreaker};
+use crate::error::ApiError;
+use crate::outbound;
 use actix_web::{dev::Payload, web, Error as ActixWebError, FromRequest, HttpRequest};
 use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::future::Future;
 use std::pin::Pin;
@@ -13,6 +17,9 @@ use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::RwLock;
 
+/// The realm role of users who may review and manage data on behalf of everyone.
+pub const ADMIN_ROLE: &str = "admin";
+
 /// Represents the possible errors that can occur during authentication.
 #[derive(Debug, Error)]
 pub enum AuthError {
@@ -31,6 +38,9 @@ pub enum AuthError {
     /// Error when a valid RSA public key cannot be constructed from JWK components.
     #[error("Could not construct a valid RSA public key from JWK components")]
     KeyConstructionError,
+    /// Error when the identity provider failed too often and is not called for a while.
+    #[error("The identity provider is unavailable; try again later")]
+    IdpUnavailable,
 }
 
 impl actix_web::ResponseError for AuthError {
@@ -39,6 +49,10 @@ impl actix_web::ResponseError for AuthError {
             AuthError::MissingToken | AuthError::InvalidToken(_) | AuthError::KeyNotFound(_) => {
                 actix_web::http::StatusCode::UNAUTHORIZED
             }
+            // Tokens cannot be checked while the identity provider is down, but it may come back
+            AuthError::NetworkError(_) | AuthError::IdpUnavailable => {
+                actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+            }
             _ => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -93,12 +107,160 @@ pub struct Claims {
     pub preferred_username: String,
     /// The email address of the user.
     pub email: Option<String>,
+    /// The first name of the user.
+    pub given_name: Option<String>,
+    /// The last name of the user.
+    pub family_name: Option<String>,
     /// The audience for which the token is intended.
     pub aud: String,
     /// The issuer of the token.
     pub iss: String,
     /// The expiration time of the token (as a Unix timestamp).
     pub exp: usize,
+    /// The realm roles of the user. Tokens without roles get an empty list.
+    #[serde(default)]
+    pub realm_access: RealmAccess,
+    /// The workspace of the user, if the IDP adds a `workspace` claim.
+    #[serde(default)]
+    pub workspace: Option<String>,
+}
+
+/// The realm roles claim that Keycloak adds to access tokens.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RealmAccess {
+    /// The names of the roles, e.g. `admin`.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+impl Claims {
+    /// Returns a stable identifier for the user.
+    ///
+    /// This is the `sub` claim, or the preferred username when `sub` is missing.
+    pub fn user_id(&self) -> &str {
+        self.sub.as_deref().unwrap_or(&self.preferred_username)
+    }
+}
+
+/// An API key that a caller authenticated with.
+#[derive(Debug)]
+pub struct KeyInfo {
+    /// The identifier of the key, never the secret itself.
+    pub id: String,
+    /// The roles granted to the key.
+    pub roles: Vec<String>,
+    /// The workspace the key belongs to, if any.
+    pub workspace: Option<String>,
+}
+
+/// A service that authenticated with a client certificate (mTLS).
+#[derive(Debug)]
+pub struct ServiceIdentity {
+    /// The subject of the certificate, e.g. `CN=billing`.
+    pub subject: String,
+    /// The roles granted to the service.
+    pub roles: Vec<String>,
+}
+
+/// The authenticated caller of a request, whichever way they authenticated.
+///
+/// Handlers take a `Principal` instead of `Claims`, so they work the same for every kind of caller.
+/// Only JWTs are accepted today; API keys and client certificates are plugged in by adding
+/// their check to `Principal::from_request`.
+#[derive(Debug)]
+pub enum Principal {
+    /// A user with a JWT from the identity provider.
+    User(Claims),
+    /// A caller with an API key.
+    #[allow(dead_code)]
+    ApiKey(KeyInfo),
+    /// A service with a client certificate.
+    #[allow(dead_code)]
+    Service(ServiceIdentity),
+}
+
+impl Principal {
+    /// Returns a stable identifier for the caller: the user ID, the key ID or the certificate subject.
+    pub fn id(&self) -> &str {
+        match self {
+            Principal::User(claims) => claims.user_id(),
+            Principal::ApiKey(key) => &key.id,
+            Principal::Service(service) => &service.subject,
+        }
+    }
+
+    /// Returns the roles of the caller.
+    pub fn roles(&self) -> &[String] {
+        match self {
+            Principal::User(claims) => &claims.realm_access.roles,
+            Principal::ApiKey(key) => &key.roles,
+            Principal::Service(service) => &service.roles,
+        }
+    }
+
+    /// Returns whether the caller has a role.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles().iter().any(|r| r == role)
+    }
+
+    /// Returns the workspace of the caller, if they belong to one.
+    pub fn workspace(&self) -> Option<&str> {
+        match self {
+            Principal::User(claims) => claims.workspace.as_deref(),
+            Principal::ApiKey(key) => key.workspace.as_deref(),
+            Principal::Service(_) => None,
+        }
+    }
+
+    /// Checks that the caller has a role.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The name of the role, e.g. `admin`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the caller has the role.
+    /// * `Err(ApiError::Forbidden)` otherwise.
+    pub fn require_role(&self, role: &str) -> Result<(), ApiError> {
+        if self.has_role(role) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden(format!(
+                "This action requires the '{}' role",
+                role
+            )))
+        }
+    }
+
+    /// Returns the claims of a user, for the endpoints that are about the user themselves.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(&Claims)` if the caller is a user.
+    /// * `Err(ApiError::Forbidden)` for API keys and services.
+    pub fn user(&self) -> Result<&Claims, ApiError> {
+        match self {
+            Principal::User(claims) => Ok(claims),
+            _ => Err(ApiError::Forbidden(
+                "This action is only available to signed-in users".to_string(),
+            )),
+        }
+    }
+}
+
+/// How long one fetch from the identity provider may take before it counts as failed.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Tells whether a failed fetch is worth retrying.
+///
+/// Timeouts, connection errors, server errors and rate limits usually pass;
+/// other client errors and malformed documents do not.
+fn is_transient(e: &reqwest::Error) -> bool {
+    match e.status() {
+        Some(status) => status.is_server_error() || status.as_u16() == 429,
+        None => e.is_timeout() || e.is_connect() || e.is_request(),
+    }
 }
 
 /// A simple cache for OIDC configuration and JWKS.
@@ -113,12 +275,15 @@ struct Cache {
 /// A service for validating JWTs using OIDC and JWKS.
 ///
 /// It includes a caching mechanism to avoid fetching the configuration and keys on every request.
+/// Fetches are retried and go through a circuit breaker, so an identity provider that is down
+/// gives fast 503s instead of slow requests.
 pub struct TokenValidator {
     client: Client,
     idp_url: String,
     audience: String,
     cache: RwLock<Cache>,
     cache_ttl: Duration,
+    breaker: CircuitBreaker,
 }
 
 impl TokenValidator {
@@ -131,14 +296,110 @@ impl TokenValidator {
     ///
     /// # Returns
     ///
-    /// * A new `TokenValidator` instance.
+    /// * A new `TokenValidator` instance, with the retry policy and circuit breaker from the environment.
     pub fn new(idp_url: &str, audience: &str) -> Self {
         Self {
-            client: Client::new(),
+            // A hung connection counts as a failed try instead of holding the request
+            client: outbound::client_builder()
+                .timeout(FETCH_TIMEOUT)
+                .build()
+                .expect("Failed to build the HTTP client for the identity provider"),
             idp_url: idp_url.to_string(),
             audience: audience.to_string(),
             cache: RwLock::new(Cache::default()),
             cache_ttl: Duration::from_secs(300), // 5 minutes cache
+            breaker: CircuitBreaker::from_env(),
+        }
+    }
+
+    /// Creates a `TokenValidator` that trusts a fixed set of keys and never calls an identity provider.
+    ///
+    /// It is meant for tests and for setups where the keys are distributed with the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `issuer` - The expected issuer of the JWTs.
+    /// * `audience` - The expected audience of the JWTs.
+    /// * `jwks` - The keys the JWTs may be signed with.
+    ///
+    /// # Returns
+    ///
+    /// * A new `TokenValidator` instance, whose cache never expires.
+    pub fn with_keys(issuer: &str, audience: &str, jwks: Jwks) -> Self {
+        let config = OidcConfig {
+            jwks_uri: format!("{}/protocol/openid-connect/certs", issuer),
+            issuer: issuer.to_string(),
+        };
+        Self {
+            client: outbound::client(),
+            idp_url: issuer.to_string(),
+            audience: audience.to_string(),
+            cache: RwLock::new(Cache {
+                well_known_config: Some((config, Instant::now())),
+                jwks: Some((jwks, Instant::now())),
+            }),
+            cache_ttl: Duration::MAX,
+            breaker: CircuitBreaker::from_env(),
+        }
+    }
+
+    /// Returns the circuit breaker of the calls to the identity provider, for health checks and metrics.
+    pub fn circuit(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// Fetches a JSON document from the identity provider, with retries and the circuit breaker.
+    ///
+    /// Timeouts, connection errors and 5xx or 429 answers are retried with a jittered backoff.
+    /// A fetch that still fails counts as one failure for the circuit breaker.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the document.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(T)` with the document.
+    /// * `Err(AuthError::IdpUnavailable)` if the circuit is open.
+    /// * `Err(AuthError::NetworkError)` if every try failed.
+    async fn fetch_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, AuthError> {
+        if !self.breaker.allow() {
+            return Err(AuthError::IdpUnavailable);
+        }
+        let mut retry = 0;
+        loop {
+            let result = async {
+                self.client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<T>()
+                    .await
+            }
+            .await;
+            match result {
+                Ok(document) => {
+                    self.breaker.record_success();
+                    return Ok(document);
+                }
+                Err(e) if retry < self.breaker.retries && is_transient(&e) => {
+                    retry += 1;
+                    let wait = backoff(retry);
+                    log::warn!(
+                        "Fetching {} failed ({}); retry {} in {:?}",
+                        url,
+                        e,
+                        retry,
+                        wait
+                    );
+                    tokio::time::sleep(wait).await;
+                }
+                Err(e) => {
+                    self.breaker.record_failure();
+                    return Err(e.into());
+                }
+            }
         }
     }
 
@@ -151,16 +412,16 @@ impl TokenValidator {
     async fn get_well_known_config(&self) -> Result<OidcConfig, AuthError> {
         // Check read-only cache first
         let cached_config = self.cache.read().await.well_known_config.clone();
-        if let Some((config, timestamp)) = cached_config {
-            if timestamp.elapsed() < self.cache_ttl {
-                return Ok(config);
-            }
+        if let Some((config, timestamp)) = cached_config
+            && timestamp.elapsed() < self.cache_ttl
+        {
+            return Ok(config);
         }
 
         // If not in cache or expired, fetch
         log::info!("Fetching new OIDC well-known configuration...");
         let url = format!("{}/.well-known/openid-configuration", self.idp_url);
-        let config: OidcConfig = self.client.get(&url).send().await?.json().await?;
+        let config: OidcConfig = self.fetch_json(&url).await?;
 
         // Acquire write lock to update cache
         let mut cache = self.cache.write().await;
@@ -178,10 +439,10 @@ impl TokenValidator {
     async fn get_jwks(&self) -> Result<Jwks, AuthError> {
         // Check read-only cache first
         let cached_jwks = self.cache.read().await.jwks.clone();
-        if let Some((jwks, timestamp)) = cached_jwks {
-            if timestamp.elapsed() < self.cache_ttl {
-                return Ok(jwks);
-            }
+        if let Some((jwks, timestamp)) = cached_jwks
+            && timestamp.elapsed() < self.cache_ttl
+        {
+            return Ok(jwks);
         }
 
         // If not in cache or expired, fetch config
@@ -189,13 +450,7 @@ impl TokenValidator {
 
         // Now fetch JWKS
         log::info!("Fetching new JWKS...");
-        let jwks: Jwks = self
-            .client
-            .get(&config.jwks_uri)
-            .send()
-            .await?
-            .json()
-            .await?;
+        let jwks: Jwks = self.fetch_json(&config.jwks_uri).await?;
 
         // Acquire write lock to update cache
         let mut cache = self.cache.write().await;
@@ -246,7 +501,7 @@ impl TokenValidator {
         let decoding_key = self.get_decoding_key(&kid).await?;
 
         let mut validation = Validation::new(header.alg);
-        validation.set_audience(&[self.audience.clone()]);
+        validation.set_audience(std::slice::from_ref(&self.audience));
 
         let config = self.get_well_known_config().await?;
         validation.set_issuer(&[config.issuer]);
@@ -256,10 +511,10 @@ impl TokenValidator {
     }
 }
 
-/// Implements `FromRequest` for `Claims`, allowing it to be used as a request guard.
+/// Implements `FromRequest` for `Principal`, allowing it to be used as a request guard.
 ///
 /// This extracts the token from the `Authorization` header, validates it, and extracts the claims.
-impl FromRequest for Claims {
+impl FromRequest for Principal {
     type Error = ActixWebError;
     type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
 
@@ -277,10 +532,11 @@ impl FromRequest for Claims {
                 .and_then(|s| s.strip_prefix("Bearer "))
                 .ok_or(AuthError::MissingToken)?;
 
-            validator.decode_token(token).await.map_err(|e| {
+            let claims = validator.decode_token(token).await.map_err(|e| {
                 log::error!("Token validation error: {:?}", e);
-                e.into()
-            })
+                ActixWebError::from(e)
+            })?;
+            Ok(Principal::User(claims))
         })
     }
 }
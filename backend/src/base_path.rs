@@ -0,0 +1,77 @@
+// backend/src/base_path.rs
+// This file serves the API under a path prefix, e.g. `/contacts-api`, by removing the prefix before routing.
+// It exists so the server can share an ingress with other services, without every route and path check knowing the prefix.
+// RELEVANT FILES: backend/src/main.rs, backend/src/links.rs, backend/src/trusted_proxies.rs
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Uri;
+use actix_web::middleware::Next;
+use actix_web::Error as ActixWebError;
+use std::rc::Rc;
+
+/// The path prefix the server is reached under, from `BASE_PATH`.
+#[derive(Clone, Debug, Default)]
+pub struct BasePath(String);
+
+impl BasePath {
+    /// Reads the prefix from the `BASE_PATH` environment variable, e.g. `/contacts-api`.
+    ///
+    /// A missing leading slash is added and a trailing slash is dropped.
+    ///
+    /// # Returns
+    ///
+    /// * The `BasePath`. Empty when the variable is not set or `/`.
+    pub fn from_env() -> Self {
+        let path = std::env::var("BASE_PATH").unwrap_or_default();
+        let path = path.trim().trim_matches('/');
+        if path.is_empty() {
+            Self::default()
+        } else {
+            Self(format!("/{}", path))
+        }
+    }
+
+    /// Returns the prefix, e.g. `/contacts-api`, or an empty string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Middleware that removes the base path from the request before it is routed.
+///
+/// `/contacts-api/api/contacts` is then handled as `/api/contacts`. Paths without the prefix are
+/// passed on as they are, so probes that call `/readyz` on the pod directly keep working.
+/// It must wrap the app, because scopes are matched before their middleware runs.
+///
+/// # Arguments
+///
+/// * `base_path` - The prefix, see `BasePath::from_env`.
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service.
+pub async fn strip_base_path(
+    base_path: Rc<BasePath>,
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    let stripped = req
+        .path()
+        .strip_prefix(base_path.as_str())
+        // `/contacts-apix` is not under `/contacts-api`
+        .filter(|rest| !base_path.as_str().is_empty() && (rest.is_empty() || rest.starts_with('/')))
+        .map(|rest| match req.query_string() {
+            "" => format!("/{}", rest.trim_start_matches('/')),
+            query => format!("/{}?{}", rest.trim_start_matches('/'), query),
+        });
+    if let Some(path_and_query) = stripped
+        && let Ok(uri) = path_and_query.parse::<Uri>()
+    {
+        req.match_info_mut().get_mut().update(&uri);
+        req.head_mut().uri = uri;
+    }
+    next.call(req).await
+}
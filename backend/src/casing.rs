@@ -0,0 +1,127 @@
+// backend/src/casing.rs
+// This file renames the fields of JSON responses to camelCase when a client asks for it.
+// It exists so the JS frontend can use its own naming, while snake_case stays the default for everyone else.
+// RELEVANT FILES: backend/src/formats.rs, backend/src/envelope.rs, backend/src/main.rs
+
+use crate::error::ApiError;
+use crate::formats::is_json;
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, VARY};
+use actix_web::middleware::Next;
+use actix_web::{web, Error as ActixWebError};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// The request header clients use to ask for a field naming, e.g. `Field-Case: camel`.
+pub const FIELD_CASE_HEADER: HeaderName = HeaderName::from_static("field-case");
+
+/// The field namings the API can send.
+const CASES: [&str; 2] = ["snake", "camel"];
+
+/// The query parameter that asks for a field naming, e.g. `?case=camel`.
+#[derive(Deserialize)]
+struct CaseQuery {
+    /// `snake` or `camel`.
+    case: Option<String>,
+}
+
+/// Turns a snake_case name into camelCase.
+///
+/// Leading underscores are kept, so `_links` and `_embedded` stay as they are.
+///
+/// # Arguments
+///
+/// * `name` - The field name, e.g. `first_name`.
+///
+/// # Returns
+///
+/// * The camelCase name, e.g. `firstName`.
+fn to_camel(name: &str) -> String {
+    let stem = name.trim_start_matches('_');
+    let mut camel = name[..name.len() - stem.len()].to_string();
+    for (i, word) in stem.split('_').filter(|w| !w.is_empty()).enumerate() {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(first) if i > 0 => {
+                camel.extend(first.to_uppercase());
+                camel.push_str(chars.as_str());
+            }
+            _ => camel.push_str(word),
+        }
+    }
+    camel
+}
+
+/// Renames every key of a value and everything in it to camelCase.
+fn camel_keys(value: Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, item)| (to_camel(&key), camel_keys(item)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(camel_keys).collect()),
+        other => other,
+    }
+}
+
+/// Middleware that renames the fields of JSON responses to camelCase when the client asks for it.
+///
+/// Clients ask with `?case=camel` or the `Field-Case: camel` header, and the query parameter wins.
+/// Every JSON response is renamed, errors included, so clients only deal with one naming.
+/// Query parameters and request bodies still use snake_case.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service, with camelCase fields if the client asked for them.
+/// * `Err(ApiError::BadRequest)` if the naming is not `snake` or `camel`.
+pub async fn apply_field_case(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, ActixWebError> {
+    let from_query = web::Query::<CaseQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.into_inner().case);
+    let from_header = req
+        .headers()
+        .get(FIELD_CASE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_lowercase());
+    let case = from_query.or(from_header);
+    if let Some(case) = case.as_deref()
+        && !CASES.contains(&case)
+    {
+        return Err(
+            ApiError::BadRequest(format!("Case must be one of: {}", CASES.join(", "))).into(),
+        );
+    }
+    let mut res = next.call(req).await?;
+
+    if !is_json(&res) {
+        return Ok(res.map_into_boxed_body());
+    }
+    res.headers_mut()
+        .append(VARY, HeaderValue::from_static("field-case"));
+    if case.as_deref() != Some("camel") {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = to_bytes(body)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.into()))?;
+    let Ok(value) = serde_json::from_slice::<Value>(&bytes) else {
+        // Not valid JSON after all, so send it as it is
+        return Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(bytes))));
+    };
+    let body = serde_json::to_vec(&camel_keys(value))?;
+    Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))))
+}
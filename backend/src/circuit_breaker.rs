@@ -0,0 +1,183 @@
+// backend/src/circuit_breaker.rs
+// This file implements the retry policy and circuit breaker for calls to the identity provider.
+// It exists so a short IDP outage is retried away, and a long one fails fast instead of making every request wait.
+// RELEVANT FILES: backend/src/auth.rs, backend/src/health.rs, backend/src/query_metrics.rs
+
+use ring::rand::{SecureRandom, SystemRandom};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How many times a failed call is retried when not configured.
+const DEFAULT_RETRIES: u32 = 2;
+
+/// How many failed calls in a row open the circuit when not configured.
+const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+
+/// How long the circuit stays open when not configured, in seconds.
+const DEFAULT_OPEN_SECS: u64 = 30;
+
+/// The wait before the first retry. Each later retry waits up to twice as long.
+const BASE_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The longest wait between two tries.
+const MAX_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Reads a number from an environment variable, or returns the default.
+fn number_from_env<T: std::str::FromStr>(variable: &str, default: T) -> T {
+    std::env::var(variable)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Returns how long to wait before a retry.
+///
+/// The wait is random between zero and an exponentially growing bound ("full jitter"),
+/// so instances that failed together do not all retry at the same moment.
+///
+/// # Arguments
+///
+/// * `retry` - The number of the retry, starting at 1.
+///
+/// # Returns
+///
+/// * The time to wait.
+pub fn backoff(retry: u32) -> Duration {
+    let bound = BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+        .min(MAX_BACKOFF);
+    let mut random = [0u8; 4];
+    // Without randomness the full bound is used, which is still a valid backoff
+    if SystemRandom::new().fill(&mut random).is_err() {
+        return bound;
+    }
+    bound.mul_f64(u32::from_be_bytes(random) as f64 / u32::MAX as f64)
+}
+
+/// The state of a circuit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    /// Calls go through.
+    Closed,
+    /// Calls fail at once, until the instant has passed.
+    Open(Instant),
+    /// One trial call is going through, started at the instant, to see if the service is back.
+    HalfOpen(Instant),
+}
+
+/// The mutable part of a circuit breaker.
+struct Inner {
+    /// The state of the circuit.
+    state: State,
+    /// How many calls in a row have failed.
+    failures: u32,
+    /// How many times the circuit has opened since the server started.
+    trips: u64,
+}
+
+/// A circuit breaker for calls to one service.
+///
+/// After `failure_threshold` failed calls in a row the circuit opens, and calls fail at once
+/// for `open_for`. Then one trial call is let through: if it succeeds the circuit closes,
+/// otherwise it opens again.
+pub struct CircuitBreaker {
+    /// How many failed calls in a row open the circuit.
+    failure_threshold: u32,
+    /// How long the circuit stays open.
+    open_for: Duration,
+    /// How many times a failed call is retried before it counts as failed.
+    pub retries: u32,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    /// Reads the policy from the `IDP_RETRIES`, `IDP_FAILURE_THRESHOLD`
+    /// and `IDP_CIRCUIT_OPEN_SECS` environment variables.
+    ///
+    /// # Returns
+    ///
+    /// * A closed `CircuitBreaker`.
+    pub fn from_env() -> Self {
+        Self {
+            failure_threshold: number_from_env("IDP_FAILURE_THRESHOLD", DEFAULT_FAILURE_THRESHOLD)
+                .max(1),
+            open_for: Duration::from_secs(number_from_env(
+                "IDP_CIRCUIT_OPEN_SECS",
+                DEFAULT_OPEN_SECS,
+            )),
+            retries: number_from_env("IDP_RETRIES", DEFAULT_RETRIES),
+            inner: Mutex::new(Inner {
+                state: State::Closed,
+                failures: 0,
+                trips: 0,
+            }),
+        }
+    }
+
+    fn inner(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Checks whether a call may go through.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if the circuit is closed, or if this call is the trial of a half-open circuit.
+    /// * `false` if the call should fail at once.
+    pub fn allow(&self) -> bool {
+        let mut inner = self.inner();
+        match inner.state {
+            State::Closed => true,
+            State::Open(until) if Instant::now() < until => false,
+            // A trial that never reported back (e.g. its request timed out) is replaced
+            State::HalfOpen(started) if started.elapsed() < self.open_for => false,
+            State::Open(_) | State::HalfOpen(_) => {
+                inner.state = State::HalfOpen(Instant::now());
+                true
+            }
+        }
+    }
+
+    /// Records a successful call, which closes the circuit.
+    pub fn record_success(&self) {
+        let mut inner = self.inner();
+        if inner.state != State::Closed {
+            log::info!("The identity provider answers again; closing the circuit.");
+        }
+        inner.state = State::Closed;
+        inner.failures = 0;
+    }
+
+    /// Records a failed call, which may open the circuit.
+    pub fn record_failure(&self) {
+        let mut inner = self.inner();
+        inner.failures += 1;
+        let trial_failed = matches!(inner.state, State::HalfOpen(_));
+        if trial_failed || inner.failures >= self.failure_threshold {
+            if !matches!(inner.state, State::Open(_)) {
+                inner.trips += 1;
+                log::warn!(
+                    "The identity provider failed {} times in a row; failing fast for {:?}.",
+                    inner.failures,
+                    self.open_for
+                );
+            }
+            inner.state = State::Open(Instant::now() + self.open_for);
+        }
+    }
+
+    /// Returns the state of the circuit: `closed`, `open` or `half_open`.
+    pub fn state(&self) -> &'static str {
+        match self.inner().state {
+            State::Closed => "closed",
+            State::Open(until) if Instant::now() < until => "open",
+            // The next call will be a trial
+            State::Open(_) | State::HalfOpen(_) => "half_open",
+        }
+    }
+
+    /// Returns how many times the circuit has opened since the server started.
+    pub fn trips(&self) -> u64 {
+        self.inner().trips
+    }
+}
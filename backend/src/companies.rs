@@ -0,0 +1,359 @@
+// backend/src/companies.rs
+// This file contains the handlers for companies and the rule that links contacts to them.
+// It exists so companies are records of their own, with contacts pointing to them through `company_id`.
+// RELEVANT FILES: backend/src/models.rs, backend/src/handlers.rs, backend/contacts-types/src/schema.rs
+
+use crate::addresses::AddressValidator;
+use crate::archive::ArchivedQuery;
+use crate::auth::Principal;
+use crate::error::ApiError;
+use crate::establish_connection;
+use crate::feature_flags::FeatureFlags;
+use crate::field_permissions::FieldRules;
+use crate::identifiers::ContactKey;
+use crate::models::{Company, Contact, NewCompany, NewContact};
+use crate::normalization::normalize_company_data;
+use crate::pending_changes::ChangeApproval;
+use crate::schema::{companies, contacts};
+use crate::transaction::Transaction;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+
+/// Holds the optional rules for linking contacts to companies.
+#[derive(Clone, Copy, Default)]
+pub struct CompanyRules {
+    /// Whether new contacts without a company are linked by email domain.
+    pub auto_link_by_domain: bool,
+}
+
+impl CompanyRules {
+    /// Reads the rules from the `AUTO_LINK_COMPANIES` environment variable.
+    ///
+    /// # Returns
+    ///
+    /// * `CompanyRules` with auto-linking enabled when the variable is `true` or `1`.
+    pub fn from_env() -> Self {
+        let auto_link_by_domain = std::env::var("AUTO_LINK_COMPANIES")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        Self {
+            auto_link_by_domain,
+        }
+    }
+}
+
+/// Finds the company whose domain matches the domain of an email address.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection.
+/// * `email` - The email address of a contact.
+///
+/// # Returns
+///
+/// * `Ok(Some(id))` with the ID of the matching company.
+/// * `Ok(None)` if the email has no domain or no company matches.
+/// * `Err` if there is a database error.
+pub fn company_for_email(conn: &mut SqliteConnection, email: &str) -> QueryResult<Option<i32>> {
+    let Some((_, domain)) = email.rsplit_once('@') else {
+        return Ok(None);
+    };
+
+    // Domains are stored in lowercase, see `normalize`
+    companies::table
+        .filter(companies::domain.eq(domain.to_lowercase()))
+        .select(companies::id)
+        .order(companies::id.asc())
+        .first::<i32>(conn)
+        .optional()
+}
+
+/// Links a new contact to a company by email domain, when the rule is enabled.
+///
+/// Contacts that already have a company are left as they are. It is only for new contacts:
+/// on an edit it would link a contact again after its company was removed by hand.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection.
+/// * `rules` - The company rules.
+/// * `contact` - The contact data to update in place.
+///
+/// # Returns
+///
+/// * `Ok(())` when done.
+/// * `Err` if there is a database error.
+pub fn apply_rules(
+    conn: &mut SqliteConnection,
+    rules: &CompanyRules,
+    contact: &mut NewContact,
+) -> QueryResult<()> {
+    if rules.auto_link_by_domain && contact.company_id.is_none() {
+        contact.company_id = company_for_email(conn, &contact.email)?;
+    }
+    Ok(())
+}
+
+/// Prepares company data for storage.
+///
+/// The name is cleaned up by the normalization rules of the workspace.
+/// Domains are case-insensitive, so they are stored in lowercase.
+/// Addresses are stored in their standard form, with whether they can be delivered to.
+///
+/// # Arguments
+///
+/// * `company` - The company data from the request body.
+/// * `addresses` - The address validator.
+/// * `flags` - The feature flags of the caller's workspace, which switch the normalization of the name.
+///
+/// # Returns
+///
+/// * The normalized company data.
+async fn normalize(
+    mut company: NewCompany,
+    addresses: &AddressValidator,
+    flags: &FeatureFlags,
+) -> NewCompany {
+    normalize_company_data(flags, &mut company);
+    company.domain = company.domain.map(|d| d.trim().to_lowercase());
+    company.address = company.address.filter(|a| !a.trim().is_empty());
+    company.address_status = None;
+    if let Some(address) = &company.address {
+        let check = addresses.check_for_storage(address).await;
+        company.address = Some(check.standardized);
+        company.address_status = Some(check.deliverability.as_str().to_string());
+    }
+    company
+}
+
+/// Handles the creation of a new company.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `flags` - The feature flags of the caller's workspace, which switch the normalization of the name.
+/// * `company` - The new company data from the request body.
+/// * `addresses` - The address validator, which standardizes the address.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the created company.
+/// * `Err(ApiError)` if there is a database error.
+pub async fn create_company(
+    flags: FeatureFlags,
+    company: web::Json<NewCompany>,
+    addresses: web::Data<AddressValidator>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    // The address check may call an external API, so it runs before the connection is opened
+    let company = normalize(company.into_inner(), &addresses, &flags).await;
+    let mut conn = transaction.conn()?;
+
+    let created = diesel::insert_into(companies::table)
+        .values(&company)
+        .get_result::<Company>(&mut *conn)?;
+
+    Ok(HttpResponse::Ok().json(created))
+}
+
+/// Handles reading all companies from the database.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a JSON array of companies, sorted by name.
+/// * `Err(ApiError)` if there is a database error.
+pub async fn read_companies(_principal: Principal) -> Result<HttpResponse, ApiError> {
+    let mut conn = establish_connection()?;
+
+    let companies = companies::table
+        .order(companies::name.asc())
+        .load::<Company>(&mut conn)?;
+
+    Ok(HttpResponse::Ok().json(companies))
+}
+
+/// Handles reading a specific company by its ID.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `id` - The ID of the company to read, from the URL path.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the company.
+/// * `Err(ApiError)` if the company is not found or there is a database error.
+pub async fn read_company(
+    _principal: Principal,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = establish_connection()?;
+
+    let company = companies::table
+        .find(id.into_inner())
+        .first::<Company>(&mut conn)?;
+
+    Ok(HttpResponse::Ok().json(company))
+}
+
+/// Handles updating an existing company by its ID.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `flags` - The feature flags of the caller's workspace, which switch the normalization of the name.
+/// * `id` - The ID of the company to update, from the URL path.
+/// * `company` - The updated company data from the request body.
+/// * `addresses` - The address validator, which standardizes the address.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the updated company.
+/// * `Err(ApiError)` if the company is not found or there is a database error.
+pub async fn update_company(
+    flags: FeatureFlags,
+    id: web::Path<i32>,
+    company: web::Json<NewCompany>,
+    addresses: web::Data<AddressValidator>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let company = normalize(company.into_inner(), &addresses, &flags).await;
+    let mut conn = transaction.conn()?;
+
+    let updated = diesel::update(companies::table.find(id.into_inner()))
+        .set(company)
+        .get_result::<Company>(&mut *conn)?;
+
+    Ok(HttpResponse::Ok().json(updated))
+}
+
+/// Handles deleting a company by its ID.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Contacts of the company are kept, but lose their link to it.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `id` - The ID of the company to delete, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a success message if the company is deleted.
+/// * `Err(ApiError)` if there is a database error.
+pub async fn delete_company(
+    _principal: Principal,
+    id: web::Path<i32>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = transaction.conn()?;
+
+    diesel::delete(companies::table.find(id.into_inner())).execute(&mut *conn)?;
+
+    Ok(HttpResponse::Ok().body("Company deleted successfully"))
+}
+
+/// Handles removing a contact from its company.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// An update cannot clear `company_id`, since fields left out of it are kept, so this is the way to unlink.
+/// The company rules do not link the contact again, as they only link new contacts.
+/// While edits need approval, only admins can unlink, as a pending change cannot hold it.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to decide if the edit needs approval.
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `id` - The ID or UUID of the contact, from the URL path.
+/// * `approval` - The setting that decides if edits need approval.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the contact without a company.
+/// * `Err(ApiError)` if `company_id` is read-only for the user, the edit would need approval,
+///   the contact is not found or there is a database error.
+pub async fn unlink_company(
+    principal: Principal,
+    field_rules: FieldRules,
+    id: web::Path<ContactKey>,
+    approval: web::Data<ChangeApproval>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    field_rules.check_writable("company_id")?;
+    if approval.applies_to(&principal) {
+        return Err(ApiError::Forbidden(
+            "Only admins can remove a contact from its company while edits need approval"
+                .to_string(),
+        ));
+    }
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+    let id = id.resolve(conn)?;
+    let contact = diesel::update(contacts::table.find(id))
+        .set(contacts::company_id.eq(None::<i32>))
+        .get_result::<Contact>(conn)?;
+    Ok(HttpResponse::Ok().json(field_rules.masked(contact)))
+}
+
+/// Handles reading all contacts that belong to a company.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Archived contacts are left out unless the query has `include_archived=true`.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `id` - The ID of the company, from the URL path.
+/// * `archived` - Whether to return archived contacts too.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a JSON array of contacts.
+/// * `Err(ApiError)` if the company is not found or there is a database error.
+pub async fn read_company_contacts(
+    field_rules: FieldRules,
+    id: web::Path<i32>,
+    archived: web::Query<ArchivedQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = establish_connection()?;
+
+    let company_id = id.into_inner();
+
+    // Return 404 for unknown companies instead of an empty list
+    companies::table
+        .find(company_id)
+        .select(companies::id)
+        .first::<i32>(&mut conn)?;
+
+    let mut query = contacts::table
+        .filter(contacts::company_id.eq(company_id))
+        .into_boxed();
+    if !archived.include_archived {
+        query = query.filter(contacts::archived_at.is_null());
+    }
+    let contacts = query
+        .order((contacts::last_name.asc(), contacts::first_name.asc()))
+        .load::<Contact>(&mut conn)?;
+
+    let contacts: Vec<Contact> = contacts
+        .into_iter()
+        .map(|c| field_rules.masked(c))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(contacts))
+}
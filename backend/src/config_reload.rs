@@ -0,0 +1,140 @@
+// backend/src/config_reload.rs
+// This file re-reads the settings that can change at runtime from the environment and the .env file, and applies them.
+// It exists so admins can change a limit without restarting the server and dropping the requests in flight.
+// RELEVANT FILES: backend/src/contact_requests.rs, backend/src/reports.rs, backend/.env.example
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::contact_requests::ContactRequestGuard;
+use crate::error::ApiError;
+use crate::reports::ReportCache;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The settings that can be reloaded. Others only change with a restart.
+const RELOADABLE: [&str; 2] = ["CONTACT_REQUEST_LIMIT", "REPORT_CACHE_SECS"];
+
+/// One setting that a reload changed.
+#[derive(Serialize)]
+pub struct SettingChange {
+    /// The environment variable, e.g. `CONTACT_REQUEST_LIMIT`.
+    pub setting: &'static str,
+    /// The value before the reload.
+    pub from: String,
+    /// The value after the reload.
+    pub to: String,
+}
+
+/// The outcome of a reload.
+#[derive(Serialize)]
+pub struct ReloadResult {
+    /// The settings that changed.
+    pub changed: Vec<SettingChange>,
+    /// The settings that can be reloaded, whether they changed or not.
+    pub reloadable: [&'static str; 2],
+}
+
+/// Reads the current settings.
+///
+/// The `.env` file is read again, and its values win: the environment of a running process
+/// cannot be changed from outside, and at startup the file filled in what the environment lacked.
+/// The environment of the process itself is not changed.
+///
+/// # Returns
+///
+/// * The value of every reloadable setting that is set.
+fn read_settings() -> HashMap<&'static str, String> {
+    let file: HashMap<String, String> = dotenvy::dotenv_iter()
+        .map(|entries| entries.filter_map(Result::ok).collect())
+        .unwrap_or_default();
+    RELOADABLE
+        .iter()
+        .filter_map(|name| {
+            let value = file
+                .get(*name)
+                .cloned()
+                .or_else(|| std::env::var(name).ok())?;
+            Some((*name, value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Reads a setting as a whole number.
+fn parse<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, ApiError> {
+    value.parse().map_err(|_| {
+        ApiError::BadRequest(format!("{} must be a whole number, not '{}'", name, value))
+    })
+}
+
+/// Handles reloading the settings that can change at runtime.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// Every value is checked before any is applied, so a bad value changes nothing.
+/// Settings that are not set keep their current value. The change is logged with who made it.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and log who reloaded.
+/// * `guard` - The guard of the public contact form, which holds `CONTACT_REQUEST_LIMIT`.
+/// * `report_cache` - The report cache, which holds `REPORT_CACHE_SECS`.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the settings that changed.
+/// * `Err(ApiError)` if the user is not an admin or a value is invalid.
+pub async fn reload_config(
+    principal: Principal,
+    guard: web::Data<ContactRequestGuard>,
+    report_cache: web::Data<ReportCache>,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let settings = read_settings();
+
+    // Checked first, so a reload is all or nothing
+    let limit = settings
+        .get("CONTACT_REQUEST_LIMIT")
+        .map(|value| parse::<u32>("CONTACT_REQUEST_LIMIT", value))
+        .transpose()?;
+    let cache_secs = settings
+        .get("REPORT_CACHE_SECS")
+        .map(|value| parse::<u64>("REPORT_CACHE_SECS", value))
+        .transpose()?;
+
+    let mut changed = Vec::new();
+    if let Some(limit) = limit
+        && limit != guard.limit_per_hour()
+    {
+        changed.push(SettingChange {
+            setting: "CONTACT_REQUEST_LIMIT",
+            from: guard.limit_per_hour().to_string(),
+            to: limit.to_string(),
+        });
+        guard.set_limit_per_hour(limit);
+    }
+    if let Some(seconds) = cache_secs
+        && seconds != report_cache.ttl_secs()
+    {
+        changed.push(SettingChange {
+            setting: "REPORT_CACHE_SECS",
+            from: report_cache.ttl_secs().to_string(),
+            to: seconds.to_string(),
+        });
+        report_cache.set_ttl_secs(seconds);
+    }
+
+    // There is no audit table, so the log is the record of who changed what
+    for change in &changed {
+        log::info!(
+            "Configuration reloaded by {}: {} changed from {} to {}",
+            principal.id(),
+            change.setting,
+            change.from,
+            change.to
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(ReloadResult {
+        changed,
+        reloadable: RELOADABLE,
+    }))
+}
@@ -0,0 +1,132 @@
+// backend/src/consent.rs
+// This file contains the handlers for recording and revoking a contact's consent.
+// It exists so we know who agreed to receive email or text messages, when, and where that came from.
+// RELEVANT FILES: backend/src/models.rs, backend/src/handlers.rs, backend/contacts-types/src/schema.rs
+
+use crate::error::ApiError;
+use crate::field_permissions::FieldRules;
+use crate::identifiers::ContactKey;
+use crate::models::{ConsentChange, Contact};
+use crate::schema::contacts;
+use crate::transaction::Transaction;
+use crate::unix_now;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+
+/// The consent columns to update. Fields that are `None` are left as they are.
+#[derive(AsChangeset)]
+#[diesel(table_name = contacts)]
+struct ConsentUpdate {
+    email_opt_in: Option<bool>,
+    sms_opt_in: Option<bool>,
+    consent_source: Option<Option<String>>,
+    consent_timestamp: Option<i64>,
+}
+
+/// Opts a contact in or out of the channels named in a consent change.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection, with the transaction of the request.
+/// * `field_rules` - The fields the user may not write. Every change sets the source and the time.
+/// * `id` - The ID of the contact.
+/// * `change` - The channels to change and where the change came from.
+/// * `opt_in` - `true` to record consent, `false` to revoke it.
+///
+/// # Returns
+///
+/// * `Ok(Contact)` with the updated contact.
+/// * `Err(ApiError::Forbidden)` if the user may not change a field the change sets.
+/// * `Err(ApiError)` if no channel is named, the contact is not found or there is a database error.
+fn apply_consent(
+    conn: &mut SqliteConnection,
+    field_rules: &FieldRules,
+    id: i32,
+    change: ConsentChange,
+    opt_in: bool,
+) -> Result<Contact, ApiError> {
+    if !change.email && !change.sms {
+        return Err(ApiError::BadRequest(
+            "Name at least one channel: email or sms".to_string(),
+        ));
+    }
+    field_rules.check_writable("consent_source")?;
+    field_rules.check_writable("consent_timestamp")?;
+    if change.email {
+        field_rules.check_writable("email_opt_in")?;
+    }
+    if change.sms {
+        field_rules.check_writable("sms_opt_in")?;
+    }
+
+    let update = ConsentUpdate {
+        email_opt_in: change.email.then_some(opt_in),
+        sms_opt_in: change.sms.then_some(opt_in),
+        // Always overwrite the source, so it never belongs to an older change
+        consent_source: Some(change.source),
+        consent_timestamp: Some(unix_now()),
+    };
+
+    Ok(diesel::update(contacts::table.find(id))
+        .set(update)
+        .get_result::<Contact>(conn)?)
+}
+
+/// Handles recording a contact's consent for email and/or text messages.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `id` - The ID or UUID of the contact, from the URL path.
+/// * `change` - The channels to opt in to, and where the consent came from.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the updated contact.
+/// * `Err(ApiError::Forbidden)` if the user may not change the consent fields.
+/// * `Err(ApiError)` if the request is invalid, the contact is not found or there is a database error.
+pub async fn record_consent(
+    field_rules: FieldRules,
+    id: web::Path<ContactKey>,
+    change: web::Json<ConsentChange>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+    let id = id.resolve(conn)?;
+    let contact = apply_consent(conn, &field_rules, id, change.into_inner(), true)?;
+    Ok(HttpResponse::Ok().json(field_rules.masked(contact)))
+}
+
+/// Handles revoking a contact's consent for email and/or text messages.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `id` - The ID or UUID of the contact, from the URL path.
+/// * `change` - The channels to opt out of, and where the request came from.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the updated contact.
+/// * `Err(ApiError::Forbidden)` if the user may not change the consent fields.
+/// * `Err(ApiError)` if the request is invalid, the contact is not found or there is a database error.
+pub async fn revoke_consent(
+    field_rules: FieldRules,
+    id: web::Path<ContactKey>,
+    change: web::Json<ConsentChange>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+    let id = id.resolve(conn)?;
+    let contact = apply_consent(conn, &field_rules, id, change.into_inner(), false)?;
+    Ok(HttpResponse::Ok().json(field_rules.masked(contact)))
+}
@@ -0,0 +1,390 @@
+// backend/src/contact_requests.rs
+// This file contains the public "contact me" form and the admin queue that reviews its submissions.
+// It exists so people without an account can send us their details, without writing straight into the address book.
+// RELEVANT FILES: backend/src/models.rs, backend/src/auth.rs, backend/src/main.rs
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::companies::{apply_rules, CompanyRules};
+use crate::error::ApiError;
+use crate::feature_flags::FeatureFlags;
+use crate::identifiers::new_uuid;
+use crate::models::{ContactRequest, NewContact, NewContactRequest};
+use crate::normalization::normalize_contact;
+use crate::schema::{contact_requests, contacts};
+use crate::transaction::Transaction;
+use crate::trusted_proxies::TrustedProxies;
+use crate::{establish_connection, unix_now};
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+/// The header that carries the shared secret of the public form.
+const TOKEN_HEADER: &str = "X-Contact-Request-Token";
+
+/// How many submissions one IP address may make per window when not configured.
+const DEFAULT_LIMIT_PER_HOUR: u32 = 5;
+
+/// The length of a rate limit window, in seconds.
+const WINDOW_SECONDS: i64 = 60 * 60;
+
+/// The source given to contacts created from an approved request.
+const CONTACT_REQUEST_SOURCE: &str = "contact-request";
+
+/// The states a contact request can be in.
+const REQUEST_STATUSES: [&str; 3] = ["pending", "approved", "rejected"];
+
+/// Guards the public form with a shared secret and a per-IP rate limit.
+pub struct ContactRequestGuard {
+    /// The SHA-256 digest of the secret, or `None` when the form is switched off.
+    token_digest: Option<Vec<u8>>,
+    /// How many submissions one IP address may make per window. It can be reloaded at runtime.
+    limit_per_hour: AtomicU32,
+    /// The start of the current window and the number of submissions in it, per IP address.
+    windows: Mutex<HashMap<IpAddr, (i64, u32)>>,
+}
+
+impl ContactRequestGuard {
+    /// Reads the settings from the `CONTACT_REQUEST_TOKEN` and `CONTACT_REQUEST_LIMIT` environment variables.
+    ///
+    /// Without a token the public form is switched off.
+    ///
+    /// # Returns
+    ///
+    /// * A new `ContactRequestGuard`.
+    pub fn from_env() -> Self {
+        let limit_per_hour = std::env::var("CONTACT_REQUEST_LIMIT")
+            .ok()
+            .and_then(|limit| limit.parse().ok())
+            .unwrap_or(DEFAULT_LIMIT_PER_HOUR);
+        Self::from_settings(
+            &std::env::var("CONTACT_REQUEST_TOKEN").unwrap_or_default(),
+            limit_per_hour,
+        )
+    }
+
+    /// Builds the guard from settings in the form of `CONTACT_REQUEST_TOKEN` and `CONTACT_REQUEST_LIMIT`.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The shared secret of the form. Empty switches the form off.
+    /// * `limit_per_hour` - How many submissions one IP address may make per hour.
+    ///
+    /// # Returns
+    ///
+    /// * A new `ContactRequestGuard`.
+    pub fn from_settings(token: &str, limit_per_hour: u32) -> Self {
+        let token_digest = (!token.is_empty()).then(|| {
+            digest::digest(&digest::SHA256, token.as_bytes())
+                .as_ref()
+                .to_vec()
+        });
+        Self {
+            token_digest,
+            limit_per_hour: AtomicU32::new(limit_per_hour),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns how many submissions one IP address may make per hour.
+    pub fn limit_per_hour(&self) -> u32 {
+        self.limit_per_hour.load(Ordering::Relaxed)
+    }
+
+    /// Changes how many submissions one IP address may make per hour. Submissions already counted stay counted.
+    pub fn set_limit_per_hour(&self, limit: u32) {
+        self.limit_per_hour.store(limit, Ordering::Relaxed);
+    }
+
+    /// Checks the secret of a submission.
+    ///
+    /// The digests are compared instead of the secrets, so the time taken says nothing about the secret.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The secret sent by the client, if any.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the secret is right.
+    /// * `Err(ApiError::Forbidden)` if it is missing or wrong, or the form is switched off.
+    fn check_token(&self, token: Option<&str>) -> Result<(), ApiError> {
+        let expected = self
+            .token_digest
+            .as_ref()
+            .ok_or_else(|| ApiError::Forbidden("The contact form is not enabled".to_string()))?;
+        let given = token.map(|token| digest::digest(&digest::SHA256, token.as_bytes()));
+        match given {
+            Some(given) if given.as_ref() == expected.as_slice() => Ok(()),
+            _ => Err(ApiError::Forbidden(format!(
+                "A valid {} header is required",
+                TOKEN_HEADER
+            ))),
+        }
+    }
+
+    /// Counts a submission from an IP address against its limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address of the client.
+    /// * `now` - The current time, as a Unix timestamp.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the submission is allowed.
+    /// * `Err(ApiError::TooManyRequests)` if the address has used up its limit.
+    fn check_rate(&self, ip: IpAddr, now: i64) -> Result<(), ApiError> {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        // Forget finished windows, so the map does not grow forever
+        windows.retain(|_, (start, _)| now - *start < WINDOW_SECONDS);
+
+        let (_, count) = windows.entry(ip).or_insert((now, 0));
+        if *count >= self.limit_per_hour() {
+            return Err(ApiError::TooManyRequests(
+                "Too many contact requests, please try again later".to_string(),
+            ));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// The optional filter for listing contact requests.
+#[derive(Deserialize)]
+pub struct ContactRequestFilter {
+    /// Only return requests in this state: pending, approved or rejected.
+    pub status: Option<String>,
+}
+
+/// The answer to a public submission.
+///
+/// It does not contain the stored data, so the form cannot be used to read anything back.
+#[derive(Serialize)]
+pub struct SubmissionReceipt {
+    /// Always `pending`: the request waits for an admin.
+    pub status: &'static str,
+}
+
+/// Handles a submission from the public "contact me" form.
+///
+/// This endpoint is public and does not require a JWT.
+/// It requires the shared secret in the `X-Contact-Request-Token` header,
+/// and each IP address may only submit a few requests per hour.
+///
+/// # Arguments
+///
+/// * `req` - The HTTP request, used for the secret and the client address.
+/// * `request` - The details the person submitted.
+/// * `guard` - The secret and rate limit settings.
+/// * `proxies` - The trusted proxies, which tell the client address behind them.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with 202 Accepted when the request is queued.
+/// * `Err(ApiError)` if the secret is wrong, the limit is reached, the data is invalid or there is a database error.
+pub async fn submit_contact_request(
+    req: HttpRequest,
+    request: web::Json<NewContactRequest>,
+    guard: web::Data<ContactRequestGuard>,
+    proxies: web::Data<TrustedProxies>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let token = req
+        .headers()
+        .get(TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok());
+    guard.check_token(token)?;
+
+    let mut request = request.into_inner();
+    if request.first_name.trim().is_empty()
+        || request.last_name.trim().is_empty()
+        || !request.email.contains('@')
+    {
+        return Err(ApiError::BadRequest(
+            "first_name, last_name and a valid email are required".to_string(),
+        ));
+    }
+
+    // Forwarding headers only count from trusted proxies, so clients cannot fake their address
+    let now = unix_now();
+    if let Some(ip) = proxies.client_ip(&req) {
+        guard.check_rate(ip, now)?;
+    }
+
+    request.created_at = now;
+    let mut conn = transaction.conn()?;
+    diesel::insert_into(contact_requests::table)
+        .values(&request)
+        .execute(&mut *conn)?;
+
+    Ok(HttpResponse::Accepted().json(SubmissionReceipt { status: "pending" }))
+}
+
+/// Handles listing contact requests, newest first.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+/// * `filter` - The optional `status` filter from the query string.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a JSON array of contact requests.
+/// * `Err(ApiError)` if the user is not an admin, the status is unknown or there is a database error.
+pub async fn read_contact_requests(
+    principal: Principal,
+    filter: web::Query<ContactRequestFilter>,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let mut conn = establish_connection()?;
+
+    let mut query = contact_requests::table.into_boxed();
+    if let Some(status) = &filter.status {
+        if !REQUEST_STATUSES.contains(&status.as_str()) {
+            return Err(ApiError::BadRequest(format!(
+                "Status must be one of: {}",
+                REQUEST_STATUSES.join(", ")
+            )));
+        }
+        query = query.filter(contact_requests::status.eq(status));
+    }
+    let requests = query
+        .order(contact_requests::id.desc())
+        .load::<ContactRequest>(&mut conn)?;
+
+    Ok(HttpResponse::Ok().json(requests))
+}
+
+/// Loads a contact request that is still waiting for a decision.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection.
+/// * `id` - The ID of the request.
+///
+/// # Returns
+///
+/// * `Ok(ContactRequest)` if the request is pending.
+/// * `Err(ApiError::NotFound)` if it does not exist, or `Err(ApiError::Conflict)` if it was already decided.
+fn pending_request(conn: &mut SqliteConnection, id: i32) -> Result<ContactRequest, ApiError> {
+    let request = contact_requests::table
+        .find(id)
+        .first::<ContactRequest>(conn)?;
+    if request.status != "pending" {
+        return Err(ApiError::Conflict(format!(
+            "The contact request was already {}",
+            request.status
+        )));
+    }
+    Ok(request)
+}
+
+/// Handles approving a contact request into the address book.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// A new contact is created from the request, with the source `contact-request`. It is normalized
+/// with the rules of the reviewer's workspace.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and record the reviewer.
+/// * `id` - The ID of the request, from the URL path.
+/// * `rules` - The rules for linking contacts to companies.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the approved request, which holds the ID of the new contact.
+/// * `Err(ApiError)` if the user is not an admin, the request is not pending or there is a database error.
+pub async fn approve_contact_request(
+    principal: Principal,
+    id: web::Path<i32>,
+    rules: web::Data<CompanyRules>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let id = id.into_inner();
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+
+    // The request transaction holds the write lock, so a request cannot be approved twice
+    let request = pending_request(conn, id)?;
+    let mut contact = NewContact {
+        first_name: request.first_name,
+        last_name: request.last_name,
+        email: request.email,
+        phone_number: request.phone_number,
+        company_id: None,
+        status: None,
+        source: Some(CONTACT_REQUEST_SOURCE.to_string()),
+        owner: None,
+        uuid: Some(new_uuid()),
+        external_id: None,
+    };
+    normalize_contact(
+        &FeatureFlags::load(conn, principal.workspace())?,
+        &mut contact,
+    );
+    apply_rules(conn, &rules, &mut contact)?;
+    let contact_id = diesel::insert_into(contacts::table)
+        .values(&contact)
+        .returning(contacts::id)
+        .get_result::<i32>(conn)?;
+
+    let approved = diesel::update(contact_requests::table.find(id))
+        .set((
+            contact_requests::status.eq("approved"),
+            contact_requests::decided_at.eq(unix_now()),
+            contact_requests::decided_by.eq(principal.id()),
+            contact_requests::contact_id.eq(contact_id),
+        ))
+        .get_result::<ContactRequest>(conn)?;
+
+    Ok(HttpResponse::Ok().json(approved))
+}
+
+/// Handles rejecting a contact request.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// The request is kept, so the decision can be looked up later.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and record the reviewer.
+/// * `id` - The ID of the request, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the rejected request.
+/// * `Err(ApiError)` if the user is not an admin, the request is not pending or there is a database error.
+pub async fn reject_contact_request(
+    principal: Principal,
+    id: web::Path<i32>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let id = id.into_inner();
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+
+    pending_request(conn, id)?;
+    let rejected = diesel::update(contact_requests::table.find(id))
+        .set((
+            contact_requests::status.eq("rejected"),
+            contact_requests::decided_at.eq(unix_now()),
+            contact_requests::decided_by.eq(principal.id()),
+        ))
+        .get_result::<ContactRequest>(conn)?;
+
+    Ok(HttpResponse::Ok().json(rejected))
+}
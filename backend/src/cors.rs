@@ -0,0 +1,294 @@
+// backend/src/cors.rs
+// This file answers CORS preflights and adds CORS headers, with a policy per route group read from the environment.
+// It exists so public routes can be called from any site while admin routes only answer the intranet, and browsers can read our headers.
+// RELEVANT FILES: backend/src/main.rs, backend/src/fallback.rs, backend/.env.example
+
+use crate::error::ApiError;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{
+    HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{Error as ActixWebError, HttpResponse};
+use std::rc::Rc;
+
+/// The origins of the default policy when `CORS_ORIGINS` is not set: the local frontends.
+const DEFAULT_ORIGINS: &str = "http://localhost:3000,http://localhost:5173";
+
+/// How long browsers may cache a preflight when not configured, in seconds.
+const DEFAULT_MAX_AGE: u64 = 3600;
+
+/// The response headers browsers may read when `CORS_EXPOSE_HEADERS` is not set: pagination links,
+/// rate limits, versions and deprecations, the language of errors and where created resources are.
+const DEFAULT_EXPOSE_HEADERS: &str =
+    "Link, Retry-After, API-Version, Deprecation, Sunset, Warning, Content-Language, Location, Content-Disposition";
+
+/// The methods browsers may use.
+const METHODS: &str = "GET, HEAD, POST, PUT, DELETE";
+
+/// The request headers browsers may send: what the API reads, plus the token of the public contact form.
+const HEADERS: [&str; 6] = [
+    "authorization",
+    "accept",
+    "accept-language",
+    "content-type",
+    "field-case",
+    "x-contact-request-token",
+];
+
+/// The route groups with a policy of their own, as the prefix of their settings and their path
+/// under `/api` or `/api/v1`. Other paths get the default policy.
+const GROUPS: [(&str, &str); 2] = [("CORS_PUBLIC", "/public/"), ("CORS_ADMIN", "/admin/")];
+
+/// Which sites may call a route group.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AllowedOrigins {
+    /// Every site, answered with `Access-Control-Allow-Origin: *`.
+    Any,
+    /// Only these origins, e.g. `https://intranet.example.com`.
+    List(Vec<String>),
+}
+
+impl AllowedOrigins {
+    /// Reads a comma-separated list of origins, where `*` stands for every site.
+    fn parse(value: &str) -> Self {
+        let origins: Vec<String> = value
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        if origins.iter().any(|origin| origin == "*") {
+            AllowedOrigins::Any
+        } else {
+            AllowedOrigins::List(origins)
+        }
+    }
+
+    /// Tells whether an origin may call the routes.
+    fn allows(&self, origin: &str) -> bool {
+        match self {
+            AllowedOrigins::Any => true,
+            AllowedOrigins::List(origins) => origins.iter().any(|allowed| allowed == origin),
+        }
+    }
+}
+
+/// The CORS policy of a route group.
+#[derive(Clone, Debug)]
+pub struct CorsPolicy {
+    /// Which sites may call the routes.
+    pub origins: AllowedOrigins,
+    /// How long browsers may cache a preflight, in seconds.
+    pub max_age: u64,
+}
+
+/// The CORS policies of the server, read once at startup.
+#[derive(Clone, Debug)]
+pub struct CorsConfig {
+    /// The policy of each route group in `GROUPS`, in the same order.
+    groups: Vec<CorsPolicy>,
+    /// The policy of every other route.
+    default: CorsPolicy,
+    /// The value of `Access-Control-Expose-Headers`.
+    expose_headers: String,
+}
+
+impl CorsConfig {
+    /// Reads the policies from the environment.
+    ///
+    /// The default policy comes from `CORS_ORIGINS` and `CORS_MAX_AGE`. Public routes take
+    /// `CORS_PUBLIC_ORIGINS` (every site by default) and admin routes `CORS_ADMIN_ORIGINS`
+    /// (the default origins by default), each with a `_MAX_AGE` of their own that falls back to
+    /// `CORS_MAX_AGE`. `CORS_EXPOSE_HEADERS` applies to every route.
+    ///
+    /// # Returns
+    ///
+    /// * The `CorsConfig`.
+    pub fn from_env() -> Self {
+        let setting = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let max_age = |name: &str| setting(name).and_then(|v| v.trim().parse().ok());
+
+        let default_origins = setting("CORS_ORIGINS").unwrap_or_else(|| DEFAULT_ORIGINS.into());
+        let default = CorsPolicy {
+            origins: AllowedOrigins::parse(&default_origins),
+            max_age: max_age("CORS_MAX_AGE").unwrap_or(DEFAULT_MAX_AGE),
+        };
+        let groups = GROUPS
+            .iter()
+            .map(|(prefix, _)| {
+                let fallback = if *prefix == "CORS_PUBLIC" {
+                    "*"
+                } else {
+                    default_origins.as_str()
+                };
+                CorsPolicy {
+                    origins: AllowedOrigins::parse(
+                        &setting(&format!("{}_ORIGINS", prefix))
+                            .unwrap_or_else(|| fallback.to_string()),
+                    ),
+                    max_age: max_age(&format!("{}_MAX_AGE", prefix)).unwrap_or(default.max_age),
+                }
+            })
+            .collect();
+        let expose_headers =
+            setting("CORS_EXPOSE_HEADERS").unwrap_or_else(|| DEFAULT_EXPOSE_HEADERS.to_string());
+
+        Self {
+            groups,
+            default,
+            expose_headers,
+        }
+    }
+
+    /// Finds the policy of a path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The decoded path of the request, e.g. `/api/v1/admin/maintenance`. Take it from
+    ///   `Path::as_str` like the network check of the admin routes, so `/api/v1/%61dmin/...` is
+    ///   an admin route here too.
+    ///
+    /// # Returns
+    ///
+    /// * The policy of the route group the path is in, or the default policy.
+    pub fn policy(&self, path: &str) -> &CorsPolicy {
+        let Some(rest) = path
+            .strip_prefix("/api/v1")
+            .or_else(|| path.strip_prefix("/api"))
+        else {
+            return &self.default;
+        };
+        GROUPS
+            .iter()
+            .zip(&self.groups)
+            .find(|((_, group_path), _)| rest.starts_with(group_path))
+            .map(|(_, policy)| policy)
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Tells whether the request headers of a preflight are all allowed.
+fn headers_allowed(requested: &str) -> bool {
+    requested
+        .split(',')
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .all(|name| HEADERS.contains(&name.as_str()))
+}
+
+/// Adds the headers that tell the browser which origin may read the response.
+fn allow_origin(res: &mut HttpResponse<BoxBody>, policy: &CorsPolicy, origin: &HeaderValue) {
+    let headers = res.headers_mut();
+    match policy.origins {
+        AllowedOrigins::Any => {
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+        }
+        AllowedOrigins::List(_) => {
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+            // Caches must not hand the answer for one origin to another
+            headers.append(VARY, HeaderValue::from_static("origin"));
+        }
+    }
+}
+
+/// Adds the headers of a response to a cross-origin request: who may read it, and which of its headers.
+fn add_response_headers(
+    res: &mut HttpResponse<BoxBody>,
+    config: &CorsConfig,
+    policy: &CorsPolicy,
+    origin: &HeaderValue,
+) {
+    allow_origin(res, policy, origin);
+    if let Ok(expose) = HeaderValue::from_str(&config.expose_headers) {
+        res.headers_mut()
+            .insert(ACCESS_CONTROL_EXPOSE_HEADERS, expose);
+    }
+}
+
+/// Middleware that applies the CORS policy of the route group of each request.
+///
+/// Requests without an `Origin` header are passed on as they are. A preflight is answered here,
+/// with the allowed methods and headers and how long to cache them. Other requests are passed on,
+/// and the response says which origin may read it and which headers it may read.
+///
+/// # Arguments
+///
+/// * `config` - The CORS policies, see `CorsConfig::from_env`.
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The preflight answer, or the response of the next service with CORS headers.
+/// * `Err(ApiError::Forbidden)` if the origin may not call the route, or a preflight asks for a method or header that is not allowed.
+pub async fn apply_cors(
+    config: Rc<CorsConfig>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, ActixWebError> {
+    let Some(origin) = req.headers().get(ORIGIN).cloned() else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    let policy = config.policy(req.match_info().as_str()).clone();
+    let origin_text = origin.to_str().unwrap_or_default();
+    if !policy.origins.allows(origin_text) {
+        return Err(ApiError::Forbidden(format!(
+            "The origin '{}' may not call this route",
+            origin_text
+        ))
+        .into());
+    }
+
+    let requested_method = req.headers().get(ACCESS_CONTROL_REQUEST_METHOD);
+    if req.method() == Method::OPTIONS
+        && let Some(requested_method) = requested_method
+    {
+        let requested_method = requested_method.to_str().unwrap_or_default();
+        let requested_headers = req
+            .headers()
+            .get(ACCESS_CONTROL_REQUEST_HEADERS)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !METHODS.split(", ").any(|method| method == requested_method)
+            || !headers_allowed(requested_headers)
+        {
+            return Err(ApiError::Forbidden(format!(
+                "Cross-origin requests may only use the methods {} and the headers {}",
+                METHODS,
+                HEADERS.join(", ")
+            ))
+            .into());
+        }
+        let mut res = HttpResponse::NoContent()
+            .insert_header((ACCESS_CONTROL_ALLOW_METHODS, METHODS))
+            .insert_header((ACCESS_CONTROL_ALLOW_HEADERS, HEADERS.join(", ")))
+            .insert_header((ACCESS_CONTROL_MAX_AGE, policy.max_age.to_string()))
+            .finish();
+        allow_origin(&mut res, &policy, &origin);
+        res.headers_mut().append(
+            VARY,
+            HeaderValue::from_static(
+                "access-control-request-method, access-control-request-headers",
+            ),
+        );
+        return Ok(req.into_response(res));
+    }
+
+    // Errors of other middleware need the headers too, or the browser hides them from the client
+    let res = match next.call(req).await {
+        Ok(res) => res.map_into_boxed_body(),
+        Err(e) => {
+            let mut res = e.error_response();
+            add_response_headers(&mut res, &config, &policy, &origin);
+            return Err(InternalError::from_response(e, res).into());
+        }
+    };
+    let (http_req, mut res) = res.into_parts();
+    add_response_headers(&mut res, &config, &policy, &origin);
+    Ok(ServiceResponse::new(http_req, res))
+}
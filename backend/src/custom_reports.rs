@@ -0,0 +1,483 @@
+// backend/src/custom_reports.rs
+// This file lets users define their own aggregate reports from allowed fields, saves them and runs them on demand.
+// It exists so new questions about the address book do not each need a canned report, without letting users send SQL.
+// RELEVANT FILES: backend/src/reports.rs, backend/contacts-types/src/schema.rs, backend/src/feature_flags.rs
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::error::ApiError;
+use crate::field_permissions::FieldRules;
+use crate::reports::{respond, Report};
+use crate::schema::saved_reports;
+use crate::transaction::Transaction;
+use crate::{establish_connection, unix_now};
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text};
+use diesel::sqlite::Sqlite;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The fields a report can group and filter by, with the SQL expression of each.
+///
+/// Only these expressions ever reach the SQL of a report; user values are bound as parameters.
+const DIMENSIONS: [(&str, &str); 8] = [
+    ("status", "status"),
+    ("source", "source"),
+    ("owner", "owner"),
+    ("company_id", "company_id"),
+    ("email_status", "email_status"),
+    ("email_opt_in", "email_opt_in"),
+    ("sms_opt_in", "sms_opt_in"),
+    ("archived", "archived_at IS NOT NULL"),
+];
+
+/// The numbers a report can compute per group, with the SQL expression of each.
+const MEASURES: [(&str, &str); 5] = [
+    ("contacts", "COUNT(*)"),
+    ("with_company", "COUNT(company_id)"),
+    ("email_opt_ins", "COALESCE(SUM(email_opt_in), 0)"),
+    ("sms_opt_ins", "COALESCE(SUM(sms_opt_in), 0)"),
+    ("valid_emails", "COALESCE(SUM(email_status = 'valid'), 0)"),
+];
+
+/// The contact field a dimension or measure reads, where it is not the field of the same name.
+///
+/// A report on a field reveals its values, so the fields hidden from the caller cannot be reported on.
+const FIELDS_READ: [(&str, &str); 3] = [
+    ("archived", "archived_at"),
+    ("with_company", "company_id"),
+    ("valid_emails", "email_status"),
+];
+
+/// The ways a filter can compare a field.
+const OPERATORS: [&str; 4] = ["eq", "ne", "is_null", "not_null"];
+
+/// The most fields a report can group by.
+const MAX_DIMENSIONS: usize = 3;
+
+/// The most filters a report can have.
+const MAX_FILTERS: usize = 10;
+
+/// The most rows a report returns.
+const MAX_ROWS: usize = 1000;
+
+/// One condition on the contacts a report counts.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ReportFilter {
+    /// The field, one of the dimensions, e.g. `status`.
+    pub field: String,
+    /// How to compare: `eq`, `ne`, `is_null` or `not_null`.
+    pub op: String,
+    /// The value to compare with, for `eq` and `ne`: a string, a number or a boolean.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub value: Value,
+}
+
+/// What a report computes.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ReportDefinition {
+    /// The fields to group by, e.g. `["status", "source"]`. Without any, the report has one row.
+    #[serde(default)]
+    pub dimensions: Vec<String>,
+    /// The numbers to compute per group, e.g. `["contacts"]`. The rows are sorted by the first one.
+    pub measures: Vec<String>,
+    /// The conditions every counted contact must meet.
+    #[serde(default)]
+    pub filters: Vec<ReportFilter>,
+}
+
+/// The request body for saving a report.
+#[derive(Deserialize)]
+pub struct NewSavedReport {
+    /// The name to show in lists of reports.
+    pub name: String,
+    /// What the report computes.
+    pub definition: ReportDefinition,
+}
+
+/// A saved report, as stored.
+#[derive(Queryable)]
+#[diesel(table_name = saved_reports)]
+struct StoredReport {
+    id: i32,
+    name: String,
+    definition: String,
+    created_by: String,
+    created_at: i64,
+}
+
+/// A saved report, as shown to users.
+#[derive(Serialize)]
+pub struct SavedReport {
+    /// The ID of the report.
+    pub id: i32,
+    /// The name of the report.
+    pub name: String,
+    /// What the report computes.
+    pub definition: ReportDefinition,
+    /// Who saved the report. They and admins can delete it.
+    pub created_by: String,
+    /// When the report was saved, as a Unix timestamp in seconds.
+    pub created_at: i64,
+}
+
+impl TryFrom<StoredReport> for SavedReport {
+    type Error = ApiError;
+
+    fn try_from(stored: StoredReport) -> Result<Self, ApiError> {
+        Ok(Self {
+            id: stored.id,
+            name: stored.name,
+            definition: serde_json::from_str(&stored.definition)
+                .map_err(|e| ApiError::BadRequest(e.to_string()))?,
+            created_by: stored.created_by,
+            created_at: stored.created_at,
+        })
+    }
+}
+
+/// A value bound to a parameter of the report SQL.
+enum Bind {
+    Text(String),
+    Integer(i64),
+}
+
+/// A report definition turned into SQL.
+struct CompiledReport {
+    /// The query. It selects the values of the dimensions and of the measures as two JSON arrays.
+    sql: String,
+    /// The values of the `?` parameters, in order.
+    binds: Vec<Bind>,
+    /// The names of the dimensions, then of the measures.
+    columns: Vec<&'static str>,
+}
+
+/// One row of a report, as SQLite returns it.
+#[derive(QueryableByName)]
+struct ReportRow {
+    /// The values of the dimensions, as a JSON array.
+    #[diesel(sql_type = Text)]
+    dimension_values: String,
+    /// The values of the measures, as a JSON array.
+    #[diesel(sql_type = Text)]
+    measure_values: String,
+}
+
+/// Looks up a name in a list of allowed names and their SQL expressions.
+fn allowed(
+    list: &[(&'static str, &'static str)],
+    name: &str,
+    kind: &str,
+) -> Result<(&'static str, &'static str), ApiError> {
+    list.iter()
+        .find(|(allowed, _)| *allowed == name)
+        .copied()
+        .ok_or_else(|| {
+            let names: Vec<&str> = list.iter().map(|(name, _)| *name).collect();
+            ApiError::BadRequest(format!("{} must be one of: {}", kind, names.join(", ")))
+        })
+}
+
+/// Checks that the caller may read the field behind a dimension or measure.
+fn check_readable(field_rules: &FieldRules, name: &str) -> Result<(), ApiError> {
+    let field = FIELDS_READ
+        .iter()
+        .find(|(read_by, _)| *read_by == name)
+        .map_or(name, |(_, field)| *field);
+    field_rules.check_readable(field)
+}
+
+/// Turns a report definition into SQL, checking every name against the allowed fields.
+///
+/// # Arguments
+///
+/// * `definition` - The report definition from the user.
+/// * `field_rules` - The fields the caller may not read, which the report may not use.
+///
+/// # Returns
+///
+/// * `Ok(CompiledReport)` with the query and its parameters.
+/// * `Err(ApiError::BadRequest)` if the definition names a field, measure or operator that is not allowed,
+///   has no measure, or has too many dimensions or filters.
+/// * `Err(ApiError::Forbidden)` if it uses a field that is hidden from the caller.
+fn compile(
+    definition: &ReportDefinition,
+    field_rules: &FieldRules,
+) -> Result<CompiledReport, ApiError> {
+    if definition.measures.is_empty() {
+        return Err(ApiError::BadRequest(
+            "A report needs at least one measure".to_string(),
+        ));
+    }
+    if definition.dimensions.len() > MAX_DIMENSIONS || definition.filters.len() > MAX_FILTERS {
+        return Err(ApiError::BadRequest(format!(
+            "A report can group by at most {} fields and have at most {} filters",
+            MAX_DIMENSIONS, MAX_FILTERS
+        )));
+    }
+
+    let mut columns = Vec::new();
+    let mut dimensions = Vec::new();
+    for name in &definition.dimensions {
+        let (name, expression) = allowed(&DIMENSIONS, name, "Dimension")?;
+        check_readable(field_rules, name)?;
+        columns.push(name);
+        dimensions.push(expression);
+    }
+    let mut measures = Vec::new();
+    for name in &definition.measures {
+        let (name, expression) = allowed(&MEASURES, name, "Measure")?;
+        check_readable(field_rules, name)?;
+        columns.push(name);
+        measures.push(expression);
+    }
+
+    let mut conditions = Vec::new();
+    let mut binds = Vec::new();
+    for filter in &definition.filters {
+        let (name, expression) = allowed(&DIMENSIONS, &filter.field, "Dimension")?;
+        check_readable(field_rules, name)?;
+        let condition = match filter.op.as_str() {
+            "is_null" => format!("{} IS NULL", expression),
+            "not_null" => format!("{} IS NOT NULL", expression),
+            "eq" | "ne" => {
+                binds.push(match &filter.value {
+                    Value::String(text) => Bind::Text(text.clone()),
+                    Value::Bool(flag) => Bind::Integer(i64::from(*flag)),
+                    Value::Number(number) if number.is_i64() => {
+                        Bind::Integer(number.as_i64().unwrap_or_default())
+                    }
+                    _ => {
+                        return Err(ApiError::BadRequest(format!(
+                            "The filter on '{}' needs a string, integer or boolean value",
+                            filter.field
+                        )));
+                    }
+                });
+                // `IS NOT` also keeps the contacts where the field is empty
+                let operator = if filter.op == "eq" { "=" } else { "IS NOT" };
+                format!("{} {} ?", expression, operator)
+            }
+            _ => {
+                return Err(ApiError::BadRequest(format!(
+                    "Operator must be one of: {}",
+                    OPERATORS.join(", ")
+                )));
+            }
+        };
+        conditions.push(condition);
+    }
+
+    let mut sql = format!(
+        "SELECT json_array({}) AS dimension_values, json_array({}) AS measure_values FROM contacts",
+        dimensions.join(", "),
+        measures.join(", ")
+    );
+    if !conditions.is_empty() {
+        sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+    }
+    if !dimensions.is_empty() {
+        sql.push_str(&format!(" GROUP BY {}", dimensions.join(", ")));
+    }
+    sql.push_str(&format!(
+        " ORDER BY {} DESC LIMIT {}",
+        measures[0], MAX_ROWS
+    ));
+
+    Ok(CompiledReport {
+        sql,
+        binds,
+        columns,
+    })
+}
+
+/// Runs a report definition.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection.
+/// * `definition` - The report definition.
+/// * `field_rules` - The fields the caller may not read, which the report may not use.
+///
+/// # Returns
+///
+/// * `Ok(Report)` with a column per dimension and measure, and a row per group.
+/// * `Err(ApiError)` if the definition is not allowed or there is a database error.
+fn run(
+    conn: &mut SqliteConnection,
+    definition: &ReportDefinition,
+    field_rules: &FieldRules,
+) -> Result<Report, ApiError> {
+    let compiled = compile(definition, field_rules)?;
+    let mut query = diesel::sql_query(compiled.sql).into_boxed::<Sqlite>();
+    for bind in compiled.binds {
+        query = match bind {
+            Bind::Text(value) => query.bind::<Text, _>(value),
+            Bind::Integer(value) => query.bind::<BigInt, _>(value),
+        };
+    }
+    let rows = query
+        .load::<ReportRow>(conn)?
+        .into_iter()
+        .map(|row| {
+            let parse = |json: &str| serde_json::from_str::<Vec<Value>>(json).unwrap_or_default();
+            let mut values = parse(&row.dimension_values);
+            values.extend(parse(&row.measure_values));
+            values
+        })
+        .collect();
+    Ok(Report {
+        columns: compiled.columns,
+        rows,
+    })
+}
+
+/// Reads a saved report.
+fn find_report(conn: &mut SqliteConnection, id: i32) -> Result<SavedReport, ApiError> {
+    saved_reports::table
+        .find(id)
+        .first::<StoredReport>(conn)?
+        .try_into()
+}
+
+/// Handles saving a report definition.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// The definition is checked against the allowed fields, and the fields hidden from the caller, before it is saved.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, recorded as the creator.
+/// * `field_rules` - The fields the caller may not read, which the report may not use.
+/// * `report` - The name and definition of the report.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the saved report.
+/// * `Err(ApiError)` if the name is empty, the definition is not allowed or there is a database error.
+pub async fn create_saved_report(
+    principal: Principal,
+    field_rules: FieldRules,
+    report: web::Json<NewSavedReport>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let report = report.into_inner();
+    if report.name.trim().is_empty() {
+        return Err(ApiError::BadRequest("The report needs a name".to_string()));
+    }
+    compile(&report.definition, &field_rules)?;
+    let definition = serde_json::to_string(&report.definition)
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    let mut conn = transaction.conn()?;
+    let stored = diesel::insert_into(saved_reports::table)
+        .values((
+            saved_reports::name.eq(report.name.trim()),
+            saved_reports::definition.eq(definition),
+            saved_reports::created_by.eq(principal.id()),
+            saved_reports::created_at.eq(unix_now()),
+        ))
+        .get_result::<StoredReport>(&mut *conn)?;
+
+    Ok(HttpResponse::Ok().json(SavedReport::try_from(stored)?))
+}
+
+/// Handles listing the saved reports.
+///
+/// This endpoint is protected and requires a valid JWT. Every user sees every saved report.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a JSON array of saved reports, sorted by name.
+/// * `Err(ApiError)` if there is a database error.
+pub async fn read_saved_reports(_principal: Principal) -> Result<HttpResponse, ApiError> {
+    let mut conn = establish_connection()?;
+    let reports = saved_reports::table
+        .order((saved_reports::name.asc(), saved_reports::id.asc()))
+        .load::<StoredReport>(&mut conn)?
+        .into_iter()
+        .map(SavedReport::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(HttpResponse::Ok().json(reports))
+}
+
+/// Handles reading a saved report's definition.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `id` - The ID of the report, from the URL path.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the saved report.
+/// * `Err(ApiError)` if the report is not found or there is a database error.
+pub async fn read_saved_report(
+    _principal: Principal,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = establish_connection()?;
+    Ok(HttpResponse::Ok().json(find_report(&mut conn, id.into_inner())?))
+}
+
+/// Handles running a saved report.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// The report is computed from the current data every time; at most 1000 rows are returned.
+/// A report that uses a field hidden from the caller is not run, also when someone else saved it.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the caller may not read, which the report may not use.
+/// * `req` - The HTTP request. `Accept: text/csv` asks for CSV.
+/// * `id` - The ID of the report, from the URL path.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a row per group and a column per dimension and measure, as JSON or CSV.
+/// * `Err(ApiError)` if the report is not found, uses a hidden field or there is a database error.
+pub async fn run_saved_report(
+    field_rules: FieldRules,
+    req: HttpRequest,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = establish_connection()?;
+    let saved = find_report(&mut conn, id.into_inner())?;
+    let report = run(&mut conn, &saved.definition, &field_rules)?;
+    Ok(respond(&req, &format!("report-{}", saved.id), &report))
+}
+
+/// Handles deleting a saved report.
+///
+/// This endpoint is protected and requires a valid JWT. Only the creator of the report and admins can delete it.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check they may delete the report.
+/// * `id` - The ID of the report, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with no content.
+/// * `Err(ApiError)` if the report is not found, the caller may not delete it or there is a database error.
+pub async fn delete_saved_report(
+    principal: Principal,
+    id: web::Path<i32>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = transaction.conn()?;
+    let saved = find_report(&mut conn, id.into_inner())?;
+    if saved.created_by != principal.id() {
+        principal.require_role(ADMIN_ROLE)?;
+    }
+    diesel::delete(saved_reports::table.find(saved.id)).execute(&mut *conn)?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,507 @@
+// backend/src/debug_capture.rs
+// This file records the requests and responses of one user or route for a limited time, with credentials removed.
+// It exists so support can see what a client sent and what it got back when a user reports that "it just says error".
+// RELEVANT FILES: backend/src/lib.rs, backend/src/log_level.rs, backend/src/query_metrics.rs
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::error::ApiError;
+use crate::unix_now;
+use actix_web::body::{to_bytes, BodySize, BoxBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, CONTENT_LENGTH};
+use actix_web::middleware::Next;
+use actix_web::{web, Error as ActixWebError, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// How many exchanges are kept when `DEBUG_CAPTURE_SIZE` is not set.
+const DEFAULT_CAPTURE_SIZE: usize = 200;
+
+/// How long a capture runs when the admin does not say, in seconds.
+const DEFAULT_DURATION_SECS: u64 = 600;
+
+/// The longest a capture can run when `DEBUG_CAPTURE_MAX_SECS` is not set, in seconds.
+const DEFAULT_MAX_DURATION_SECS: u64 = 3600;
+
+/// How much of a body is kept, in bytes. The rest is cut off.
+const MAX_BODY_BYTES: usize = 16 * 1024;
+
+/// Headers, query parameters and JSON fields whose name contains one of these hold credentials, and their values are never kept.
+const SENSITIVE: [&str; 7] = [
+    "authorization",
+    "cookie",
+    "token",
+    "secret",
+    "password",
+    "key",
+    "signature",
+];
+
+/// The largest request body actix reads without a `PayloadConfig`, in bytes. Larger ones are not captured.
+const MAX_REQUEST_BYTES: usize = 262_144;
+
+/// What is kept in place of a credential.
+const REDACTED: &str = "[redacted]";
+
+/// The path of the endpoints that manage captures, which are never captured themselves.
+const CAPTURE_PATH: &str = "/admin/debug-capture";
+
+/// The path of public share links, whose last segment is the signed token.
+const SHARE_PATH: &str = "/public/share/";
+
+/// A capture that is running.
+#[derive(Clone, Serialize)]
+pub struct CaptureSession {
+    /// Only requests of this user are captured, if set.
+    pub user_id: Option<String>,
+    /// Only requests whose path starts with this are captured, if set, e.g. `/api/contacts`.
+    pub route: Option<String>,
+    /// The admin who started the capture.
+    pub started_by: String,
+    /// When the capture started, as a Unix timestamp.
+    pub started_at: i64,
+    /// When the capture stops by itself, as a Unix timestamp.
+    pub expires_at: i64,
+}
+
+/// One request and the response to it, with credentials removed.
+#[derive(Clone, Serialize)]
+pub struct CapturedExchange {
+    /// When the request came in, as a Unix timestamp.
+    pub at: i64,
+    /// The user who sent the request, if the capture is for a user.
+    pub user_id: Option<String>,
+    /// The HTTP method.
+    pub method: String,
+    /// The path, e.g. `/api/contacts/42`.
+    pub path: String,
+    /// The query parameters.
+    pub query: BTreeMap<String, String>,
+    /// The request headers.
+    pub request_headers: BTreeMap<String, String>,
+    /// The request body: JSON as JSON, other text as a string, `null` if empty.
+    pub request_body: Value,
+    /// The response status, e.g. `422`.
+    pub status: u16,
+    /// The response headers.
+    pub response_headers: BTreeMap<String, String>,
+    /// The response body, as for the request.
+    pub response_body: Value,
+    /// How long the API took to answer, in milliseconds.
+    pub duration_ms: u128,
+}
+
+/// The request body for starting a capture. At least one of `user_id` and `route` must be set.
+#[derive(Deserialize)]
+pub struct StartCapture {
+    /// Only capture requests of this user, the `sub` claim of their token.
+    pub user_id: Option<String>,
+    /// Only capture requests whose path starts with this, e.g. `/api/contacts`.
+    pub route: Option<String>,
+    /// How long the capture runs, in seconds. Defaults to 600.
+    pub duration_secs: Option<u64>,
+}
+
+/// The state of the capture, as shown to admins.
+#[derive(Serialize)]
+pub struct CaptureReport {
+    /// The running capture, or `null` when none is running.
+    pub capture: Option<CaptureSession>,
+    /// The captured exchanges, oldest first.
+    pub exchanges: Vec<CapturedExchange>,
+}
+
+/// Holds the capture and what it recorded, shared by all workers as app data.
+pub struct DebugCapture {
+    session: Mutex<Option<CaptureSession>>,
+    exchanges: Mutex<VecDeque<CapturedExchange>>,
+    /// How many exchanges are kept. The oldest are dropped first.
+    capacity: usize,
+    /// The longest a capture can run, in seconds.
+    max_duration_secs: u64,
+}
+
+impl DebugCapture {
+    /// Reads the settings from the `DEBUG_CAPTURE_SIZE` and `DEBUG_CAPTURE_MAX_SECS` environment variables.
+    ///
+    /// # Returns
+    ///
+    /// * A `DebugCapture` with no capture running.
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("DEBUG_CAPTURE_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CAPTURE_SIZE);
+        let max_duration_secs = std::env::var("DEBUG_CAPTURE_MAX_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_DURATION_SECS);
+        Self {
+            session: Mutex::new(None),
+            exchanges: Mutex::new(VecDeque::new()),
+            capacity,
+            max_duration_secs,
+        }
+    }
+
+    /// Returns the running capture, or `None` when none is running or it has expired.
+    fn session(&self) -> Option<CaptureSession> {
+        self.session
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .filter(|session| session.expires_at > unix_now())
+    }
+
+    /// Adds an exchange, dropping the oldest when the buffer is full.
+    fn push(&self, exchange: CapturedExchange) {
+        let mut exchanges = self.exchanges.lock().unwrap_or_else(|e| e.into_inner());
+        while exchanges.len() >= self.capacity.max(1) {
+            exchanges.pop_front();
+        }
+        exchanges.push_back(exchange);
+    }
+
+    /// Returns the running capture and everything recorded.
+    fn report(&self) -> CaptureReport {
+        CaptureReport {
+            capture: self.session(),
+            exchanges: self
+                .exchanges
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Starts a capture in place of the running one, and drops what was recorded before.
+    fn start(&self, session: CaptureSession) {
+        self.exchanges
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        *self.session.lock().unwrap_or_else(|e| e.into_inner()) = Some(session);
+    }
+
+    /// Stops the capture and drops what was recorded.
+    fn stop(&self) {
+        *self.session.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.exchanges
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+/// Tells whether a name, e.g. of a header, stands for a credential.
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SENSITIVE.iter().any(|word| name.contains(word))
+}
+
+/// Copies headers, with the values of credentials replaced.
+fn sanitize_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_sensitive(name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).to_string()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// Reads a query string, with the values of credentials replaced.
+fn sanitize_query(query: &str) -> BTreeMap<String, String> {
+    web::Query::<Vec<(String, String)>>::from_query(query)
+        .map(|q| q.into_inner())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| {
+            let value = if is_sensitive(&name) {
+                REDACTED.to_string()
+            } else {
+                value
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+/// Replaces the signed token of a share link path, which works like a password.
+fn sanitize_path(path: &str) -> String {
+    match path.find(SHARE_PATH) {
+        Some(i) => format!("{}{}", &path[..i + SHARE_PATH.len()], REDACTED),
+        None => path.to_string(),
+    }
+}
+
+/// Replaces the values of every field that holds a credential, also in nested objects and lists.
+fn sanitize_json(value: Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, item)| {
+                    let item = if is_sensitive(&key) {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        sanitize_json(item)
+                    };
+                    (key, item)
+                })
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(sanitize_json).collect()),
+        other => other,
+    }
+}
+
+/// Turns a body into something that can be shown: JSON with its credentials replaced, or text cut to `MAX_BODY_BYTES`.
+fn sanitize_body(bytes: &[u8]) -> Value {
+    if bytes.is_empty() {
+        return Value::Null;
+    }
+    if bytes.len() <= MAX_BODY_BYTES
+        && let Ok(value) = serde_json::from_slice::<Value>(bytes)
+    {
+        return sanitize_json(value);
+    }
+    // Cut text is not valid JSON, so its credentials cannot be found; it is kept only if short enough to read in full
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_BODY_BYTES)]).to_string();
+    if bytes.len() > MAX_BODY_BYTES {
+        Value::String(format!(
+            "{}... [{} bytes cut]",
+            text,
+            bytes.len() - MAX_BODY_BYTES
+        ))
+    } else {
+        Value::String(text)
+    }
+}
+
+/// Middleware that records the request and response when a capture is running and the request matches it.
+///
+/// Requests are matched by path first, then by user, so the token is only read when a capture
+/// for a user is running. Requests without a valid token never match a capture for a user.
+/// The endpoints that manage captures are never captured.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service, unchanged.
+pub async fn capture_exchanges(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, ActixWebError> {
+    let Some(capture) = req.app_data::<web::Data<DebugCapture>>().cloned() else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    let Some(session) = capture.session() else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+    let path = req.path().to_string();
+    if path.ends_with(CAPTURE_PATH)
+        || session
+            .route
+            .as_deref()
+            .is_some_and(|route| !path.starts_with(route))
+    {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+    let user_id = match &session.user_id {
+        Some(wanted) => match req.extract::<Principal>().await {
+            Ok(principal) if principal.id() == wanted => Some(wanted.clone()),
+            _ => return Ok(next.call(req).await?.map_into_boxed_body()),
+        },
+        None => None,
+    };
+
+    // Bodies larger than the default limit would fail to read here, so they are only noted
+    let too_large = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok())
+        .is_some_and(|length| length > MAX_REQUEST_BYTES);
+    let request_body = if too_large {
+        Value::String("[not captured: too large]".to_string())
+    } else {
+        let bytes = req.extract::<web::Bytes>().await?;
+        // The body was read from the stream, so the handler gets a copy
+        req.set_payload(Payload::from(bytes.clone()));
+        sanitize_body(&bytes)
+    };
+    let at = unix_now();
+    let method = req.method().to_string();
+    let query = sanitize_query(req.query_string());
+    let request_headers = sanitize_headers(req.headers());
+    let started = Instant::now();
+
+    let res = match next.call(req).await {
+        Ok(res) => res.map_into_boxed_body(),
+        Err(e) => {
+            // Errors from other middleware never become a response here, so the one they would become is recorded
+            let res = e.error_response();
+            let status = res.status().as_u16();
+            let response_headers = sanitize_headers(res.headers());
+            let bytes = to_bytes(res.into_body()).await.unwrap_or_default();
+            capture.push(CapturedExchange {
+                at,
+                user_id,
+                method,
+                path: sanitize_path(&path),
+                query,
+                request_headers,
+                request_body,
+                status,
+                response_headers,
+                response_body: sanitize_body(&bytes),
+                duration_ms: started.elapsed().as_millis(),
+            });
+            return Err(e);
+        }
+    };
+    let duration_ms = started.elapsed().as_millis();
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    // A streamed body, like `/contacts/stream`, can be far too large to hold, so it is passed on untouched
+    let (response_body, body) = if matches!(body.size(), BodySize::Stream) {
+        (Value::String("[not captured: streamed]".to_string()), body)
+    } else {
+        let bytes = to_bytes(body)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        (sanitize_body(&bytes), BoxBody::new(bytes))
+    };
+    capture.push(CapturedExchange {
+        at,
+        user_id,
+        method,
+        path: sanitize_path(&path),
+        query,
+        request_headers,
+        request_body,
+        status: res.status().as_u16(),
+        response_headers: sanitize_headers(res.headers()),
+        response_body,
+        duration_ms,
+    });
+    Ok(ServiceResponse::new(req, res.set_body(body)))
+}
+
+/// Handles starting a capture of the requests of a user or a route.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// A running capture is replaced, and what it recorded is dropped. The capture stops by itself
+/// after `duration_secs`, at most `DEBUG_CAPTURE_MAX_SECS`.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and log who started the capture.
+/// * `capture` - The capture shared by all workers.
+/// * `body` - Whose requests to capture, and for how long.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the capture that now runs.
+/// * `Err(ApiError)` if the user is not an admin, neither a user nor a route is given, or the duration is out of range.
+pub async fn start_debug_capture(
+    principal: Principal,
+    capture: web::Data<DebugCapture>,
+    body: web::Json<StartCapture>,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let body = body.into_inner();
+    let user_id = body.user_id.filter(|id| !id.trim().is_empty());
+    let route = body.route.filter(|route| !route.trim().is_empty());
+    // Capturing everyone would fill the buffer at once and keep far more personal data than needed
+    if user_id.is_none() && route.is_none() {
+        return Err(ApiError::BadRequest(
+            "A capture needs a user_id or a route".to_string(),
+        ));
+    }
+    let duration = body.duration_secs.unwrap_or(DEFAULT_DURATION_SECS);
+    if duration == 0 || duration > capture.max_duration_secs {
+        return Err(ApiError::BadRequest(format!(
+            "duration_secs must be between 1 and {}",
+            capture.max_duration_secs
+        )));
+    }
+
+    let started_at = unix_now();
+    let session = CaptureSession {
+        user_id,
+        route,
+        started_by: principal.id().to_string(),
+        started_at,
+        expires_at: started_at + duration as i64,
+    };
+    capture.start(session.clone());
+    log::warn!(
+        "Debug capture started by {} for user {} and route {}, for {}s",
+        principal.id(),
+        session.user_id.as_deref().unwrap_or("any"),
+        session.route.as_deref().unwrap_or("any"),
+        duration
+    );
+    Ok(HttpResponse::Ok().json(session))
+}
+
+/// Handles reading the running capture and what it recorded.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// What a capture recorded can still be read after it expires, until a new one starts or it is stopped.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+/// * `capture` - The capture shared by all workers.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the running capture and the exchanges, oldest first.
+/// * `Err(ApiError)` if the user is not an admin.
+pub async fn read_debug_capture(
+    principal: Principal,
+    capture: web::Data<DebugCapture>,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    Ok(HttpResponse::Ok().json(capture.report()))
+}
+
+/// Handles stopping the capture and dropping what it recorded.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and log who stopped the capture.
+/// * `capture` - The capture shared by all workers.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with no content.
+/// * `Err(ApiError)` if the user is not an admin.
+pub async fn stop_debug_capture(
+    principal: Principal,
+    capture: web::Data<DebugCapture>,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    capture.stop();
+    log::warn!("Debug capture stopped by {}", principal.id());
+    Ok(HttpResponse::NoContent().finish())
+}
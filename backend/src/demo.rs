@@ -0,0 +1,154 @@
+// backend/src/demo.rs
+// This file implements the read-only demo mode of the API.
+// It exists so we can host a public demo with synthetic contacts and no real data.
+// RELEVANT FILES: backend/src/main.rs, backend/src/handlers.rs, backend/src/error.rs, backend/src/fallback.rs
+
+use crate::error::ApiError;
+use crate::fallback;
+use crate::models::{Contact, CONTACT_STATUSES};
+use crate::quality;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::Error as ActixWebError;
+
+/// First names used to build the synthetic demo contacts.
+const FIRST_NAMES: [&str; 5] = ["Alice", "Bob", "Carla", "David", "Eva"];
+
+/// Last names used to build the synthetic demo contacts.
+const LAST_NAMES: [&str; 4] = ["Andersson", "Berg", "Carlsson", "Dahl"];
+
+/// Holds the demo mode setting, shared with the handlers as app data.
+#[derive(Clone, Copy, Default)]
+pub struct DemoMode {
+    /// Whether the API serves synthetic data, and rejects all mutations and reads of real data.
+    pub enabled: bool,
+}
+
+impl DemoMode {
+    /// Reads the demo mode setting from the `DEMO_MODE` environment variable.
+    ///
+    /// # Returns
+    ///
+    /// * A `DemoMode` that is enabled when `DEMO_MODE` is `true` or `1`.
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("DEMO_MODE")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        Self { enabled }
+    }
+}
+
+/// Builds the list of synthetic demo contacts.
+///
+/// The list is deterministic, so every visitor sees the same data.
+/// It is sorted by last name and first name, like the real list endpoint.
+///
+/// # Returns
+///
+/// * A vector of generated `Contact`s.
+pub fn demo_contacts() -> Vec<Contact> {
+    let mut contacts = Vec::new();
+    for last_name in LAST_NAMES {
+        for first_name in FIRST_NAMES {
+            let id = contacts.len() as i32 + 1;
+            let mut contact = Contact {
+                id,
+                first_name: first_name.to_string(),
+                last_name: last_name.to_string(),
+                // example.com is reserved, so these addresses can never reach a real person
+                email: format!(
+                    "{}.{}@example.com",
+                    first_name.to_lowercase(),
+                    last_name.to_lowercase()
+                ),
+                phone_number: format!("555-01{:02}", id),
+                company_id: None,
+                status: CONTACT_STATUSES[id as usize % CONTACT_STATUSES.len()].to_string(),
+                source: Some("demo".to_string()),
+                owner: None,
+                email_opt_in: false,
+                sms_opt_in: false,
+                consent_source: None,
+                consent_timestamp: None,
+                email_status: "unverified".to_string(),
+                email_verified_at: None,
+                uuid: Some(format!("00000000-0000-4000-8000-{:012}", id)),
+                external_id: None,
+                archived_at: None,
+                quality_score: 0,
+            };
+            contact.quality_score = quality::score(&contact);
+            contacts.push(contact);
+        }
+    }
+    contacts
+}
+
+/// The routes that serve synthetic contacts in demo mode, or no contact data at all, as (module, pattern).
+///
+/// Every other route reads the real database, so the demo does not serve it.
+const DEMO_ROUTES: [(&str, &str); 12] = [
+    ("handlers", "/contacts"),
+    ("handlers", "/contacts/count"),
+    ("handlers", "/contacts/sample"),
+    ("handlers", "/contacts/stats/by-status"),
+    ("handlers", "/contacts/{id}"),
+    ("ndjson", "/contacts/stream"),
+    ("deprecations", "/deprecations"),
+    ("i18n", "/locales"),
+    ("introspection", "/schema/contacts"),
+    ("maintenance", "/maintenance"),
+    ("meta", "/meta/countries"),
+    ("meta", "/meta/phone-prefixes"),
+];
+
+/// Returns whether the demo serves a read of this path.
+///
+/// A path can match several routes, e.g. `/contacts/autocomplete` matches `/contacts/{id}` too,
+/// so every route it matches must serve synthetic data. A path that matches no route is passed on,
+/// so it gets the usual 404.
+///
+/// # Arguments
+///
+/// * `method` - The method of the request, GET or HEAD.
+/// * `path` - The path of the request, with the scope.
+fn serves(method: &Method, path: &str) -> bool {
+    fallback::matching_routes(path)
+        .filter(|route| {
+            route.method == method.as_str() || (*method == Method::HEAD && route.method == "GET")
+        })
+        .all(|route| DEMO_ROUTES.contains(&(route.module, route.path)))
+}
+
+/// Middleware that keeps the demo to synthetic data, with a friendly 403 for everything else.
+///
+/// Mutations are rejected, and so are reads of routes that would return real data.
+/// OPTIONS is passed on, so CORS preflights work.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service for reads the demo serves.
+/// * `Err(ApiError::Forbidden)` for all other requests.
+pub async fn restrict(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    match *req.method() {
+        Method::OPTIONS => next.call(req).await,
+        Method::GET | Method::HEAD if serves(req.method(), req.path()) => next.call(req).await,
+        Method::GET | Method::HEAD => {
+            Err(ApiError::Forbidden("This is not part of the read-only demo.".to_string()).into())
+        }
+        _ => Err(ApiError::Forbidden(
+            "This is a read-only demo. Changes are not saved.".to_string(),
+        )
+        .into()),
+    }
+}
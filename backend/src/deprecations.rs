@@ -0,0 +1,215 @@
+// backend/src/deprecations.rs
+// This file keeps the registry of deprecated routes and fields and tells clients about them.
+// It exists so clients get a warning in the response headers long before anything is removed.
+// RELEVANT FILES: backend/src/main.rs, backend/src/versioning.rs, backend/src/models.rs
+
+use crate::auth::Principal;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, HttpDate};
+use actix_web::middleware::Next;
+use actix_web::{Error as ActixWebError, HttpResponse};
+use serde::Serialize;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The header that tells when a resource was deprecated (RFC 9745).
+const DEPRECATION_HEADER: HeaderName = HeaderName::from_static("deprecation");
+
+/// The header that tells when a resource will stop working (RFC 8594).
+const SUNSET_HEADER: HeaderName = HeaderName::from_static("sunset");
+
+/// The header that carries what clients should do instead, as `299` warnings (RFC 7234).
+const WARNING_HEADER: HeaderName = HeaderName::from_static("warning");
+
+/// Describes one deprecated route or field.
+#[derive(Serialize)]
+pub struct Deprecation {
+    /// The affected routes, as `METHOD /path` relative to the version scope, e.g. `GET /contacts/{id}`.
+    pub routes: &'static [&'static str],
+    /// The deprecated field, or `None` if the whole route is deprecated.
+    pub field: Option<&'static str>,
+    /// When the deprecation was announced, as a Unix timestamp.
+    pub deprecated_at: i64,
+    /// When the route or field will be removed, as a Unix timestamp, if that is decided.
+    pub sunset_at: Option<i64>,
+    /// What clients should do instead.
+    pub message: &'static str,
+}
+
+/// Every deprecation that is in effect.
+///
+/// Add an entry here to announce a deprecation, once there is something for clients to move to;
+/// the middleware and the listing endpoint pick it up.
+pub const DEPRECATIONS: &[Deprecation] = &[];
+
+/// Finds the deprecations that apply to a matched route.
+///
+/// # Arguments
+///
+/// * `registry` - The deprecations to look in.
+/// * `method` - The HTTP method of the request.
+/// * `pattern` - The full route pattern, e.g. `/api/v1/contacts/{id}`.
+///
+/// # Returns
+///
+/// * The matching deprecations, in registry order.
+fn deprecations_for<'a>(
+    registry: &'a [Deprecation],
+    method: &str,
+    pattern: &str,
+) -> Vec<&'a Deprecation> {
+    // Routes are registered the same way under every version scope
+    let path = pattern
+        .strip_prefix("/api/v1")
+        .or_else(|| pattern.strip_prefix("/api"))
+        .unwrap_or(pattern);
+    let route = format!("{} {}", method, path);
+
+    registry
+        .iter()
+        .filter(|d| d.routes.contains(&route.as_str()))
+        .collect()
+}
+
+/// Formats a Unix timestamp as an HTTP date, e.g. `Thu, 15 Oct 2026 00:00:00 GMT`.
+fn http_date(timestamp: i64) -> String {
+    HttpDate::from(UNIX_EPOCH + Duration::from_secs(timestamp.max(0) as u64)).to_string()
+}
+
+/// Formats a deprecation as a `Warning` header value, e.g. `299 - "Use /people instead."`.
+fn warning(deprecation: &Deprecation) -> String {
+    let text = match deprecation.field {
+        Some(field) => format!(
+            "The field '{}' is deprecated. {}",
+            field, deprecation.message
+        ),
+        None => deprecation.message.to_string(),
+    };
+    format!(
+        "299 - \"{}\"",
+        text.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// Adds the deprecation headers to a response.
+///
+/// # Arguments
+///
+/// * `headers` - The headers of the response.
+/// * `deprecations` - The deprecations that apply to the route, at least one.
+fn add_headers(headers: &mut HeaderMap, deprecations: &[&Deprecation]) {
+    if let Some(deprecated_at) = deprecations.iter().map(|d| d.deprecated_at).min() {
+        let value = format!("@{}", deprecated_at);
+        headers.insert(
+            DEPRECATION_HEADER,
+            HeaderValue::from_str(&value).expect("valid header value"),
+        );
+    }
+    if let Some(sunset_at) = deprecations.iter().filter_map(|d| d.sunset_at).min() {
+        headers.insert(
+            SUNSET_HEADER,
+            HeaderValue::from_str(&http_date(sunset_at)).expect("valid header value"),
+        );
+    }
+    for deprecation in deprecations {
+        // Messages are written in this file, so they are plain text a header can carry
+        if let Ok(value) = HeaderValue::from_str(&warning(deprecation)) {
+            headers.append(WARNING_HEADER, value);
+        }
+    }
+}
+
+/// Middleware that tells clients when they use a deprecated route or field.
+///
+/// Matching responses get a `Deprecation` header with the earliest announcement, a `Sunset`
+/// header with the earliest removal date when one is set, and a `Warning` header per deprecation
+/// with what to do instead. Bodies are left alone, so responses keep their shape.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service, with deprecation notices where they apply.
+pub async fn add_deprecation_notices(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    let mut res = next.call(req).await?;
+
+    // The route is only known after the request was matched
+    let deprecations = match res.request().match_pattern() {
+        Some(pattern) => deprecations_for(DEPRECATIONS, res.request().method().as_str(), &pattern),
+        None => Vec::new(),
+    };
+    if !deprecations.is_empty() {
+        add_headers(res.headers_mut(), &deprecations);
+    }
+    Ok(res)
+}
+
+/// Handles listing every deprecation that is in effect.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+///
+/// # Returns
+///
+/// * `HttpResponse` with a JSON array of deprecations.
+pub async fn read_deprecations(_principal: Principal) -> HttpResponse {
+    HttpResponse::Ok().json(DEPRECATIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_headers, deprecations_for, Deprecation};
+    use actix_web::http::header::HeaderMap;
+
+    const REGISTRY: &[Deprecation] = &[
+        Deprecation {
+            routes: &["GET /contacts/{id}"],
+            field: Some("email"),
+            deprecated_at: 1792022400,
+            sunset_at: Some(1800000000),
+            message: "Use \"emails\" instead.",
+        },
+        Deprecation {
+            routes: &["GET /contacts/{id}", "GET /contacts"],
+            field: None,
+            deprecated_at: 1790000000,
+            sunset_at: None,
+            message: "Use /people instead.",
+        },
+    ];
+
+    #[test]
+    fn deprecated_routes_get_headers_for_every_notice() {
+        let deprecations = deprecations_for(REGISTRY, "GET", "/api/v1/contacts/{id}");
+        assert_eq!(deprecations.len(), 2);
+        assert!(deprecations_for(REGISTRY, "PUT", "/api/contacts/{id}").is_empty());
+
+        let mut headers = HeaderMap::new();
+        add_headers(&mut headers, &deprecations);
+        assert_eq!(headers.get("deprecation").unwrap(), "@1790000000");
+        assert_eq!(
+            headers.get("sunset").unwrap(),
+            "Fri, 15 Jan 2027 08:00:00 GMT"
+        );
+        let warnings: Vec<_> = headers
+            .get_all("warning")
+            .map(|value| value.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                r#"299 - "The field 'email' is deprecated. Use \"emails\" instead.""#,
+                r#"299 - "Use /people instead.""#,
+            ]
+        );
+    }
+}
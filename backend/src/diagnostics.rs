@@ -0,0 +1,255 @@
+// backend/src/diagnostics.rs
+// This file checks the setup of an instance: the database, its migrations and the external services it is configured to call.
+// It exists so operators can find a broken setting with `contacts-api doctor` before starting, or ask a running instance.
+// RELEVANT FILES: backend/src/main.rs, backend/src/health.rs, backend/src/migrations.rs
+
+use crate::auth::{Jwks, OidcConfig, Principal, ADMIN_ROLE};
+use crate::error::ApiError;
+use crate::{establish_connection, migrations, outbound};
+use actix_web::HttpResponse;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use serde::Serialize;
+use std::time::Duration;
+
+/// How long an external service may take to answer a check.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The optional external services, as the environment variable with their URL and the name of the check.
+const SERVICES: [(&str, &str); 2] = [
+    ("ENRICHMENT_URL", "enrichment"),
+    ("ADDRESS_VALIDATION_URL", "address_validation"),
+];
+
+/// The outcome of one check.
+#[derive(Serialize)]
+pub struct Check {
+    /// What was checked, e.g. `database`.
+    pub name: &'static str,
+    /// `pass`, `fail`, or `skip` when the service is not configured.
+    pub status: &'static str,
+    /// What was found, or what went wrong.
+    pub detail: String,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: "pass",
+            detail: detail.into(),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: "fail",
+            detail: detail.into(),
+        }
+    }
+
+    fn skip(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: "skip",
+            detail: detail.into(),
+        }
+    }
+}
+
+/// The outcome of all checks.
+#[derive(Serialize)]
+pub struct Diagnostics {
+    /// `pass` when no check failed, otherwise `fail`.
+    pub status: &'static str,
+    /// The checks, in the order they ran.
+    pub checks: Vec<Check>,
+}
+
+impl Diagnostics {
+    /// Tells whether every check passed or was skipped.
+    pub fn passed(&self) -> bool {
+        self.status == "pass"
+    }
+}
+
+/// One value of a pragma, as text.
+#[derive(QueryableByName)]
+struct PragmaValue {
+    #[diesel(sql_type = Text)]
+    value: String,
+}
+
+/// Reads a pragma, e.g. `foreign_keys`, as text.
+fn pragma(conn: &mut SqliteConnection, name: &str) -> QueryResult<String> {
+    // Only called with the names below, which are not user input
+    diesel::sql_query(format!(
+        "SELECT CAST({name} AS TEXT) AS value FROM pragma_{name}()"
+    ))
+    .get_result::<PragmaValue>(conn)
+    .map(|row| row.value)
+}
+
+/// Checks that the database can be opened and that the pragmas the API relies on are set.
+fn check_database() -> Check {
+    let name = "database";
+    if std::env::var("DATABASE_URL").is_err() {
+        return Check::fail(name, "DATABASE_URL is not set");
+    }
+    let mut conn = match establish_connection() {
+        Ok(conn) => conn,
+        Err(e) => return Check::fail(name, format!("Cannot connect: {}", e)),
+    };
+    let pragmas = pragma(&mut conn, "foreign_keys").and_then(|foreign_keys| {
+        Ok((
+            foreign_keys,
+            pragma(&mut conn, "journal_mode")?,
+            pragma(&mut conn, "quick_check")?,
+        ))
+    });
+    match pragmas {
+        Err(e) => Check::fail(name, format!("Cannot read the pragmas: {}", e)),
+        Ok((foreign_keys, _, _)) if foreign_keys != "1" => {
+            Check::fail(name, "Foreign key checks are off")
+        }
+        Ok((_, _, quick_check)) if quick_check != "ok" => {
+            Check::fail(name, format!("The database is damaged: {}", quick_check))
+        }
+        Ok((_, journal_mode, _)) => Check::pass(
+            name,
+            format!(
+                "Connected; foreign keys on, journal mode {}, quick check ok",
+                journal_mode
+            ),
+        ),
+    }
+}
+
+/// Checks that the pending migrations, if any, can run at startup.
+fn check_migrations() -> Check {
+    let name = "migrations";
+    let pending = match establish_connection()
+        .map_err(|e| e.to_string())
+        .and_then(|mut conn| migrations::pending_migrations(&mut conn).map_err(|e| e.to_string()))
+    {
+        Ok(pending) => pending,
+        Err(e) => return Check::fail(name, format!("Cannot read the migrations: {}", e)),
+    };
+    if pending.is_empty() {
+        return Check::pass(name, "Up to date");
+    }
+    match migrations::check_pending_migrations() {
+        Ok(()) => Check::pass(
+            name,
+            format!(
+                "{} pending, they run at startup: {}",
+                pending.len(),
+                pending.join(", ")
+            ),
+        ),
+        Err(e) => Check::fail(name, e.to_string()),
+    }
+}
+
+/// Checks that the identity provider serves its configuration and at least one signing key.
+async fn check_identity_provider() -> Check {
+    let name = "identity_provider";
+    let Ok(idp_url) = std::env::var("IDP_URL") else {
+        return Check::fail(name, "IDP_URL is not set");
+    };
+    let client = match outbound::client_builder().timeout(CHECK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => return Check::fail(name, e.to_string()),
+    };
+    let fetch = async {
+        let url = format!("{}/.well-known/openid-configuration", idp_url);
+        let config: OidcConfig = client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let jwks: Jwks = client
+            .get(&config.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok::<_, reqwest::Error>((config, jwks))
+    };
+    match fetch.await {
+        Err(e) => Check::fail(name, format!("Cannot reach {}: {}", idp_url, e)),
+        Ok((_, jwks)) if jwks.keys.is_empty() => {
+            Check::fail(name, "The identity provider has no signing keys")
+        }
+        Ok((config, jwks)) => Check::pass(
+            name,
+            format!(
+                "Issuer {} with {} signing key(s)",
+                config.issuer,
+                jwks.keys.len()
+            ),
+        ),
+    }
+}
+
+/// Checks that an optional external service answers. Any HTTP answer counts, as the check sends no real request.
+async fn check_service(variable: &str, name: &'static str) -> Check {
+    let url = match std::env::var(variable) {
+        Ok(url) if !url.trim().is_empty() => url,
+        _ => return Check::skip(name, format!("{} is not set", variable)),
+    };
+    let client = match outbound::client_builder().timeout(CHECK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => return Check::fail(name, e.to_string()),
+    };
+    match client.get(&url).send().await {
+        Ok(response) => Check::pass(name, format!("{} answered {}", url, response.status())),
+        Err(e) => Check::fail(name, format!("Cannot reach {}: {}", url, e)),
+    }
+}
+
+/// Runs every check.
+///
+/// The checks read the configuration from the environment, like the server does at startup.
+///
+/// # Returns
+///
+/// * The outcome of every check, and `fail` overall if any of them failed.
+pub async fn run() -> Diagnostics {
+    let mut checks = vec![check_database()];
+    // Migrations cannot be read from a database that cannot be opened
+    if checks[0].status == "pass" {
+        checks.push(check_migrations());
+    }
+    checks.push(check_identity_provider().await);
+    for (variable, name) in SERVICES {
+        checks.push(check_service(variable, name).await);
+    }
+    let failed = checks.iter().any(|check| check.status == "fail");
+    Diagnostics {
+        status: if failed { "fail" } else { "pass" },
+        checks,
+    }
+}
+
+/// Handles running the setup checks on a running instance.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// It calls the identity provider and the configured services, so it can take a few seconds.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the outcome of every check. It is 200 also when a check failed.
+/// * `Err(ApiError)` if the user is not an admin.
+pub async fn read_diagnostics(principal: Principal) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    Ok(HttpResponse::Ok().json(run().await))
+}
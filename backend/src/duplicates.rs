@@ -0,0 +1,224 @@
+// backend/src/duplicates.rs
+// This file finds existing contacts that a new contact may duplicate: same email, same phone number or a similar name.
+// It exists so the create form can ask "did you mean this existing contact?" before a duplicate is saved.
+// RELEVANT FILES: backend/src/handlers.rs, backend/src/lookup.rs, backend/src/field_permissions.rs
+
+use crate::error::ApiError;
+use crate::establish_connection;
+use crate::field_permissions::FieldRules;
+use crate::lookup::{email_keys, lower};
+use crate::models::Contact;
+use crate::schema::contacts;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The most possible duplicates returned.
+const MAX_MATCHES: usize = 10;
+
+/// The fewest digits a phone number needs to be compared. Shorter ones are extensions or typos.
+const MIN_PHONE_DIGITS: usize = 7;
+
+/// The contact the user is about to create. Every field is optional, so a half-filled form can be checked.
+#[derive(Deserialize)]
+pub struct DraftContact {
+    /// The first name typed so far.
+    #[serde(default)]
+    pub first_name: String,
+    /// The last name typed so far.
+    #[serde(default)]
+    pub last_name: String,
+    /// The email address typed so far.
+    #[serde(default)]
+    pub email: String,
+    /// The phone number typed so far.
+    #[serde(default)]
+    pub phone_number: String,
+}
+
+/// An existing contact that may be the same person as the draft.
+#[derive(Serialize)]
+pub struct PossibleDuplicate {
+    /// The existing contact.
+    pub contact: Contact,
+    /// What matched: `email`, `phone_number` and/or `name`.
+    pub matched_on: Vec<&'static str>,
+    /// How likely the contact is the same person, from 0 to 1.
+    pub score: f32,
+}
+
+/// Keeps only the digits of a phone number, e.g. `+44 20 7946-0000` as `442079460000`.
+fn phone_digits(phone: &str) -> String {
+    phone.chars().filter(char::is_ascii_digit).collect()
+}
+
+/// Tells whether two phone numbers are the same number.
+///
+/// Formatting is ignored, and so is a country code or trunk prefix on one of them,
+/// so `+44 20 7946 0000` and `020 7946 0000` match.
+fn same_phone(a: &str, b: &str) -> bool {
+    let a = phone_digits(a);
+    let b = phone_digits(b);
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    if a.len() < MIN_PHONE_DIGITS || b.len() < MIN_PHONE_DIGITS {
+        return false;
+    }
+    a.ends_with(b) || b.ends_with(a)
+}
+
+/// Writes a name in the form names are compared in: lowercase, with single spaces.
+fn name_key(first_name: &str, last_name: &str) -> String {
+    format!("{} {}", first_name, last_name)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Counts the single-character edits that turn one text into the other (Levenshtein distance).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // The distances from the part of `a` read so far to every prefix of `b`
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Scores how similar two names are.
+///
+/// Names match when they are equal, when first and last name are swapped, or when they differ
+/// by at most one typo for every six letters (so `Jon Smith` matches `John Smith`).
+///
+/// # Returns
+///
+/// * A score from 0.5 for the most distant match to 0.8 for the same name, or `None` if they do not match.
+fn name_score(draft: &str, first_name: &str, last_name: &str) -> Option<f32> {
+    let forward = name_key(first_name, last_name);
+    let swapped = name_key(last_name, first_name);
+    let distance = edit_distance(draft, &forward).min(edit_distance(draft, &swapped));
+    let allowed = (draft.chars().count() / 6).max(1);
+    (distance <= allowed).then(|| 0.8 - 0.3 * distance as f32 / allowed as f32)
+}
+
+/// Handles checking a draft contact for existing contacts it may duplicate, before it is created.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Emails are compared without case, using the index on `lower(email)`. Phone numbers and names
+/// are compared in the server, so every contact's name and number are read. Fields the user may not
+/// read are not compared, or a match would tell what they hold.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read, which are neither compared nor shown in the matches.
+/// * `draft` - The contact from the create form, as far as it is filled in.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with at most 10 possible duplicates, the most likely first. Empty if there are none.
+/// * `Err(ApiError)` if the draft has no email, phone number or name the user may read, or there is a database error.
+pub async fn check_duplicate(
+    field_rules: FieldRules,
+    draft: web::Json<DraftContact>,
+) -> Result<HttpResponse, ApiError> {
+    // Fields the user may not read count as not filled in
+    let readable = |field: &str, value: &str| {
+        if field_rules.hides(field) {
+            String::new()
+        } else {
+            value.trim().to_string()
+        }
+    };
+    let email = readable("email", &draft.email);
+    let phone = readable("phone_number", &draft.phone_number);
+    let name = if field_rules.hides("first_name") || field_rules.hides("last_name") {
+        String::new()
+    } else {
+        name_key(&draft.first_name, &draft.last_name)
+    };
+    if email.is_empty() && phone.is_empty() && name.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Give an email, a phone number or a name to check".to_string(),
+        ));
+    }
+
+    let mut conn = establish_connection()?;
+    // The reasons and the best score of every contact that matched, by ID
+    let mut found: BTreeMap<i32, (Vec<&'static str>, f32)> = BTreeMap::new();
+
+    if !email.is_empty() {
+        let ids = contacts::table
+            .filter(lower(contacts::email).eq_any(email_keys(&email)))
+            .select(contacts::id)
+            .load::<i32>(&mut conn)?;
+        for id in ids {
+            found.insert(id, (vec!["email"], 1.0));
+        }
+    }
+
+    if !phone.is_empty() || !name.is_empty() {
+        let rows = contacts::table
+            .select((
+                contacts::id,
+                contacts::first_name,
+                contacts::last_name,
+                contacts::phone_number,
+            ))
+            .load::<(i32, String, String, String)>(&mut conn)?;
+        for (id, first_name, last_name, phone_number) in rows {
+            if !phone.is_empty() && same_phone(&phone, &phone_number) {
+                let entry = found.entry(id).or_insert((Vec::new(), 0.0));
+                entry.0.push("phone_number");
+                entry.1 = entry.1.max(0.9);
+            }
+            if name.is_empty() {
+                continue;
+            }
+            if let Some(score) = name_score(&name, &first_name, &last_name) {
+                let entry = found.entry(id).or_insert((Vec::new(), 0.0));
+                entry.0.push("name");
+                entry.1 = entry.1.max(score);
+            }
+        }
+    }
+
+    // Each further reason makes the match more likely, up to 1
+    let mut ranked: Vec<(i32, Vec<&'static str>, f32)> = found
+        .into_iter()
+        .map(|(id, (reasons, score))| {
+            let score = (score + 0.05 * (reasons.len() - 1) as f32).min(1.0);
+            (id, reasons, score)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.0.cmp(&b.0)));
+    ranked.truncate(MAX_MATCHES);
+
+    let ids: Vec<i32> = ranked.iter().map(|(id, _, _)| *id).collect();
+    let mut contacts: BTreeMap<i32, Contact> = contacts::table
+        .filter(contacts::id.eq_any(&ids))
+        .load::<Contact>(&mut conn)?
+        .into_iter()
+        .map(|contact| (contact.id, contact))
+        .collect();
+    let duplicates: Vec<PossibleDuplicate> = ranked
+        .into_iter()
+        .filter_map(|(id, matched_on, score)| {
+            contacts.remove(&id).map(|contact| PossibleDuplicate {
+                contact: field_rules.masked(contact),
+                matched_on,
+                score,
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(duplicates))
+}
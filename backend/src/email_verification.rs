@@ -0,0 +1,313 @@
+// backend/src/email_verification.rs
+// This file checks whether contact email addresses can receive mail, by looking up MX records.
+// It exists so users can spot broken addresses before they rely on them.
+// RELEVANT FILES: backend/src/models.rs, backend/src/handlers.rs, backend/src/main.rs
+
+use crate::error::ApiError;
+use crate::field_permissions::{FieldRules, MASK};
+use crate::identifiers::ContactKey;
+use crate::models::Contact;
+use crate::schema::contacts;
+use crate::transaction::Transaction;
+use crate::{establish_connection, unix_now};
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use hickory_resolver::TokioResolver;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The most contacts one bulk check covers. Without IDs, the contacts are checked in pages of this size.
+const MAX_BULK_SIZE: usize = 500;
+
+/// Looks up MX records for email domains.
+pub struct EmailVerifier {
+    /// The DNS resolver, or `None` if the system DNS config could not be read.
+    resolver: Option<TokioResolver>,
+}
+
+impl EmailVerifier {
+    /// Creates a verifier that uses the system DNS configuration.
+    ///
+    /// # Returns
+    ///
+    /// * A new `EmailVerifier`. If DNS is not available, every check returns "unknown".
+    pub fn from_system_config() -> Self {
+        let resolver = TokioResolver::builder_tokio()
+            .and_then(|builder| builder.build())
+            .map_err(|e| {
+                log::warn!(
+                    "DNS resolver not available, email checks are disabled: {}",
+                    e
+                )
+            })
+            .ok();
+        Self { resolver }
+    }
+
+    /// Checks if a domain has at least one MX record.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain part of an email address.
+    ///
+    /// # Returns
+    ///
+    /// * `"valid"` if the domain has MX records.
+    /// * `"invalid"` if the domain does not exist or has no MX records.
+    /// * `"unknown"` if the lookup failed, e.g. because of a timeout.
+    async fn check_domain(&self, domain: &str) -> &'static str {
+        let Some(resolver) = &self.resolver else {
+            return "unknown";
+        };
+        // A trailing dot stops the resolver from trying local search domains
+        match resolver.mx_lookup(format!("{}.", domain)).await {
+            Ok(lookup) if lookup.answers().is_empty() => "invalid",
+            Ok(_) => "valid",
+            Err(e) if e.is_no_records_found() || e.is_nx_domain() => "invalid",
+            Err(e) => {
+                log::warn!("MX lookup for {} failed: {}", domain, e);
+                "unknown"
+            }
+        }
+    }
+}
+
+/// Finds the domain of an email address, if the address looks well-formed.
+///
+/// # Arguments
+///
+/// * `email` - The email address.
+///
+/// # Returns
+///
+/// * `Some(domain)` in lowercase, or `None` if the address is malformed.
+pub(crate) fn email_domain(email: &str) -> Option<String> {
+    let (local, domain) = email.trim().rsplit_once('@')?;
+    let well_formed = !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !domain.contains(char::is_whitespace);
+    well_formed.then(|| domain.to_lowercase())
+}
+
+/// The result of checking one contact's email address.
+#[derive(Serialize)]
+pub struct VerificationResult {
+    /// The ID of the contact.
+    pub id: i32,
+    /// The email address that was checked.
+    pub email: String,
+    /// The result: valid, invalid or unknown.
+    pub email_status: String,
+}
+
+/// The report returned by the bulk check.
+#[derive(Serialize)]
+pub struct VerificationReport {
+    /// The number of contacts checked.
+    pub checked: usize,
+    /// The number of valid addresses.
+    pub valid: usize,
+    /// The number of invalid addresses.
+    pub invalid: usize,
+    /// The number of addresses that could not be checked.
+    pub unknown: usize,
+    /// The result for each contact.
+    pub results: Vec<VerificationResult>,
+    /// When every contact is checked and more are left, the `after_id` that checks the next page.
+    pub next_after_id: Option<i32>,
+}
+
+/// The request body for the bulk check.
+#[derive(Deserialize)]
+pub struct BulkVerificationRequest {
+    /// The IDs of the contacts to check, at most 500. When this is missing, every contact is
+    /// checked, a page of 500 per request.
+    pub ids: Option<Vec<i32>>,
+    /// Without `ids`, the page starts after this contact ID, from `next_after_id` of the last report.
+    pub after_id: Option<i32>,
+}
+
+/// Checks contacts' email addresses and stores the results.
+///
+/// Each domain is looked up only once, even if many contacts share it.
+///
+/// # Arguments
+///
+/// * `verifier` - The email verifier.
+/// * `contacts` - The contacts to check.
+/// * `transaction` - The transaction of the request, which the results are stored in.
+/// * `field_rules` - The fields the user may not read, which are masked in the results.
+///
+/// # Returns
+///
+/// * `Ok(Vec<VerificationResult>)` with one result per contact.
+/// * `Err(ApiError)` if there is a database error.
+async fn verify_contacts(
+    verifier: &EmailVerifier,
+    contacts: Vec<Contact>,
+    transaction: &Transaction,
+    field_rules: &FieldRules,
+) -> Result<Vec<VerificationResult>, ApiError> {
+    let mut domain_results: HashMap<String, &'static str> = HashMap::new();
+    let mut results = Vec::with_capacity(contacts.len());
+
+    for contact in contacts {
+        let status = match email_domain(&contact.email) {
+            None => "invalid",
+            Some(domain) => match domain_results.get(&domain) {
+                Some(status) => *status,
+                None => {
+                    let status = verifier.check_domain(&domain).await;
+                    domain_results.insert(domain, status);
+                    status
+                }
+            },
+        };
+        results.push(VerificationResult {
+            id: contact.id,
+            email: contact.email,
+            email_status: status.to_string(),
+        });
+    }
+
+    // Store all results after the DNS lookups, so the write lock is not held while waiting
+    let mut conn = transaction.conn()?;
+    let now = unix_now();
+    for result in &results {
+        let verified_at = (result.email_status == "valid").then_some(now);
+        // An address changed during the lookups was not checked, and its status stays unverified
+        diesel::update(
+            contacts::table
+                .find(result.id)
+                .filter(contacts::email.eq(&result.email)),
+        )
+        .set((
+            contacts::email_status.eq(&result.email_status),
+            contacts::email_verified_at.eq(verified_at),
+        ))
+        .execute(&mut *conn)?;
+    }
+
+    for result in &mut results {
+        if field_rules.hides("email") {
+            result.email = MASK.to_string();
+        }
+        if field_rules.hides("email_status") {
+            result.email_status = MASK.to_string();
+        }
+    }
+    Ok(results)
+}
+
+/// Checks that the user may change what a check stores.
+fn check_results_writable(field_rules: &FieldRules) -> Result<(), ApiError> {
+    field_rules.check_writable("email_status")?;
+    field_rules.check_writable("email_verified_at")
+}
+
+/// Handles checking the email address of a single contact.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// The address is checked for a valid format and for MX records on its domain. Sending a
+/// confirmation link to the address is not implemented, so `valid` means the domain takes mail,
+/// not that the mailbox exists.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `id` - The ID or UUID of the contact, from the URL path.
+/// * `verifier` - The email verifier.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the result of the check.
+/// * `Err(ApiError::Forbidden)` if the user may not change the email status.
+/// * `Err(ApiError)` if the contact is not found or there is a database error.
+pub async fn verify_email(
+    field_rules: FieldRules,
+    id: web::Path<ContactKey>,
+    verifier: web::Data<EmailVerifier>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    check_results_writable(&field_rules)?;
+    let contact = {
+        let mut conn = establish_connection()?;
+        let id = id.resolve(&mut conn)?;
+        contacts::table.find(id).first::<Contact>(&mut conn)?
+    };
+
+    let mut results = verify_contacts(&verifier, vec![contact], &transaction, &field_rules).await?;
+
+    Ok(HttpResponse::Ok().json(results.remove(0)))
+}
+
+/// Handles checking the email addresses of many contacts at once.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Each request checks one page of at most 500 contacts and commits its results, so a large
+/// address book is checked over several requests instead of in one long transaction.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `request` - The IDs of the contacts to check, or where the next page of all contacts starts.
+/// * `verifier` - The email verifier.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a report of the results and where the next page starts.
+/// * `Err(ApiError::BadRequest)` if there are more than 500 IDs.
+/// * `Err(ApiError::Forbidden)` if the user may not change the email status.
+/// * `Err(ApiError)` if there is a database error.
+pub async fn verify_emails(
+    field_rules: FieldRules,
+    request: web::Json<BulkVerificationRequest>,
+    verifier: web::Data<EmailVerifier>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    check_results_writable(&field_rules)?;
+    if request
+        .ids
+        .as_ref()
+        .is_some_and(|ids| ids.len() > MAX_BULK_SIZE)
+    {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} contacts can be checked at once",
+            MAX_BULK_SIZE
+        )));
+    }
+    let contacts = {
+        let mut conn = establish_connection()?;
+        let mut query = contacts::table.into_boxed();
+        match &request.ids {
+            Some(ids) => query = query.filter(contacts::id.eq_any(ids)),
+            None => {
+                query = query
+                    .filter(contacts::id.gt(request.after_id.unwrap_or(0)))
+                    .limit(MAX_BULK_SIZE as i64)
+            }
+        }
+        query.order(contacts::id.asc()).load::<Contact>(&mut conn)?
+    };
+    // A full page may have more contacts after it
+    let next_after_id = match request.ids {
+        None if contacts.len() == MAX_BULK_SIZE => contacts.last().map(|c| c.id),
+        _ => None,
+    };
+
+    let results = verify_contacts(&verifier, contacts, &transaction, &field_rules).await?;
+    let count = |status: &str| results.iter().filter(|r| r.email_status == status).count();
+
+    Ok(HttpResponse::Ok().json(VerificationReport {
+        checked: results.len(),
+        valid: count("valid"),
+        invalid: count("invalid"),
+        unknown: count("unknown"),
+        results,
+        next_after_id,
+    }))
+}
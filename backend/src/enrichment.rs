@@ -0,0 +1,261 @@
+// backend/src/enrichment.rs
+// This file enriches contacts with company data (name and logo) looked up from their email domain.
+// It exists so users get useful company details without typing them in, from a provider we can swap out.
+// RELEVANT FILES: backend/src/models.rs, backend/src/handlers.rs, backend/src/email_verification.rs
+
+use crate::auth::Principal;
+use crate::email_verification::email_domain;
+use crate::error::ApiError;
+use crate::feature_flags::FeatureFlags;
+use crate::identifiers::ContactKey;
+use crate::models::{Enrichment, NewEnrichment};
+use crate::outbound;
+use crate::schema::{contacts, enrichments};
+use crate::transaction::Transaction;
+use crate::{establish_connection, unix_now};
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use thiserror::Error;
+
+/// How long the enrichment provider may take to answer, so a hung call does not hold a request or a background task.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Represents the possible errors that can occur while calling a provider.
+#[derive(Debug, Error)]
+pub enum EnrichmentError {
+    /// Error for network issues or unexpected responses from the provider.
+    #[error("Network error while calling the enrichment provider: {0}")]
+    NetworkError(#[from] reqwest::Error),
+}
+
+/// The company data a provider found for a domain.
+#[derive(Debug, Deserialize)]
+pub struct CompanyInfo {
+    /// The name of the company.
+    pub name: Option<String>,
+    /// The URL of the company logo.
+    pub logo: Option<String>,
+}
+
+/// The future returned by `EnrichmentProvider::lookup_domain`.
+pub type LookupFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Option<CompanyInfo>, EnrichmentError>> + Send + 'a>>;
+
+/// A source of company data, looked up by email domain.
+///
+/// Implement this trait to plug in another provider.
+pub trait EnrichmentProvider: Send + Sync {
+    /// Returns the name stored with each result, to record where the data came from.
+    fn name(&self) -> &'static str;
+
+    /// Looks up the company behind a domain.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The email domain, in lowercase.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(CompanyInfo))` if the provider knows the domain.
+    /// * `Ok(None)` if it does not.
+    /// * `Err(EnrichmentError)` if the call failed.
+    fn lookup_domain<'a>(&'a self, domain: &'a str) -> LookupFuture<'a>;
+}
+
+/// A provider for APIs compatible with the Clearbit Company API.
+///
+/// It calls `GET <url>?domain=<domain>` and reads `name` and `logo` from the response.
+pub struct ClearbitProvider {
+    client: Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+impl EnrichmentProvider for ClearbitProvider {
+    fn name(&self) -> &'static str {
+        "clearbit"
+    }
+
+    fn lookup_domain<'a>(&'a self, domain: &'a str) -> LookupFuture<'a> {
+        Box::pin(async move {
+            let mut request = self.client.get(&self.url).query(&[("domain", domain)]);
+            if let Some(api_key) = &self.api_key {
+                request = request.bearer_auth(api_key);
+            }
+
+            let response = request.send().await?;
+            // Unknown domains are answered with 404, which is not an error for us
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            Ok(Some(response.error_for_status()?.json().await?))
+        })
+    }
+}
+
+/// Runs the configured enrichment provider and stores its results.
+pub struct Enricher {
+    /// The provider, or `None` if enrichment is not configured.
+    provider: Option<Box<dyn EnrichmentProvider>>,
+}
+
+impl Enricher {
+    /// Creates an enricher from the `ENRICHMENT_URL` and `ENRICHMENT_API_KEY` environment variables.
+    ///
+    /// # Returns
+    ///
+    /// * An `Enricher` with the built-in provider, or a disabled one if `ENRICHMENT_URL` is not set.
+    pub fn from_env() -> Self {
+        let provider = std::env::var("ENRICHMENT_URL").ok().map(|url| {
+            Box::new(ClearbitProvider {
+                client: outbound::client_builder()
+                    .timeout(LOOKUP_TIMEOUT)
+                    .build()
+                    .expect("Failed to build the HTTP client for enrichment"),
+                url,
+                api_key: std::env::var("ENRICHMENT_API_KEY").ok(),
+            }) as Box<dyn EnrichmentProvider>
+        });
+        Self { provider }
+    }
+
+    /// Returns whether a provider is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.provider.is_some()
+    }
+
+    /// Looks up company data for a contact's email.
+    ///
+    /// # Arguments
+    ///
+    /// * `contact_id` - The ID of the contact.
+    /// * `email` - The email address of the contact.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(NewEnrichment)` with the result, ready to store.
+    /// * `Err(ApiError)` if enrichment is off, the email has no domain or the provider fails.
+    async fn lookup(&self, contact_id: i32, email: &str) -> Result<NewEnrichment, ApiError> {
+        let provider = self
+            .provider
+            .as_ref()
+            .ok_or_else(|| ApiError::BadRequest("Enrichment is not configured".to_string()))?;
+        let domain = email_domain(email).ok_or_else(|| {
+            ApiError::BadRequest("The contact has no valid email domain".to_string())
+        })?;
+
+        let info = provider.lookup_domain(&domain).await.map_err(|e| {
+            log::error!("Enrichment of contact {} failed: {}", contact_id, e);
+            ApiError::Upstream(e.to_string())
+        })?;
+
+        let (company_name, logo_url) = info.map(|i| (i.name, i.logo)).unwrap_or_default();
+        Ok(NewEnrichment {
+            contact_id,
+            provider: provider.name().to_string(),
+            domain,
+            company_name,
+            logo_url,
+            fetched_at: unix_now(),
+        })
+    }
+
+    /// Looks up company data for a contact's email and stores the result on a connection of its own.
+    ///
+    /// This is for enrichment in the background, after the request that created the contact
+    /// has been answered.
+    ///
+    /// # Arguments
+    ///
+    /// * `contact_id` - The ID of the contact.
+    /// * `email` - The email address of the contact.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Enrichment)` with the stored result.
+    /// * `Err(ApiError)` if enrichment is off, the email has no domain, the provider fails or there is a database error.
+    pub async fn enrich(&self, contact_id: i32, email: &str) -> Result<Enrichment, ApiError> {
+        let enrichment = self.lookup(contact_id, email).await?;
+        store(&mut establish_connection()?, &enrichment)
+    }
+}
+
+/// Stores an enrichment result.
+fn store(conn: &mut SqliteConnection, enrichment: &NewEnrichment) -> Result<Enrichment, ApiError> {
+    Ok(diesel::insert_into(enrichments::table)
+        .values(enrichment)
+        .get_result::<Enrichment>(conn)?)
+}
+
+/// Handles running enrichment for a contact again.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `flags` - The feature flags of the caller's workspace, which can switch enrichment off.
+/// * `id` - The ID or UUID of the contact, from the URL path.
+/// * `enricher` - The configured enricher.
+/// * `transaction` - The transaction of the request, which the result is stored in.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the new enrichment result.
+/// * `Err(ApiError)` if enrichment is switched off, the contact is not found, enrichment fails or there is a database error.
+pub async fn enrich_contact(
+    flags: FeatureFlags,
+    id: web::Path<ContactKey>,
+    enricher: web::Data<Enricher>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    flags.require("enrichment")?;
+    let (contact_id, email) = {
+        let mut conn = establish_connection()?;
+        let contact_id = id.resolve(&mut conn)?;
+        let email = contacts::table
+            .find(contact_id)
+            .select(contacts::email)
+            .first::<String>(&mut conn)?;
+        (contact_id, email)
+    };
+
+    // The provider is called before the transaction begins, so no lock is held while waiting
+    let enrichment = enricher.lookup(contact_id, &email).await?;
+    let enrichment = store(&mut *transaction.conn()?, &enrichment)?;
+
+    Ok(HttpResponse::Ok().json(enrichment))
+}
+
+/// Handles reading all enrichment results of a contact.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `id` - The ID or UUID of the contact, from the URL path.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a JSON array of enrichment results, newest first.
+/// * `Err(ApiError)` if no contact has the UUID or there is a database error.
+pub async fn read_enrichments(
+    _principal: Principal,
+    id: web::Path<ContactKey>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = establish_connection()?;
+
+    let contact_id = id.resolve(&mut conn)?;
+    let results = enrichments::table
+        .filter(enrichments::contact_id.eq(contact_id))
+        .order(enrichments::id.desc())
+        .load::<Enrichment>(&mut conn)?;
+
+    Ok(HttpResponse::Ok().json(results))
+}
@@ -0,0 +1,163 @@
+// backend/src/envelope.rs
+// This file wraps list responses in an envelope with the data, metadata and links when a client asks for it.
+// It exists for clients that cannot read response headers, e.g. some fetch wrappers behind proxies.
+// RELEVANT FILES: backend/src/links.rs, backend/src/formats.rs, backend/src/main.rs
+
+use crate::formats::is_json;
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::LINK;
+use actix_web::middleware::Next;
+use actix_web::{web, Error as ActixWebError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+/// Whether lists are wrapped when the client does not say, shared as app data.
+#[derive(Clone, Copy, Default)]
+pub struct EnvelopeDefault {
+    /// Whether lists are wrapped without `?envelope=true`.
+    pub enabled: bool,
+}
+
+impl EnvelopeDefault {
+    /// Reads the default from the `RESPONSE_ENVELOPE` environment variable.
+    ///
+    /// # Returns
+    ///
+    /// * An `EnvelopeDefault` that is enabled when the variable is `true` or `1`.
+    pub fn from_env() -> Self {
+        let enabled = std::env::var("RESPONSE_ENVELOPE")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        Self { enabled }
+    }
+}
+
+/// The query parameter that switches the envelope on or off for one request.
+#[derive(Deserialize)]
+struct EnvelopeQuery {
+    /// `true` to wrap the list, `false` to send it as a plain array.
+    envelope: Option<bool>,
+}
+
+/// The metadata of a wrapped list.
+#[derive(Serialize)]
+struct Meta {
+    /// How many items the list has.
+    total: usize,
+    /// The page of the list. Lists are not paged, so this is always 1.
+    page: u32,
+    /// How long the server took to build the list, in milliseconds.
+    took_ms: u128,
+}
+
+/// A list with its metadata and links.
+#[derive(Serialize)]
+struct Envelope {
+    /// The list itself, as it would be sent without the envelope.
+    data: Value,
+    /// The metadata of the list.
+    meta: Meta,
+    /// The links from the `Link` header, by relation, e.g. `self`.
+    links: BTreeMap<String, String>,
+}
+
+/// Reads the links of a `Link` header (RFC 8288) by relation.
+///
+/// # Arguments
+///
+/// * `header` - The header value, e.g. `<https://host/api/contacts>; rel="self"`.
+///
+/// # Returns
+///
+/// * The URL of each relation. Links without a `rel` are left out.
+fn parse_links(header: &str) -> BTreeMap<String, String> {
+    let mut links = BTreeMap::new();
+    // Each link is `<url>; rel="x"`; URLs are cut at `<` and `>`, so commas in them do no harm
+    let mut rest = header;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>').map(|end| start + end) else {
+            break;
+        };
+        let url = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+        let params = rest.split('<').next().unwrap_or("");
+        let rel = params.split(';').find_map(|param| {
+            param
+                .trim()
+                .trim_end_matches(',')
+                .strip_prefix("rel=")
+                .map(|rel| rel.trim_matches('"').to_string())
+        });
+        if let Some(rel) = rel {
+            links.insert(rel, url.to_string());
+        }
+    }
+    links
+}
+
+/// Middleware that wraps JSON list responses in `{ data, meta, links }`.
+///
+/// Lists are wrapped when the request has `?envelope=true`, or when `RESPONSE_ENVELOPE` is set and
+/// the request does not have `?envelope=false`. Other responses, and errors, are passed on unchanged.
+/// The `Link` header is kept, so clients that read it keep working.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service, wrapped if it is a list and the client asked for it.
+pub async fn wrap_lists(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, ActixWebError> {
+    let default = req
+        .app_data::<web::Data<EnvelopeDefault>>()
+        .map(|d| d.enabled)
+        .unwrap_or(false);
+    // A malformed value is left to the handlers' own query parsing
+    let wanted = web::Query::<EnvelopeQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.envelope)
+        .unwrap_or(default);
+    let started = Instant::now();
+    let res = next.call(req).await?;
+
+    if !wanted || !res.status().is_success() || !is_json(&res) {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let links = res
+        .headers()
+        .get_all(LINK)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| parse_links(value).into_iter())
+        .collect();
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = to_bytes(body)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.into()))?;
+    let data = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(data @ Value::Array(_)) => data,
+        // Only lists are wrapped
+        _ => return Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(bytes)))),
+    };
+
+    let envelope = Envelope {
+        meta: Meta {
+            total: data.as_array().map_or(0, Vec::len),
+            page: 1,
+            took_ms: started.elapsed().as_millis(),
+        },
+        data,
+        links,
+    };
+    let body = serde_json::to_vec(&envelope)?;
+    Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))))
+}
@@ -3,11 +3,72 @@
 // It provides a unified way to handle different kinds of errors and convert them into appropriate HTTP responses.
 // RELEVANT FILES: backend/src/handlers.rs, backend/src/main.rs
 
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::StatusCode;
 use actix_web::{error::ResponseError, HttpResponse};
-use diesel::result::Error as DieselError;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 use diesel::ConnectionError;
+use serde::Serialize;
 use std::fmt;
 
+/// The content type of problem details (RFC 7807).
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// An error in the problem details format of RFC 7807, for errors that are not raised by a handler.
+#[derive(Serialize)]
+pub struct Problem {
+    /// A URI that identifies the kind of problem. `about:blank` means the status says it all.
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    /// The reason phrase of the status, e.g. `Not Found`.
+    pub title: String,
+    /// The HTTP status code.
+    pub status: u16,
+    /// An explanation for the user.
+    pub detail: String,
+    /// The path of the request the problem is about.
+    pub instance: String,
+    /// The methods the path does accept, when the method was wrong.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_methods: Option<Vec<&'static str>>,
+}
+
+impl Problem {
+    /// Creates a problem without a specific type.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The HTTP status of the response.
+    /// * `detail` - An explanation for the user.
+    /// * `instance` - The path of the request.
+    ///
+    /// # Returns
+    ///
+    /// * A `Problem` titled after the status.
+    pub fn new(status: StatusCode, detail: impl Into<String>, instance: impl Into<String>) -> Self {
+        Self {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: detail.into(),
+            instance: instance.into(),
+            allowed_methods: None,
+        }
+    }
+
+    /// Builds the response for the problem.
+    ///
+    /// # Returns
+    ///
+    /// * An `HttpResponse` with the status and an `application/problem+json` body.
+    pub fn response(&self) -> HttpResponse {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        HttpResponse::build(status)
+            .content_type(PROBLEM_JSON)
+            .body(serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
 /// Represents the possible errors that can occur in the API.
 #[derive(Debug)]
 pub enum ApiError {
@@ -17,6 +78,21 @@ pub enum ApiError {
     ConnectionError(ConnectionError),
     /// An error indicating that a requested resource was not found.
     NotFound,
+    /// An error indicating that the action is not allowed, with a message for the user.
+    Forbidden(String),
+    /// An error indicating that the request data is invalid, with a message for the user.
+    BadRequest(String),
+    /// An error indicating that the request clashes with existing data, with a message for the user.
+    Conflict(String),
+    /// An error indicating that the requested representation or API version is not available.
+    NotAcceptable(String),
+    /// An error from an external service the API depends on.
+    Upstream(String),
+    /// An error indicating that the client sent too many requests, with a message for the user.
+    TooManyRequests(String),
+    /// An error indicating that the API cannot take the request for now,
+    /// with a message and the number of seconds after which to retry.
+    Unavailable(String, u64),
 }
 
 impl fmt::Display for ApiError {
@@ -25,6 +101,13 @@ impl fmt::Display for ApiError {
             ApiError::DatabaseError(e) => write!(f, "Database error: {}", e),
             ApiError::ConnectionError(e) => write!(f, "Connection error: {}", e),
             ApiError::NotFound => write!(f, "Not Found"),
+            ApiError::Forbidden(message) => write!(f, "Forbidden: {}", message),
+            ApiError::BadRequest(message) => write!(f, "Bad Request: {}", message),
+            ApiError::Conflict(message) => write!(f, "Conflict: {}", message),
+            ApiError::NotAcceptable(message) => write!(f, "Not Acceptable: {}", message),
+            ApiError::Upstream(message) => write!(f, "Upstream error: {}", message),
+            ApiError::TooManyRequests(message) => write!(f, "Too Many Requests: {}", message),
+            ApiError::Unavailable(message, _) => write!(f, "Service Unavailable: {}", message),
         }
     }
 }
@@ -41,6 +124,15 @@ impl ResponseError for ApiError {
                 HttpResponse::InternalServerError().json("Internal Server Error")
             }
             ApiError::NotFound => HttpResponse::NotFound().json("Not Found"),
+            ApiError::Forbidden(message) => HttpResponse::Forbidden().json(message),
+            ApiError::BadRequest(message) => HttpResponse::BadRequest().json(message),
+            ApiError::Conflict(message) => HttpResponse::Conflict().json(message),
+            ApiError::NotAcceptable(message) => HttpResponse::NotAcceptable().json(message),
+            ApiError::Upstream(_) => HttpResponse::BadGateway().json("Bad Gateway"),
+            ApiError::TooManyRequests(message) => HttpResponse::TooManyRequests().json(message),
+            ApiError::Unavailable(message, retry_after) => HttpResponse::ServiceUnavailable()
+                .insert_header((RETRY_AFTER, retry_after.to_string()))
+                .json(message),
         }
     }
 }
@@ -58,6 +150,16 @@ impl From<DieselError> for ApiError {
     fn from(e: DieselError) -> Self {
         match e {
             DieselError::NotFound => ApiError::NotFound,
+            // Constraint violations are caused by bad input, not by a broken database
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                ApiError::BadRequest("A referenced record does not exist".to_string())
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                ApiError::Conflict("A record with the same unique value already exists".to_string())
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::CheckViolation, _) => {
+                ApiError::BadRequest("A value is not allowed".to_string())
+            }
             _ => ApiError::DatabaseError(e),
         }
     }
@@ -76,4 +178,4 @@ impl From<ConnectionError> for ApiError {
     fn from(e: ConnectionError) -> Self {
         ApiError::ConnectionError(e)
     }
-}
\ No newline at end of file
+}
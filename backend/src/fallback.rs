@@ -0,0 +1,117 @@
+// backend/src/fallback.rs
+// This file answers requests that match no route with a problem body, telling a wrong path from a wrong method.
+// It exists because actix answers such requests with an empty 404, which leaves API consumers guessing.
+// RELEVANT FILES: backend/src/main.rs, backend/src/error.rs, backend/src/lib.rs, backend/src/demo.rs
+
+use crate::error::Problem;
+use crate::i18n;
+use crate::{Route, ROUTE_SCOPES};
+use actix_web::dev::ResourceDef;
+use actix_web::http::header::{HeaderValue, ALLOW, CONTENT_LANGUAGE, VARY};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse};
+
+/// Finds the routes whose pattern matches a path, among the routes of the scope the path is in.
+///
+/// Actix does not say which methods a path accepts, so the `Allow` header is built from the route tables.
+///
+/// # Arguments
+///
+/// * `path` - The path of the request, with the scope, e.g. `/api/v1/contacts/42`.
+///
+/// # Returns
+///
+/// * The matching routes.
+pub(crate) fn matching_routes(path: &str) -> impl Iterator<Item = &'static Route> + '_ {
+    // A scope only takes paths that continue with a new segment, so `/apis` is not in `/api`
+    let (routes, path) = ROUTE_SCOPES
+        .iter()
+        .find_map(|(scope, routes)| {
+            path.strip_prefix(scope)
+                .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+                .map(|rest| (*routes, rest))
+        })
+        .unwrap_or((&[], path));
+
+    routes
+        .iter()
+        .filter(move |route| ResourceDef::new(route.path).is_match(path))
+}
+
+/// Finds the methods a path accepts.
+///
+/// Whether a route has the path at all is up to the router of the app, so only paths it knows
+/// are looked up here.
+///
+/// # Arguments
+///
+/// * `req` - The HTTP request, whose resource map has every registered route.
+///
+/// # Returns
+///
+/// * The methods of every route that matches the path, in a fixed order. Empty if no route matches.
+fn allowed_methods(req: &HttpRequest) -> Vec<&'static str> {
+    let path = req.path();
+    if !req.resource_map().has_resource(path) {
+        return Vec::new();
+    }
+    let mut methods: Vec<&'static str> = matching_routes(path).map(|route| route.method).collect();
+    // Several patterns can match one path, e.g. `/contacts/count` and `/contacts/{id}`
+    let order = ["GET", "HEAD", "POST", "PUT", "DELETE"];
+    methods.sort_by_key(|method| order.iter().position(|m| m == method));
+    methods.dedup();
+    methods
+}
+
+/// Handles requests that match no route.
+///
+/// A path that no route has gets a 404. A path that exists, but not with the method of the request,
+/// gets a 405 with the methods it does accept, in the `Allow` header and in the body.
+/// The problem is in the language of `Accept-Language`.
+///
+/// # Arguments
+///
+/// * `req` - The HTTP request.
+///
+/// # Returns
+///
+/// * `HttpResponse` with an `application/problem+json` body.
+pub async fn route_not_found(req: HttpRequest) -> HttpResponse {
+    // The API scopes translate their errors in middleware, which does not run here
+    let locale = i18n::negotiate(req.headers());
+    let methods = allowed_methods(&req);
+    let mut problem = if methods.is_empty() {
+        Problem::new(
+            StatusCode::NOT_FOUND,
+            format!("No route matches {}", req.path()),
+            req.path(),
+        )
+    } else {
+        let mut problem = Problem::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!(
+                "{} is not allowed here. Use one of: {}",
+                req.method(),
+                methods.join(", ")
+            ),
+            req.path(),
+        );
+        problem.allowed_methods = Some(methods.clone());
+        problem
+    };
+    problem.title = i18n::translate(locale, &problem.title);
+    problem.detail = i18n::translate(locale, &problem.detail);
+
+    let mut response = problem.response();
+    let allow = Some(methods.join(", ")).filter(|allow| !allow.is_empty());
+    if let Some(value) = allow.and_then(|allow| allow.parse().ok()) {
+        response.headers_mut().insert(ALLOW, value);
+    }
+    response
+        .headers_mut()
+        .insert(CONTENT_LANGUAGE, HeaderValue::from_static(locale));
+    response
+        .headers_mut()
+        .append(VARY, HeaderValue::from_static("accept-language"));
+    response
+}
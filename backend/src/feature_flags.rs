@@ -0,0 +1,304 @@
+// backend/src/feature_flags.rs
+// This file switches capabilities like enrichment and public share links on or off per workspace, at runtime.
+// It exists so admins can turn a capability off for one workspace without a deploy or affecting the others.
+// RELEVANT FILES: backend/src/auth.rs, backend/src/enrichment.rs, backend/src/share.rs
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::error::ApiError;
+use crate::schema::feature_flags;
+use crate::transaction::Transaction;
+use crate::{establish_connection, unix_now};
+use actix_web::{
+    dev::Payload, web, Error as ActixWebError, FromRequest, HttpRequest, HttpResponse,
+};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
+/// The flags that can be switched, with whether they are on when no admin has set them.
+const FLAGS: [(&str, bool); 6] = [
+    // Looking up company data for contacts at an external provider
+    ("enrichment", true),
+    // Public, unauthenticated links to a contact
+    ("share_links", true),
+    // The normalization rules for incoming data, see normalization.rs
+    ("normalize_names", true),
+    ("normalize_emails", true),
+    ("normalize_phones", true),
+    ("normalize_companies", true),
+];
+
+/// The workspace of callers whose token has no `workspace` claim.
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// Represents a flag an admin has set for a workspace.
+#[derive(Queryable, Insertable, AsChangeset)]
+#[diesel(table_name = feature_flags)]
+struct StoredFlag {
+    workspace: String,
+    flag: String,
+    enabled: bool,
+    updated_by: String,
+    updated_at: i64,
+}
+
+/// The state of one flag in a workspace, as shown to admins.
+#[derive(Serialize)]
+pub struct FlagState {
+    /// The name of the flag, e.g. `enrichment`.
+    pub flag: &'static str,
+    /// Whether the capability is on in the workspace.
+    pub enabled: bool,
+    /// Whether an admin has set the flag. Flags that are not set use their default.
+    pub overridden: bool,
+    /// Who set the flag last.
+    pub updated_by: Option<String>,
+    /// When the flag was set last, as a Unix timestamp in seconds.
+    pub updated_at: Option<i64>,
+}
+
+/// The request body for setting a flag.
+#[derive(Deserialize)]
+pub struct SetFlag {
+    /// Whether the capability is on.
+    pub enabled: bool,
+}
+
+/// The flags of the caller's workspace.
+///
+/// Handlers take it to check whether a capability is on, e.g. `flags.require("enrichment")?`.
+pub struct FeatureFlags {
+    /// The workspace the flags are for.
+    workspace: String,
+    /// The flags an admin has set for the workspace.
+    overrides: HashMap<String, bool>,
+}
+
+/// Checks that a flag exists.
+fn known_flag(flag: &str) -> Result<(&'static str, bool), ApiError> {
+    FLAGS
+        .iter()
+        .find(|(name, _)| *name == flag)
+        .copied()
+        .ok_or_else(|| {
+            let names: Vec<&str> = FLAGS.iter().map(|(name, _)| *name).collect();
+            ApiError::BadRequest(format!("Flag must be one of: {}", names.join(", ")))
+        })
+}
+
+/// Reads the flags an admin has set for a workspace.
+fn stored_flags(conn: &mut SqliteConnection, workspace: &str) -> Result<Vec<StoredFlag>, ApiError> {
+    Ok(feature_flags::table
+        .filter(feature_flags::workspace.eq(workspace))
+        .load::<StoredFlag>(conn)?)
+}
+
+impl FeatureFlags {
+    /// Loads the flags of a workspace.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - The database connection.
+    /// * `workspace` - The workspace, or `None` for the default one.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(FeatureFlags)` with the flags.
+    /// * `Err(ApiError)` if there is a database error.
+    pub fn load(conn: &mut SqliteConnection, workspace: Option<&str>) -> Result<Self, ApiError> {
+        let workspace = workspace.unwrap_or(DEFAULT_WORKSPACE).to_string();
+        let overrides = stored_flags(conn, &workspace)?
+            .into_iter()
+            .map(|stored| (stored.flag, stored.enabled))
+            .collect();
+        Ok(Self {
+            workspace,
+            overrides,
+        })
+    }
+
+    /// Returns the workspace the flags are for.
+    pub fn workspace(&self) -> &str {
+        &self.workspace
+    }
+
+    /// Tells whether a capability is on. Unknown flags are off.
+    ///
+    /// # Arguments
+    ///
+    /// * `flag` - The name of the flag, e.g. `enrichment`.
+    pub fn enabled(&self, flag: &str) -> bool {
+        match known_flag(flag) {
+            Ok((name, default)) => self.overrides.get(name).copied().unwrap_or(default),
+            Err(_) => false,
+        }
+    }
+
+    /// Checks that a capability is on.
+    ///
+    /// # Arguments
+    ///
+    /// * `flag` - The name of the flag, e.g. `enrichment`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if it is on.
+    /// * `Err(ApiError::Forbidden)` if it is switched off for the workspace.
+    pub fn require(&self, flag: &str) -> Result<(), ApiError> {
+        if self.enabled(flag) {
+            Ok(())
+        } else {
+            Err(ApiError::Forbidden(format!(
+                "{} is switched off for workspace {}",
+                flag, self.workspace
+            )))
+        }
+    }
+}
+
+impl FromRequest for FeatureFlags {
+    type Error = ActixWebError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    /// Authenticates the caller like `Principal` does, then loads the flags of their workspace.
+    ///
+    /// Handlers that take `FeatureFlags` are protected, so they do not need `Principal` as well.
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let principal = Principal::from_request(req, payload);
+        Box::pin(async move {
+            let principal = principal.await?;
+            let mut conn = establish_connection()?;
+            Ok(FeatureFlags::load(&mut conn, principal.workspace())?)
+        })
+    }
+}
+
+/// Builds the state of every flag in a workspace.
+fn flag_states(conn: &mut SqliteConnection, workspace: &str) -> Result<Vec<FlagState>, ApiError> {
+    let stored: HashMap<String, StoredFlag> = stored_flags(conn, workspace)?
+        .into_iter()
+        .map(|stored| (stored.flag.clone(), stored))
+        .collect();
+    Ok(FLAGS
+        .iter()
+        .map(|(name, default)| match stored.get(*name) {
+            Some(flag) => FlagState {
+                flag: name,
+                enabled: flag.enabled,
+                overridden: true,
+                updated_by: Some(flag.updated_by.clone()),
+                updated_at: Some(flag.updated_at),
+            },
+            None => FlagState {
+                flag: name,
+                enabled: *default,
+                overridden: false,
+                updated_by: None,
+                updated_at: None,
+            },
+        })
+        .collect())
+}
+
+/// Handles reading the flags of a workspace.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+/// * `workspace` - The workspace, from the URL path. `default` is the one of users without a workspace.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with every flag, its state and whether an admin has set it.
+/// * `Err(ApiError)` if the user is not an admin or there is a database error.
+pub async fn read_feature_flags(
+    principal: Principal,
+    workspace: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let mut conn = establish_connection()?;
+    Ok(HttpResponse::Ok().json(flag_states(&mut conn, &workspace)?))
+}
+
+/// Handles switching a flag on or off for a workspace. It applies to the next request.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and record who set the flag.
+/// * `path` - The workspace and the flag, from the URL path.
+/// * `body` - Whether the capability is on.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with every flag of the workspace.
+/// * `Err(ApiError)` if the user is not an admin, the flag is unknown or there is a database error.
+pub async fn set_feature_flag(
+    principal: Principal,
+    path: web::Path<(String, String)>,
+    body: web::Json<SetFlag>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let (workspace, flag) = path.into_inner();
+    known_flag(&flag)?;
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+
+    let stored = StoredFlag {
+        workspace: workspace.clone(),
+        flag,
+        enabled: body.enabled,
+        updated_by: principal.id().to_string(),
+        updated_at: unix_now(),
+    };
+    diesel::insert_into(feature_flags::table)
+        .values(&stored)
+        .on_conflict((feature_flags::workspace, feature_flags::flag))
+        .do_update()
+        .set(&stored)
+        .execute(conn)?;
+
+    Ok(HttpResponse::Ok().json(flag_states(conn, &workspace)?))
+}
+
+/// Handles resetting a flag of a workspace to its default.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+/// * `path` - The workspace and the flag, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with every flag of the workspace.
+/// * `Err(ApiError)` if the user is not an admin, the flag is unknown or there is a database error.
+pub async fn reset_feature_flag(
+    principal: Principal,
+    path: web::Path<(String, String)>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let (workspace, flag) = path.into_inner();
+    known_flag(&flag)?;
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+
+    diesel::delete(
+        feature_flags::table
+            .filter(feature_flags::workspace.eq(&workspace))
+            .filter(feature_flags::flag.eq(&flag)),
+    )
+    .execute(conn)?;
+
+    Ok(HttpResponse::Ok().json(flag_states(conn, &workspace)?))
+}
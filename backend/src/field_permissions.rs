@@ -0,0 +1,415 @@
+// backend/src/field_permissions.rs
+// This file decides which contact fields a user may read and write, based on their realm roles.
+// It exists so some roles (e.g. interns) can use the address book without seeing or changing sensitive fields.
+// RELEVANT FILES: backend/src/auth.rs, backend/src/handlers.rs, backend/src/models.rs
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::error::ApiError;
+use crate::models::{Contact, ContactFilter, NewContact, PendingChange};
+use actix_web::{dev::Payload, web, Error as ActixWebError, FromRequest, HttpRequest};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+
+/// The value shown instead of a hidden text field.
+pub const MASK: &str = "***";
+
+/// The fields that can be hidden. Hidden text fields read as `***`, a hidden quality score as 0
+/// and other hidden fields as `null`.
+///
+/// `id` is needed to address a contact, and the opt-in flags cannot be `null`, so they are always shown.
+/// The opt-in flags can be made read-only, see `OPT_IN_FIELDS`.
+const MASKABLE_FIELDS: [&str; 16] = [
+    "first_name",
+    "last_name",
+    "email",
+    "phone_number",
+    "company_id",
+    "status",
+    "source",
+    "owner",
+    "consent_source",
+    "consent_timestamp",
+    "email_status",
+    "email_verified_at",
+    "uuid",
+    "external_id",
+    "archived_at",
+    "quality_score",
+];
+
+/// The fields that can be read-only but not hidden: the opt-in flags, which have no placeholder.
+const OPT_IN_FIELDS: [&str; 2] = ["email_opt_in", "sms_opt_in"];
+
+/// The fields the quality score is computed from. When one of them is hidden, so is the score,
+/// or it would tell whether the hidden field is set.
+const SCORED_FIELDS: [&str; 7] = [
+    "first_name",
+    "last_name",
+    "email",
+    "email_status",
+    "phone_number",
+    "company_id",
+    "consent_timestamp",
+];
+
+/// Holds which fields each role may not read or not write.
+#[derive(Default)]
+pub struct FieldPermissions {
+    /// The hidden fields per role.
+    hidden: HashMap<String, Vec<String>>,
+    /// The read-only fields per role.
+    read_only: HashMap<String, Vec<String>>,
+}
+
+/// Parses a setting like `intern:phone_number,email;guest:owner` into fields per role.
+///
+/// Fields that cannot be restricted are skipped with a warning.
+///
+/// # Arguments
+///
+/// * `variable` - The name of the setting, for the warnings.
+/// * `value` - The value of the setting.
+/// * `also_allowed` - The fields this setting may restrict besides `MASKABLE_FIELDS`.
+fn parse_rules(variable: &str, value: &str, also_allowed: &[&str]) -> HashMap<String, Vec<String>> {
+    let mut rules: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in value.split(';').filter(|e| !e.trim().is_empty()) {
+        let Some((role, fields)) = entry.split_once(':') else {
+            log::warn!(
+                "Ignoring '{}' in {}: expected role:field,field",
+                entry,
+                variable
+            );
+            continue;
+        };
+        for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            if MASKABLE_FIELDS.contains(&field) || also_allowed.contains(&field) {
+                rules
+                    .entry(role.trim().to_string())
+                    .or_default()
+                    .push(field.to_string());
+            } else {
+                log::warn!(
+                    "Ignoring field '{}' in {}: it cannot be restricted",
+                    field,
+                    variable
+                );
+            }
+        }
+    }
+    rules
+}
+
+impl FieldPermissions {
+    /// Reads the rules from the `HIDDEN_FIELDS` and `READ_ONLY_FIELDS` environment variables.
+    ///
+    /// Both have the form `role:field,field;role:field`. Without them every field is open to everyone.
+    ///
+    /// # Returns
+    ///
+    /// * A new `FieldPermissions`.
+    pub fn from_env() -> Self {
+        Self::from_settings(
+            &std::env::var("HIDDEN_FIELDS").unwrap_or_default(),
+            &std::env::var("READ_ONLY_FIELDS").unwrap_or_default(),
+        )
+    }
+
+    /// Builds the rules from settings in the form of `HIDDEN_FIELDS` and `READ_ONLY_FIELDS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hidden` - The hidden fields per role, e.g. `intern:phone_number,email`.
+    /// * `read_only` - The read-only fields per role.
+    ///
+    /// # Returns
+    ///
+    /// * A new `FieldPermissions`.
+    pub fn from_settings(hidden: &str, read_only: &str) -> Self {
+        Self {
+            hidden: parse_rules("HIDDEN_FIELDS", hidden, &[]),
+            read_only: parse_rules("READ_ONLY_FIELDS", read_only, &OPT_IN_FIELDS),
+        }
+    }
+
+    /// Collects the restrictions for a caller from all of their roles.
+    ///
+    /// Admins have no restrictions.
+    ///
+    /// # Arguments
+    ///
+    /// * `principal` - The authenticated caller.
+    ///
+    /// # Returns
+    ///
+    /// * The `FieldRules` for the caller.
+    pub fn for_principal(&self, principal: &Principal) -> FieldRules {
+        let mut rules = FieldRules::default();
+        if principal.has_role(ADMIN_ROLE) {
+            return rules;
+        }
+        for role in principal.roles() {
+            if let Some(fields) = self.hidden.get(role) {
+                rules.hidden.extend(fields.iter().cloned());
+            }
+            if let Some(fields) = self.read_only.get(role) {
+                rules.read_only.extend(fields.iter().cloned());
+            }
+        }
+        if SCORED_FIELDS
+            .iter()
+            .any(|field| rules.hidden.contains(*field))
+        {
+            rules.hidden.insert("quality_score".to_string());
+        }
+        // A field the user cannot see cannot be changed either
+        rules.read_only.extend(rules.hidden.iter().cloned());
+        rules
+    }
+}
+
+/// The restrictions that apply to one user.
+#[derive(Default)]
+pub struct FieldRules {
+    /// The fields the user may not read.
+    hidden: HashSet<String>,
+    /// The fields the user may not write, including the hidden ones.
+    read_only: HashSet<String>,
+}
+
+impl FromRequest for FieldRules {
+    type Error = ActixWebError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    /// Authenticates the caller like `Principal` does, then collects the restrictions for their roles.
+    ///
+    /// Handlers that take `FieldRules` are protected, so they do not need `Principal` as well.
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let principal = Principal::from_request(req, payload);
+        let permissions = req.app_data::<web::Data<FieldPermissions>>().cloned();
+        Box::pin(async move {
+            let principal = principal.await?;
+            Ok(match permissions {
+                Some(permissions) => permissions.for_principal(&principal),
+                None => FieldRules::default(),
+            })
+        })
+    }
+}
+
+impl FieldRules {
+    /// Replaces the hidden fields of a contact with placeholders.
+    ///
+    /// # Arguments
+    ///
+    /// * `contact` - The contact to mask.
+    ///
+    /// # Returns
+    ///
+    /// * The contact without the values the user may not read.
+    pub fn masked(&self, mut contact: Contact) -> Contact {
+        for field in &self.hidden {
+            match field.as_str() {
+                "first_name" => contact.first_name = MASK.to_string(),
+                "last_name" => contact.last_name = MASK.to_string(),
+                "email" => contact.email = MASK.to_string(),
+                "phone_number" => contact.phone_number = MASK.to_string(),
+                "company_id" => contact.company_id = None,
+                "status" => contact.status = MASK.to_string(),
+                "source" => contact.source = None,
+                "owner" => contact.owner = None,
+                "consent_source" => contact.consent_source = None,
+                "consent_timestamp" => contact.consent_timestamp = None,
+                "email_status" => contact.email_status = MASK.to_string(),
+                "email_verified_at" => contact.email_verified_at = None,
+                "uuid" => contact.uuid = None,
+                "external_id" => contact.external_id = None,
+                "archived_at" => contact.archived_at = None,
+                "quality_score" => contact.quality_score = 0,
+                _ => {}
+            }
+        }
+        contact
+    }
+
+    /// Replaces the hidden fields of a pending change with placeholders.
+    ///
+    /// The proposed data of a change holds the stored values of the fields the edit leaves alone,
+    /// so it needs masking like a contact does.
+    ///
+    /// # Arguments
+    ///
+    /// * `change` - The pending change to mask.
+    ///
+    /// # Returns
+    ///
+    /// * The change with `***` for every hidden field that has a value.
+    pub fn masked_change(&self, mut change: PendingChange) -> PendingChange {
+        if self.hidden.is_empty() {
+            return change;
+        }
+        if let Ok(Value::Object(mut proposed)) = serde_json::from_str::<Value>(&change.changes) {
+            for (field, value) in proposed.iter_mut() {
+                if self.hides(field) && !value.is_null() {
+                    *value = Value::String(MASK.to_string());
+                }
+            }
+            change.changes = Value::Object(proposed).to_string();
+        }
+        change
+    }
+
+    /// Returns whether the user may not read a field.
+    pub fn hides(&self, field: &str) -> bool {
+        self.hidden.contains(field)
+    }
+
+    /// Checks that the user may read a field, for features that reveal values without returning a contact.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The name of the contact field.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the field is not hidden.
+    /// * `Err(ApiError::Forbidden)` naming the field if it is.
+    pub fn check_readable(&self, field: &str) -> Result<(), ApiError> {
+        if self.hides(field) {
+            return Err(ApiError::Forbidden(format!(
+                "You are not allowed to read the field '{}'",
+                field
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that a list filter only uses fields the user may read.
+    ///
+    /// Filtering on a hidden field would reveal its values one guess at a time, even though
+    /// the contacts that match come back masked.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The filters from the query string.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if every filter that is set is on a readable field.
+    /// * `Err(ApiError::Forbidden)` naming the first hidden field that is filtered on.
+    pub fn check_filter(&self, filter: &ContactFilter) -> Result<(), ApiError> {
+        let filtered = [
+            ("status", filter.status.is_some()),
+            ("source", filter.source.is_some()),
+            ("owner", filter.owner.is_some()),
+            ("first_name", filter.first_name_prefix.is_some()),
+            ("last_name", filter.last_name_prefix.is_some()),
+            ("email", filter.email_prefix.is_some()),
+            ("archived_at", filter.include_archived.is_some()),
+            ("quality_score", filter.min_quality.is_some()),
+        ];
+        for (field, _) in filtered.iter().filter(|(_, set)| *set) {
+            self.check_readable(field)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the user may change a field, for routes that change one field on its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The name of the contact field.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if the field is not read-only.
+    /// * `Err(ApiError::Forbidden)` naming the field if it is.
+    pub fn check_writable(&self, field: &str) -> Result<(), ApiError> {
+        if self.read_only.contains(field) {
+            return Err(not_writable(field));
+        }
+        Ok(())
+    }
+
+    /// Checks that a new contact leaves the read-only fields empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `contact` - The contact data from the request body.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if no read-only field is set.
+    /// * `Err(ApiError::Forbidden)` naming the first read-only field that is set.
+    pub fn check_create(&self, contact: &NewContact) -> Result<(), ApiError> {
+        if self.read_only.is_empty() {
+            return Ok(());
+        }
+        let proposed = to_object(contact)?;
+        for (field, value) in &proposed {
+            let is_empty = value.is_null() || value.as_str().is_some_and(str::is_empty);
+            if self.read_only.contains(field) && !is_empty {
+                return Err(not_writable(field));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that an update leaves the read-only fields as they are.
+    ///
+    /// Clients often send back what they read, so a read-only field that is unchanged
+    /// or still holds the `***` placeholder is set back to the current value.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - The contact as it is now.
+    /// * `contact` - The contact data from the request body, fixed up in place.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` if no read-only field is changed.
+    /// * `Err(ApiError::Forbidden)` naming the first read-only field that is changed.
+    pub fn check_update(
+        &self,
+        current: &Contact,
+        contact: &mut NewContact,
+    ) -> Result<(), ApiError> {
+        if self.read_only.is_empty() {
+            return Ok(());
+        }
+        let current = to_object(current)?;
+        let mut proposed = to_object(&*contact)?;
+        for (field, value) in proposed.iter_mut() {
+            if !self.read_only.contains(field) {
+                continue;
+            }
+            let current_value = current.get(field).cloned().unwrap_or_default();
+            // Fields left out of an update are not changed, so `null` is fine too
+            if value.is_null() || *value == current_value || value.as_str() == Some(MASK) {
+                *value = current_value;
+            } else {
+                return Err(not_writable(field));
+            }
+        }
+        *contact = serde_json::from_value(Value::Object(proposed))
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Turns a value into a JSON object, to compare fields by name.
+fn to_object<T: serde::Serialize>(value: &T) -> Result<serde_json::Map<String, Value>, ApiError> {
+    match serde_json::to_value(value) {
+        Ok(Value::Object(object)) => Ok(object),
+        _ => Err(ApiError::BadRequest("Expected a JSON object".to_string())),
+    }
+}
+
+/// Builds the error for a write to a read-only field.
+fn not_writable(field: &str) -> ApiError {
+    ApiError::Forbidden(format!(
+        "You are not allowed to change the field '{}'",
+        field
+    ))
+}
@@ -0,0 +1,70 @@
+// backend/src/fields.rs
+// This file implements sparse fieldsets: `?fields=` picks which keys a contact response contains.
+// It exists so mobile clients on slow networks can fetch only the fields they show.
+// RELEVANT FILES: backend/src/handlers.rs, backend/src/introspection.rs, backend/src/links.rs
+
+use crate::error::ApiError;
+use crate::introspection::CONTACT_FIELDS;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Keys that are not contact fields, but can still be picked.
+const EXTRA_KEYS: [&str; 2] = ["_links", "_embedded"];
+
+/// The `fields` query parameter.
+#[derive(Deserialize)]
+pub struct FieldsQuery {
+    /// A comma-separated list of keys to return, e.g. `id,first_name,last_name`.
+    pub fields: Option<String>,
+}
+
+impl FieldsQuery {
+    /// Parses and checks the `fields` parameter.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(keys))` with the keys to keep.
+    /// * `Ok(None)` if all fields should be returned.
+    /// * `Err(ApiError::BadRequest)` if a key is not known.
+    pub fn parse(&self) -> Result<Option<Vec<String>>, ApiError> {
+        let Some(fields) = self.fields.as_deref() else {
+            return Ok(None);
+        };
+        let mut keys = Vec::new();
+        for key in fields.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+            let known = CONTACT_FIELDS.iter().any(|f| f.name == key) || EXTRA_KEYS.contains(&key);
+            if !known {
+                return Err(ApiError::BadRequest(format!("Unknown field '{}'", key)));
+            }
+            keys.push(key.to_string());
+        }
+        Ok(Some(keys))
+    }
+}
+
+/// Serializes a contact, or a list of contacts, with only the given keys.
+///
+/// # Arguments
+///
+/// * `value` - The contact or list of contacts.
+/// * `keys` - The keys to keep in each contact.
+///
+/// # Returns
+///
+/// * The reduced JSON.
+pub fn select_fields<T: Serialize>(value: &T, keys: &[String]) -> Value {
+    // Contacts only hold plain data, so serializing them cannot fail
+    let mut value = serde_json::to_value(value).expect("contacts serialize to JSON");
+    let keep = |object: &mut serde_json::Map<String, Value>| {
+        object.retain(|key, _| keys.iter().any(|k| k == key));
+    };
+    match &mut value {
+        Value::Object(object) => keep(object),
+        Value::Array(items) => items
+            .iter_mut()
+            .filter_map(Value::as_object_mut)
+            .for_each(keep),
+        _ => {}
+    }
+    value
+}
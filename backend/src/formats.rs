@@ -0,0 +1,177 @@
+// backend/src/formats.rs
+// This file picks the response format from the `Accept` header and converts JSON responses to it.
+// It exists so consumers that cannot read JSON get the same data without every handler knowing about formats.
+// RELEVANT FILES: backend/src/xml.rs, backend/src/msgpack.rs, backend/src/main.rs, backend/src/versioning.rs
+
+use crate::error::PROBLEM_JSON;
+use crate::msgpack::to_msgpack;
+use crate::xml::{singular, to_xml};
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, ACCEPT, CONTENT_TYPE, VARY};
+use actix_web::middleware::Next;
+use actix_web::Error as ActixWebError;
+
+/// The response formats the API can send.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// JSON, the default.
+    Json,
+    /// XML, for `Accept: application/xml` or `text/xml`.
+    Xml,
+    /// MessagePack, for `Accept: application/msgpack` or `application/x-msgpack`.
+    MsgPack,
+}
+
+impl Format {
+    /// Finds the format a client asked for in its `Accept` header.
+    ///
+    /// The first media type the API knows wins, like the version in `versioning.rs`.
+    /// Quality values are not compared, and anything else falls back to JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `accept` - The value of the `Accept` header.
+    ///
+    /// # Returns
+    ///
+    /// * The format to send.
+    pub fn from_accept(accept: &str) -> Self {
+        accept
+            .split(',')
+            // Drop parameters like `;q=0.9`
+            .map(|media_type| media_type.split(';').next().unwrap_or("").trim())
+            .find_map(|media_type| match media_type {
+                "application/xml" | "text/xml" => Some(Format::Xml),
+                "application/msgpack" | "application/x-msgpack" => Some(Format::MsgPack),
+                "application/json" | "*/*" => Some(Format::Json),
+                _ if media_type.ends_with("+json") => Some(Format::Json),
+                _ => None,
+            })
+            .unwrap_or(Format::Json)
+    }
+
+    /// Returns the content type of the format.
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Xml => "application/xml; charset=utf-8",
+            Format::MsgPack => "application/msgpack",
+        }
+    }
+}
+
+/// Names the root element of a response body.
+///
+/// Lists are named after the last fixed segment of their route, e.g. `contacts` or `relationships`,
+/// and one resource after its collection, e.g. `contact` for `/contacts/{id}`.
+/// Other objects, like counts, are named `response`.
+///
+/// # Arguments
+///
+/// * `pattern` - The route pattern, e.g. `/api/v1/contacts/{id}`.
+/// * `is_list` - Whether the body is a JSON array.
+///
+/// # Returns
+///
+/// * The name of the root element.
+fn root_element(pattern: &str, is_list: bool) -> String {
+    // Routes are registered the same way under every version scope
+    let path = pattern
+        .strip_prefix("/api/v1")
+        .or_else(|| pattern.strip_prefix("/api"))
+        .unwrap_or(pattern);
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let is_parameter = |segment: &&str| segment.starts_with('{');
+    let Some(name) = segments.iter().rev().find(|s| !is_parameter(s)) else {
+        return "response".to_string();
+    };
+
+    let is_resource = segments.len() == 1 || segments.last().is_some_and(is_parameter);
+    match singular(name).as_str() {
+        _ if is_list => name.to_string(),
+        "item" => "response".to_string(),
+        single if is_resource => single.to_string(),
+        _ => "response".to_string(),
+    }
+}
+
+/// Tells whether a response has a JSON body, including problem details (see `error::Problem`).
+///
+/// # Arguments
+///
+/// * `res` - The response.
+///
+/// # Returns
+///
+/// * `true` if the content type is `application/json` or `application/problem+json`.
+pub(crate) fn is_json<B>(res: &ServiceResponse<B>) -> bool {
+    res.headers().get(CONTENT_TYPE).is_some_and(|value| {
+        value.as_bytes().starts_with(b"application/json")
+            || value.as_bytes().starts_with(PROBLEM_JSON.as_bytes())
+    })
+}
+
+/// Middleware that converts JSON responses to the format the client asked for.
+///
+/// Responses that are not JSON (e.g. vCards or NDJSON streams) are passed on unchanged.
+/// Every JSON response gets `Vary: Accept`, so caches keep the formats apart.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service, in the negotiated format.
+pub async fn negotiate_format(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, ActixWebError> {
+    let format = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(Format::from_accept)
+        .unwrap_or(Format::Json);
+    let mut res = next.call(req).await?;
+
+    if !is_json(&res) {
+        return Ok(res.map_into_boxed_body());
+    }
+    res.headers_mut()
+        .append(VARY, HeaderValue::from_static("accept"));
+    if format == Format::Json {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let pattern = res.request().match_pattern().unwrap_or_default();
+    let is_error = res.status().is_client_error() || res.status().is_server_error();
+    let (req, res) = res.into_parts();
+    let (mut res, body) = res.into_parts();
+    let bytes = to_bytes(body)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.into()))?;
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        // Not valid JSON after all, so send it as it is
+        return Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(bytes))));
+    };
+
+    let body = match format {
+        Format::Json => bytes.to_vec(),
+        // Error bodies are named `error`, everything else after its route
+        Format::Xml if is_error => to_xml("error", &value).into_bytes(),
+        Format::Xml => {
+            // A list in an envelope (see `envelope.rs`) is still named after its route
+            let is_list = value.is_array() || value.get("data").is_some_and(|d| d.is_array());
+            to_xml(&root_element(&pattern, is_list), &value).into_bytes()
+        }
+        Format::MsgPack => to_msgpack(&value),
+    };
+    res.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(format.content_type()),
+    );
+    Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))))
+}
@@ -3,36 +3,318 @@
 // It defines the logic for creating, reading, updating, and deleting contacts.
 // RELEVANT FILES: backend/src/main.rs, backend/src/models.rs, backend/src/error.rs
 
-use crate::auth::Claims;
+use crate::auth::Principal;
+use crate::companies::{apply_rules, CompanyRules};
+use crate::demo::{demo_contacts, DemoMode};
+use crate::enrichment::Enricher;
 use crate::error::ApiError;
 use crate::establish_connection;
-use crate::models::{Contact, NewContact};
-use actix_web::{delete, get, post, put, web, HttpResponse};
+use crate::feature_flags::FeatureFlags;
+use crate::field_permissions::FieldRules;
+use crate::fields::{select_fields, FieldsQuery};
+use crate::identifiers::{uuid_or_new, ContactKey};
+use crate::includes::{embed, IncludeQuery};
+use crate::links::{link_contact, self_link_header, ExternalBaseUrl, LinkedContact};
+use crate::lookup::{lower, prefix_range};
+use crate::models::{Contact, ContactFilter, NewContact, PendingChange, CONTACT_STATUSES};
+use crate::normalization::normalize_contact;
+use crate::pending_changes::{propose_change, ChangeApproval};
+use crate::schema::contacts;
+use crate::transaction::Transaction;
+use actix_web::{web, HttpRequest, HttpResponse};
 use diesel::prelude::*;
+use diesel::sqlite::{Sqlite, SqliteConnection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// The number of contacts with a given status.
+#[derive(Serialize)]
+pub struct StatusCount {
+    /// The lifecycle status.
+    pub status: String,
+    /// The number of contacts with this status.
+    pub count: i64,
+}
+
+/// How many contacts a sample has when the caller does not say.
+const DEFAULT_SAMPLE_SIZE: i64 = 5;
+
+/// The largest sample a caller can ask for.
+const MAX_SAMPLE_SIZE: i64 = 50;
+
+diesel::define_sql_function! {
+    /// SQLite's `RANDOM()`, used to shuffle rows.
+    fn random() -> diesel::sql_types::BigInt;
+}
+
+/// The most contacts one batch can read.
+const MAX_BATCH_SIZE: usize = 100;
+
+/// The request body for reading many contacts at once.
+#[derive(Deserialize)]
+pub struct BatchGetRequest {
+    /// The IDs of the contacts to read.
+    pub ids: Vec<i32>,
+}
+
+/// The contacts found by a batch read.
+#[derive(Serialize)]
+pub struct BatchGetResponse {
+    /// The contacts that were found, in the order of the requested IDs.
+    pub contacts: Vec<LinkedContact>,
+    /// The requested IDs that do not exist.
+    pub missing: Vec<i32>,
+}
+
+/// The query parameters for a contact sample.
+#[derive(Deserialize)]
+pub struct SampleQuery {
+    /// The number of contacts to return.
+    pub n: Option<i64>,
+}
+
+/// Checks that contact data is valid before it is written.
+///
+/// # Arguments
+///
+/// * `contact` - The contact data from the request body.
+///
+/// # Returns
+///
+/// * `Ok(())` if the data is valid.
+/// * `Err(ApiError::BadRequest)` if the status is unknown.
+fn validate_contact(contact: &NewContact) -> Result<(), ApiError> {
+    if let Some(status) = &contact.status
+        && !CONTACT_STATUSES.contains(&status.as_str())
+    {
+        return Err(ApiError::BadRequest(format!(
+            "Status must be one of: {}",
+            CONTACT_STATUSES.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Builds a query for all contacts that match a filter.
+///
+/// # Arguments
+///
+/// * `filter` - The filters from the query string. Archived contacts are left out unless it has `include_archived`.
+///
+/// # Returns
+///
+/// * A boxed query that can be extended, e.g. with ordering.
+pub(crate) fn filtered_contacts(filter: &ContactFilter) -> contacts::BoxedQuery<'_, Sqlite> {
+    let mut query = contacts::table.into_boxed();
+    if let Some(status) = &filter.status {
+        query = query.filter(contacts::status.eq(status));
+    }
+    if let Some(source) = &filter.source {
+        query = query.filter(contacts::source.eq(source));
+    }
+    if let Some(owner) = &filter.owner {
+        query = query.filter(contacts::owner.eq(owner));
+    }
+    // Prefixes are ranges on the `lower()` indexes, like in autocomplete
+    if let Some((from, to)) = filter.first_name_prefix.as_deref().and_then(prefix_range) {
+        query = query.filter(
+            lower(contacts::first_name)
+                .ge(from)
+                .and(lower(contacts::first_name).lt(to)),
+        );
+    }
+    if let Some((from, to)) = filter.last_name_prefix.as_deref().and_then(prefix_range) {
+        query = query.filter(
+            lower(contacts::last_name)
+                .ge(from)
+                .and(lower(contacts::last_name).lt(to)),
+        );
+    }
+    if let Some((from, to)) = filter.email_prefix.as_deref().and_then(prefix_range) {
+        query = query.filter(
+            lower(contacts::email)
+                .ge(from)
+                .and(lower(contacts::email).lt(to)),
+        );
+    }
+    if let Some(min_quality) = filter.min_quality {
+        query = query.filter(contacts::quality_score.ge(min_quality));
+    }
+    if filter.include_archived != Some(true) {
+        query = query.filter(contacts::archived_at.is_null());
+    }
+    query
+}
+
+/// Marks a contact's email as unchecked when it is about to change.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection.
+/// * `id` - The ID of the contact.
+/// * `email` - The new email address.
+///
+/// # Returns
+///
+/// * `Ok(())` when done.
+/// * `Err` if there is a database error.
+pub(crate) fn reset_changed_email_status(
+    conn: &mut SqliteConnection,
+    id: i32,
+    email: &str,
+) -> QueryResult<()> {
+    diesel::update(contacts::table.find(id).filter(contacts::email.ne(email)))
+        .set((
+            contacts::email_status.eq("unverified"),
+            contacts::email_verified_at.eq(None::<i64>),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// What an edit of a stored contact did.
+pub(crate) enum Edit {
+    /// The contact was updated.
+    Updated(Box<Contact>),
+    /// The edit waits for a reviewer.
+    Proposed(PendingChange),
+}
+
+/// Decides how an edit of a stored contact is applied, from the caller and the settings.
+///
+/// Every route that changes a stored contact goes through it, so none of them skips the field
+/// rules or the approval.
+pub(crate) struct ContactEditor<'a> {
+    /// The authenticated caller.
+    pub principal: &'a Principal,
+    /// The fields the caller may not read or write.
+    pub field_rules: &'a FieldRules,
+    /// The setting that decides if edits need approval.
+    pub approval: &'a ChangeApproval,
+    /// The feature flags of the caller's workspace, which can switch normalization rules off.
+    pub flags: &'a FeatureFlags,
+}
+
+impl ContactEditor<'_> {
+    /// Applies an edit to a stored contact, or stores it for a reviewer when the caller's edits need approval.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - The database connection.
+    /// * `current` - The contact as it is stored.
+    /// * `contact` - The new contact data.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Edit)` with the updated contact, or the pending change.
+    /// * `Err(ApiError)` if a read-only field is changed or there is a database error.
+    pub(crate) fn apply(
+        &self,
+        conn: &mut SqliteConnection,
+        current: &Contact,
+        mut contact: NewContact,
+    ) -> Result<Edit, ApiError> {
+        // The UUID names the contact for good, so it cannot be changed
+        contact.uuid = None;
+        self.field_rules.check_update(current, &mut contact)?;
+        // Normalized before it may wait for approval, so the reviewer sees what will be stored
+        normalize_contact(self.flags, &mut contact);
+
+        if self.approval.applies_to(self.principal) {
+            let change = propose_change(conn, current.id, &contact, self.principal.id())?;
+            return Ok(Edit::Proposed(self.field_rules.masked_change(change)));
+        }
+
+        // A new email address has not been checked yet
+        reset_changed_email_status(conn, current.id, &contact.email)?;
+        let updated = diesel::update(contacts::table.find(current.id))
+            .set(&contact)
+            .get_result::<Contact>(conn)?;
+        Ok(Edit::Updated(Box::new(updated)))
+    }
+}
 
 /// Handles the creation of a new contact.
 ///
 /// This endpoint is protected and requires a valid JWT.
+/// Clients may send their own `uuid`. Sending the same UUID again edits that contact
+/// instead of creating a second one, so a retried request is safe. The edit is checked
+/// like one through `PUT /contacts/{id}`, and may wait for approval.
 ///
 /// # Arguments
 ///
-/// * `_claims` - The claims extracted from the JWT, used for authentication.
+/// * `principal` - The authenticated caller, used to decide if an edit needs approval.
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
 /// * `contact` - The new contact data from the request body.
+/// * `rules` - The company rules, used to link the contact to a company.
+/// * `approval` - The setting that decides if edits need approval.
+/// * `enricher` - The enricher. When it is enabled, the new contact is enriched in the background once it is committed.
+/// * `flags` - The feature flags of the user's workspace, which can switch enrichment and normalization rules off.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
 ///
 /// # Returns
 ///
-/// * `Ok(HttpResponse)` with a success message if the contact is created.
-/// * `Err(ApiError)` if there is a database error.
-#[post("/contacts")]
+/// * `Ok(HttpResponse)` with a success message if the contact is created or updated,
+///   or 202 Accepted with the pending change if an edit waits for a reviewer.
+/// * `Err(ApiError)` if the UUID is malformed, a read-only field is set or there is a database error.
+// Actix handlers take everything they need as arguments
+#[allow(clippy::too_many_arguments)]
 pub async fn create_contact(
-    _claims: Claims,
+    principal: Principal,
+    field_rules: FieldRules,
     contact: web::Json<NewContact>,
+    rules: web::Data<CompanyRules>,
+    approval: web::Data<ChangeApproval>,
+    enricher: web::Data<Enricher>,
+    flags: FeatureFlags,
+    transaction: Transaction,
 ) -> Result<HttpResponse, ApiError> {
-    let mut conn = establish_connection()?;
+    validate_contact(&contact)?;
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+
+    let mut contact = contact.into_inner();
+    let uuid = uuid_or_new(contact.uuid.take())?;
+
+    // When the UUID exists, this is a retry or an edit
+    let existing = contacts::table
+        .filter(contacts::uuid.eq(&uuid))
+        .first::<Contact>(conn)
+        .optional()?;
+    if let Some(current) = existing {
+        let editor = ContactEditor {
+            principal: &principal,
+            field_rules: &field_rules,
+            approval: &approval,
+            flags: &flags,
+        };
+        return Ok(match editor.apply(conn, &current, contact)? {
+            Edit::Updated(_) => HttpResponse::Ok().body("Contact updated successfully"),
+            Edit::Proposed(change) => HttpResponse::Accepted().json(change),
+        });
+    }
+
+    field_rules.check_create(&contact)?;
+    normalize_contact(&flags, &mut contact);
+    apply_rules(conn, &rules, &mut contact)?;
+    contact.uuid = Some(uuid);
+    let id = diesel::insert_into(crate::schema::contacts::table)
+        .values(&contact)
+        .returning(contacts::id)
+        .get_result::<i32>(conn)?;
 
-    diesel::insert_into(crate::schema::contacts::table)
-        .values(&contact.into_inner())
-        .execute(&mut conn)?;
+    // Enrichment calls an external API, so it must not slow down the response. It starts after
+    // the commit, so it finds the contact and does not wait for the write lock of this request
+    if enricher.is_enabled() && flags.enabled("enrichment") {
+        let enricher = enricher.into_inner();
+        transaction.after_commit(move || {
+            actix_web::rt::spawn(async move {
+                if let Err(e) = enricher.enrich(id, &contact.email).await {
+                    log::warn!("Could not enrich contact {}: {}", id, e);
+                }
+            });
+        });
+    }
 
     Ok(HttpResponse::Ok().body("Contact created successfully"))
 }
@@ -40,83 +322,518 @@ pub async fn create_contact(
 /// Handles reading all contacts from the database.
 ///
 /// This endpoint is protected and requires a valid JWT.
+/// The list can be filtered by `status`, `source` and `owner`.
+/// Each contact has `_links`, and the link to the list itself is sent in the `Link` header.
 ///
 /// # Arguments
 ///
-/// * `_claims` - The claims extracted from the JWT, used for authentication.
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `req` - The HTTP request, used to build links.
+/// * `filter` - The optional filters from the query string.
+/// * `include` - The related data to embed, e.g. `?include=company`.
+/// * `fields` - The keys to return for each contact, e.g. `?fields=id,first_name`.
+/// * `demo` - The demo mode setting. In demo mode, synthetic contacts are returned.
+/// * `base_url` - The external base URL for links.
 ///
 /// # Returns
 ///
 /// * `Ok(HttpResponse)` with a JSON array of contacts.
+/// * `Err(ApiError::Forbidden)` if a filter is on a field the user may not read.
+/// * `Err(ApiError)` if an include or field is not supported or there is a database error.
+pub async fn read_contacts(
+    field_rules: FieldRules,
+    req: HttpRequest,
+    filter: web::Query<ContactFilter>,
+    include: web::Query<IncludeQuery>,
+    fields: web::Query<FieldsQuery>,
+    demo: web::Data<DemoMode>,
+    base_url: web::Data<ExternalBaseUrl>,
+) -> Result<HttpResponse, ApiError> {
+    field_rules.check_filter(&filter)?;
+    let includes = include.parse()?;
+    let fields = fields.parse()?;
+    let contacts = if demo.enabled {
+        demo_contacts()
+            .into_iter()
+            .filter(|c| filter.matches(c))
+            .collect()
+    } else {
+        let mut conn = establish_connection()?;
+        filtered_contacts(&filter)
+            .order((
+                crate::schema::contacts::last_name.asc(),
+                crate::schema::contacts::first_name.asc(),
+            ))
+            .load::<Contact>(&mut conn)?
+    };
+
+    let base = base_url.resolve(&req);
+    let mut contacts: Vec<LinkedContact> = contacts
+        .into_iter()
+        .map(|c| link_contact(&base, field_rules.masked(c)))
+        .collect();
+
+    // Demo contacts have no related data in the database
+    if !demo.enabled && !includes.is_empty() {
+        embed(&mut establish_connection()?, includes, &mut contacts)?;
+    }
+
+    let mut response = HttpResponse::Ok();
+    response.insert_header(("Link", self_link_header(&base, &req)));
+    Ok(match fields {
+        Some(keys) => response.json(select_fields(&contacts, &keys)),
+        None => response.json(contacts),
+    })
+}
+
+/// The number of contacts that match a filter.
+#[derive(Serialize)]
+pub struct ContactCount {
+    /// The number of contacts.
+    pub count: i64,
+}
+
+/// Handles counting contacts without loading them.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// It takes the same filters as the list endpoint.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `filter` - The optional filters from the query string.
+/// * `demo` - The demo mode setting. In demo mode, synthetic contacts are counted.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the number of matching contacts.
+/// * `Err(ApiError::Forbidden)` if a filter is on a field the user may not read.
 /// * `Err(ApiError)` if there is a database error.
-#[get("/contacts")]
-pub async fn read_contacts(_claims: Claims) -> Result<HttpResponse, ApiError> {
-    let mut conn = establish_connection()?;
-
-    let contacts = crate::schema::contacts::table
-        .order((
-            crate::schema::contacts::last_name.asc(),
-            crate::schema::contacts::first_name.asc(),
-        ))
-        .load::<Contact>(&mut conn)?;
+pub async fn count_contacts(
+    field_rules: FieldRules,
+    filter: web::Query<ContactFilter>,
+    demo: web::Data<DemoMode>,
+) -> Result<HttpResponse, ApiError> {
+    field_rules.check_filter(&filter)?;
+    let count = if demo.enabled {
+        demo_contacts().iter().filter(|c| filter.matches(c)).count() as i64
+    } else {
+        let mut conn = establish_connection()?;
+        filtered_contacts(&filter)
+            .count()
+            .get_result::<i64>(&mut conn)?
+    };
+
+    Ok(HttpResponse::Ok().json(ContactCount { count }))
+}
+
+/// Handles reading a random sample of contacts.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// It takes the same filters as the list endpoint. It is meant for UI tests and demo screens.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `query` - The sample size from the query string, at most 50.
+/// * `filter` - The optional filters from the query string.
+/// * `demo` - The demo mode setting. In demo mode, synthetic contacts are sampled.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a JSON array of contacts in random order.
+/// * `Err(ApiError::Forbidden)` if a filter is on a field the user may not read.
+/// * `Err(ApiError)` if there is a database error.
+pub async fn read_contact_sample(
+    field_rules: FieldRules,
+    query: web::Query<SampleQuery>,
+    filter: web::Query<ContactFilter>,
+    demo: web::Data<DemoMode>,
+) -> Result<HttpResponse, ApiError> {
+    field_rules.check_filter(&filter)?;
+    let n = query
+        .n
+        .unwrap_or(DEFAULT_SAMPLE_SIZE)
+        .clamp(1, MAX_SAMPLE_SIZE);
+
+    let contacts: Vec<Contact> = if demo.enabled {
+        // Demo data is fixed, so start at a time-based offset to vary the sample
+        let contacts: Vec<Contact> = demo_contacts()
+            .into_iter()
+            .filter(|c| filter.matches(c))
+            .collect();
+        let offset = crate::unix_now() as usize % contacts.len().max(1);
+        contacts
+            .iter()
+            .cycle()
+            .skip(offset)
+            .take(contacts.len().min(n as usize))
+            .cloned()
+            .collect()
+    } else {
+        let mut conn = establish_connection()?;
+        filtered_contacts(&filter)
+            .order(random())
+            .limit(n)
+            .load::<Contact>(&mut conn)?
+    };
+
+    let contacts: Vec<Contact> = contacts
+        .into_iter()
+        .map(|c| field_rules.masked(c))
+        .collect();
 
     Ok(HttpResponse::Ok().json(contacts))
 }
 
+/// Handles counting contacts per lifecycle status.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Every status is listed, also those without contacts.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `demo` - The demo mode setting. In demo mode, synthetic contacts are counted.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a JSON array of statuses and counts, in pipeline order.
+/// * `Err(ApiError::Forbidden)` if the user may not read the status.
+/// * `Err(ApiError)` if there is a database error.
+pub async fn read_status_stats(
+    field_rules: FieldRules,
+    demo: web::Data<DemoMode>,
+) -> Result<HttpResponse, ApiError> {
+    field_rules.check_readable("status")?;
+    let counts: Vec<(String, i64)> = if demo.enabled {
+        let mut counts: HashMap<String, i64> = HashMap::new();
+        for contact in demo_contacts() {
+            *counts.entry(contact.status).or_default() += 1;
+        }
+        counts.into_iter().collect()
+    } else {
+        let mut conn = establish_connection()?;
+        contacts::table
+            .group_by(contacts::status)
+            .select((contacts::status, diesel::dsl::count_star()))
+            .load(&mut conn)?
+    };
+
+    let stats: Vec<StatusCount> = CONTACT_STATUSES
+        .iter()
+        .map(|status| StatusCount {
+            status: status.to_string(),
+            count: counts
+                .iter()
+                .find(|(s, _)| s == status)
+                .map_or(0, |(_, count)| *count),
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
 /// Handles reading a specific contact by its ID.
 ///
 /// This endpoint is protected and requires a valid JWT.
 ///
 /// # Arguments
 ///
-/// * `_claims` - The claims extracted from the JWT, used for authentication.
-/// * `id` - The ID of the contact to read, from the URL path.
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `req` - The HTTP request, used to build links.
+/// * `id` - The ID or UUID of the contact to read, from the URL path.
+/// * `include` - The related data to embed, e.g. `?include=company,enrichments`.
+/// * `fields` - The keys to return, e.g. `?fields=id,first_name`.
+/// * `demo` - The demo mode setting. In demo mode, a synthetic contact is returned.
+/// * `base_url` - The external base URL for links.
 ///
 /// # Returns
 ///
-/// * `Ok(HttpResponse)` with the JSON data for the contact.
-/// * `Err(ApiError)` if the contact is not found or there is a database error.
-#[get("/contacts/{id}")]
+/// * `Ok(HttpResponse)` with the JSON data for the contact, including its `_links`.
+/// * `Err(ApiError)` if the contact is not found, an include or field is not supported or there is a database error.
 pub async fn read_contact(
-    _claims: Claims,
-    id: web::Path<i32>,
+    field_rules: FieldRules,
+    req: HttpRequest,
+    id: web::Path<ContactKey>,
+    include: web::Query<IncludeQuery>,
+    fields: web::Query<FieldsQuery>,
+    demo: web::Data<DemoMode>,
+    base_url: web::Data<ExternalBaseUrl>,
+) -> Result<HttpResponse, ApiError> {
+    let includes = include.parse()?;
+    let fields = fields.parse()?;
+    let id = id.into_inner();
+    let base = base_url.resolve(&req);
+
+    let contact = if demo.enabled {
+        let contact = demo_contacts()
+            .into_iter()
+            .find(|c| id.matches(c.id, c.uuid.as_deref()))
+            .ok_or(ApiError::NotFound)?;
+        link_contact(&base, field_rules.masked(contact))
+    } else {
+        let mut conn = establish_connection()?;
+        let id = id.resolve(&mut conn)?;
+        let contact = crate::schema::contacts::table
+            .find(id)
+            .first::<Contact>(&mut conn)?;
+
+        let mut contacts = [link_contact(&base, field_rules.masked(contact))];
+        embed(&mut conn, includes, &mut contacts)?;
+        let [contact] = contacts;
+        contact
+    };
+
+    Ok(match fields {
+        Some(keys) => HttpResponse::Ok().json(select_fields(&contact, &keys)),
+        None => HttpResponse::Ok().json(contact),
+    })
+}
+
+/// Handles checking if a contact exists.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// The response has no body, only the status code tells the result.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `id` - The ID or UUID of the contact to check, from the URL path.
+/// * `demo` - The demo mode setting. In demo mode, synthetic contacts are checked.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with status 200 if the contact exists.
+/// * `Err(ApiError)` with status 404 if it does not, or if there is a database error.
+pub async fn contact_exists(
+    _principal: Principal,
+    id: web::Path<ContactKey>,
+    demo: web::Data<DemoMode>,
+) -> Result<HttpResponse, ApiError> {
+    let exists = if demo.enabled {
+        demo_contacts()
+            .iter()
+            .any(|c| id.matches(c.id, c.uuid.as_deref()))
+    } else {
+        let mut conn = establish_connection()?;
+        let query = match id.into_inner() {
+            ContactKey::Id(id) => contacts::table.filter(contacts::id.eq(id)).into_boxed(),
+            ContactKey::Uuid(uuid) => contacts::table.filter(contacts::uuid.eq(uuid)).into_boxed(),
+        };
+        diesel::select(diesel::dsl::exists(query)).get_result(&mut conn)?
+    };
+
+    if exists {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Err(ApiError::NotFound)
+    }
+}
+
+/// Handles reading many contacts by their IDs in one request.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// It saves clients from sending one request per contact, e.g. when showing a list of members.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `req` - The HTTP request, used to build links.
+/// * `request` - The IDs to read, at most 100.
+/// * `include` - The related data to embed, e.g. `?include=company`.
+/// * `demo` - The demo mode setting. In demo mode, synthetic contacts are read.
+/// * `base_url` - The external base URL for links.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the found contacts and the IDs that were not found.
+/// * `Err(ApiError)` if there are too many IDs, an include is not supported or there is a database error.
+pub async fn batch_get_contacts(
+    field_rules: FieldRules,
+    req: HttpRequest,
+    request: web::Json<BatchGetRequest>,
+    include: web::Query<IncludeQuery>,
+    demo: web::Data<DemoMode>,
+    base_url: web::Data<ExternalBaseUrl>,
 ) -> Result<HttpResponse, ApiError> {
-    let mut conn = establish_connection()?;
+    let includes = include.parse()?;
+    let mut ids = request.into_inner().ids;
+    if ids.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} IDs can be read at once",
+            MAX_BATCH_SIZE
+        )));
+    }
+    // Keep the first occurrence of each ID, in request order
+    let mut seen = HashSet::new();
+    ids.retain(|id| seen.insert(*id));
+
+    let found: Vec<Contact> = if demo.enabled {
+        demo_contacts()
+            .into_iter()
+            .filter(|c| ids.contains(&c.id))
+            .collect()
+    } else {
+        let mut conn = establish_connection()?;
+        contacts::table
+            .filter(contacts::id.eq_any(&ids))
+            .load::<Contact>(&mut conn)?
+    };
+
+    let mut by_id: HashMap<i32, Contact> = found.into_iter().map(|c| (c.id, c)).collect();
+    let base = base_url.resolve(&req);
+    let mut contacts = Vec::with_capacity(by_id.len());
+    let mut missing = Vec::new();
+    for id in ids {
+        match by_id.remove(&id) {
+            Some(contact) => contacts.push(link_contact(&base, field_rules.masked(contact))),
+            None => missing.push(id),
+        }
+    }
 
-    let contact = crate::schema::contacts::table
-        .find(id.into_inner())
-        .first::<Contact>(&mut conn)?;
+    // Demo contacts have no related data in the database
+    if !demo.enabled && !includes.is_empty() {
+        embed(&mut establish_connection()?, includes, &mut contacts)?;
+    }
 
-    Ok(HttpResponse::Ok().json(contact))
+    Ok(HttpResponse::Ok().json(BatchGetResponse { contacts, missing }))
 }
 
 /// Handles updating an existing contact by its ID.
 ///
 /// This endpoint is protected and requires a valid JWT.
+/// When change approval is required, edits by users without the `admin` role
+/// are stored as a pending change for a reviewer instead.
 ///
 /// # Arguments
 ///
-/// * `_claims` - The claims extracted from the JWT, used for authentication.
-/// * `id` - The ID of the contact to update, from the URL path.
+/// * `principal` - The authenticated caller, used to decide if the edit needs approval and which normalization rules apply.
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `id` - The ID or UUID of the contact to update, from the URL path.
 /// * `contact` - The updated contact data from the request body.
+/// * `approval` - The setting that decides if edits need approval.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
 ///
 /// # Returns
 ///
-/// * `Ok(HttpResponse)` with a success message if the contact is updated.
-/// * `Err(ApiError)` if the contact is not found or there is a database error.
-#[put("/contacts/{id}")]
+/// * `Ok(HttpResponse)` with a success message if the contact is updated,
+///   or 202 Accepted with the pending change if it waits for a reviewer.
+/// * `Err(ApiError)` if the contact is not found, a read-only field is changed or there is a database error.
 pub async fn update_contact(
-    _claims: Claims,
-    id: web::Path<i32>,
+    principal: Principal,
+    field_rules: FieldRules,
+    id: web::Path<ContactKey>,
+    contact: web::Json<NewContact>,
+    approval: web::Data<ChangeApproval>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    validate_contact(&contact)?;
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+
+    let id = id.resolve(conn)?;
+    let current = contacts::table.find(id).first::<Contact>(conn)?;
+    let flags = FeatureFlags::load(conn, principal.workspace())?;
+    let editor = ContactEditor {
+        principal: &principal,
+        field_rules: &field_rules,
+        approval: &approval,
+        flags: &flags,
+    };
+
+    Ok(match editor.apply(conn, &current, contact.into_inner())? {
+        Edit::Updated(_) => HttpResponse::Ok().body("Contact updated successfully"),
+        Edit::Proposed(change) => HttpResponse::Accepted().json(change),
+    })
+}
+
+/// Handles creating or updating a contact by its ID in another system.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Integrations that sync from other systems can send every record here without
+/// checking first if it exists. The `source` and `external_id` from the path
+/// override those in the body.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to decide if an edit needs approval.
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `req` - The HTTP request, used to build links.
+/// * `path` - The source system and the ID of the contact in it.
+/// * `contact` - The contact data from the request body.
+/// * `rules` - The company rules, used to link the contact to a company.
+/// * `approval` - The setting that decides if edits need approval.
+/// * `base_url` - The external base URL for links.
+/// * `flags` - The feature flags of the user's workspace, which can switch normalization rules off.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with status 201 and the contact if it was created, 200 if it was updated,
+///   or 202 Accepted with the pending change if the edit waits for a reviewer.
+/// * `Err(ApiError)` if the data is invalid, a read-only field is written or there is a database error.
+// Actix handlers take everything they need as arguments
+#[allow(clippy::too_many_arguments)]
+pub async fn upsert_contact_by_external_id(
+    principal: Principal,
+    field_rules: FieldRules,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
     contact: web::Json<NewContact>,
+    rules: web::Data<CompanyRules>,
+    approval: web::Data<ChangeApproval>,
+    base_url: web::Data<ExternalBaseUrl>,
+    flags: FeatureFlags,
+    transaction: Transaction,
 ) -> Result<HttpResponse, ApiError> {
-    let mut conn = establish_connection()?;
+    validate_contact(&contact)?;
+    let (source, external_id) = path.into_inner();
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+
+    let mut contact = contact.into_inner();
+    contact.source = Some(source.clone());
+    contact.external_id = Some(external_id.clone());
 
-    diesel::update(crate::schema::contacts::table.find(id.into_inner()))
-        .set(contact.into_inner())
-        .execute(&mut conn)?;
+    // The request transaction holds the write lock, so two syncs of the same record cannot both insert
+    let existing = contacts::table
+        .filter(contacts::source.eq(&source))
+        .filter(contacts::external_id.eq(&external_id))
+        .first::<Contact>(conn)
+        .optional()?;
 
-    Ok(HttpResponse::Ok().body("Contact updated successfully"))
+    let (saved, created) = match existing {
+        Some(current) => {
+            let editor = ContactEditor {
+                principal: &principal,
+                field_rules: &field_rules,
+                approval: &approval,
+                flags: &flags,
+            };
+            match editor.apply(conn, &current, contact)? {
+                Edit::Updated(updated) => (*updated, false),
+                Edit::Proposed(change) => return Ok(HttpResponse::Accepted().json(change)),
+            }
+        }
+        None => {
+            field_rules.check_create(&contact)?;
+            normalize_contact(&flags, &mut contact);
+            apply_rules(conn, &rules, &mut contact)?;
+            contact.uuid = Some(uuid_or_new(contact.uuid.take())?);
+            let created = diesel::insert_into(contacts::table)
+                .values(&contact)
+                .get_result::<Contact>(conn)?;
+            (created, true)
+        }
+    };
+
+    let saved = link_contact(&base_url.resolve(&req), field_rules.masked(saved));
+    Ok(if created {
+        HttpResponse::Created().json(saved)
+    } else {
+        HttpResponse::Ok().json(saved)
+    })
 }
 
 /// Handles deleting a contact by its ID.
@@ -125,18 +842,24 @@ pub async fn update_contact(
 ///
 /// # Arguments
 ///
-/// * `_claims` - The claims extracted from the JWT, used for authentication.
-/// * `id` - The ID of the contact to delete, from the URL path.
+/// * `_principal` - The authenticated caller.
+/// * `id` - The ID or UUID of the contact to delete, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
 ///
 /// # Returns
 ///
 /// * `Ok(HttpResponse)` with a success message if the contact is deleted.
 /// * `Err(ApiError)` if the contact is not found or there is a database error.
-#[delete("/contacts/{id}")]
-pub async fn delete_contact(_claims: Claims, id: web::Path<i32>) -> Result<HttpResponse, ApiError> {
-    let mut conn = establish_connection()?;
+pub async fn delete_contact(
+    _principal: Principal,
+    id: web::Path<ContactKey>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
 
-    diesel::delete(crate::schema::contacts::table.find(id.into_inner())).execute(&mut conn)?;
+    let id = id.resolve(conn)?;
+    diesel::delete(crate::schema::contacts::table.find(id)).execute(conn)?;
 
     Ok(HttpResponse::Ok().body("Contact deleted successfully"))
 }
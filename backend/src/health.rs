@@ -0,0 +1,58 @@
+// backend/src/health.rs
+// This file contains the readiness check that load balancers and orchestrators call.
+// It exists so traffic is only sent to instances that can reach their database, and so operators see the maintenance state.
+// RELEVANT FILES: backend/src/main.rs, backend/src/maintenance.rs, backend/src/circuit_breaker.rs
+
+use crate::auth::TokenValidator;
+use crate::establish_connection;
+use crate::maintenance::{MaintenanceMode, MaintenanceState};
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use serde::Serialize;
+
+/// The result of the readiness check.
+#[derive(Serialize)]
+pub struct Readiness {
+    /// `ready` when the instance can serve requests, otherwise `unavailable`.
+    pub status: &'static str,
+    /// `ok` when the database answered, otherwise `error`.
+    pub database: &'static str,
+    /// The maintenance state. Reads still work when it is read-only, so the instance stays ready.
+    pub maintenance: MaintenanceState,
+    /// The circuit of the calls to the identity provider: `closed`, `open` or `half_open`.
+    /// Every instance shares the identity provider, so an open circuit does not make this one unready.
+    pub identity_provider: &'static str,
+}
+
+/// Handles the readiness check.
+///
+/// This endpoint is public and does not require a JWT, so probes can call it.
+///
+/// # Arguments
+///
+/// * `mode` - The maintenance mode.
+/// * `validator` - The token validator, whose circuit breaker state is reported.
+///
+/// # Returns
+///
+/// * `HttpResponse` with 200 when the database can be reached, or 503 when it cannot.
+pub async fn readyz(
+    mode: web::Data<MaintenanceMode>,
+    validator: web::Data<TokenValidator>,
+) -> HttpResponse {
+    let database_ok = establish_connection()
+        .and_then(|mut conn| Ok(diesel::sql_query("SELECT 1").execute(&mut conn)?))
+        .is_ok();
+
+    let readiness = Readiness {
+        status: if database_ok { "ready" } else { "unavailable" },
+        database: if database_ok { "ok" } else { "error" },
+        maintenance: mode.state(),
+        identity_provider: validator.circuit().state(),
+    };
+    if database_ok {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
@@ -0,0 +1,365 @@
+// backend/src/i18n.rs
+// This file translates error messages and problem titles into the language the client asks for with Accept-Language.
+// It exists so forms can show the API's validation errors to users as they are, in their own language.
+// RELEVANT FILES: backend/locales/sv.po, backend/src/error.rs, backend/src/fallback.rs
+
+use crate::formats::is_json;
+use actix_web::body::{to_bytes, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{
+    CacheControl, CacheDirective, HeaderMap, HeaderValue, ACCEPT_LANGUAGE, CONTENT_LANGUAGE,
+    CONTENT_TYPE, VARY,
+};
+use actix_web::middleware::Next;
+use actix_web::{Error as ActixWebError, HttpResponse};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+/// The language of the messages in the code, used when the client asks for none we have.
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// The supported languages, as language code, name in the language itself and catalog.
+///
+/// The catalogs are gettext `.po` files. English has none, as its messages are the keys.
+const LOCALES: [(&str, &str, &str); 3] = [
+    ("en", "English", ""),
+    ("sv", "Svenska", include_str!("../locales/sv.po")),
+    ("de", "Deutsch", include_str!("../locales/de.po")),
+];
+
+/// How long clients may cache the list of languages, in seconds. It only changes with a release.
+const MAX_AGE: u32 = 86_400;
+
+/// One translated message.
+struct Entry {
+    /// The English message, cut at its `{}` placeholders.
+    parts: Vec<String>,
+    /// The translation, with the same number of `{}` placeholders.
+    translation: String,
+}
+
+/// The translations of one language.
+struct Catalog {
+    /// The language code, e.g. `sv`.
+    locale: &'static str,
+    /// The translated messages.
+    entries: Vec<Entry>,
+}
+
+/// A supported language, as listed to clients.
+#[derive(Serialize)]
+pub struct Locale {
+    /// The language code to send in `Accept-Language`, e.g. `sv`.
+    pub code: &'static str,
+    /// The name of the language in the language itself, e.g. `Svenska`.
+    pub name: &'static str,
+    /// Whether this is the language used when the client asks for none we have.
+    pub default: bool,
+}
+
+/// Reads the text of a quoted `.po` string, e.g. `"Say \"hi\""` as `Say "hi"`.
+fn unquote(quoted: &str) -> String {
+    let inner = quoted
+        .trim()
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or("");
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some(other) => text.push(other),
+            None => {}
+        }
+    }
+    text
+}
+
+/// Reads the messages of a gettext `.po` file.
+///
+/// Only `msgid` and `msgstr` are read, with strings continued on the next lines.
+/// Comments, plurals and contexts are skipped, and so are untranslated messages and the header.
+///
+/// # Arguments
+///
+/// * `po` - The content of the file.
+///
+/// # Returns
+///
+/// * The English messages with their translations.
+fn parse_po(po: &str) -> Vec<(String, String)> {
+    let mut messages = Vec::new();
+    let mut msgid = String::new();
+    let mut msgstr = String::new();
+    // Which of the two a continued string belongs to
+    let mut in_msgstr = false;
+
+    let mut finish = |msgid: &mut String, msgstr: &mut String| {
+        if !msgid.is_empty() && !msgstr.is_empty() {
+            messages.push((std::mem::take(msgid), std::mem::take(msgstr)));
+        }
+        msgid.clear();
+        msgstr.clear();
+    };
+
+    for line in po.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("msgid ") {
+            finish(&mut msgid, &mut msgstr);
+            msgid = unquote(rest);
+            in_msgstr = false;
+        } else if let Some(rest) = line.strip_prefix("msgstr ") {
+            msgstr = unquote(rest);
+            in_msgstr = true;
+        } else if line.starts_with('"') {
+            if in_msgstr {
+                msgstr.push_str(&unquote(line));
+            } else {
+                msgid.push_str(&unquote(line));
+            }
+        }
+    }
+    finish(&mut msgid, &mut msgstr);
+    messages
+}
+
+/// Returns the catalogs, read from the `.po` files the first time they are needed.
+fn catalogs() -> &'static [Catalog] {
+    static CATALOGS: OnceLock<Vec<Catalog>> = OnceLock::new();
+    CATALOGS.get_or_init(|| {
+        LOCALES
+            .iter()
+            .filter(|(_, _, po)| !po.is_empty())
+            .map(|(locale, _, po)| Catalog {
+                locale,
+                entries: parse_po(po)
+                    .into_iter()
+                    .map(|(msgid, translation)| Entry {
+                        parts: msgid.split("{}").map(str::to_string).collect(),
+                        translation,
+                    })
+                    .collect(),
+            })
+            .collect()
+    })
+}
+
+/// Matches a message against an English message with placeholders.
+///
+/// # Arguments
+///
+/// * `parts` - The English message, cut at its `{}` placeholders.
+/// * `message` - The message to translate, e.g. `Unknown field 'nickname'`.
+///
+/// # Returns
+///
+/// * The values of the placeholders in order, e.g. `["nickname"]`, or `None` if the message does not match.
+fn match_parts<'a>(parts: &[String], message: &'a str) -> Option<Vec<&'a str>> {
+    let (first, rest) = parts.split_first()?;
+    let mut remaining = message.strip_prefix(first.as_str())?;
+    let mut values = Vec::new();
+    for (i, part) in rest.iter().enumerate() {
+        if i == rest.len() - 1 {
+            values.push(remaining.strip_suffix(part.as_str())?);
+            remaining = "";
+        } else {
+            // The value ends where the next fixed text starts
+            let end = remaining.find(part.as_str())?;
+            values.push(&remaining[..end]);
+            remaining = &remaining[end + part.len()..];
+        }
+    }
+    remaining.is_empty().then_some(values)
+}
+
+/// Translates a message.
+///
+/// # Arguments
+///
+/// * `locale` - The language code, e.g. `sv`, as returned by `negotiate`.
+/// * `message` - The English message, e.g. `Unknown field 'nickname'`.
+///
+/// # Returns
+///
+/// * The translated message, with the values of the English one filled in.
+///   Messages without a translation are returned as they are.
+pub fn translate(locale: &str, message: &str) -> String {
+    let Some(catalog) = catalogs().iter().find(|c| c.locale == locale) else {
+        return message.to_string();
+    };
+    for entry in &catalog.entries {
+        if let Some(values) = match_parts(&entry.parts, message) {
+            let mut translated = String::new();
+            let mut values = values.into_iter();
+            for (i, part) in entry.translation.split("{}").enumerate() {
+                if i > 0 {
+                    translated.push_str(values.next().unwrap_or(""));
+                }
+                translated.push_str(part);
+            }
+            return translated;
+        }
+    }
+    message.to_string()
+}
+
+/// Picks the language of a response from the `Accept-Language` header (RFC 9110).
+///
+/// Languages are tried in the order of their `q` weight. A regional tag like `sv-SE`
+/// matches its language, and `*` or no match at all gives English.
+///
+/// # Arguments
+///
+/// * `headers` - The headers of the request.
+///
+/// # Returns
+///
+/// * The code of a supported language, e.g. `sv`.
+pub fn negotiate(headers: &HeaderMap) -> &'static str {
+    let Some(header) = headers
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return DEFAULT_LOCALE;
+    };
+
+    let mut wanted: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut params = item.split(';');
+            let tag = params.next()?.trim().to_ascii_lowercase();
+            let q = params
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .and_then(|q| q.parse().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && q > 0.0).then_some((tag, q))
+        })
+        .collect();
+    // The sort is stable, so languages with the same weight keep the client's order
+    wanted.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    wanted
+        .iter()
+        .find_map(|(tag, _)| {
+            let language = tag.split('-').next().unwrap_or(tag);
+            LOCALES
+                .iter()
+                .find(|(code, _, _)| *code == language)
+                .map(|(code, _, _)| *code)
+        })
+        .unwrap_or(DEFAULT_LOCALE)
+}
+
+/// Translates the messages of an error body.
+///
+/// # Arguments
+///
+/// * `locale` - The language code.
+/// * `json` - Whether the body is JSON: a string (from `ApiError`) or a problem with `title` and `detail`.
+///   Other bodies are plain text, e.g. authentication errors.
+/// * `bytes` - The body as sent.
+///
+/// # Returns
+///
+/// * The translated body, or `None` if it has nothing to translate.
+fn translate_body(locale: &str, json: bool, bytes: &[u8]) -> Option<Vec<u8>> {
+    if !json {
+        let text = std::str::from_utf8(bytes).ok()?;
+        return Some(translate(locale, text).into_bytes());
+    }
+    let translated = match serde_json::from_slice::<Value>(bytes).ok()? {
+        Value::String(message) => Value::String(translate(locale, &message)),
+        Value::Object(mut problem) => {
+            for key in ["title", "detail"] {
+                if let Some(Value::String(message)) = problem.get(key) {
+                    let message = translate(locale, message);
+                    problem.insert(key.to_string(), Value::String(message));
+                }
+            }
+            Value::Object(problem)
+        }
+        _ => return None,
+    };
+    serde_json::to_vec(&translated).ok()
+}
+
+/// Middleware that translates the messages of error responses into the language of `Accept-Language`.
+///
+/// JSON error messages, the `title` and `detail` of problems and plain text errors are translated.
+/// Every error response gets `Content-Language` and `Vary: Accept-Language`. Successful responses,
+/// and messages without a translation, are passed on in English.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service, with its error message translated.
+pub async fn localize_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, ActixWebError> {
+    let locale = negotiate(req.headers());
+    let mut res = next.call(req).await?;
+
+    let status = res.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return Ok(res.map_into_boxed_body());
+    }
+    res.headers_mut()
+        .insert(CONTENT_LANGUAGE, HeaderValue::from_static(locale));
+    res.headers_mut()
+        .append(VARY, HeaderValue::from_static("accept-language"));
+
+    let json = is_json(&res);
+    let text = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_none_or(|value| value.as_bytes().starts_with(b"text/plain"));
+    if locale == DEFAULT_LOCALE || !(json || text) {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = to_bytes(body)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.into()))?;
+    let body = match translate_body(locale, json, &bytes) {
+        Some(translated) => BoxBody::new(translated),
+        None => BoxBody::new(bytes),
+    };
+    Ok(ServiceResponse::new(req, res.set_body(body)))
+}
+
+/// Handles listing the languages error messages are available in.
+///
+/// This endpoint is public. The list only changes with a release, so clients may cache it for a day.
+///
+/// # Returns
+///
+/// * `HttpResponse` with the languages, the default one marked.
+pub async fn read_locales() -> HttpResponse {
+    let locales: Vec<Locale> = LOCALES
+        .iter()
+        .map(|(code, name, _)| Locale {
+            code,
+            name,
+            default: *code == DEFAULT_LOCALE,
+        })
+        .collect();
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(MAX_AGE),
+        ]))
+        .json(locales)
+}
@@ -0,0 +1,123 @@
+// backend/src/identifiers.rs
+// This file creates and checks contact UUIDs, and resolves a contact from its ID or UUID in a URL.
+// It exists so offline clients can name contacts before the server has given them an integer ID.
+// RELEVANT FILES: backend/src/handlers.rs, backend/src/models.rs, backend/contacts-types/src/schema.rs
+
+use crate::error::ApiError;
+use crate::schema::contacts;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Deserializer};
+
+/// Creates a random (version 4) UUID in lowercase, e.g. `0b7e6a1c-3f2d-4c8e-9a51-2f4b8c7d6e10`.
+pub fn new_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("the system random number generator is available");
+    // Set the version (4) and the variant (RFC 4122) bits
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Checks that a string is a UUID in the 8-4-4-4-12 hex format.
+///
+/// # Arguments
+///
+/// * `value` - The string to check.
+///
+/// # Returns
+///
+/// * `true` if the string is a well-formed UUID.
+pub fn is_uuid(value: &str) -> bool {
+    value.len() == 36
+        && value.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Prepares a client-supplied UUID for storage, or creates one.
+///
+/// # Arguments
+///
+/// * `uuid` - The UUID from the request body, if any.
+///
+/// # Returns
+///
+/// * `Ok(uuid)` in lowercase, so the same UUID always matches.
+/// * `Err(ApiError::BadRequest)` if the UUID is malformed.
+pub fn uuid_or_new(uuid: Option<String>) -> Result<String, ApiError> {
+    match uuid {
+        None => Ok(new_uuid()),
+        Some(uuid) if is_uuid(&uuid) => Ok(uuid.to_lowercase()),
+        Some(_) => Err(ApiError::BadRequest(
+            "uuid must have the format xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx".to_string(),
+        )),
+    }
+}
+
+/// A contact as named in a URL: by its integer ID or by its UUID.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContactKey {
+    /// The integer ID given by the server.
+    Id(i32),
+    /// The globally unique ID, in lowercase.
+    Uuid(String),
+}
+
+impl<'de> Deserialize<'de> for ContactKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        if let Ok(id) = value.parse::<i32>() {
+            Ok(ContactKey::Id(id))
+        } else if is_uuid(&value) {
+            Ok(ContactKey::Uuid(value.to_lowercase()))
+        } else {
+            Err(serde::de::Error::custom("expected a contact ID or UUID"))
+        }
+    }
+}
+
+impl ContactKey {
+    /// Finds the integer ID of the contact.
+    ///
+    /// # Arguments
+    ///
+    /// * `conn` - The database connection.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(id)` with the integer ID.
+    /// * `Err(ApiError::NotFound)` if no contact has this UUID, or a database error.
+    pub fn resolve(&self, conn: &mut SqliteConnection) -> Result<i32, ApiError> {
+        match self {
+            ContactKey::Id(id) => Ok(*id),
+            ContactKey::Uuid(uuid) => Ok(contacts::table
+                .filter(contacts::uuid.eq(uuid))
+                .select(contacts::id)
+                .first::<i32>(conn)?),
+        }
+    }
+
+    /// Checks if a contact has this ID or UUID.
+    ///
+    /// This is used for data that does not come from the database, like demo contacts.
+    pub fn matches(&self, id: i32, uuid: Option<&str>) -> bool {
+        match self {
+            ContactKey::Id(key) => *key == id,
+            ContactKey::Uuid(key) => Some(key.as_str()) == uuid,
+        }
+    }
+}
@@ -0,0 +1,145 @@
+// backend/src/includes.rs
+// This file loads related data that clients ask for with `?include=` and embeds it in contact responses.
+// It exists so responses stay lean by default, while clients that need more can get it in one request.
+// RELEVANT FILES: backend/src/links.rs, backend/src/handlers.rs, backend/src/models.rs
+
+use crate::error::ApiError;
+use crate::links::LinkedContact;
+use crate::models::{Company, Enrichment};
+use crate::schema::{companies, enrichments};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The related data that can be included.
+const SUPPORTED: [&str; 2] = ["company", "enrichments"];
+
+/// The `include` query parameter.
+#[derive(Deserialize, Default)]
+pub struct IncludeQuery {
+    /// A comma-separated list of related data, e.g. `company,enrichments`.
+    pub include: Option<String>,
+}
+
+/// The related data a client asked for.
+#[derive(Clone, Copy, Default)]
+pub struct Includes {
+    /// Whether to embed the company of each contact.
+    pub company: bool,
+    /// Whether to embed the enrichment results of each contact.
+    pub enrichments: bool,
+}
+
+impl Includes {
+    /// Returns whether no related data was asked for.
+    pub fn is_empty(&self) -> bool {
+        !self.company && !self.enrichments
+    }
+}
+
+impl IncludeQuery {
+    /// Parses the `include` parameter.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Includes)` with the requested related data.
+    /// * `Err(ApiError::BadRequest)` if a name is not supported.
+    pub fn parse(&self) -> Result<Includes, ApiError> {
+        let mut includes = Includes::default();
+        let names = self.include.as_deref().unwrap_or("");
+        for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match name {
+                "company" => includes.company = true,
+                "enrichments" => includes.enrichments = true,
+                _ => {
+                    return Err(ApiError::BadRequest(format!(
+                        "Cannot include '{}'. Supported: {}",
+                        name,
+                        SUPPORTED.join(", ")
+                    )));
+                }
+            }
+        }
+        Ok(includes)
+    }
+}
+
+/// Related data embedded in a contact response.
+#[derive(Default, Serialize)]
+pub struct Embedded {
+    /// The company of the contact. Only set when requested and the contact has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company: Option<Company>,
+    /// The enrichment results of the contact, newest first. Only set when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enrichments: Option<Vec<Enrichment>>,
+}
+
+impl Embedded {
+    /// Returns whether nothing is embedded, so the `_embedded` key can be left out.
+    pub fn is_empty(&self) -> bool {
+        self.company.is_none() && self.enrichments.is_none()
+    }
+}
+
+/// Loads the requested related data and embeds it in the contacts.
+///
+/// Each kind of related data is loaded with one query for all contacts, not one per contact.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection.
+/// * `includes` - The related data to load.
+/// * `contacts` - The contacts to embed the data in.
+///
+/// # Returns
+///
+/// * `Ok(())` when done.
+/// * `Err` if there is a database error.
+pub fn embed(
+    conn: &mut SqliteConnection,
+    includes: Includes,
+    contacts: &mut [LinkedContact],
+) -> QueryResult<()> {
+    if includes.company {
+        let company_ids: Vec<i32> = contacts
+            .iter()
+            .filter_map(|c| c.contact.company_id)
+            .collect();
+        let companies: HashMap<i32, Company> = companies::table
+            .filter(companies::id.eq_any(&company_ids))
+            .load::<Company>(conn)?
+            .into_iter()
+            .map(|company| (company.id, company))
+            .collect();
+        for contact in contacts.iter_mut() {
+            // Several contacts can share a company, so each gets its own copy
+            contact._embedded.company = contact
+                .contact
+                .company_id
+                .and_then(|id| companies.get(&id).cloned());
+        }
+    }
+
+    if includes.enrichments {
+        let contact_ids: Vec<i32> = contacts.iter().map(|c| c.contact.id).collect();
+        let mut by_contact: HashMap<i32, Vec<Enrichment>> = HashMap::new();
+        for enrichment in enrichments::table
+            .filter(enrichments::contact_id.eq_any(&contact_ids))
+            .order(enrichments::id.desc())
+            .load::<Enrichment>(conn)?
+        {
+            by_contact
+                .entry(enrichment.contact_id)
+                .or_default()
+                .push(enrichment);
+        }
+        for contact in contacts.iter_mut() {
+            contact._embedded.enrichments =
+                Some(by_contact.remove(&contact.contact.id).unwrap_or_default());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+// backend/src/indexes.rs
+// This file lets admins refresh the query planner statistics and see which indexes the API's queries do not use.
+// It exists so indexes can be kept in step with the filters clients really use as the data grows.
+// RELEVANT FILES: backend/src/query_metrics.rs, backend/src/main.rs, backend/migrations
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::error::ApiError;
+use crate::establish_connection;
+use crate::query_metrics::seen_queries;
+use actix_web::HttpResponse;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use serde::Serialize;
+
+/// An index as listed in `sqlite_master`.
+#[derive(QueryableByName)]
+struct IndexRow {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Text)]
+    tbl_name: String,
+    #[diesel(sql_type = Text)]
+    sql: String,
+}
+
+/// One step of a query plan from `EXPLAIN QUERY PLAN`, e.g. `SEARCH contacts USING INDEX contacts_status (status=?)`.
+#[derive(QueryableByName)]
+struct PlanRow {
+    #[diesel(sql_type = Text)]
+    detail: String,
+}
+
+/// How an index is used by the queries the API has run.
+#[derive(Serialize)]
+pub struct IndexUsage {
+    /// The name of the index.
+    pub name: String,
+    /// The table the index is on.
+    pub table: String,
+    /// Whether the index enforces uniqueness. Such indexes are needed even when no query reads them.
+    pub unique: bool,
+    /// How many of the checked queries use the index.
+    pub queries: usize,
+}
+
+/// The result of refreshing the statistics and checking the indexes.
+#[derive(Serialize)]
+pub struct IndexReport {
+    /// How many distinct queries were checked, all those run since the server started.
+    pub queries_checked: usize,
+    /// Every index created by the migrations.
+    pub indexes: Vec<IndexUsage>,
+    /// The names of indexes that no checked query uses and that do not enforce uniqueness.
+    pub unused: Vec<String>,
+}
+
+/// Handles refreshing the planner statistics and reporting index usage.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// It runs `ANALYZE`, so SQLite picks indexes by how selective they are on the current data,
+/// and then asks SQLite how it would run each query the API has run since it started.
+/// SQLite does not count index use itself, so an index counts as unused when no plan mentions it.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the index report.
+/// * `Err(ApiError)` if the user is not an admin or there is a database error.
+pub async fn analyze_indexes(principal: Principal) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let mut conn = establish_connection()?;
+
+    diesel::sql_query("ANALYZE").execute(&mut conn)?;
+
+    // Automatic indexes for UNIQUE and PRIMARY KEY constraints have no SQL and are left out
+    let rows = diesel::sql_query(
+        "SELECT name, tbl_name, sql FROM sqlite_master WHERE type = 'index' AND sql IS NOT NULL ORDER BY tbl_name, name",
+    )
+    .load::<IndexRow>(&mut conn)?;
+
+    let queries = seen_queries();
+    let mut plans = Vec::new();
+    for query in &queries {
+        // Unbound `?` parameters are allowed here; the plan does not depend on their values
+        match diesel::sql_query(format!("EXPLAIN QUERY PLAN {}", query)).load::<PlanRow>(&mut conn)
+        {
+            Ok(steps) => plans.push(steps.into_iter().map(|s| s.detail).collect::<Vec<_>>()),
+            Err(e) => log::warn!("Could not explain a query: {}", e),
+        }
+    }
+
+    let indexes: Vec<IndexUsage> = rows
+        .into_iter()
+        .map(|row| {
+            let used_by = format!("INDEX {}", row.name);
+            IndexUsage {
+                queries: plans
+                    .iter()
+                    .filter(|steps| {
+                        steps.iter().any(|detail| {
+                            // Match the whole name, so `contacts_email` does not match `contacts_email_lower`
+                            detail.contains(&format!("{} ", used_by)) || detail.ends_with(&used_by)
+                        })
+                    })
+                    .count(),
+                unique: row.sql.to_uppercase().starts_with("CREATE UNIQUE"),
+                table: row.tbl_name,
+                name: row.name,
+            }
+        })
+        .collect();
+    let unused = indexes
+        .iter()
+        .filter(|index| index.queries == 0 && !index.unique)
+        .map(|index| index.name.clone())
+        .collect();
+
+    Ok(HttpResponse::Ok().json(IndexReport {
+        queries_checked: plans.len(),
+        indexes,
+        unused,
+    }))
+}
@@ -0,0 +1,190 @@
+// backend/src/introspection.rs
+// This file describes the contact fields, their types, rules and filters, for dynamic clients.
+// It exists so generic frontends and integrations can build forms without hardcoding the contact model.
+// RELEVANT FILES: backend/src/models.rs, backend/src/handlers.rs, backend/src/main.rs
+
+use crate::auth::Principal;
+use crate::models::CONTACT_STATUSES;
+use actix_web::HttpResponse;
+use serde::Serialize;
+
+/// The values `email_status` can have.
+const EMAIL_STATUSES: [&str; 4] = ["unverified", "valid", "invalid", "unknown"];
+
+/// Describes one field of the contact model.
+#[derive(Serialize)]
+pub struct FieldDescription {
+    /// The name of the field in JSON payloads.
+    pub name: &'static str,
+    /// The JSON type: string, integer, boolean or timestamp (Unix seconds).
+    #[serde(rename = "type")]
+    pub field_type: &'static str,
+    /// Whether the field must be sent when creating a contact.
+    pub required: bool,
+    /// Whether the field can only be changed by the server or a dedicated endpoint.
+    pub read_only: bool,
+    /// Whether the list endpoint can be filtered by this field.
+    pub filterable: bool,
+    /// The only values the field accepts, if it is limited to a set.
+    pub allowed_values: Option<&'static [&'static str]>,
+    /// The value used when the field is not sent.
+    pub default: Option<&'static str>,
+    /// A short description for form labels and help texts.
+    pub description: &'static str,
+}
+
+/// Builds a description for a plain, optional and writable field.
+///
+/// The other fields are filled in with struct update syntax where needed.
+const fn field(
+    name: &'static str,
+    field_type: &'static str,
+    description: &'static str,
+) -> FieldDescription {
+    FieldDescription {
+        name,
+        field_type,
+        required: false,
+        read_only: false,
+        filterable: false,
+        allowed_values: None,
+        default: None,
+        description,
+    }
+}
+
+/// The description of every contact field, in payload order.
+pub(crate) const CONTACT_FIELDS: [FieldDescription; 19] = [
+    FieldDescription {
+        read_only: true,
+        ..field("id", "integer", "The unique identifier for the contact.")
+    },
+    FieldDescription {
+        required: true,
+        ..field("first_name", "string", "The first name of the contact.")
+    },
+    FieldDescription {
+        required: true,
+        ..field("last_name", "string", "The last name of the contact.")
+    },
+    FieldDescription {
+        required: true,
+        ..field("email", "string", "The email address of the contact.")
+    },
+    FieldDescription {
+        required: true,
+        ..field("phone_number", "string", "The phone number of the contact.")
+    },
+    field(
+        "company_id",
+        "integer",
+        "The ID of the company the contact works for. Must refer to an existing company.",
+    ),
+    FieldDescription {
+        filterable: true,
+        allowed_values: Some(&CONTACT_STATUSES),
+        default: Some("lead"),
+        ..field("status", "string", "The lifecycle status of the contact.")
+    },
+    FieldDescription {
+        filterable: true,
+        ..field("source", "string", "Where the contact came from.")
+    },
+    FieldDescription {
+        filterable: true,
+        ..field("owner", "string", "The person responsible for the contact.")
+    },
+    FieldDescription {
+        read_only: true,
+        default: Some("false"),
+        ..field(
+            "email_opt_in",
+            "boolean",
+            "Whether the contact agreed to receive email. Changed through the consent endpoints.",
+        )
+    },
+    FieldDescription {
+        read_only: true,
+        default: Some("false"),
+        ..field(
+            "sms_opt_in",
+            "boolean",
+            "Whether the contact agreed to receive text messages. Changed through the consent endpoints.",
+        )
+    },
+    FieldDescription {
+        read_only: true,
+        ..field(
+            "consent_source",
+            "string",
+            "Where the last consent change came from.",
+        )
+    },
+    FieldDescription {
+        read_only: true,
+        ..field(
+            "consent_timestamp",
+            "timestamp",
+            "When consent last changed.",
+        )
+    },
+    FieldDescription {
+        read_only: true,
+        allowed_values: Some(&EMAIL_STATUSES),
+        default: Some("unverified"),
+        ..field(
+            "email_status",
+            "string",
+            "The result of the last email check.",
+        )
+    },
+    FieldDescription {
+        read_only: true,
+        ..field(
+            "email_verified_at",
+            "timestamp",
+            "When the email was last found valid.",
+        )
+    },
+    field(
+        "uuid",
+        "string",
+        "The globally unique ID of the contact. Created by the server when it is not sent, and can be used instead of the ID in URLs. Cannot be changed.",
+    ),
+    field(
+        "external_id",
+        "string",
+        "The ID of the contact in the system named by source. Unique per source.",
+    ),
+    FieldDescription {
+        read_only: true,
+        ..field(
+            "archived_at",
+            "timestamp",
+            "When the contact was archived. Changed through the archive endpoints. Archived contacts are left out of lists unless include_archived=true.",
+        )
+    },
+    FieldDescription {
+        read_only: true,
+        ..field(
+            "quality_score",
+            "integer",
+            "How complete the contact is, from 0 to 100: names, a checked email, a phone number, a company and recorded consent. Lists can filter on it with min_quality.",
+        )
+    },
+];
+
+/// Handles describing the fields of the contact model.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+///
+/// # Returns
+///
+/// * `HttpResponse` with a JSON array of field descriptions.
+pub async fn read_contact_schema(_principal: Principal) -> HttpResponse {
+    HttpResponse::Ok().json(CONTACT_FIELDS)
+}
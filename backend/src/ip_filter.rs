@@ -0,0 +1,154 @@
+// backend/src/ip_filter.rs
+// This file limits the admin routes and the metrics to the networks an operator allows, and keeps out the ones they deny.
+// It exists so a leaked admin token or an open metrics port is not enough to reach them from anywhere on the internet.
+// RELEVANT FILES: backend/src/trusted_proxies.rs, backend/src/query_metrics.rs, backend/src/lib.rs
+
+use crate::error::ApiError;
+use crate::trusted_proxies::{cidrs_from_list, Cidr, TrustedProxies};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error as ActixWebError};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The path of the metrics endpoint, which is protected like the admin routes.
+const METRICS_PATH: &str = "/metrics";
+
+/// The path of the admin routes under `/api` or `/api/v1`.
+const ADMIN_PATH: &str = "/admin/";
+
+/// The networks that may reach the admin routes and the metrics, and how often they were let in or kept out.
+pub struct IpFilter {
+    /// When not empty, only these ranges are let in.
+    allow: Vec<Cidr>,
+    /// These ranges are always kept out, also when they are in `allow`.
+    deny: Vec<Cidr>,
+    /// How many requests were let in since the server started.
+    allowed: AtomicU64,
+    /// How many requests were kept out since the server started.
+    denied: AtomicU64,
+}
+
+impl IpFilter {
+    /// Reads the ranges from the `ADMIN_ALLOW_CIDRS` and `ADMIN_DENY_CIDRS` environment variables,
+    /// comma-separated lists of addresses and CIDR ranges.
+    ///
+    /// # Returns
+    ///
+    /// * The `IpFilter`. When neither variable is set, every address is let in.
+    ///
+    /// # Panics
+    ///
+    /// If a range is invalid.
+    pub fn from_env() -> Self {
+        Self::from_settings(
+            &std::env::var("ADMIN_ALLOW_CIDRS").unwrap_or_default(),
+            &std::env::var("ADMIN_DENY_CIDRS").unwrap_or_default(),
+        )
+    }
+
+    /// Builds the filter from lists in the form of `ADMIN_ALLOW_CIDRS` and `ADMIN_DENY_CIDRS`.
+    ///
+    /// # Arguments
+    ///
+    /// * `allow` - The ranges that are let in, e.g. `10.0.0.0/8`. Empty lets in every address.
+    /// * `deny` - The ranges that are kept out.
+    ///
+    /// # Panics
+    ///
+    /// If a range is invalid.
+    pub fn from_settings(allow: &str, deny: &str) -> Self {
+        Self {
+            allow: cidrs_from_list("ADMIN_ALLOW_CIDRS", allow),
+            deny: cidrs_from_list("ADMIN_DENY_CIDRS", deny),
+            allowed: AtomicU64::new(0),
+            denied: AtomicU64::new(0),
+        }
+    }
+
+    /// Tells whether any range is configured. Without ranges there is nothing to decide.
+    fn is_active(&self) -> bool {
+        !self.allow.is_empty() || !self.deny.is_empty()
+    }
+
+    /// Decides whether an address may reach the protected routes.
+    ///
+    /// The deny list wins, then the allow list when it has ranges. An unknown address is kept out
+    /// whenever a list is configured, so the filter fails closed.
+    fn allows(&self, ip: Option<IpAddr>) -> bool {
+        let Some(ip) = ip else {
+            return false;
+        };
+        if self.deny.iter().any(|range| range.contains(ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|range| range.contains(ip))
+    }
+
+    /// Returns how many requests were let in and kept out since the server started.
+    pub fn decisions(&self) -> (u64, u64) {
+        (
+            self.allowed.load(Ordering::Relaxed),
+            self.denied.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Tells whether a path is protected: the metrics, or an admin route under `/api` or `/api/v1`.
+///
+/// The path must be the decoded one the router matches, see `Path::as_str`, or `/api/v1/%61dmin/...`
+/// would reach an admin route without being protected.
+fn is_protected(path: &str) -> bool {
+    if path == METRICS_PATH {
+        return true;
+    }
+    path.strip_prefix("/api/v1")
+        .or_else(|| path.strip_prefix("/api"))
+        .is_some_and(|rest| rest.starts_with(ADMIN_PATH))
+}
+
+/// Middleware that lets only the allowed networks reach the admin routes and the metrics.
+///
+/// Other routes are passed on as they are. The client address is the connecting peer, or the one
+/// it forwarded when the peer is a trusted proxy, see `TrustedProxies::client_ip`.
+/// Every decision is counted for `/metrics`, and requests that are kept out are logged.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service.
+/// * `Err(ApiError::Forbidden)` if the address may not reach the route.
+pub async fn restrict_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    if let Some(filter) = req.app_data::<web::Data<IpFilter>>()
+        && filter.is_active()
+        && is_protected(req.match_info().as_str())
+    {
+        let ip = req
+            .app_data::<web::Data<TrustedProxies>>()
+            .and_then(|proxies| proxies.client_ip(req.request()));
+        if filter.allows(ip) {
+            filter.allowed.fetch_add(1, Ordering::Relaxed);
+        } else {
+            filter.denied.fetch_add(1, Ordering::Relaxed);
+            log::warn!(
+                "Kept {} from {} {}: the address is not allowed",
+                ip.map_or_else(|| "an unknown address".to_string(), |ip| ip.to_string()),
+                req.method(),
+                req.path()
+            );
+            return Err(ApiError::Forbidden(
+                "This route is not available from your network".to_string(),
+            )
+            .into());
+        }
+    }
+    next.call(req).await
+}
@@ -0,0 +1,604 @@
+// backend/src/lib.rs
+// This file is the contacts library: the models, the handlers and the builder that mounts the API in an actix app.
+// It exists so integrators can run the contact API inside their own actix application, and so the binary stays thin.
+// RELEVANT FILES: backend/src/main.rs, backend/src/handlers.rs, backend/src/auth.rs
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::web;
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use dotenvy::dotenv;
+use std::env;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod addresses;
+pub mod archive;
+pub mod auth;
+pub mod base_path;
+pub mod casing;
+pub mod circuit_breaker;
+pub mod companies;
+pub mod config_reload;
+pub mod consent;
+pub mod contact_requests;
+pub mod cors;
+pub mod custom_reports;
+pub mod debug_capture;
+pub mod demo;
+pub mod deprecations;
+pub mod diagnostics;
+pub mod duplicates;
+pub mod email_verification;
+pub mod enrichment;
+pub mod envelope;
+pub mod error;
+pub mod fallback;
+pub mod feature_flags;
+pub mod field_permissions;
+pub mod fields;
+pub mod formats;
+pub mod handlers;
+pub mod health;
+pub mod i18n;
+pub mod identifiers;
+pub mod includes;
+pub mod indexes;
+pub mod introspection;
+pub mod ip_filter;
+pub mod links;
+pub mod listeners;
+pub mod log_level;
+pub mod lookup;
+pub mod maintenance;
+pub mod me;
+pub mod meta;
+pub mod migrations;
+pub mod models;
+pub mod msgpack;
+pub mod ndjson;
+pub mod normalization;
+pub mod outbound;
+pub mod pending_changes;
+pub mod quality;
+pub mod query_metrics;
+pub mod relationships;
+pub mod reports;
+pub mod scim;
+pub mod share;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+pub mod timeouts;
+pub mod transaction;
+pub mod trusted_proxies;
+pub mod vcard;
+pub mod versioning;
+pub mod xml;
+
+// The tables live with the types that are read from and written to them
+pub use contacts_types::schema;
+
+use crate::addresses::AddressValidator;
+use crate::auth::TokenValidator;
+use crate::companies::CompanyRules;
+use crate::contact_requests::ContactRequestGuard;
+use crate::debug_capture::DebugCapture;
+use crate::demo::DemoMode;
+use crate::email_verification::EmailVerifier;
+use crate::enrichment::Enricher;
+use crate::envelope::EnvelopeDefault;
+use crate::error::ApiError;
+use crate::field_permissions::FieldPermissions;
+use crate::ip_filter::IpFilter;
+use crate::links::ExternalBaseUrl;
+use crate::maintenance::MaintenanceMode;
+use crate::pending_changes::ChangeApproval;
+use crate::reports::ReportCache;
+use crate::share::ShareLinkSigner;
+use crate::timeouts::RequestTimeouts;
+use crate::trusted_proxies::TrustedProxies;
+
+/// Establishes a connection to the SQLite database.
+///
+/// It reads the `DATABASE_URL` from the environment variables (e.g., from a `.env` file).
+/// `DATABASE_URL=:memory:` keeps the database in memory, shared by every connection, until the process exits.
+/// Foreign key checks are switched on, because SQLite has them off by default. A locked database
+/// is waited for up to `DATABASE_BUSY_TIMEOUT_MS` (5000 by default) instead of failing at once,
+/// and file databases use write-ahead logging, so reads go on while a request writes.
+/// Every query is timed, so slow ones are logged and show up in `/metrics`.
+///
+/// # Returns
+///
+/// * `Ok(SqliteConnection)` if the connection is successful.
+/// * `Err(ApiError)` if the connection fails.
+pub fn establish_connection() -> Result<SqliteConnection, ApiError> {
+    dotenv().ok();
+    // Tests point each of their threads to an ephemeral database of their own
+    #[cfg(feature = "test-support")]
+    let test_database = test_support::database_url();
+    #[cfg(not(feature = "test-support"))]
+    let test_database: Option<String> = None;
+    let database_url = match test_database {
+        Some(url) => url,
+        None => env::var("DATABASE_URL").expect("DATABASE_URL must be set"),
+    };
+    let database_url = if database_url == MEMORY_DATABASE_URL {
+        shared_memory_url().to_string()
+    } else {
+        database_url
+    };
+    let mut conn = SqliteConnection::establish(&database_url)?;
+    conn.batch_execute(&format!(
+        "PRAGMA foreign_keys = ON; PRAGMA busy_timeout = {}; PRAGMA journal_mode = WAL;",
+        busy_timeout_ms()
+    ))?;
+    query_metrics::instrument(&mut conn);
+    Ok(conn)
+}
+
+/// How long a connection waits for a lock when `DATABASE_BUSY_TIMEOUT_MS` is not set, in milliseconds.
+const DEFAULT_BUSY_TIMEOUT_MS: u64 = 5000;
+
+/// Reads how long a connection waits for another one to release a lock, from `DATABASE_BUSY_TIMEOUT_MS`.
+fn busy_timeout_ms() -> u64 {
+    env::var("DATABASE_BUSY_TIMEOUT_MS")
+        .ok()
+        .and_then(|ms| ms.trim().parse().ok())
+        .unwrap_or(DEFAULT_BUSY_TIMEOUT_MS)
+}
+
+/// The `DATABASE_URL` that keeps the database in memory, e.g. for demos and ephemeral deployments.
+pub const MEMORY_DATABASE_URL: &str = ":memory:";
+
+/// Returns the URL of the in-memory database of this process.
+///
+/// SQLite gives every connection to `:memory:` an empty database of its own, so the migrations
+/// and each request would all see a different one. Instead `:memory:` stands for one database in
+/// the `memdb` VFS, which every connection that opens the same name shares. Unlike a shared-cache
+/// database, it locks like a database file: a request that finds it locked waits for the busy
+/// timeout instead of failing at once. SQLite drops it when its last connection closes, so a
+/// connection is kept open here for as long as the process runs.
+///
+/// # Panics
+///
+/// If the database cannot be created.
+fn shared_memory_url() -> &'static str {
+    static DATABASE: OnceLock<(String, Mutex<SqliteConnection>)> = OnceLock::new();
+    let (url, _keeper) = DATABASE.get_or_init(|| {
+        let url = memory_database_url(&format!("contacts-memory-{}", std::process::id()));
+        let keeper =
+            SqliteConnection::establish(&url).expect("Could not create the in-memory database");
+        (url, Mutex::new(keeper))
+    });
+    url
+}
+
+/// Returns the URL of a named in-memory database that every connection in the process can open.
+///
+/// The name must start with `/` for `memdb` to share the database between connections.
+///
+/// # Arguments
+///
+/// * `name` - The name of the database, unique in the process.
+pub fn memory_database_url(name: &str) -> String {
+    format!("file:/{}?vfs=memdb", name)
+}
+
+/// Returns the current time as a Unix timestamp in seconds.
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// A route of the app: the module of its handler, its method and path, and how it is registered.
+///
+/// The route tables below are the only place routes are declared, so the 405 fallback and the demo
+/// know every method a path accepts.
+pub struct Route {
+    /// The module of the handler, e.g. `handlers`.
+    pub module: &'static str,
+    /// The HTTP method, e.g. `GET`.
+    pub method: &'static str,
+    /// The path pattern, relative to the scope the route is served under, e.g. `/contacts/{id}`.
+    pub path: &'static str,
+    /// Adds the route to the config of its scope.
+    register: fn(&mut web::ServiceConfig),
+}
+
+/// Declares a route, e.g. `route!(GET "/contacts/{id}" => handlers::read_contact)`.
+///
+/// `wrap` adds middleware to the route alone.
+macro_rules! route {
+    ($method:ident $path:literal => $module:ident::$handler:ident $(, wrap $middleware:path)?) => {
+        Route {
+            module: stringify!($module),
+            method: stringify!($method),
+            path: $path,
+            register: |cfg| {
+                cfg.service(
+                    // The method is a guard of the resource, so a path with several methods
+                    // falls through to the resource of the next one
+                    web::resource($path)
+                        .guard(actix_web::guard::Method(actix_web::http::Method::$method))
+                        $(.wrap(actix_web::middleware::from_fn($middleware)))?
+                        .to($module::$handler),
+                );
+            },
+        }
+    };
+}
+
+/// The path the API of version 1 is served under.
+pub const API_V1_PATH: &str = "/api/v1";
+
+/// The unversioned path of the API, kept for existing clients. It serves version 1.
+pub const API_PATH: &str = "/api";
+
+/// The path of the SCIM routes. SCIM clients expect the standard path, outside of `/api`.
+pub const SCIM_PATH: &str = "/scim/v2";
+
+/// The routes served at the root, outside of the API scopes.
+pub const ROOT_ROUTES: &[Route] = &[
+    route!(GET "/readyz" => health::readyz),
+    route!(GET "/metrics" => query_metrics::read_metrics, wrap ip_filter::restrict_admin),
+];
+
+/// The routes of the API of version 1.
+///
+/// Routes with a more specific path come before `/contacts/{id}`, so they are matched first.
+pub const API_V1_ROUTES: &[Route] = &[
+    route!(POST "/contacts" => handlers::create_contact),
+    route!(GET "/contacts" => handlers::read_contacts),
+    route!(GET "/contacts/stats/by-status" => handlers::read_status_stats),
+    route!(GET "/contacts/count" => handlers::count_contacts),
+    route!(GET "/contacts/sample" => handlers::read_contact_sample),
+    route!(GET "/contacts/stream" => ndjson::stream_contacts),
+    route!(GET "/contacts/autocomplete" => lookup::autocomplete),
+    route!(POST "/contacts/batch-get" => handlers::batch_get_contacts),
+    route!(POST "/contacts/check-duplicate" => duplicates::check_duplicate),
+    route!(GET "/contacts/{id}" => handlers::read_contact),
+    route!(HEAD "/contacts/{id}" => handlers::contact_exists),
+    route!(PUT "/contacts/{id}" => handlers::update_contact),
+    route!(PUT "/contacts/by-external-id/{source}/{external_id}" => handlers::upsert_contact_by_external_id),
+    route!(DELETE "/contacts/{id}" => handlers::delete_contact),
+    route!(GET "/schema/contacts" => introspection::read_contact_schema),
+    route!(GET "/deprecations" => deprecations::read_deprecations),
+    route!(GET "/lookup" => lookup::lookup),
+    route!(GET "/meta/countries" => meta::read_countries),
+    route!(GET "/meta/phone-prefixes" => meta::read_phone_prefixes),
+    route!(GET "/locales" => i18n::read_locales),
+    route!(POST "/companies" => companies::create_company),
+    route!(GET "/companies" => companies::read_companies),
+    route!(GET "/companies/{id}" => companies::read_company),
+    route!(PUT "/companies/{id}" => companies::update_company),
+    route!(DELETE "/companies/{id}" => companies::delete_company),
+    route!(GET "/companies/{id}/contacts" => companies::read_company_contacts),
+    route!(DELETE "/contacts/{id}/company" => companies::unlink_company),
+    route!(POST "/addresses/validate" => addresses::validate_address),
+    route!(POST "/contacts/verify-email" => email_verification::verify_emails),
+    route!(POST "/contacts/{id}/verify-email" => email_verification::verify_email),
+    route!(POST "/contacts/{id}/enrich" => enrichment::enrich_contact),
+    route!(GET "/contacts/{id}/enrichments" => enrichment::read_enrichments),
+    route!(POST "/contacts/{id}/archive" => archive::archive_contact),
+    route!(POST "/contacts/{id}/unarchive" => archive::unarchive_contact),
+    route!(POST "/contacts/{id}/consent" => consent::record_consent),
+    route!(POST "/contacts/{id}/consent/revoke" => consent::revoke_consent),
+    route!(GET "/contacts/{id}/relationships" => relationships::read_contact_relationships),
+    route!(POST "/relationships" => relationships::create_relationship),
+    route!(GET "/relationships/{id}" => relationships::read_relationship),
+    route!(PUT "/relationships/{id}" => relationships::update_relationship),
+    route!(DELETE "/relationships/{id}" => relationships::delete_relationship),
+    route!(GET "/me/card" => me::read_my_card),
+    route!(PUT "/me/card" => me::update_my_card),
+    route!(GET "/me/card/vcard" => me::export_my_card),
+    route!(POST "/contacts/{id}/share-link" => share::create_share_link),
+    route!(DELETE "/share-links/{id}" => share::revoke_share_link),
+    route!(GET "/public/share/{token}" => share::read_shared_contact),
+    route!(POST "/public/contact-requests" => contact_requests::submit_contact_request),
+    route!(GET "/contact-requests" => contact_requests::read_contact_requests),
+    route!(POST "/contact-requests/{id}/approve" => contact_requests::approve_contact_request),
+    route!(POST "/contact-requests/{id}/reject" => contact_requests::reject_contact_request),
+    route!(GET "/admin/feature-flags/{workspace}" => feature_flags::read_feature_flags),
+    route!(PUT "/admin/feature-flags/{workspace}/{flag}" => feature_flags::set_feature_flag),
+    route!(DELETE "/admin/feature-flags/{workspace}/{flag}" => feature_flags::reset_feature_flag),
+    route!(GET "/pending-changes" => pending_changes::read_pending_changes),
+    route!(GET "/pending-changes/{id}" => pending_changes::read_pending_change),
+    route!(POST "/pending-changes/{id}/approve" => pending_changes::approve_pending_change),
+    route!(POST "/pending-changes/{id}/reject" => pending_changes::reject_pending_change),
+    route!(GET "/maintenance" => maintenance::read_maintenance),
+    route!(PUT "/admin/maintenance" => maintenance::update_maintenance),
+    route!(GET "/admin/schema-version" => migrations::read_schema_version),
+    route!(GET "/admin/diagnostics" => diagnostics::read_diagnostics),
+    route!(POST "/admin/config/reload" => config_reload::reload_config),
+    route!(GET "/admin/log-level" => log_level::read_log_level),
+    route!(PUT "/admin/log-level" => log_level::set_log_level),
+    route!(DELETE "/admin/log-level" => log_level::reset_log_level),
+    route!(POST "/admin/debug-capture" => debug_capture::start_debug_capture),
+    route!(GET "/admin/debug-capture" => debug_capture::read_debug_capture),
+    route!(DELETE "/admin/debug-capture" => debug_capture::stop_debug_capture),
+    route!(POST "/admin/indexes/analyze" => indexes::analyze_indexes),
+    route!(GET "/reports/contacts-by-company" => reports::report_contacts_by_company),
+    route!(GET "/reports/contacts-by-source" => reports::report_contacts_by_source),
+    route!(GET "/reports/data-quality" => quality::read_quality_summary),
+    route!(POST "/normalization/preview" => normalization::preview_normalization),
+    route!(POST "/reports/custom" => custom_reports::create_saved_report),
+    route!(GET "/reports/custom" => custom_reports::read_saved_reports),
+    route!(GET "/reports/custom/{id}" => custom_reports::read_saved_report),
+    route!(GET "/reports/custom/{id}/run" => custom_reports::run_saved_report),
+    route!(DELETE "/reports/custom/{id}" => custom_reports::delete_saved_report),
+];
+
+/// The SCIM routes, served under `SCIM_PATH`.
+pub const SCIM_ROUTES: &[Route] = &[
+    route!(GET "/Users" => scim::list_users),
+    route!(GET "/Users/{id}" => scim::read_user),
+    route!(POST "/Users" => scim::create_user),
+    route!(PUT "/Users/{id}" => scim::replace_user),
+    route!(DELETE "/Users/{id}" => scim::delete_user),
+];
+
+/// The route tables by the path of the scope that serves them, in the order the router tries the scopes.
+pub const ROUTE_SCOPES: [(&str, &[Route]); 4] = [
+    // Versioned scopes must come before `/api`, which would match their paths too
+    (API_V1_PATH, API_V1_ROUTES),
+    (API_PATH, API_V1_ROUTES),
+    (SCIM_PATH, SCIM_ROUTES),
+    ("", ROOT_ROUTES),
+];
+
+/// Registers the routes of a table.
+///
+/// # Arguments
+///
+/// * `cfg` - The service config of the scope the routes are added to.
+/// * `routes` - The route table, registered in its order.
+fn register_routes(cfg: &mut web::ServiceConfig, routes: &[Route]) {
+    for route in routes {
+        (route.register)(cfg);
+    }
+}
+
+/// Registers the API routes of version 1.
+///
+/// # Arguments
+///
+/// * `cfg` - The service config of the scope the routes are added to.
+fn api_v1_routes(cfg: &mut web::ServiceConfig) {
+    register_routes(cfg, API_V1_ROUTES);
+}
+
+/// Builds a scope that serves the API of version 1 under a path, with its middleware.
+///
+/// `/api/v1` and `/api` both use it, so their middleware cannot drift apart. The last `wrap`
+/// runs first: the debug capture sees the request as the client sent it, and the transaction
+/// is the closest to the handler.
+///
+/// # Arguments
+///
+/// * `path` - The path of the scope, e.g. `/api/v1`.
+/// * `demo` - The demo mode setting, which decides whether mutations and reads of real data are rejected.
+///
+/// # Returns
+///
+/// * The scope, to register with `ServiceConfig::service`.
+fn api_scope(
+    path: &'static str,
+    demo: DemoMode,
+) -> actix_web::Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    web::scope(path)
+        .wrap(actix_web::middleware::from_fn(
+            transaction::transaction_per_request,
+        ))
+        .wrap(actix_web::middleware::from_fn(timeouts::enforce_timeout))
+        .wrap(actix_web::middleware::Condition::new(
+            demo.enabled,
+            actix_web::middleware::from_fn(demo::restrict),
+        ))
+        .wrap(actix_web::middleware::from_fn(
+            maintenance::reject_mutations,
+        ))
+        .wrap(actix_web::middleware::from_fn(ip_filter::restrict_admin))
+        .wrap(actix_web::middleware::from_fn(
+            deprecations::add_deprecation_notices,
+        ))
+        .wrap(actix_web::middleware::from_fn(|req, next| {
+            versioning::negotiate_version(1, req, next)
+        }))
+        .wrap(actix_web::middleware::from_fn(envelope::wrap_lists))
+        .wrap(actix_web::middleware::from_fn(i18n::localize_errors))
+        .wrap(actix_web::middleware::from_fn(casing::apply_field_case))
+        .wrap(actix_web::middleware::from_fn(formats::negotiate_format))
+        .wrap(actix_web::middleware::from_fn(
+            debug_capture::capture_exchanges,
+        ))
+        .configure(api_v1_routes)
+}
+
+/// The contact API, ready to be mounted in an actix application.
+///
+/// It holds the shared state of the API (the token validator, the settings and the guards),
+/// so every worker of the server uses the same instances. Build it once, outside of the
+/// `HttpServer::new` closure, and configure each worker's `App` with it:
+///
+/// ```no_run
+/// use actix_web::{App, HttpServer};
+/// use contacts_core::ContactsApi;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let api = ContactsApi::from_env();
+/// HttpServer::new(move || {
+///     let api = api.clone();
+///     App::new().configure(move |cfg| api.configure(cfg))
+/// })
+/// .bind(("0.0.0.0", 8081))?
+/// .run()
+/// .await
+/// # }
+/// ```
+///
+/// The routes are registered under `/api/v1`, `/api` and `/scim/v2`, with `/readyz` and `/metrics` at the root.
+/// The database comes from `DATABASE_URL` and must be migrated first, see `migrations::run_migrations`.
+#[derive(Clone)]
+pub struct ContactsApi {
+    validator: web::Data<TokenValidator>,
+    demo: DemoMode,
+    company_rules: CompanyRules,
+    change_approval: ChangeApproval,
+    field_permissions: web::Data<FieldPermissions>,
+    share_link_signer: web::Data<ShareLinkSigner>,
+    email_verifier: web::Data<EmailVerifier>,
+    enricher: web::Data<Enricher>,
+    addresses: web::Data<AddressValidator>,
+    base_url: web::Data<ExternalBaseUrl>,
+    envelope_default: web::Data<EnvelopeDefault>,
+    request_timeouts: web::Data<RequestTimeouts>,
+    // Shared by all workers, so the rate limit counts every submission
+    contact_request_guard: web::Data<ContactRequestGuard>,
+    // Shared by all workers, so switching it applies to every request
+    maintenance_mode: web::Data<MaintenanceMode>,
+    // Shared by all workers, so a report is computed once per cache period
+    report_cache: web::Data<ReportCache>,
+    // Shared by all workers, so a capture records every matching request
+    debug_capture: web::Data<DebugCapture>,
+    trusted_proxies: web::Data<TrustedProxies>,
+    // Shared by all workers, so the metrics count every decision
+    ip_filter: web::Data<IpFilter>,
+}
+
+impl ContactsApi {
+    /// Reads the configuration of the API from environment variables.
+    ///
+    /// The Identity Provider (IDP) configuration creates the `TokenValidator`. Then it reads
+    /// the demo mode setting, the company rules, the change approval setting,
+    /// the field permissions and the share link signing key, and sets up the DNS resolver
+    /// for email checks, the enrichment provider, the address validator, the base URL for links, the response envelope
+    /// default, the request time limits, the guard of the public contact form, the maintenance mode, the report cache, the debug capture, the trusted proxies and the networks allowed to reach the admin routes.
+    ///
+    /// # Returns
+    ///
+    /// * The `ContactsApi`.
+    ///
+    /// # Panics
+    ///
+    /// If `IDP_URL` or `IDP_AUDIENCE` is not set, or another setting is invalid.
+    pub fn from_env() -> Self {
+        let idp_url = std::env::var("IDP_URL")
+            .expect("IDP_URL environment variable must be set, e.g., in a .env file.");
+        let idp_audience = std::env::var("IDP_AUDIENCE")
+            .expect("IDP_AUDIENCE environment variable must be set, e.g., in a .env file.");
+
+        Self::with_validator(TokenValidator::new(&idp_url, &idp_audience))
+    }
+
+    /// Reads the configuration of the API from environment variables, but validates tokens with the given validator.
+    ///
+    /// # Arguments
+    ///
+    /// * `validator` - The validator of the tokens, e.g. one from `TokenValidator::with_keys`.
+    ///
+    /// # Returns
+    ///
+    /// * The `ContactsApi`.
+    ///
+    /// # Panics
+    ///
+    /// If a setting is invalid.
+    pub fn with_validator(validator: TokenValidator) -> Self {
+        Self {
+            validator: web::Data::new(validator),
+            demo: DemoMode::from_env(),
+            company_rules: CompanyRules::from_env(),
+            change_approval: ChangeApproval::from_env(),
+            field_permissions: web::Data::new(FieldPermissions::from_env()),
+            share_link_signer: web::Data::new(ShareLinkSigner::from_env()),
+            email_verifier: web::Data::new(EmailVerifier::from_system_config()),
+            enricher: web::Data::new(Enricher::from_env()),
+            addresses: web::Data::new(AddressValidator::from_env()),
+            base_url: web::Data::new(ExternalBaseUrl::from_env()),
+            envelope_default: web::Data::new(EnvelopeDefault::from_env()),
+            request_timeouts: web::Data::new(RequestTimeouts::from_env()),
+            contact_request_guard: web::Data::new(ContactRequestGuard::from_env()),
+            maintenance_mode: web::Data::new(MaintenanceMode::from_env()),
+            report_cache: web::Data::new(ReportCache::from_env()),
+            debug_capture: web::Data::new(DebugCapture::from_env()),
+            trusted_proxies: web::Data::new(TrustedProxies::from_env()),
+            ip_filter: web::Data::new(IpFilter::from_env()),
+        }
+    }
+
+    /// Returns the demo mode setting.
+    pub fn demo(&self) -> DemoMode {
+        self.demo
+    }
+
+    /// Returns the maintenance mode, shared by every worker.
+    pub fn maintenance_mode(&self) -> &MaintenanceMode {
+        &self.maintenance_mode
+    }
+
+    /// Registers the shared state, the health and metrics endpoints and the API scopes on an app.
+    ///
+    /// Every scope gets a database transaction and a time limit per request, demo and maintenance mode
+    /// and the debug capture, and the API scopes also get the network check of the admin routes,
+    /// deprecation notices, version and format negotiation, the response envelope, translated error
+    /// messages and camelCase field names. `/metrics` gets the network check too.
+    ///
+    /// # Arguments
+    ///
+    /// * `cfg` - The configuration of the app, or of a scope to mount the API under.
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(self.validator.clone())
+            .app_data(web::Data::new(self.demo))
+            .app_data(web::Data::new(self.company_rules))
+            .app_data(web::Data::new(self.change_approval))
+            .app_data(self.field_permissions.clone())
+            .app_data(self.share_link_signer.clone())
+            .app_data(self.email_verifier.clone())
+            .app_data(self.enricher.clone())
+            .app_data(self.addresses.clone())
+            .app_data(self.base_url.clone())
+            .app_data(self.envelope_default.clone())
+            .app_data(self.request_timeouts.clone())
+            .app_data(self.contact_request_guard.clone())
+            .app_data(self.maintenance_mode.clone())
+            .app_data(self.report_cache.clone())
+            .app_data(self.debug_capture.clone())
+            .app_data(self.trusted_proxies.clone())
+            .app_data(self.ip_filter.clone())
+            .configure(|cfg| register_routes(cfg, ROOT_ROUTES))
+            // Versioned scopes must come before `/api`, which would match their paths too
+            .service(api_scope(API_V1_PATH, self.demo))
+            // Unversioned paths are kept for existing clients and serve version 1
+            .service(api_scope(API_PATH, self.demo))
+            .service(
+                web::scope(SCIM_PATH)
+                    .wrap(actix_web::middleware::from_fn(
+                        transaction::transaction_per_request,
+                    ))
+                    .wrap(actix_web::middleware::from_fn(timeouts::enforce_timeout))
+                    .wrap(actix_web::middleware::Condition::new(
+                        self.demo.enabled,
+                        actix_web::middleware::from_fn(demo::restrict),
+                    ))
+                    .wrap(actix_web::middleware::from_fn(
+                        maintenance::reject_mutations,
+                    ))
+                    .wrap(actix_web::middleware::from_fn(
+                        debug_capture::capture_exchanges,
+                    ))
+                    .configure(|cfg| register_routes(cfg, SCIM_ROUTES)),
+            );
+    }
+}
@@ -0,0 +1,149 @@
+// backend/src/links.rs
+// This file builds the hypermedia links (`_links`) that are added to contact responses.
+// It exists so clients and the API gateway can discover related resources without hardcoding URLs.
+// RELEVANT FILES: backend/src/handlers.rs, backend/src/models.rs, backend/src/main.rs
+
+use crate::base_path::BasePath;
+use crate::includes::Embedded;
+use crate::models::Contact;
+use crate::trusted_proxies::TrustedProxies;
+use actix_web::HttpRequest;
+use serde::Serialize;
+
+/// Holds the external base URL that links are built from.
+#[derive(Clone, Default)]
+pub struct ExternalBaseUrl {
+    /// The configured URL, e.g. `https://contacts.example.com`, without a trailing slash.
+    url: Option<String>,
+    /// The path prefix the server is served under, added to URLs built from the request.
+    base_path: BasePath,
+    /// The proxies whose forwarded scheme and host are believed.
+    proxies: TrustedProxies,
+}
+
+impl ExternalBaseUrl {
+    /// Reads the base URL from the `EXTERNAL_BASE_URL` environment variable, and `BASE_PATH` and
+    /// `TRUSTED_PROXIES` for URLs built from the request.
+    ///
+    /// # Returns
+    ///
+    /// * An `ExternalBaseUrl`. When the variable is not set, URLs are built from the request.
+    ///
+    /// # Panics
+    ///
+    /// If `TRUSTED_PROXIES` is invalid.
+    pub fn from_env() -> Self {
+        let url = std::env::var("EXTERNAL_BASE_URL")
+            .ok()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty());
+        Self {
+            url,
+            base_path: BasePath::from_env(),
+            proxies: TrustedProxies::from_env(),
+        }
+    }
+
+    /// Returns the base URL to use for a request.
+    ///
+    /// Behind a gateway the host of the request is not the one clients use,
+    /// so the configured URL always wins. Otherwise the URL is the scheme and host clients used,
+    /// as forwarded by a trusted proxy, followed by the base path.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The HTTP request, used when no URL is configured.
+    ///
+    /// # Returns
+    ///
+    /// * The base URL, without a trailing slash.
+    pub fn resolve(&self, req: &HttpRequest) -> String {
+        match &self.url {
+            Some(url) => url.clone(),
+            None => format!("{}{}", self.proxies.origin(req), self.base_path.as_str()),
+        }
+    }
+}
+
+/// A single hypermedia link.
+#[derive(Serialize)]
+pub struct Link {
+    /// The absolute URL of the linked resource.
+    pub href: String,
+}
+
+/// The links of a contact resource.
+#[derive(Serialize)]
+pub struct ContactLinks {
+    /// The contact itself.
+    #[serde(rename = "self")]
+    pub self_link: Link,
+    /// The company of the contact, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company: Option<Link>,
+    /// The relationships of the contact.
+    pub relationships: Link,
+    /// The enrichment results of the contact.
+    pub enrichments: Link,
+}
+
+/// A contact together with its links.
+#[derive(Serialize)]
+pub struct LinkedContact {
+    /// The contact fields, inlined so the payload keeps its shape.
+    #[serde(flatten)]
+    pub contact: Contact,
+    /// The links to the contact and its related resources.
+    pub _links: ContactLinks,
+    /// The related data requested with `?include=`.
+    #[serde(skip_serializing_if = "Embedded::is_empty")]
+    pub _embedded: Embedded,
+}
+
+/// Adds links to a contact.
+///
+/// # Arguments
+///
+/// * `base` - The base URL, from `ExternalBaseUrl::resolve`.
+/// * `contact` - The contact.
+///
+/// # Returns
+///
+/// * The contact with its links.
+pub fn link_contact(base: &str, contact: Contact) -> LinkedContact {
+    let contact_url = format!("{}/api/v1/contacts/{}", base, contact.id);
+    let _links = ContactLinks {
+        company: contact.company_id.map(|id| Link {
+            href: format!("{}/api/v1/companies/{}", base, id),
+        }),
+        relationships: Link {
+            href: format!("{}/relationships", contact_url),
+        },
+        enrichments: Link {
+            href: format!("{}/enrichments", contact_url),
+        },
+        self_link: Link { href: contact_url },
+    };
+    LinkedContact {
+        contact,
+        _links,
+        _embedded: Embedded::default(),
+    }
+}
+
+/// Builds the value of a `Link` header that points to a collection itself.
+///
+/// Collections are plain JSON arrays, so their links are sent as a header (RFC 8288)
+/// instead of in the body.
+///
+/// # Arguments
+///
+/// * `base` - The base URL, from `ExternalBaseUrl::resolve`.
+/// * `req` - The HTTP request for the collection.
+///
+/// # Returns
+///
+/// * The header value, e.g. `<https://host/api/contacts?status=lead>; rel="self"`.
+pub fn self_link_header(base: &str, req: &HttpRequest) -> String {
+    format!("<{}{}>; rel=\"self\"", base, req.uri())
+}
@@ -0,0 +1,136 @@
+// backend/src/listeners.rs
+// This file opens the sockets the server accepts connections on: sockets passed by systemd, a Unix socket, or TCP port 8081.
+// It exists so the server can run behind nginx over a Unix socket, and be started by systemd on the first connection on small servers.
+// RELEVANT FILES: backend/src/main.rs, backend/.env.example, backend/README.md
+
+use std::io;
+use std::net::TcpListener;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::{FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::net::UnixListener;
+
+/// The address the server listens on when no socket is configured.
+pub const DEFAULT_ADDRESS: (&str, u16) = ("0.0.0.0", 8081);
+
+/// The permissions of the Unix socket when `UNIX_SOCKET_MODE` is not set: the owner and the group
+/// can connect, so a proxy in the group (e.g. `www-data`) can reach it.
+const DEFAULT_SOCKET_MODE: u32 = 0o660;
+
+/// The first file descriptor systemd passes, after standard input, output and error.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// A socket the server accepts connections on.
+pub enum Listener {
+    /// A TCP socket.
+    Tcp(TcpListener),
+    /// A Unix domain socket.
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// Describes the socket for the log, e.g. `0.0.0.0:8081` or `/run/contacts-api.sock`.
+    pub fn describe(&self) -> String {
+        match self {
+            Listener::Tcp(listener) => listener
+                .local_addr()
+                .map_or_else(|_| "a TCP socket".to_string(), |addr| addr.to_string()),
+            Listener::Unix(listener) => listener
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(|path| path.display().to_string()))
+                .unwrap_or_else(|| "a Unix socket".to_string()),
+        }
+    }
+}
+
+/// Takes the sockets systemd passed with socket activation, if it started this process.
+///
+/// systemd sets `LISTEN_PID` to the process it means and `LISTEN_FDS` to how many sockets it
+/// passed, from file descriptor 3 on. The variables are inherited by child processes, so they
+/// only count when `LISTEN_PID` is this process.
+///
+/// # Returns
+///
+/// * The sockets, or an empty list without socket activation.
+fn systemd_listeners() -> Vec<Listener> {
+    let for_this_process = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    if !for_this_process {
+        return Vec::new();
+    }
+    let count = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse::<RawFd>().ok())
+        .unwrap_or(0);
+    (SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count)
+        .map(|fd| {
+            // Safety: systemd passed these descriptors to this process, and nothing else owns them
+            let unix = unsafe { UnixListener::from_raw_fd(fd) };
+            // A Unix address can only be read from a Unix socket, so anything else is TCP
+            if unix.local_addr().is_ok() {
+                Listener::Unix(unix)
+            } else {
+                let fd = unix.into_raw_fd();
+                // Safety: the descriptor was just released by the Unix listener above
+                Listener::Tcp(unsafe { TcpListener::from_raw_fd(fd) })
+            }
+        })
+        .collect()
+}
+
+/// Binds a Unix socket, replacing the file a previous run left behind.
+///
+/// # Arguments
+///
+/// * `path` - The path of the socket, e.g. `/run/contacts-api/api.sock`.
+///
+/// # Returns
+///
+/// * `Ok(UnixListener)` with the socket, with the permissions from `UNIX_SOCKET_MODE` (`660` by default).
+/// * `Err(io::Error)` if the socket cannot be created, or `UNIX_SOCKET_MODE` is not an octal mode.
+fn bind_unix(path: &str) -> io::Result<UnixListener> {
+    if let Err(e) = std::fs::remove_file(path)
+        && e.kind() != io::ErrorKind::NotFound
+    {
+        return Err(e);
+    }
+    let mode = match std::env::var("UNIX_SOCKET_MODE") {
+        Ok(mode) if !mode.trim().is_empty() => {
+            u32::from_str_radix(mode.trim(), 8).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "UNIX_SOCKET_MODE must be an octal mode like 660, not '{}'",
+                        mode
+                    ),
+                )
+            })?
+        }
+        _ => DEFAULT_SOCKET_MODE,
+    };
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+/// Opens the sockets the server accepts connections on.
+///
+/// In order of preference: the sockets systemd passed with socket activation, the Unix socket at
+/// `UNIX_SOCKET`, or TCP on `0.0.0.0:8081`.
+///
+/// # Returns
+///
+/// * `Ok(Vec<Listener>)` with at least one socket.
+/// * `Err(io::Error)` if a socket cannot be opened.
+pub fn from_env() -> io::Result<Vec<Listener>> {
+    let activated = systemd_listeners();
+    if !activated.is_empty() {
+        return Ok(activated);
+    }
+    match std::env::var("UNIX_SOCKET") {
+        Ok(path) if !path.trim().is_empty() => Ok(vec![Listener::Unix(bind_unix(path.trim())?)]),
+        _ => Ok(vec![Listener::Tcp(TcpListener::bind(DEFAULT_ADDRESS)?)]),
+    }
+}
@@ -0,0 +1,297 @@
+// backend/src/log_level.rs
+// This file holds the logger of the server, whose filter admins can change at runtime and which reverts by itself.
+// It exists so a production issue can be debugged with more detailed logs, without a redeploy and without staying noisy.
+// RELEVANT FILES: backend/src/main.rs, backend/src/config_reload.rs, backend/.env.example
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::error::ApiError;
+use crate::unix_now;
+use actix_web::{web, HttpResponse};
+use log::{Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+/// The filter when `RUST_LOG` is not set.
+const DEFAULT_DIRECTIVES: &str = "info";
+
+/// How long a changed filter lasts when not configured, in seconds.
+const DEFAULT_REVERT_SECS: u64 = 900;
+
+/// The levels a directive can have.
+const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+/// The filter in use and how it came to be, as shown to admins.
+#[derive(Clone, Serialize)]
+pub struct LogLevelState {
+    /// The directives in use, e.g. `contacts_core::auth=debug,info`.
+    pub directives: String,
+    /// The directives the server started with, which it reverts to.
+    pub default_directives: String,
+    /// Who changed the filter, if it is not the default.
+    pub changed_by: Option<String>,
+    /// When the filter reverts to the default, as a Unix timestamp. `None` if it does not.
+    pub revert_at: Option<i64>,
+    /// Counts the changes, so a revert that was planned for an older change does nothing.
+    #[serde(skip)]
+    generation: u64,
+}
+
+/// The request body for changing the filter.
+#[derive(Deserialize)]
+pub struct SetLogLevel {
+    /// The directives, like `RUST_LOG`: comma-separated `level` or `module=level`.
+    pub directives: String,
+    /// After how many seconds the default comes back. `0` keeps the filter until it is changed again.
+    /// Defaults to `LOG_LEVEL_REVERT_SECS`.
+    pub revert_after_secs: Option<u64>,
+}
+
+/// The logger of the server: `env_logger` output, with a filter that can be swapped.
+struct DynamicLogger {
+    /// The logger for the directives in use. It is rebuilt when they change.
+    logger: RwLock<env_logger::Logger>,
+    /// The directives in use and when they revert.
+    state: RwLock<LogLevelState>,
+    /// How long a changed filter lasts by default. Zero means until it is changed again.
+    revert_after: Duration,
+}
+
+/// Builds an `env_logger` logger for some directives.
+fn build_logger(directives: &str) -> env_logger::Logger {
+    env_logger::Builder::new().parse_filters(directives).build()
+}
+
+/// Returns the logger, created from `RUST_LOG` and `LOG_LEVEL_REVERT_SECS` the first time it is needed.
+fn logger() -> &'static DynamicLogger {
+    static LOGGER: OnceLock<DynamicLogger> = OnceLock::new();
+    LOGGER.get_or_init(|| {
+        let directives = std::env::var("RUST_LOG")
+            .ok()
+            .filter(|d| !d.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_DIRECTIVES.to_string());
+        let revert_secs = std::env::var("LOG_LEVEL_REVERT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_REVERT_SECS);
+        DynamicLogger {
+            logger: RwLock::new(build_logger(&directives)),
+            state: RwLock::new(LogLevelState {
+                directives: directives.clone(),
+                default_directives: directives,
+                changed_by: None,
+                revert_at: None,
+                generation: 0,
+            }),
+            revert_after: Duration::from_secs(revert_secs),
+        }
+    })
+}
+
+impl Log for DynamicLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.logger
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        self.logger
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .log(record);
+    }
+
+    fn flush(&self) {
+        self.logger
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .flush();
+    }
+}
+
+impl DynamicLogger {
+    /// Swaps the filter.
+    ///
+    /// # Returns
+    ///
+    /// * The new state, whose generation names this change.
+    fn apply(
+        &self,
+        directives: &str,
+        changed_by: Option<String>,
+        revert_at: Option<i64>,
+    ) -> LogLevelState {
+        let built = build_logger(directives);
+        // The log macros skip everything above the maximum level, so it follows the filter
+        log::set_max_level(built.filter());
+        *self.logger.write().unwrap_or_else(|e| e.into_inner()) = built;
+
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.directives = directives.to_string();
+        state.changed_by = changed_by;
+        state.revert_at = revert_at;
+        state.generation += 1;
+        state.clone()
+    }
+
+    fn state(&self) -> LogLevelState {
+        self.state.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Installs the logger of the server, with the filter from `RUST_LOG` (`info` by default).
+///
+/// It replaces `env_logger::init`, and writes the same output.
+///
+/// # Panics
+///
+/// If a logger is already installed.
+pub fn init() {
+    let logger = logger();
+    log::set_max_level(
+        logger
+            .logger
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .filter(),
+    );
+    log::set_logger(logger).expect("A logger is already installed");
+}
+
+/// Checks directives before they are used, as `env_logger` skips the ones it cannot read.
+///
+/// # Arguments
+///
+/// * `directives` - Comma-separated `level` or `module=level`, e.g. `contacts_core::auth=debug,info`.
+///
+/// # Returns
+///
+/// * `Ok(())` if every directive is valid.
+/// * `Err(ApiError::BadRequest)` if one is invalid.
+fn validate(directives: &str) -> Result<(), ApiError> {
+    let invalid = || {
+        ApiError::BadRequest(format!(
+            "Directives must be comma-separated 'level' or 'module=level', with a level of: {}",
+            LEVELS.join(", ")
+        ))
+    };
+    if directives.trim().is_empty() {
+        return Err(invalid());
+    }
+    for directive in directives.split(',').map(str::trim) {
+        let (module, level) = match directive.split_once('=') {
+            Some((module, level)) => (Some(module.trim()), level.trim()),
+            None => (None, directive),
+        };
+        let module_ok = module.is_none_or(|m| {
+            !m.is_empty()
+                && m.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+        });
+        if !module_ok || !LEVELS.contains(&level.to_ascii_lowercase().as_str()) {
+            return Err(invalid());
+        }
+    }
+    Ok(())
+}
+
+/// Handles reading the log filter in use.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the directives in use, the default ones and when they revert.
+/// * `Err(ApiError)` if the user is not an admin.
+pub async fn read_log_level(principal: Principal) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    Ok(HttpResponse::Ok().json(logger().state()))
+}
+
+/// Handles changing the log filter, e.g. to `contacts_core::auth=debug,info`.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// The change applies to every worker at once. Unless `revert_after_secs` is `0`,
+/// the default filter comes back by itself, so detailed logs are not left on by accident.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and log who changed the filter.
+/// * `body` - The directives and when they revert.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the new state.
+/// * `Err(ApiError)` if the user is not an admin or the directives are invalid.
+pub async fn set_log_level(
+    principal: Principal,
+    body: web::Json<SetLogLevel>,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let body = body.into_inner();
+    let directives = body.directives.trim();
+    validate(directives)?;
+
+    let logger = logger();
+    let revert_after = body
+        .revert_after_secs
+        .map(Duration::from_secs)
+        .unwrap_or(logger.revert_after);
+    let revert_at = (!revert_after.is_zero()).then(|| unix_now() + revert_after.as_secs() as i64);
+    let state = logger.apply(directives, Some(principal.id().to_string()), revert_at);
+    log::warn!(
+        "Log filter set to '{}' by {}{}",
+        directives,
+        principal.id(),
+        match revert_at {
+            Some(_) => format!(", reverting in {}s", revert_after.as_secs()),
+            None => String::new(),
+        }
+    );
+
+    if revert_at.is_some() {
+        let generation = state.generation;
+        actix_web::rt::spawn(async move {
+            actix_web::rt::time::sleep(revert_after).await;
+            // A later change plans its own revert
+            let current = logger.state();
+            if current.generation == generation {
+                logger.apply(&current.default_directives, None, None);
+                log::warn!("Log filter reverted to '{}'", current.default_directives);
+            }
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(state))
+}
+
+/// Handles reverting the log filter to the default at once.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and log who reverted the filter.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the default state.
+/// * `Err(ApiError)` if the user is not an admin.
+pub async fn reset_log_level(principal: Principal) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let logger = logger();
+    let default_directives = logger.state().default_directives;
+    let state = logger.apply(&default_directives, None, None);
+    log::warn!(
+        "Log filter reverted to '{}' by {}",
+        default_directives,
+        principal.id()
+    );
+    Ok(HttpResponse::Ok().json(state))
+}
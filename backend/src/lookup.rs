@@ -0,0 +1,239 @@
+// backend/src/lookup.rs
+// This file contains the fast contact searches: email lookup and autocomplete.
+// It exists so mail clients and compose UIs can resolve people with cheap, indexed queries.
+// RELEVANT FILES: backend/src/scim.rs, backend/src/models.rs, backend/src/main.rs
+
+use crate::archive::ArchivedQuery;
+use crate::error::ApiError;
+use crate::establish_connection;
+use crate::field_permissions::FieldRules;
+use crate::models::Contact;
+use crate::schema::contacts;
+use crate::scim::SCIM_SOURCE;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use diesel::sqlite::Sqlite;
+use serde::{Deserialize, Serialize};
+
+diesel::define_sql_function! {
+    /// SQLite's `lower()`. Searches use it so the `contacts_*_lower` indexes apply.
+    fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text;
+}
+
+/// Turns a prefix into the range of lowercase values that start with it.
+///
+/// Every string that starts with the prefix sorts between the prefix and the upper bound,
+/// so `lower(column) >= from AND lower(column) < to` is a range scan on a `lower()` index,
+/// where `LIKE 'prefix%'` would read the whole table.
+/// SQLite's `lower()` only folds ASCII letters, so the prefix is folded the same way:
+/// `Å` finds `Åsa`, but `å` does not.
+///
+/// # Arguments
+///
+/// * `prefix` - The prefix as typed by the user.
+///
+/// # Returns
+///
+/// * `Some((from, to))`, or `None` if the prefix is empty.
+pub(crate) fn prefix_range(prefix: &str) -> Option<(String, String)> {
+    let from = prefix.trim().to_ascii_lowercase();
+    if from.is_empty() {
+        return None;
+    }
+    let to = format!("{}{}", from, char::MAX);
+    Some((from, to))
+}
+
+/// The values `lower(email)` can have for an address, to compare emails without case.
+///
+/// SQLite's `lower()` only folds ASCII letters, but stored emails may have been lowercased
+/// completely by the normalization rules, so both forms are looked for.
+///
+/// # Arguments
+///
+/// * `email` - The address as given by the user.
+///
+/// # Returns
+///
+/// * The address folded like `lower()` does, and fully lowercased if that differs.
+pub(crate) fn email_keys(email: &str) -> Vec<String> {
+    let mut keys = vec![
+        email.trim().to_ascii_lowercase(),
+        email.trim().to_lowercase(),
+    ];
+    keys.dedup();
+    keys
+}
+
+/// The query parameters of the lookup.
+#[derive(Deserialize)]
+pub struct LookupQuery {
+    /// The email address to look up.
+    pub email: String,
+}
+
+/// The most results autocomplete returns.
+const AUTOCOMPLETE_LIMIT: i64 = 10;
+
+/// The query parameters of autocomplete.
+#[derive(Deserialize)]
+pub struct AutocompleteQuery {
+    /// What the user typed so far.
+    pub q: String,
+}
+
+/// A condition on contacts, built at run time from the fields the user may search.
+type Condition = Box<dyn BoxableExpression<contacts::table, Sqlite, SqlType = Bool>>;
+
+/// A lightweight contact for autocomplete results.
+#[derive(Serialize)]
+pub struct Suggestion {
+    /// The ID of the contact.
+    pub id: i32,
+    /// The name to show, e.g. "Jane Doe".
+    pub display_name: String,
+    /// The email address of the contact.
+    pub email: String,
+}
+
+/// The best match for an email address.
+#[derive(Serialize)]
+pub struct LookupResult {
+    /// The matching contact.
+    pub contact: Contact,
+    /// Where the contact comes from: "directory" for SCIM-synced entries, else "contacts".
+    pub source: &'static str,
+    /// How sure the match is: 1.0 for the exact address, 0.9 when only the case differs.
+    pub confidence: f32,
+}
+
+/// Handles finding the contact with an email address.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// The address is compared without case, using an index on `lower(email)`.
+/// When several contacts match, the exact address wins, then the oldest contact.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `query` - The email address to look up.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the best match, its source and confidence.
+/// * `Err(ApiError)` if the email is hidden from the user, no contact matches or there is a database error.
+pub async fn lookup(
+    field_rules: FieldRules,
+    query: web::Query<LookupQuery>,
+) -> Result<HttpResponse, ApiError> {
+    // Whether an address finds a contact would tell a user who may not read emails what they are
+    field_rules.check_readable("email")?;
+    let email = query.email.trim();
+    let mut conn = establish_connection()?;
+
+    let matches = contacts::table
+        .filter(lower(contacts::email).eq_any(email_keys(email)))
+        .order(contacts::id.asc())
+        .load::<Contact>(&mut conn)?;
+
+    let exact = matches.iter().position(|c| c.email == email);
+    let (contact, confidence) = match exact {
+        Some(index) => (matches.into_iter().nth(index), 1.0),
+        None => (matches.into_iter().next(), 0.9),
+    };
+    let contact = contact.ok_or(ApiError::NotFound)?;
+
+    let source = if contact.source.as_deref() == Some(SCIM_SOURCE) {
+        "directory"
+    } else {
+        "contacts"
+    };
+
+    Ok(HttpResponse::Ok().json(LookupResult {
+        contact: field_rules.masked(contact),
+        source,
+        confidence,
+    }))
+}
+
+/// Handles suggesting contacts while the user types.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Contacts match when their first name, last name or email starts with `q`, ignoring case.
+/// Only the fields the user may read are searched, and hidden names are masked in `display_name`.
+/// Archived contacts are left out unless the query has `include_archived=true`.
+/// Prefixes are searched as ranges on the `lower()` indexes, so no table scan is needed.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `query` - The text typed so far.
+/// * `archived` - Whether to suggest archived contacts too.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a JSON array of at most 10 suggestions, sorted by name.
+/// * `Err(ApiError)` if there is a database error.
+pub async fn autocomplete(
+    field_rules: FieldRules,
+    query: web::Query<AutocompleteQuery>,
+    archived: web::Query<ArchivedQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let Some((prefix, upper)) = prefix_range(&query.q) else {
+        return Ok(HttpResponse::Ok().json(Vec::<Suggestion>::new()));
+    };
+
+    // A match on a hidden field would reveal its value, so only readable fields are searched
+    let mut conditions: Vec<Condition> = Vec::new();
+    if !field_rules.hides("first_name") {
+        conditions.push(Box::new(
+            lower(contacts::first_name)
+                .ge(prefix.clone())
+                .and(lower(contacts::first_name).lt(upper.clone())),
+        ));
+    }
+    if !field_rules.hides("last_name") {
+        conditions.push(Box::new(
+            lower(contacts::last_name)
+                .ge(prefix.clone())
+                .and(lower(contacts::last_name).lt(upper.clone())),
+        ));
+    }
+    if !field_rules.hides("email") {
+        conditions.push(Box::new(
+            lower(contacts::email)
+                .ge(prefix)
+                .and(lower(contacts::email).lt(upper)),
+        ));
+    }
+    let Some(condition) = conditions
+        .into_iter()
+        .reduce(|either, or| Box::new(either.or(or)))
+    else {
+        return Ok(HttpResponse::Ok().json(Vec::<Suggestion>::new()));
+    };
+
+    let mut conn = establish_connection()?;
+    let mut matching = contacts::table.into_boxed();
+    if !archived.include_archived {
+        matching = matching.filter(contacts::archived_at.is_null());
+    }
+    let suggestions: Vec<Suggestion> = matching
+        .filter(condition)
+        .order((contacts::last_name.asc(), contacts::first_name.asc()))
+        .limit(AUTOCOMPLETE_LIMIT)
+        .load::<Contact>(&mut conn)?
+        .into_iter()
+        .map(|contact| {
+            let contact = field_rules.masked(contact);
+            Suggestion {
+                id: contact.id,
+                display_name: format!("{} {}", contact.first_name, contact.last_name),
+                email: contact.email,
+            }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(suggestions))
+}
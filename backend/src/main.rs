@@ -1,114 +1,129 @@
 // backend/src/main.rs
 // This file is the main entry point for the backend server.
-// It sets up the database, runs migrations, and starts the HTTP server.
-// RELEVANT FILES: backend/src/handlers.rs, backend/src/auth.rs, backend/src/error.rs
+// It runs migrations and serves the contact API from the `contacts_core` library over HTTP.
+// RELEVANT FILES: backend/src/lib.rs, backend/src/migrations.rs, backend/src/fallback.rs
 
 use actix_web::{web, App, HttpServer};
-use diesel::prelude::*;
-use diesel::sqlite::SqliteConnection;
-use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use dotenvy::dotenv;
-use std::env;
-
-mod auth;
-pub mod error;
-pub mod handlers;
-pub mod models;
-pub mod schema;
-
-use crate::auth::TokenValidator;
-use crate::error::ApiError;
-
-const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
-
-/// Runs pending database migrations.
-///
-/// # Arguments
-///
-/// * `conn` - A mutable reference to a type that implements `MigrationHarness`.
-///
-/// # Returns
-///
-/// * `Ok(())` if the migrations were successful.
-/// * `Err` with a boxed error if the migrations failed.
-fn run_migrations(
-    conn: &mut impl MigrationHarness<diesel::sqlite::Sqlite>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
-    conn.run_pending_migrations(MIGRATIONS)?;
-    Ok(())
-}
-
-/// Establishes a connection to the SQLite database.
-///
-/// It reads the `DATABASE_URL` from the environment variables (e.g., from a `.env` file).
-///
-/// # Returns
-///
-/// * `Ok(SqliteConnection)` if the connection is successful.
-/// * `Err(ApiError)` if the connection fails.
-fn establish_connection() -> Result<SqliteConnection, ApiError> {
-    dotenv().ok();
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    SqliteConnection::establish(&database_url).map_err(|e| ApiError::from(e))
-}
-
-use actix_cors::Cors;
+use contacts_core::base_path::{self, BasePath};
+use contacts_core::cors::{self, CorsConfig};
+use contacts_core::listeners::{self, Listener};
+use contacts_core::trusted_proxies::TrustedProxies;
+use contacts_core::{
+    diagnostics, establish_connection, fallback, log_level, migrations, ContactsApi,
+};
+use std::rc::Rc;
 
 /// The main entry point for the Actix web server.
 ///
-/// This function performs the following steps:
+/// With the `migrate` argument, it only runs the migrations (or prints them with `--dry-run`) and exits.
+/// With the `doctor` argument, it only checks the setup, prints a report and exits, see `diagnostics::run`.
+///
+/// Every mode first loads `.env`, so its settings (e.g. `ALLOW_DESTRUCTIVE_MIGRATIONS`) apply
+/// from the start. Otherwise this function performs the following steps:
 /// 1. Establishes a database connection.
-/// 2. Runs any pending database migrations.
+/// 2. Runs any pending database migrations, unless one is destructive and that is not allowed.
 /// 3. Initializes the logger.
-/// 4. Reads Identity Provider (IDP) configuration from environment variables.
-/// 5. Creates a `TokenValidator` for authenticating requests.
-/// 6. Configures and starts the HTTP server with CORS, logging, and API routes.
+/// 4. Reads the configuration of the API, the CORS policies, the base path and the trusted proxies
+///    from environment variables, see `ContactsApi::from_env` and `CorsConfig::from_env`.
+/// 5. Configures and starts the HTTP server on the sockets from systemd, a Unix socket or TCP port 8081,
+///    see `listeners::from_env`, with CORS, the base path, logging, a problem body for unknown routes,
+///    and the API, see `ContactsApi::configure`.
 ///
 /// # Returns
 ///
 /// * `std::io::Result<()>` which indicates if the server started successfully or not.
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    // The logger is not set up yet, so a missing file is reported below
+    let env_file_found = dotenvy::dotenv().is_ok();
+
+    // `contacts-api migrate [--dry-run]` only handles the migrations and exits
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "migrate") {
+        let dry_run = args.iter().any(|a| a == "--dry-run");
+        if let Err(e) = migrations::migrate_command(dry_run) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    // `contacts-api doctor` checks the setup, and fails when a check fails
+    if args.first().is_some_and(|a| a == "doctor") {
+        let report = diagnostics::run().await;
+        for check in &report.checks {
+            println!(
+                "{:<4} {:<20} {}",
+                check.status.to_uppercase(),
+                check.name,
+                check.detail
+            );
+        }
+        if !report.passed() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Err(e) = migrations::check_pending_migrations() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
     let mut conn = establish_connection().expect("Failed to connect to database");
-    run_migrations(&mut conn).expect("Failed to run database migrations");
+    migrations::run_migrations(&mut conn).expect("Failed to run database migrations");
 
-    if dotenvy::dotenv().is_err() {
+    log_level::init();
+    if !env_file_found {
         log::warn!(".env file not found, relying on environment variables.");
     }
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
-    let idp_url = std::env::var("IDP_URL")
-        .expect("IDP_URL environment variable must be set, e.g., in a .env file.");
-    let idp_audience = std::env::var("IDP_AUDIENCE")
-        .expect("IDP_AUDIENCE environment variable must be set, e.g., in a .env file.");
+    let api = ContactsApi::from_env();
+    if api.demo().enabled {
+        log::warn!("Demo mode is enabled: serving synthetic contacts and rejecting changes.");
+    }
+    if api.maintenance_mode().state().read_only {
+        log::warn!("Maintenance mode is on: rejecting changes until an admin switches it off.");
+    }
 
-    let validator = web::Data::new(TokenValidator::new(&idp_url, &idp_audience));
+    let cors_config = CorsConfig::from_env();
+    let base_path = BasePath::from_env();
+    let proxies = TrustedProxies::from_env();
 
-    HttpServer::new(move || {
-        let cors = Cors::default()
-            .allowed_origin("http://localhost:3000") // Add your frontend origins
-            .allowed_origin("http://localhost:5173")
-            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
-            .allowed_headers(vec![
-                actix_web::http::header::AUTHORIZATION,
-                actix_web::http::header::ACCEPT,
-                actix_web::http::header::CONTENT_TYPE,
-            ])
-            .max_age(3600);
+    let mut server = HttpServer::new(move || {
+        // Each worker runs on one thread, so it gets its own copy of the policies
+        let cors_config = Rc::new(cors_config.clone());
+        let base_path = Rc::new(base_path.clone());
+        let proxies = proxies.clone();
+        let api = api.clone();
         App::new()
-            .wrap(cors)
-            .wrap(actix_web::middleware::Logger::default())
-            .app_data(validator.clone())
-            .service(
-                web::scope("/api")
-                    .service(handlers::create_contact)
-                    .service(handlers::read_contacts)
-                    .service(handlers::read_contact)
-                    .service(handlers::update_contact)
-                    .service(handlers::delete_contact),
+            .wrap(actix_web::middleware::from_fn(move |req, next| {
+                cors::apply_cors(cors_config.clone(), req, next)
+            }))
+            // Runs before CORS, so the policies see the path without the prefix
+            .wrap(actix_web::middleware::from_fn(move |req, next| {
+                base_path::strip_base_path(base_path.clone(), req, next)
+            }))
+            // The default format, with the client behind trusted proxies instead of the proxy
+            .wrap(
+                actix_web::middleware::Logger::new(
+                    "%{client_ip}xi \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T",
+                )
+                .custom_request_replace("client_ip", move |req| {
+                    proxies
+                        .client_ip(req.request())
+                        .map_or_else(|| "-".to_string(), |ip| ip.to_string())
+                }),
             )
-    })
-    .bind(("0.0.0.0", 8081))?
-    .run()
-    .await
+            // Requests that match no route get a problem body instead of an empty 404
+            .default_service(web::to(fallback::route_not_found))
+            .configure(move |cfg| api.configure(cfg))
+    });
+    // Without any configuration this is TCP port 8081, see `listeners::from_env`
+    for listener in listeners::from_env()? {
+        log::info!("Listening on {}", listener.describe());
+        server = match listener {
+            Listener::Tcp(listener) => server.listen(listener)?,
+            Listener::Unix(listener) => server.listen_uds(listener)?,
+        };
+    }
+    server.run().await
 }
@@ -0,0 +1,196 @@
+// backend/src/maintenance.rs
+// This file implements the read-only maintenance mode that admins switch on during backups and migrations.
+// It exists so the database is not written to while it is copied or changed, while users can still read.
+// RELEVANT FILES: backend/src/main.rs, backend/src/health.rs, backend/src/demo.rs
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::error::ApiError;
+use crate::unix_now;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, Error as ActixWebError, HttpResponse};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::sync::RwLock;
+
+/// How long clients are told to wait before retrying when not configured, in seconds.
+const DEFAULT_RETRY_AFTER: u64 = 300;
+
+/// The path of the endpoint that switches maintenance mode, which must keep working while it is on.
+const TOGGLE_PATH: &str = "/admin/maintenance";
+
+/// The current maintenance state, as shown to clients.
+#[derive(Clone, Serialize)]
+pub struct MaintenanceState {
+    /// Whether the API only accepts reads.
+    pub read_only: bool,
+    /// A message for the frontend to show in a banner, e.g. "Back at 14:00".
+    pub banner: Option<String>,
+    /// How many seconds clients should wait before retrying a write.
+    pub retry_after: u64,
+    /// When read-only mode was switched on, as a Unix timestamp.
+    pub since: Option<i64>,
+}
+
+/// Holds the maintenance state, shared by all workers as app data.
+pub struct MaintenanceMode {
+    state: RwLock<MaintenanceState>,
+}
+
+impl MaintenanceMode {
+    /// Reads the starting state from the `MAINTENANCE_MODE`, `MAINTENANCE_BANNER`
+    /// and `MAINTENANCE_RETRY_AFTER` environment variables.
+    ///
+    /// # Returns
+    ///
+    /// * A `MaintenanceMode` that is read-only when `MAINTENANCE_MODE` is `true` or `1`.
+    pub fn from_env() -> Self {
+        let read_only = std::env::var("MAINTENANCE_MODE")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        let banner = std::env::var("MAINTENANCE_BANNER")
+            .ok()
+            .filter(|b| !b.is_empty());
+        let retry_after = std::env::var("MAINTENANCE_RETRY_AFTER")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RETRY_AFTER);
+        Self {
+            state: RwLock::new(MaintenanceState {
+                read_only,
+                banner,
+                retry_after,
+                since: read_only.then(unix_now),
+            }),
+        }
+    }
+
+    /// Returns a copy of the current state.
+    pub fn state(&self) -> MaintenanceState {
+        self.state.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Middleware that rejects mutating requests with 503 while maintenance mode is on.
+///
+/// Safe methods (GET, HEAD and OPTIONS) and the toggle endpoint itself are always passed on.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service when the request may go through.
+/// * `Err(ApiError::Unavailable)` with a `Retry-After` header otherwise.
+pub async fn reject_mutations(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    let is_safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    if !is_safe && !req.path().ends_with(TOGGLE_PATH) {
+        let state = req
+            .app_data::<web::Data<MaintenanceMode>>()
+            .map(|mode| mode.state());
+        if let Some(state) = state.filter(|s| s.read_only) {
+            let message = state
+                .banner
+                .unwrap_or_else(|| "The API is read-only during maintenance.".to_string());
+            return Err(ApiError::Unavailable(message, state.retry_after).into());
+        }
+    }
+    next.call(req).await
+}
+
+/// The change to the maintenance state. Fields that are not sent are left as they are.
+#[derive(Deserialize)]
+pub struct MaintenanceChange {
+    /// Whether the API should only accept reads.
+    pub read_only: Option<bool>,
+    /// The message to show in a banner. `Some(None)` when `null` is sent, which clears it.
+    #[serde(default, deserialize_with = "sent")]
+    pub banner: Option<Option<String>>,
+    /// How many seconds clients should wait before retrying a write.
+    pub retry_after: Option<u64>,
+}
+
+/// Reads a field that was sent, so a `null` is told apart from a field that was left out,
+/// which `#[serde(default)]` makes `None`.
+fn sent<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Handles reading the maintenance state, so the frontend can show a banner.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `mode` - The maintenance mode.
+///
+/// # Returns
+///
+/// * `HttpResponse` with the current state.
+pub async fn read_maintenance(
+    _principal: Principal,
+    mode: web::Data<MaintenanceMode>,
+) -> HttpResponse {
+    HttpResponse::Ok().json(mode.state())
+}
+
+/// Handles switching maintenance mode on or off.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+/// * `change` - The new state.
+/// * `mode` - The maintenance mode.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the new state.
+/// * `Err(ApiError::Forbidden)` if the user is not an admin.
+pub async fn update_maintenance(
+    principal: Principal,
+    change: web::Json<MaintenanceChange>,
+    mode: web::Data<MaintenanceMode>,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let change = change.into_inner();
+
+    let state = {
+        let mut state = mode.state.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(read_only) = change.read_only {
+            // Keep the start time when the mode is already on
+            if read_only && !state.read_only {
+                state.since = Some(unix_now());
+            } else if !read_only {
+                state.since = None;
+            }
+            state.read_only = read_only;
+        }
+        if let Some(banner) = change.banner {
+            state.banner = banner;
+        }
+        if let Some(retry_after) = change.retry_after {
+            state.retry_after = retry_after;
+        }
+        state.clone()
+    };
+
+    log::warn!(
+        "Maintenance mode switched {} by {}",
+        if state.read_only { "on" } else { "off" },
+        principal.id()
+    );
+    Ok(HttpResponse::Ok().json(state))
+}
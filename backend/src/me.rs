@@ -0,0 +1,140 @@
+// backend/src/me.rs
+// This file contains the handlers for the authenticated user's own contact card.
+// It exists so each user has a personal card, kept apart from the shared address book, that they can export as a vCard.
+// RELEVANT FILES: backend/src/models.rs, backend/src/vcard.rs, backend/src/auth.rs
+
+use crate::auth::{Claims, Principal};
+use crate::error::ApiError;
+use crate::establish_connection;
+use crate::models::{MyCard, MyCardUpdate};
+use crate::schema::my_cards;
+use crate::transaction::Transaction;
+use crate::vcard::{to_vcard, VCARD_CONTENT_TYPE};
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+
+/// Loads the user's card, or the card the token claims describe if none is stored yet.
+///
+/// Nothing is written, so reading a card works in the demo and during maintenance;
+/// the card is stored on the first update.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection.
+/// * `claims` - The claims of the authenticated user.
+///
+/// # Returns
+///
+/// * `Ok(MyCard)` with the stored card, or a new one from the claims.
+/// * `Err(ApiError)` if there is a database error.
+fn load_card(conn: &mut SqliteConnection, claims: &Claims) -> Result<MyCard, ApiError> {
+    let stored = my_cards::table
+        .find(claims.user_id())
+        .first::<MyCard>(conn)
+        .optional()?;
+
+    Ok(stored.unwrap_or_else(|| MyCard {
+        sub: claims.user_id().to_string(),
+        first_name: claims.given_name.clone().unwrap_or_default(),
+        last_name: claims.family_name.clone().unwrap_or_default(),
+        email: claims.email.clone().unwrap_or_default(),
+        phone_number: String::new(),
+    }))
+}
+
+/// Handles reading the authenticated user's own contact card.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Until the user saves a card, it is filled in from the token claims without being stored.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to find the card. Only users have a card.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the card.
+/// * `Err(ApiError)` if the caller is not a user or there is a database error.
+pub async fn read_my_card(principal: Principal) -> Result<HttpResponse, ApiError> {
+    let claims = principal.user()?;
+    let mut conn = establish_connection()?;
+
+    let card = load_card(&mut conn, claims)?;
+
+    Ok(HttpResponse::Ok().json(card))
+}
+
+/// Handles updating the authenticated user's own contact card.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to find the card. Only users have a card.
+/// * `update` - The new card data from the request body.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the updated card.
+/// * `Err(ApiError)` if the caller is not a user or there is a database error.
+pub async fn update_my_card(
+    principal: Principal,
+    update: web::Json<MyCardUpdate>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let claims = principal.user()?;
+    let mut conn = transaction.conn()?;
+
+    let update = update.into_inner();
+    let card = MyCard {
+        sub: claims.user_id().to_string(),
+        first_name: update.first_name,
+        last_name: update.last_name,
+        email: update.email,
+        phone_number: update.phone_number,
+    };
+
+    // The first update stores the card, later ones overwrite it
+    diesel::insert_into(my_cards::table)
+        .values(&card)
+        .on_conflict(my_cards::sub)
+        .do_update()
+        .set(&card)
+        .execute(&mut *conn)?;
+
+    Ok(HttpResponse::Ok().json(card))
+}
+
+/// Handles exporting the authenticated user's own contact card as a vCard.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to find the card. Only users have a card.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the card as a `.vcf` download.
+/// * `Err(ApiError)` if the caller is not a user or there is a database error.
+pub async fn export_my_card(principal: Principal) -> Result<HttpResponse, ApiError> {
+    let claims = principal.user()?;
+    let mut conn = establish_connection()?;
+
+    let card = load_card(&mut conn, claims)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(VCARD_CONTENT_TYPE)
+        .insert_header((
+            actix_web::http::header::CONTENT_DISPOSITION,
+            "attachment; filename=\"card.vcf\"",
+        ))
+        .body(to_vcard(
+            &card.first_name,
+            &card.last_name,
+            &card.email,
+            &card.phone_number,
+        )))
+}
@@ -0,0 +1,253 @@
+// backend/src/migrations.rs
+// This file runs the embedded database migrations, previews them, and refuses destructive ones unless allowed.
+// It exists so a new version cannot drop or rename data that a running old version still needs, without someone saying so.
+// RELEVANT FILES: backend/src/main.rs, backend/migrations, backend/contacts-types/src/schema.rs
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::error::ApiError;
+use crate::establish_connection;
+use actix_web::HttpResponse;
+use diesel::connection::{Connection, InstrumentationEvent};
+use diesel::result::Error::QueryBuilderError;
+use diesel::sqlite::SqliteConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+/// The migrations in `backend/migrations`, built into the binary.
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
+/// The table where diesel records applied migrations. Its statements are bookkeeping, not schema changes.
+const BOOKKEEPING_TABLE: &str = "__diesel_schema_migrations";
+
+/// Keywords of statements that remove or rename data an older version of the API may still use.
+const DESTRUCTIVE_PATTERNS: [&str; 4] = ["DROP TABLE", "DROP COLUMN", "DELETE FROM", "RENAME"];
+
+/// The boxed error that diesel's migration harness uses.
+type MigrationError = Box<dyn Error + Send + Sync + 'static>;
+
+/// One pending migration and the SQL it would run.
+pub struct PlannedMigration {
+    /// The name of the migration, e.g. `2026-10-15-220000-0000_create_pending_changes`.
+    pub name: String,
+    /// The statements the migration runs, in order.
+    pub statements: Vec<String>,
+}
+
+impl PlannedMigration {
+    /// Returns the statements that would drop, delete or rename data.
+    pub fn destructive_statements(&self) -> Vec<&str> {
+        self.statements
+            .iter()
+            .filter(|sql| {
+                let upper = sql.to_uppercase();
+                DESTRUCTIVE_PATTERNS.iter().any(|p| upper.contains(p))
+            })
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Runs pending database migrations.
+///
+/// # Arguments
+///
+/// * `conn` - A mutable reference to a type that implements `MigrationHarness`.
+///
+/// # Returns
+///
+/// * `Ok(())` if the migrations were successful.
+/// * `Err` with a boxed error if the migrations failed.
+pub fn run_migrations(
+    conn: &mut impl MigrationHarness<diesel::sqlite::Sqlite>,
+) -> Result<(), MigrationError> {
+    conn.run_pending_migrations(MIGRATIONS)?;
+    Ok(())
+}
+
+/// Finds the SQL of every pending migration without changing the database.
+///
+/// The migrations are really run, in a transaction on a separate connection that is never committed.
+/// SQLite can roll back schema changes, so this shows exactly what would run, backfills included.
+///
+/// # Returns
+///
+/// * `Ok(plans)` with one entry per pending migration, oldest first.
+/// * `Err` if a migration fails or the database cannot be reached.
+fn plan_migrations() -> Result<Vec<PlannedMigration>, MigrationError> {
+    let mut conn = establish_connection().map_err(|e| e.to_string())?;
+    let pending = conn.pending_migrations(MIGRATIONS)?;
+    if pending.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let statements = Arc::new(Mutex::new(Vec::new()));
+    let recorder = Arc::clone(&statements);
+    conn.begin_test_transaction()?;
+    conn.set_instrumentation(move |event: InstrumentationEvent<'_>| {
+        if let InstrumentationEvent::StartQuery { query, .. } = event {
+            let sql = query.to_string();
+            if !sql.contains(BOOKKEEPING_TABLE) {
+                recorder.lock().unwrap_or_else(|e| e.into_inner()).push(sql);
+            }
+        }
+    });
+
+    let mut plans = Vec::new();
+    for migration in pending {
+        conn.run_migration(&migration)?;
+        let mut statements = statements.lock().unwrap_or_else(|e| e.into_inner());
+        plans.push(PlannedMigration {
+            name: migration.name().to_string(),
+            // Savepoint statements come from the harness, not from the migration
+            statements: statements
+                .drain(..)
+                .filter(|sql| !sql.to_uppercase().contains("SAVEPOINT"))
+                .collect(),
+        });
+    }
+    // Dropping the connection rolls the test transaction back
+    Ok(plans)
+}
+
+/// Refuses to start when a pending migration is destructive, unless that is allowed.
+///
+/// Old instances keep serving during a rolling deploy, so dropping or renaming what they
+/// read breaks them. Set `ALLOW_DESTRUCTIVE_MIGRATIONS=true` once no old instance is left.
+///
+/// # Returns
+///
+/// * `Ok(())` if the pending migrations are safe, or destructive ones are allowed.
+/// * `Err` naming the destructive statements otherwise.
+pub fn check_pending_migrations() -> Result<(), MigrationError> {
+    let allowed = std::env::var("ALLOW_DESTRUCTIVE_MIGRATIONS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if allowed {
+        return Ok(());
+    }
+
+    let mut found = Vec::new();
+    for plan in plan_migrations()? {
+        for sql in plan.destructive_statements() {
+            found.push(format!("{}: {}", plan.name, sql.trim()));
+        }
+    }
+    if found.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Pending migrations are destructive. Set ALLOW_DESTRUCTIVE_MIGRATIONS=true to run them:\n{}",
+            found.join("\n")
+        )
+        .into())
+    }
+}
+
+/// Prints the SQL of the pending migrations, for `contacts-api migrate --dry-run`.
+///
+/// # Returns
+///
+/// * `Ok(())` when done.
+/// * `Err` if the migrations cannot be planned.
+fn print_dry_run() -> Result<(), MigrationError> {
+    let plans = plan_migrations()?;
+    if plans.is_empty() {
+        println!("-- No pending migrations.");
+    }
+    for plan in plans {
+        println!("-- Migration {}", plan.name);
+        if !plan.destructive_statements().is_empty() {
+            println!("-- WARNING: this migration is destructive");
+        }
+        for sql in plan.statements {
+            println!("{};", sql.trim().trim_end_matches(';'));
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// Handles `contacts-api migrate`, which runs the pending migrations and exits.
+///
+/// # Arguments
+///
+/// * `dry_run` - With `--dry-run`, only print the SQL that would run.
+///
+/// # Returns
+///
+/// * `Ok(())` when done.
+/// * `Err` if a migration is destructive and not allowed, or fails.
+pub fn migrate_command(dry_run: bool) -> Result<(), MigrationError> {
+    if dry_run {
+        return print_dry_run();
+    }
+    check_pending_migrations()?;
+    run_migrations(&mut establish_connection().map_err(|e| e.to_string())?)?;
+    println!("-- Migrations are up to date.");
+    Ok(())
+}
+
+/// Lists the migrations this binary has but the database does not.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection.
+///
+/// # Returns
+///
+/// * `Ok(names)` with the names of the pending migrations, oldest first.
+/// * `Err` if the applied migrations cannot be read.
+pub fn pending_migrations(conn: &mut SqliteConnection) -> Result<Vec<String>, MigrationError> {
+    Ok(conn
+        .pending_migrations(MIGRATIONS)?
+        .iter()
+        .map(|m| m.name().to_string())
+        .collect())
+}
+
+/// The migration state of the database.
+#[derive(Serialize)]
+pub struct SchemaVersion {
+    /// The version of the newest applied migration, e.g. `202610152200000000`.
+    pub current: Option<String>,
+    /// The versions of all applied migrations, oldest first.
+    pub applied: Vec<String>,
+    /// The names of migrations this binary has but the database does not, oldest first.
+    pub pending: Vec<String>,
+}
+
+/// Handles reading the applied and pending migrations.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the schema version.
+/// * `Err(ApiError)` if the user is not an admin or the migrations cannot be read.
+pub async fn read_schema_version(principal: Principal) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let mut conn = establish_connection()?;
+
+    // The harness has its own error type, so it is wrapped like other database errors
+    let migration_error = |e: MigrationError| ApiError::DatabaseError(QueryBuilderError(e));
+    let mut applied: Vec<String> = conn
+        .applied_migrations()
+        .map_err(migration_error)?
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    applied.sort();
+    let pending = pending_migrations(&mut conn).map_err(migration_error)?;
+
+    Ok(HttpResponse::Ok().json(SchemaVersion {
+        current: applied.last().cloned(),
+        applied,
+        pending,
+    }))
+}
@@ -1,44 +1,240 @@
 // backend/src/models.rs
 // This file defines the data structures for the contacts in the database.
 // It includes structs for both reading existing contacts and creating new ones.
-// RELEVANT FILES: backend/src/handlers.rs, backend/src/schema.rs
+// RELEVANT FILES: backend/src/handlers.rs, backend/contacts-types/src/schema.rs
 
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
-/// Represents a contact retrieved from the database.
+// The types the API sends and takes are shared with its clients
+pub use contacts_types::{
+    Company, Contact, ContactFilter, NewCompany, NewContact, CONTACT_STATUSES,
+};
+
+/// Represents a change to a contact's consent.
 ///
-/// This struct is used for serialization and deserialization of contact data
-/// when reading from the database.
-#[derive(Deserialize, Serialize, Queryable)]
-#[diesel(table_name = crate::schema::contacts)]
-pub struct Contact {
-    /// The unique identifier for the contact.
-    pub id: i32,
-    /// The first name of the contact.
+/// The channels set to `true` are opted in or out, depending on the endpoint.
+#[derive(Deserialize)]
+pub struct ConsentChange {
+    /// Whether the change applies to email.
+    #[serde(default)]
+    pub email: bool,
+    /// Whether the change applies to text messages.
+    #[serde(default)]
+    pub sms: bool,
+    /// Where the change came from (e.g. "signup form" or "phone call").
+    pub source: Option<String>,
+}
+
+/// Represents a new public share link to be inserted into the database.
+///
+/// The link token itself is not stored. It is signed from the link ID and
+/// expiry time, so deleting the row is enough to revoke the link.
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::share_links)]
+pub struct NewShareLink {
+    /// The ID of the contact to share.
+    pub contact_id: i32,
+    /// When the link expires, as a Unix timestamp in seconds.
+    pub expires_at: i64,
+    /// The workspace the link was made in, whose `share_links` flag decides whether it works.
+    pub workspace: String,
+}
+
+/// Represents the authenticated user's own contact card.
+///
+/// It is stored apart from the shared address book, one card per user.
+#[derive(Deserialize, Serialize, Queryable, Insertable, AsChangeset)]
+#[diesel(table_name = crate::schema::my_cards)]
+pub struct MyCard {
+    /// The user this card belongs to (the `sub` claim of the token).
+    pub sub: String,
+    /// The first name of the user.
     pub first_name: String,
-    /// The last name of the contact.
+    /// The last name of the user.
     pub last_name: String,
-    /// The email address of the contact.
+    /// The email address of the user.
     pub email: String,
-    /// The phone number of the contact.
+    /// The phone number of the user.
     pub phone_number: String,
 }
 
-/// Represents a new contact to be inserted into the database.
+/// Represents the editable fields of the user's own contact card.
+#[derive(Deserialize)]
+pub struct MyCardUpdate {
+    /// The first name of the user.
+    pub first_name: String,
+    /// The last name of the user.
+    pub last_name: String,
+    /// The email address of the user.
+    pub email: String,
+    /// The phone number of the user.
+    pub phone_number: String,
+}
+
+/// Represents a relationship between two contacts.
 ///
-/// This struct is used for deserializing new contact data from requests
-/// and for inserting new records into the database. It is also used for updating
-/// existing contacts.
+/// The relationship reads as "contact A is the `kind` of contact B",
+/// e.g. "A is the manager of B".
+#[derive(Deserialize, Serialize, Queryable)]
+#[diesel(table_name = crate::schema::contact_relationships)]
+pub struct Relationship {
+    /// The unique identifier for the relationship.
+    pub id: i32,
+    /// The ID of the first contact.
+    pub contact_a: i32,
+    /// The ID of the second contact.
+    pub contact_b: i32,
+    /// The kind of relationship: spouse, colleague, manager or assistant.
+    pub kind: String,
+}
+
+/// Represents a new relationship to be inserted into the database.
+///
+/// It is also used for updating existing relationships.
 #[derive(Deserialize, Insertable, AsChangeset)]
-#[diesel(table_name = crate::schema::contacts)]
-pub struct NewContact {
-    /// The first name of the new contact.
+#[diesel(table_name = crate::schema::contact_relationships)]
+pub struct NewRelationship {
+    /// The ID of the first contact.
+    pub contact_a: i32,
+    /// The ID of the second contact.
+    pub contact_b: i32,
+    /// The kind of relationship: spouse, colleague, manager or assistant.
+    pub kind: String,
+}
+
+/// Represents an enrichment result stored for a contact.
+///
+/// The provider, domain and fetch time record where the data came from.
+#[derive(Deserialize, Serialize, Queryable)]
+#[diesel(table_name = crate::schema::enrichments)]
+pub struct Enrichment {
+    /// The unique identifier for the enrichment result.
+    pub id: i32,
+    /// The ID of the enriched contact.
+    pub contact_id: i32,
+    /// The name of the provider that returned the data.
+    pub provider: String,
+    /// The email domain that was looked up.
+    pub domain: String,
+    /// The company name found for the domain.
+    pub company_name: Option<String>,
+    /// The URL of the company logo found for the domain.
+    pub logo_url: Option<String>,
+    /// When the data was fetched, as a Unix timestamp in seconds.
+    pub fetched_at: i64,
+}
+
+/// Represents a new enrichment result to be inserted into the database.
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::enrichments)]
+pub struct NewEnrichment {
+    /// The ID of the enriched contact.
+    pub contact_id: i32,
+    /// The name of the provider that returned the data.
+    pub provider: String,
+    /// The email domain that was looked up.
+    pub domain: String,
+    /// The company name found for the domain.
+    pub company_name: Option<String>,
+    /// The URL of the company logo found for the domain.
+    pub logo_url: Option<String>,
+    /// When the data was fetched, as a Unix timestamp in seconds.
+    pub fetched_at: i64,
+}
+
+/// Represents details that someone submitted about themselves through the public form.
+///
+/// The request waits in a queue until an admin approves it into the address book or rejects it.
+#[derive(Serialize, Queryable)]
+#[diesel(table_name = crate::schema::contact_requests)]
+pub struct ContactRequest {
+    /// The unique identifier for the request.
+    pub id: i32,
+    /// The first name of the person.
+    pub first_name: String,
+    /// The last name of the person.
+    pub last_name: String,
+    /// The email address of the person.
+    pub email: String,
+    /// The phone number of the person, or an empty string.
+    pub phone_number: String,
+    /// A free-text message from the person.
+    pub message: Option<String>,
+    /// The state of the request: pending, approved or rejected.
+    pub status: String,
+    /// When the request was submitted, as a Unix timestamp in seconds.
+    pub created_at: i64,
+    /// When an admin decided on the request, as a Unix timestamp in seconds.
+    pub decided_at: Option<i64>,
+    /// The admin who decided on the request.
+    pub decided_by: Option<String>,
+    /// The ID of the contact created on approval.
+    pub contact_id: Option<i32>,
+}
+
+/// Represents a submission from the public form, to be inserted into the queue.
+#[derive(Deserialize, Insertable)]
+#[diesel(table_name = crate::schema::contact_requests)]
+pub struct NewContactRequest {
+    /// The first name of the person.
     pub first_name: String,
-    /// The last name of the new contact.
+    /// The last name of the person.
     pub last_name: String,
-    /// The email address of the new contact.
+    /// The email address of the person.
     pub email: String,
-    /// The phone number of the new contact.
+    /// The phone number of the person.
+    #[serde(default)]
     pub phone_number: String,
+    /// A free-text message from the person.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// When the request was submitted. Set by the server.
+    #[serde(skip)]
+    pub created_at: i64,
+}
+
+/// Represents a contact edit that waits for a reviewer.
+///
+/// The row also records who asked for the change and who decided on it, and when.
+#[derive(Serialize, Queryable)]
+#[diesel(table_name = crate::schema::pending_changes)]
+pub struct PendingChange {
+    /// The unique identifier for the pending change.
+    pub id: i32,
+    /// The ID of the contact to change.
+    pub contact_id: i32,
+    /// The proposed contact data, stored as JSON and returned as an object.
+    #[serde(serialize_with = "serialize_json_text")]
+    pub changes: String,
+    /// The state of the change: pending, approved or rejected.
+    pub status: String,
+    /// The user who made the edit.
+    pub requested_by: String,
+    /// When the edit was made, as a Unix timestamp in seconds.
+    pub requested_at: i64,
+    /// The reviewer who decided on the change.
+    pub decided_by: Option<String>,
+    /// When the change was decided on, as a Unix timestamp in seconds.
+    pub decided_at: Option<i64>,
+}
+
+/// Represents a new pending change to be inserted into the database.
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::pending_changes)]
+pub struct NewPendingChange {
+    /// The ID of the contact to change.
+    pub contact_id: i32,
+    /// The proposed contact data, as JSON.
+    pub changes: String,
+    /// The user who made the edit.
+    pub requested_by: String,
+    /// When the edit was made, as a Unix timestamp in seconds.
+    pub requested_at: i64,
+}
+
+/// Writes a column that holds JSON text as a JSON value instead of a string.
+fn serialize_json_text<S: serde::Serializer>(text: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(serde::ser::Error::custom)?;
+    value.serialize(serializer)
 }
@@ -0,0 +1,319 @@
+// backend/src/msgpack.rs
+// This file writes JSON response bodies as MessagePack, a binary form of the same data.
+// It exists so the mobile client spends fewer bytes and less CPU on large lists and syncs.
+// RELEVANT FILES: backend/src/formats.rs, backend/src/xml.rs
+
+use serde_json::Value;
+
+/// Writes a length with the smallest of the three MessagePack length prefixes of a type.
+///
+/// # Arguments
+///
+/// * `out` - The buffer to write to.
+/// * `len` - The length of the string, array or map.
+/// * `fix` - The first byte of the short form, where the length is part of the byte.
+/// * `fix_max` - The longest length the short form can hold.
+/// * `markers` - The markers of the 8-bit (if the type has one), 16-bit and 32-bit forms.
+fn write_len(out: &mut Vec<u8>, len: usize, fix: u8, fix_max: usize, markers: [Option<u8>; 3]) {
+    match (len, markers) {
+        (len, _) if len <= fix_max => out.push(fix | len as u8),
+        (len, [Some(marker), _, _]) if len <= u8::MAX as usize => {
+            out.push(marker);
+            out.push(len as u8);
+        }
+        (len, [_, Some(marker), _]) if len <= u16::MAX as usize => {
+            out.push(marker);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        (len, [_, _, Some(marker)]) => {
+            out.push(marker);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+        _ => unreachable!("every length type has a 32-bit form"),
+    }
+}
+
+/// Writes an integer in the smallest form that holds it.
+fn write_int(out: &mut Vec<u8>, n: i64) {
+    match n {
+        0..=127 => out.push(n as u8),
+        -32..=-1 => out.push(n as i8 as u8),
+        128..=0xff => {
+            out.push(0xcc);
+            out.push(n as u8);
+        }
+        0x100..=0xffff => {
+            out.push(0xcd);
+            out.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(0xce);
+            out.extend_from_slice(&(n as u32).to_be_bytes());
+        }
+        -0x80..=-33 => {
+            out.push(0xd0);
+            out.push(n as i8 as u8);
+        }
+        -0x8000..=-0x81 => {
+            out.push(0xd1);
+            out.extend_from_slice(&(n as i16).to_be_bytes());
+        }
+        -0x8000_0000..=-0x8001 => {
+            out.push(0xd2);
+            out.extend_from_slice(&(n as i32).to_be_bytes());
+        }
+        _ => {
+            out.push(0xd3);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+    }
+}
+
+/// Writes one value and everything in it.
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Null => out.push(0xc0),
+        Value::Bool(false) => out.push(0xc2),
+        Value::Bool(true) => out.push(0xc3),
+        Value::Number(number) => {
+            if let Some(n) = number.as_i64() {
+                write_int(out, n);
+            } else if let Some(n) = number.as_u64() {
+                // Only values above i64::MAX get here
+                out.push(0xcf);
+                out.extend_from_slice(&n.to_be_bytes());
+            } else {
+                out.push(0xcb);
+                out.extend_from_slice(&number.as_f64().unwrap_or_default().to_be_bytes());
+            }
+        }
+        Value::String(text) => {
+            write_len(
+                out,
+                text.len(),
+                0xa0,
+                31,
+                [Some(0xd9), Some(0xda), Some(0xdb)],
+            );
+            out.extend_from_slice(text.as_bytes());
+        }
+        Value::Array(items) => {
+            write_len(out, items.len(), 0x90, 15, [None, Some(0xdc), Some(0xdd)]);
+            for item in items {
+                write_value(out, item);
+            }
+        }
+        Value::Object(object) => {
+            write_len(out, object.len(), 0x80, 15, [None, Some(0xde), Some(0xdf)]);
+            for (key, item) in object {
+                write_len(
+                    out,
+                    key.len(),
+                    0xa0,
+                    31,
+                    [Some(0xd9), Some(0xda), Some(0xdb)],
+                );
+                out.extend_from_slice(key.as_bytes());
+                write_value(out, item);
+            }
+        }
+    }
+}
+
+/// Writes a response body as MessagePack.
+///
+/// # Arguments
+///
+/// * `value` - The JSON body.
+///
+/// # Returns
+///
+/// * The MessagePack bytes, with the same structure as the JSON.
+pub fn to_msgpack(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_value(&mut out, value);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_msgpack;
+    use crate::formats::Format;
+    use serde_json::{json, Map, Value};
+
+    /// Reads MessagePack back into JSON, the way a client library would.
+    struct Reader<'a> {
+        bytes: &'a [u8],
+    }
+
+    impl Reader<'_> {
+        fn take(&mut self, n: usize) -> &[u8] {
+            let (head, rest) = self.bytes.split_at(n);
+            self.bytes = rest;
+            head
+        }
+
+        fn uint(&mut self, n: usize) -> u64 {
+            self.take(n).iter().fold(0, |acc, b| (acc << 8) | *b as u64)
+        }
+
+        fn string(&mut self, len: usize) -> String {
+            String::from_utf8(self.take(len).to_vec()).unwrap()
+        }
+
+        fn array(&mut self, len: usize) -> Value {
+            Value::Array((0..len).map(|_| self.value()).collect())
+        }
+
+        fn map(&mut self, len: usize) -> Value {
+            let mut object = Map::new();
+            for _ in 0..len {
+                let Value::String(key) = self.value() else {
+                    panic!("map keys are strings")
+                };
+                object.insert(key, self.value());
+            }
+            Value::Object(object)
+        }
+
+        fn value(&mut self) -> Value {
+            let marker = self.take(1)[0];
+            match marker {
+                0x00..=0x7f => json!(marker),
+                0x80..=0x8f => self.map((marker & 0x0f) as usize),
+                0x90..=0x9f => self.array((marker & 0x0f) as usize),
+                0xa0..=0xbf => Value::String(self.string((marker & 0x1f) as usize)),
+                0xc0 => Value::Null,
+                0xc2 => json!(false),
+                0xc3 => json!(true),
+                0xcb => json!(f64::from_bits(self.uint(8))),
+                0xcc => json!(self.uint(1)),
+                0xcd => json!(self.uint(2)),
+                0xce => json!(self.uint(4)),
+                0xcf => json!(self.uint(8)),
+                0xd0 => json!(self.uint(1) as u8 as i8),
+                0xd1 => json!(self.uint(2) as u16 as i16),
+                0xd2 => json!(self.uint(4) as u32 as i32),
+                0xd3 => json!(self.uint(8) as i64),
+                0xd9 => {
+                    let len = self.uint(1) as usize;
+                    Value::String(self.string(len))
+                }
+                0xda => {
+                    let len = self.uint(2) as usize;
+                    Value::String(self.string(len))
+                }
+                0xdb => {
+                    let len = self.uint(4) as usize;
+                    Value::String(self.string(len))
+                }
+                0xdc => {
+                    let len = self.uint(2) as usize;
+                    self.array(len)
+                }
+                0xdd => {
+                    let len = self.uint(4) as usize;
+                    self.array(len)
+                }
+                0xde => {
+                    let len = self.uint(2) as usize;
+                    self.map(len)
+                }
+                0xdf => {
+                    let len = self.uint(4) as usize;
+                    self.map(len)
+                }
+                0xe0..=0xff => json!(marker as i8),
+                _ => panic!("unexpected marker {:#x}", marker),
+            }
+        }
+    }
+
+    fn round_trip(value: &Value) -> Value {
+        let bytes = to_msgpack(value);
+        let mut reader = Reader { bytes: &bytes };
+        let decoded = reader.value();
+        assert!(reader.bytes.is_empty(), "trailing bytes after the value");
+        decoded
+    }
+
+    #[test]
+    fn contact_list_round_trips() {
+        let contacts = json!([{
+            "id": 1,
+            "first_name": "Åsa",
+            "last_name": "Öberg",
+            "email": "asa@example.com",
+            "company_id": null,
+            "email_opt_in": true,
+            "sms_opt_in": false,
+            "consent_timestamp": 1792042110,
+            "_links": {"self": {"href": "http://localhost:8081/api/v1/contacts/1"}}
+        }]);
+        assert_eq!(round_trip(&contacts), contacts);
+    }
+
+    #[test]
+    fn numbers_round_trip_at_every_width() {
+        let numbers = json!([
+            0,
+            127,
+            128,
+            255,
+            256,
+            65535,
+            65536,
+            4294967295u64,
+            4294967296u64,
+            u64::MAX,
+            -1,
+            -32,
+            -33,
+            -128,
+            -129,
+            -32768,
+            -32769,
+            -2147483648i64,
+            -2147483649i64,
+            i64::MIN,
+            0.5,
+            -1.25,
+            1e300
+        ]);
+        assert_eq!(round_trip(&numbers), numbers);
+    }
+
+    #[test]
+    fn long_strings_lists_and_maps_round_trip() {
+        let object: Map<String, Value> = (0..300).map(|i| (format!("k{}", i), json!(i))).collect();
+        let value = json!({
+            "short": "x".repeat(31),
+            "str8": "x".repeat(32),
+            "str16": "é".repeat(200),
+            "str32": "x".repeat(70000),
+            "list": (0..20).collect::<Vec<i32>>(),
+            "map": object,
+        });
+        assert_eq!(round_trip(&value), value);
+    }
+
+    #[test]
+    fn msgpack_is_smaller_than_json() {
+        let value = json!({"id": 12345, "email_opt_in": false, "status": "customer"});
+        assert!(to_msgpack(&value).len() < serde_json::to_vec(&value).unwrap().len());
+    }
+
+    #[test]
+    fn msgpack_is_negotiated_from_accept() {
+        assert_eq!(Format::from_accept("application/msgpack"), Format::MsgPack);
+        assert_eq!(
+            Format::from_accept("application/x-msgpack, application/json"),
+            Format::MsgPack
+        );
+        assert_eq!(
+            Format::from_accept("application/json, application/msgpack"),
+            Format::Json
+        );
+        assert_eq!(Format::from_accept("text/html"), Format::Json);
+    }
+}
@@ -0,0 +1,149 @@
+// backend/src/ndjson.rs
+// This file streams all contacts as newline-delimited JSON, one contact per line.
+// It exists so data pipelines can pull the whole address book without paging, and without the server holding it in memory.
+// RELEVANT FILES: backend/src/handlers.rs, backend/src/fields.rs, backend/src/main.rs
+
+use crate::demo::{demo_contacts, DemoMode};
+use crate::error::ApiError;
+use crate::establish_connection;
+use crate::field_permissions::FieldRules;
+use crate::fields::{select_fields, FieldsQuery};
+use crate::handlers::filtered_contacts;
+use crate::models::{Contact, ContactFilter};
+use crate::schema::contacts;
+use actix_web::web::Bytes;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use futures_util::stream;
+
+/// The content type of newline-delimited JSON.
+const NDJSON: &str = "application/x-ndjson";
+
+/// How many contacts are read from the database at a time.
+const BATCH_SIZE: i64 = 500;
+
+/// Where the stream is in the contact table.
+///
+/// Contacts are read in ID order, and each batch starts after the last ID sent. Nothing is
+/// read until the client has taken the previous batch, so a slow client slows the reads down
+/// instead of filling the server's memory.
+struct Cursor {
+    /// The connection the batches are read with.
+    conn: SqliteConnection,
+    /// The filters from the query string.
+    filter: ContactFilter,
+    /// The fields the user may not read.
+    field_rules: FieldRules,
+    /// The keys to keep for each contact, or `None` for all of them.
+    fields: Option<Vec<String>>,
+    /// The ID of the last contact sent.
+    after_id: i32,
+}
+
+/// Turns contacts into NDJSON lines, masked and cut down to the requested fields.
+fn to_lines(
+    contacts: Vec<Contact>,
+    field_rules: &FieldRules,
+    fields: &Option<Vec<String>>,
+) -> Bytes {
+    let mut lines = Vec::new();
+    for contact in contacts {
+        let contact = field_rules.masked(contact);
+        // Contacts only hold plain data, so serializing them cannot fail
+        let written = match fields {
+            Some(keys) => serde_json::to_writer(&mut lines, &select_fields(&contact, keys)),
+            None => serde_json::to_writer(&mut lines, &contact),
+        };
+        written.expect("contacts serialize to JSON");
+        lines.push(b'\n');
+    }
+    Bytes::from(lines)
+}
+
+impl Cursor {
+    /// Reads the next batch of contacts.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(bytes))` with the lines of the batch.
+    /// * `Ok(None)` when every contact was sent.
+    /// * `Err(ApiError)` if there is a database error.
+    fn next_batch(&mut self) -> Result<Option<Bytes>, ApiError> {
+        let batch = filtered_contacts(&self.filter)
+            .filter(contacts::id.gt(self.after_id))
+            .order(contacts::id.asc())
+            .limit(BATCH_SIZE)
+            .load::<Contact>(&mut self.conn)?;
+        let Some(last) = batch.last() else {
+            return Ok(None);
+        };
+        self.after_id = last.id;
+        Ok(Some(to_lines(batch, &self.field_rules, &self.fields)))
+    }
+}
+
+/// Handles streaming all contacts as newline-delimited JSON, in ID order.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// It takes the same filters as the contact list, and `?fields=` to keep only some keys.
+/// Contacts changed while the stream runs may or may not be included, as with paging.
+/// If the database fails halfway, the stream ends early, so clients should check that it ends with a newline.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `filter` - The optional filters from the query string.
+/// * `fields` - The keys to return for each contact, e.g. `?fields=id,email`.
+/// * `demo` - The demo mode setting. In demo mode, synthetic contacts are returned.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with an `application/x-ndjson` body, one contact per line.
+/// * `Err(ApiError::Forbidden)` if a filter is on a field the user may not read.
+/// * `Err(ApiError)` if a field is not supported or the database cannot be reached.
+pub async fn stream_contacts(
+    field_rules: FieldRules,
+    filter: web::Query<ContactFilter>,
+    fields: web::Query<FieldsQuery>,
+    demo: web::Data<DemoMode>,
+) -> Result<HttpResponse, ApiError> {
+    field_rules.check_filter(&filter)?;
+    let fields = fields.parse()?;
+    let filter = filter.into_inner();
+
+    // Demo contacts are few and in memory, so they are sent at once
+    if demo.enabled {
+        let contacts = demo_contacts()
+            .into_iter()
+            .filter(|c| filter.matches(c))
+            .collect();
+        return Ok(HttpResponse::Ok().content_type(NDJSON).body(to_lines(
+            contacts,
+            &field_rules,
+            &fields,
+        )));
+    }
+
+    let cursor = Cursor {
+        conn: establish_connection()?,
+        filter,
+        field_rules,
+        fields,
+        after_id: 0,
+    };
+    // Actix asks for the next batch only when the client has taken the last one
+    let batches = stream::unfold(Some(cursor), |cursor| async move {
+        let mut cursor = cursor?;
+        match cursor.next_batch() {
+            Ok(Some(lines)) => Some((Ok(lines), Some(cursor))),
+            Ok(None) => None,
+            Err(e) => {
+                log::error!("Contact stream stopped: {}", e);
+                Some((Err(std::io::Error::other(e.to_string())), None))
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok().content_type(NDJSON).streaming(batches))
+}
@@ -0,0 +1,319 @@
+// backend/src/normalization.rs
+// This file cleans up incoming contact and company data: names, emails, phone numbers and company names are written one way.
+// It exists so data typed in forms, synced from other systems or approved from requests looks the same, and can be searched and deduplicated.
+// RELEVANT FILES: backend/src/feature_flags.rs, backend/src/handlers.rs, backend/src/companies.rs
+
+use crate::feature_flags::FeatureFlags;
+use crate::models::{NewCompany, NewContact};
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+/// The rules, as the feature flag that switches them and the fields they clean up, in the order they run.
+const RULES: [(&str, &[&str]); 4] = [
+    ("normalize_names", &["first_name", "last_name"]),
+    ("normalize_emails", &["email"]),
+    ("normalize_phones", &["phone_number"]),
+    ("normalize_companies", &["company_name"]),
+];
+
+/// The data to preview. Every field is optional, so a half-filled form can be previewed.
+#[derive(Deserialize, Serialize)]
+pub struct NormalizationInput {
+    /// The first name of a contact.
+    #[serde(default)]
+    pub first_name: String,
+    /// The last name of a contact.
+    #[serde(default)]
+    pub last_name: String,
+    /// The email address of a contact.
+    #[serde(default)]
+    pub email: String,
+    /// The phone number of a contact.
+    #[serde(default)]
+    pub phone_number: String,
+    /// The name of a company.
+    #[serde(default)]
+    pub company_name: String,
+}
+
+/// One field a rule would change.
+#[derive(Serialize)]
+pub struct FieldChange {
+    /// The field, e.g. `email`.
+    pub field: &'static str,
+    /// The rule that changes it, e.g. `normalize_emails`.
+    pub rule: &'static str,
+    /// The value as sent.
+    pub from: String,
+    /// The value as it would be stored.
+    pub to: String,
+}
+
+/// Whether a rule is on in the caller's workspace.
+#[derive(Serialize)]
+pub struct RuleState {
+    /// The rule, which is also the name of its feature flag.
+    pub rule: &'static str,
+    /// Whether the rule is on.
+    pub enabled: bool,
+}
+
+/// The outcome of a preview.
+#[derive(Serialize)]
+pub struct NormalizationPreview {
+    /// The data as it would be stored.
+    pub normalized: NormalizationInput,
+    /// The fields that would change. Empty if the data is already clean.
+    pub changes: Vec<FieldChange>,
+    /// The rules and whether they are on in the caller's workspace.
+    pub rules: Vec<RuleState>,
+}
+
+/// Joins the words of a text with single spaces, dropping the spaces around it.
+fn collapse_spaces(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Capitalizes every word, and every part of a word after a hyphen or apostrophe (`anne-marie o'neil` as `Anne-Marie O'Neil`).
+fn title_case(text: &str) -> String {
+    let mut cased = String::with_capacity(text.len());
+    let mut start_of_word = true;
+    for c in text.chars() {
+        if start_of_word {
+            cased.extend(c.to_uppercase());
+        } else {
+            cased.extend(c.to_lowercase());
+        }
+        start_of_word = c.is_whitespace() || c == '-' || c == '\'';
+    }
+    cased
+}
+
+/// Cleans up a person's name.
+///
+/// Spaces are collapsed. Names typed in one case, like `ADA` or `ada lovelace`, are title-cased.
+/// Names with mixed case, like `McDonald` or `van Dyke`, are kept as typed.
+fn normalize_name(name: &str) -> String {
+    let name = collapse_spaces(name);
+    let has_upper = name.chars().any(char::is_uppercase);
+    let has_lower = name.chars().any(char::is_lowercase);
+    if has_upper && has_lower {
+        name
+    } else {
+        title_case(&name)
+    }
+}
+
+/// Cleans up an email address: trimmed and in lowercase.
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// The ways an extension is written after a number, longest first: `555-1234 x12`, `ext. 12`, `;ext=12`.
+const EXTENSION_MARKERS: [&str; 6] = [";ext=", "extension", "ext.", "ext", "x", "#"];
+
+/// Splits a phone number from its extension, if one follows its digits.
+fn split_extension(phone: &str) -> (&str, Option<&str>) {
+    // ASCII lowercase keeps every byte where it is, so the positions hold for `phone`
+    let lower = phone.to_ascii_lowercase();
+    let Some(first_digit) = lower.find(|c: char| c.is_ascii_digit()) else {
+        return (phone, None);
+    };
+    let marker = EXTENSION_MARKERS
+        .iter()
+        .filter_map(|marker| {
+            lower[first_digit..]
+                .find(marker)
+                .map(|at| (first_digit + at, marker.len()))
+        })
+        .min_by_key(|(at, _)| *at);
+    match marker {
+        Some((at, len)) => (&phone[..at], Some(&phone[at + len..])),
+        None => (phone, None),
+    }
+}
+
+/// Cleans up a phone number: only the digits are kept, and a leading `+` (`+44 (20) 7946-0000` as `+442079460000`).
+/// The trunk prefix `(0)` of an international number is dropped, and an extension is kept as `;ext=`
+/// (`555-1234 x12` as `5551234;ext=12`). Text without digits is only trimmed.
+fn normalize_phone(phone: &str) -> String {
+    let phone = phone.trim();
+    let (number, extension) = split_extension(phone);
+    let international = number.starts_with('+');
+    // `+44 (0)20` shows the 0 that is only dialled from within the country
+    let number = if international {
+        number.replacen("(0)", "", 1)
+    } else {
+        number.to_string()
+    };
+    let digits: String = number.chars().filter(char::is_ascii_digit).collect();
+    if digits.is_empty() {
+        // Not a number at all, so nothing is thrown away
+        return phone.to_string();
+    }
+    let mut normalized = if international {
+        format!("+{}", digits)
+    } else {
+        digits
+    };
+    let extension: String = extension
+        .unwrap_or_default()
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+    if !extension.is_empty() {
+        normalized.push_str(";ext=");
+        normalized.push_str(&extension);
+    }
+    normalized
+}
+
+/// Cleans up a company name.
+///
+/// Spaces are collapsed. Names typed in lowercase are title-cased. Names with capitals are
+/// kept, so acronyms like `IBM` stay as they are.
+fn normalize_company(name: &str) -> String {
+    let name = collapse_spaces(name);
+    if name.chars().any(char::is_uppercase) {
+        name
+    } else {
+        title_case(&name)
+    }
+}
+
+/// Runs the rule for a field.
+fn normalize_field(field: &str, value: &str) -> String {
+    match field {
+        "first_name" | "last_name" => normalize_name(value),
+        "email" => normalize_email(value),
+        "phone_number" => normalize_phone(value),
+        "company_name" => normalize_company(value),
+        _ => value.to_string(),
+    }
+}
+
+/// Runs the rules that are on for every field, in order.
+///
+/// # Arguments
+///
+/// * `flags` - The feature flags of the caller's workspace, which switch the rules.
+/// * `fields` - The fields with their values, changed in place.
+///
+/// # Returns
+///
+/// * The fields that changed, with the rule that changed them.
+fn run_rules(flags: &FeatureFlags, fields: &mut [(&'static str, &mut String)]) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    for (rule, rule_fields) in RULES {
+        if !flags.enabled(rule) {
+            continue;
+        }
+        for (field, value) in fields.iter_mut() {
+            if !rule_fields.contains(field) {
+                continue;
+            }
+            let normalized = normalize_field(field, value);
+            if normalized != **value {
+                changes.push(FieldChange {
+                    field,
+                    rule,
+                    from: std::mem::replace(&mut **value, normalized.clone()),
+                    to: normalized,
+                });
+            }
+        }
+    }
+    changes
+}
+
+/// Cleans up contact data before it is stored, with the rules that are on in the caller's workspace.
+///
+/// # Arguments
+///
+/// * `flags` - The feature flags of the caller's workspace.
+/// * `contact` - The contact data, changed in place.
+pub fn normalize_contact(flags: &FeatureFlags, contact: &mut NewContact) {
+    run_rules(
+        flags,
+        &mut [
+            ("first_name", &mut contact.first_name),
+            ("last_name", &mut contact.last_name),
+            ("email", &mut contact.email),
+            ("phone_number", &mut contact.phone_number),
+        ],
+    );
+}
+
+/// Cleans up company data before it is stored, with the rules that are on in the caller's workspace.
+///
+/// # Arguments
+///
+/// * `flags` - The feature flags of the caller's workspace.
+/// * `company` - The company data, changed in place.
+pub fn normalize_company_data(flags: &FeatureFlags, company: &mut NewCompany) {
+    run_rules(flags, &mut [("company_name", &mut company.name)]);
+}
+
+/// Handles previewing how data would be cleaned up when it is saved, without saving it.
+///
+/// This endpoint is protected and requires a valid JWT. It uses the rules that are on in the
+/// caller's workspace; admins switch them with the feature flags of the same name.
+///
+/// # Arguments
+///
+/// * `flags` - The feature flags of the caller's workspace.
+/// * `input` - The contact and company fields to clean up.
+///
+/// # Returns
+///
+/// * `HttpResponse` with the cleaned-up data, what changed and which rules are on.
+pub async fn preview_normalization(
+    flags: FeatureFlags,
+    input: web::Json<NormalizationInput>,
+) -> HttpResponse {
+    let mut normalized = input.into_inner();
+    let changes = run_rules(
+        &flags,
+        &mut [
+            ("first_name", &mut normalized.first_name),
+            ("last_name", &mut normalized.last_name),
+            ("email", &mut normalized.email),
+            ("phone_number", &mut normalized.phone_number),
+            ("company_name", &mut normalized.company_name),
+        ],
+    );
+
+    let rules = RULES
+        .iter()
+        .map(|(rule, _)| RuleState {
+            rule,
+            enabled: flags.enabled(rule),
+        })
+        .collect();
+
+    HttpResponse::Ok().json(NormalizationPreview {
+        normalized,
+        changes,
+        rules,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_phone;
+
+    #[test]
+    fn phone_numbers_keep_their_extension() {
+        assert_eq!(normalize_phone("555-1234 x12"), "5551234;ext=12");
+        assert_eq!(normalize_phone("+1 555 1234 ext. 7"), "+15551234;ext=7");
+        // Normalizing twice changes nothing
+        assert_eq!(normalize_phone("5551234;ext=12"), "5551234;ext=12");
+    }
+
+    #[test]
+    fn the_trunk_prefix_of_international_numbers_is_dropped() {
+        assert_eq!(normalize_phone("+44 (0)20 7946 0000"), "+442079460000");
+        assert_eq!(normalize_phone("+44 (20) 7946-0000"), "+442079460000");
+        assert_eq!(normalize_phone("n/a"), "n/a");
+    }
+}
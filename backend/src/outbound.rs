@@ -0,0 +1,71 @@
+// backend/src/outbound.rs
+// This file builds the HTTP client for every call the server makes to other services, with the configured proxy and CAs.
+// It exists so the server can run inside a corporate network that routes traffic through a proxy and intercepts TLS.
+// RELEVANT FILES: backend/src/auth.rs, backend/src/enrichment.rs, backend/src/main.rs
+
+use reqwest::{Certificate, Client, ClientBuilder, NoProxy, Proxy};
+
+/// Reads a setting that is only used when it is not empty.
+fn setting(variable: &str) -> Option<String> {
+    std::env::var(variable)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+}
+
+/// Starts a client builder with the outbound settings from the environment.
+///
+/// * `OUTBOUND_PROXY` - The proxy for all outbound HTTP and HTTPS calls, e.g. `http://proxy.corp:3128`.
+/// * `OUTBOUND_NO_PROXY` - Comma-separated hosts, domains or IP ranges that are called directly.
+/// * `OUTBOUND_CA_CERTS` - The path of a PEM file with CA certificates to trust on top of the system ones,
+///   e.g. the CA of a proxy that intercepts TLS.
+///
+/// Without `OUTBOUND_PROXY`, the usual `HTTPS_PROXY` and `NO_PROXY` variables still apply.
+///
+/// # Returns
+///
+/// * A `ClientBuilder` that callers can add their own settings to, e.g. a timeout.
+///
+/// # Panics
+///
+/// If the proxy URL or the CA file is invalid. The clients are built at startup, so a wrong
+/// setting stops the server instead of failing every outbound call later.
+pub fn client_builder() -> ClientBuilder {
+    let mut builder = Client::builder();
+
+    if let Some(url) = setting("OUTBOUND_PROXY") {
+        let proxy = Proxy::all(&url)
+            .unwrap_or_else(|e| panic!("OUTBOUND_PROXY is not a valid proxy URL: {}", e))
+            .no_proxy(setting("OUTBOUND_NO_PROXY").and_then(|list| NoProxy::from_string(&list)));
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = setting("OUTBOUND_CA_CERTS") {
+        let pem = std::fs::read(&path)
+            .unwrap_or_else(|e| panic!("Could not read OUTBOUND_CA_CERTS file {}: {}", path, e));
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .unwrap_or_else(|e| panic!("OUTBOUND_CA_CERTS file {} is not valid PEM: {}", path, e));
+        if certificates.is_empty() {
+            panic!("OUTBOUND_CA_CERTS file {} holds no certificates", path);
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    builder
+}
+
+/// Builds a client with the outbound settings and nothing else.
+///
+/// # Returns
+///
+/// * The `Client`.
+///
+/// # Panics
+///
+/// If the settings are invalid, see `client_builder`.
+pub fn client() -> Client {
+    client_builder()
+        .build()
+        .unwrap_or_else(|e| panic!("Could not build the outbound HTTP client: {}", e))
+}
@@ -0,0 +1,325 @@
+// backend/src/pending_changes.rs
+// This file holds contact edits for review when approval is required, and lets reviewers approve or reject them.
+// It exists so teams can let everyone suggest corrections while only admins change the address book.
+// RELEVANT FILES: backend/src/handlers.rs, backend/src/models.rs, backend/src/auth.rs
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::error::ApiError;
+use crate::field_permissions::{FieldRules, MASK};
+use crate::handlers::reset_changed_email_status;
+use crate::models::{Contact, NewContact, NewPendingChange, PendingChange};
+use crate::schema::{contacts, pending_changes};
+use crate::transaction::Transaction;
+use crate::{establish_connection, unix_now};
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use serde::{Deserialize, Serialize};
+
+/// The states a pending change can be in.
+const CHANGE_STATUSES: [&str; 3] = ["pending", "approved", "rejected"];
+
+/// Holds the setting that decides if contact edits need approval.
+#[derive(Clone, Copy, Default)]
+pub struct ChangeApproval {
+    /// Whether edits by users without the `admin` role wait for a reviewer.
+    pub required: bool,
+}
+
+impl ChangeApproval {
+    /// Reads the setting from the `REQUIRE_CHANGE_APPROVAL` environment variable.
+    ///
+    /// # Returns
+    ///
+    /// * `ChangeApproval` that requires approval when the variable is `true` or `1`.
+    pub fn from_env() -> Self {
+        let required = std::env::var("REQUIRE_CHANGE_APPROVAL")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+        Self { required }
+    }
+
+    /// Returns whether an edit by this user must wait for a reviewer.
+    pub fn applies_to(&self, principal: &Principal) -> bool {
+        self.required && !principal.has_role(ADMIN_ROLE)
+    }
+}
+
+/// Stores a contact edit for review instead of applying it.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection.
+/// * `contact_id` - The ID of the contact to change.
+/// * `contact` - The proposed contact data.
+/// * `requested_by` - The user who made the edit.
+///
+/// # Returns
+///
+/// * `Ok(PendingChange)` with the stored change.
+/// * `Err(ApiError::NotFound)` if the contact does not exist, or a database error.
+pub fn propose_change(
+    conn: &mut SqliteConnection,
+    contact_id: i32,
+    contact: &NewContact,
+    requested_by: &str,
+) -> Result<PendingChange, ApiError> {
+    // Check first, so a missing contact is a 404 and not a foreign key error
+    contacts::table
+        .find(contact_id)
+        .select(contacts::id)
+        .first::<i32>(conn)?;
+
+    let change = NewPendingChange {
+        contact_id,
+        changes: serde_json::to_string(contact).map_err(|e| ApiError::BadRequest(e.to_string()))?,
+        requested_by: requested_by.to_string(),
+        requested_at: unix_now(),
+    };
+    Ok(diesel::insert_into(pending_changes::table)
+        .values(&change)
+        .get_result::<PendingChange>(conn)?)
+}
+
+/// The optional filter for listing pending changes.
+#[derive(Deserialize)]
+pub struct PendingChangeFilter {
+    /// Only return changes in this state: pending, approved or rejected.
+    pub status: Option<String>,
+}
+
+/// One field that a pending change would change.
+#[derive(Serialize)]
+pub struct FieldDiff {
+    /// The name of the field.
+    pub field: String,
+    /// The value the contact has now.
+    pub current: serde_json::Value,
+    /// The value the change would set.
+    pub proposed: serde_json::Value,
+}
+
+/// A pending change together with what it would change.
+#[derive(Serialize)]
+pub struct PendingChangeWithDiff {
+    /// The pending change itself.
+    #[serde(flatten)]
+    pub change: PendingChange,
+    /// The fields whose values differ from the contact as it is now, by field name.
+    pub diff: Vec<FieldDiff>,
+}
+
+/// Compares a contact with the data proposed for it.
+///
+/// Fields left out of the edit (`null` in the proposal) are not changed on approval, so they are skipped.
+///
+/// # Arguments
+///
+/// * `contact` - The contact as it is now.
+/// * `changes` - The proposed contact data, as JSON.
+///
+/// # Returns
+///
+/// * The fields with a different proposed value.
+fn diff(contact: &Contact, changes: &str) -> Result<Vec<FieldDiff>, ApiError> {
+    let current = serde_json::to_value(contact).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let proposed: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(changes).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    Ok(proposed
+        .into_iter()
+        .filter(|(_, value)| !value.is_null())
+        .filter(|(field, value)| current.get(field) != Some(value))
+        .map(|(field, proposed)| FieldDiff {
+            current: current.get(&field).cloned().unwrap_or_default(),
+            field,
+            proposed,
+        })
+        .collect())
+}
+
+/// Handles listing pending changes, newest first.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+/// * `field_rules` - The fields the user may not read, from the roles in the JWT.
+/// * `filter` - The optional `status` filter from the query string.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a JSON array of pending changes.
+/// * `Err(ApiError)` if the user is not an admin, the status is unknown or there is a database error.
+pub async fn read_pending_changes(
+    principal: Principal,
+    field_rules: FieldRules,
+    filter: web::Query<PendingChangeFilter>,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let mut conn = establish_connection()?;
+
+    let mut query = pending_changes::table.into_boxed();
+    if let Some(status) = &filter.status {
+        if !CHANGE_STATUSES.contains(&status.as_str()) {
+            return Err(ApiError::BadRequest(format!(
+                "Status must be one of: {}",
+                CHANGE_STATUSES.join(", ")
+            )));
+        }
+        query = query.filter(pending_changes::status.eq(status));
+    }
+    let changes: Vec<PendingChange> = query
+        .order(pending_changes::id.desc())
+        .load::<PendingChange>(&mut conn)?
+        .into_iter()
+        .map(|change| field_rules.masked_change(change))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(changes))
+}
+
+/// Handles reading a pending change with the fields it would change.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+/// * `field_rules` - The fields the user may not read, from the roles in the JWT.
+/// * `id` - The ID of the pending change, from the URL path.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the pending change and its `diff` against the contact.
+/// * `Err(ApiError)` if the user is not an admin, the change is not found or there is a database error.
+pub async fn read_pending_change(
+    principal: Principal,
+    field_rules: FieldRules,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let mut conn = establish_connection()?;
+
+    let change = pending_changes::table
+        .find(id.into_inner())
+        .first::<PendingChange>(&mut conn)?;
+    let contact = contacts::table
+        .find(change.contact_id)
+        .first::<Contact>(&mut conn)?;
+    let mut diff = diff(&contact, &change.changes)?;
+    for field in diff.iter_mut().filter(|d| field_rules.hides(&d.field)) {
+        field.current = serde_json::Value::String(MASK.to_string());
+        field.proposed = serde_json::Value::String(MASK.to_string());
+    }
+    let change = field_rules.masked_change(change);
+
+    Ok(HttpResponse::Ok().json(PendingChangeWithDiff { change, diff }))
+}
+
+/// Loads a pending change that is still waiting for a decision.
+///
+/// # Arguments
+///
+/// * `conn` - The database connection.
+/// * `id` - The ID of the pending change.
+///
+/// # Returns
+///
+/// * `Ok(PendingChange)` if the change is pending.
+/// * `Err(ApiError::NotFound)` if it does not exist, or `Err(ApiError::Conflict)` if it was already decided.
+fn undecided_change(conn: &mut SqliteConnection, id: i32) -> Result<PendingChange, ApiError> {
+    let change = pending_changes::table
+        .find(id)
+        .first::<PendingChange>(conn)?;
+    if change.status != "pending" {
+        return Err(ApiError::Conflict(format!(
+            "The change was already {}",
+            change.status
+        )));
+    }
+    Ok(change)
+}
+
+/// Handles approving a pending change, which applies it to the contact.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// The contact is updated and the change is marked as approved in one transaction.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and record the reviewer.
+/// * `id` - The ID of the pending change, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the approved change.
+/// * `Err(ApiError)` if the user is not an admin, the change is not pending or there is a database error.
+pub async fn approve_pending_change(
+    principal: Principal,
+    id: web::Path<i32>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let id = id.into_inner();
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+
+    let change = undecided_change(conn, id)?;
+    let contact: NewContact =
+        serde_json::from_str(&change.changes).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    // The same steps as a direct edit
+    reset_changed_email_status(conn, change.contact_id, &contact.email)?;
+    diesel::update(contacts::table.find(change.contact_id))
+        .set(&contact)
+        .execute(conn)?;
+
+    let approved = diesel::update(pending_changes::table.find(id))
+        .set((
+            pending_changes::status.eq("approved"),
+            pending_changes::decided_by.eq(principal.id()),
+            pending_changes::decided_at.eq(unix_now()),
+        ))
+        .get_result::<PendingChange>(conn)?;
+
+    Ok(HttpResponse::Ok().json(approved))
+}
+
+/// Handles rejecting a pending change. The contact is left as it is.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and record the reviewer.
+/// * `id` - The ID of the pending change, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the rejected change.
+/// * `Err(ApiError)` if the user is not an admin, the change is not pending or there is a database error.
+pub async fn reject_pending_change(
+    principal: Principal,
+    id: web::Path<i32>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let id = id.into_inner();
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+
+    undecided_change(conn, id)?;
+    let rejected = diesel::update(pending_changes::table.find(id))
+        .set((
+            pending_changes::status.eq("rejected"),
+            pending_changes::decided_by.eq(principal.id()),
+            pending_changes::decided_at.eq(unix_now()),
+        ))
+        .get_result::<PendingChange>(conn)?;
+
+    Ok(HttpResponse::Ok().json(rejected))
+}
@@ -0,0 +1,190 @@
+// backend/src/quality.rs
+// This file scores how complete each contact is and summarizes the data quality of the whole address book.
+// It exists so users can find the contacts worth cleaning up, and see whether the book gets better or worse over time.
+// RELEVANT FILES: backend/migrations/2026-10-16-040000-0000_add_quality_score_to_contacts/up.sql, backend/src/reports.rs, backend/src/demo.rs
+
+use crate::auth::Principal;
+use crate::error::ApiError;
+use crate::establish_connection;
+use crate::models::Contact;
+use actix_web::HttpResponse;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double};
+use serde::Serialize;
+
+/// The points for having both a first and a last name.
+const NAME_POINTS: i32 = 10;
+
+/// The points for an email that was found valid. Unchecked emails get half.
+const EMAIL_POINTS: i32 = 30;
+
+/// The points for having a phone number.
+const PHONE_POINTS: i32 = 25;
+
+/// The points for belonging to a company.
+const COMPANY_POINTS: i32 = 20;
+
+/// The points for having recorded consent, given or revoked.
+const CONSENT_POINTS: i32 = 15;
+
+/// Scores how complete a contact is, from 0 to 100.
+///
+/// The database computes the same score in the generated `quality_score` column, so stored
+/// contacts already carry it. This is for contacts that are not stored, like demo contacts;
+/// keep the two in step.
+///
+/// # Arguments
+///
+/// * `contact` - The contact.
+///
+/// # Returns
+///
+/// * The score: points for names, a checked email, a phone number, a company and recorded consent.
+pub fn score(contact: &Contact) -> i32 {
+    let mut score = 0;
+    if !contact.first_name.trim().is_empty() && !contact.last_name.trim().is_empty() {
+        score += NAME_POINTS;
+    }
+    if !contact.email.trim().is_empty() {
+        score += match contact.email_status.as_str() {
+            "valid" => EMAIL_POINTS,
+            "invalid" => 0,
+            _ => EMAIL_POINTS / 2,
+        };
+    }
+    if !contact.phone_number.trim().is_empty() {
+        score += PHONE_POINTS;
+    }
+    if contact.company_id.is_some() {
+        score += COMPANY_POINTS;
+    }
+    if contact.consent_timestamp.is_some() {
+        score += CONSENT_POINTS;
+    }
+    score
+}
+
+/// The numbers behind the summary, as SQLite returns them.
+#[derive(QueryableByName)]
+struct QualityCounts {
+    #[diesel(sql_type = BigInt)]
+    contacts: i64,
+    #[diesel(sql_type = Double)]
+    average_score: f64,
+    #[diesel(sql_type = BigInt)]
+    poor: i64,
+    #[diesel(sql_type = BigInt)]
+    fair: i64,
+    #[diesel(sql_type = BigInt)]
+    good: i64,
+    #[diesel(sql_type = BigInt)]
+    excellent: i64,
+    #[diesel(sql_type = BigInt)]
+    no_email: i64,
+    #[diesel(sql_type = BigInt)]
+    unverified_email: i64,
+    #[diesel(sql_type = BigInt)]
+    no_phone_number: i64,
+    #[diesel(sql_type = BigInt)]
+    no_company: i64,
+    #[diesel(sql_type = BigInt)]
+    no_consent: i64,
+}
+
+/// How many contacts have a score in a range.
+#[derive(Serialize)]
+pub struct ScoreBand {
+    /// The name of the band: `poor`, `fair`, `good` or `excellent`.
+    pub band: &'static str,
+    /// The lowest score in the band.
+    pub min: i32,
+    /// The highest score in the band.
+    pub max: i32,
+    /// How many contacts have a score in the band.
+    pub contacts: i64,
+}
+
+/// How many contacts lack each part of the score.
+#[derive(Serialize)]
+pub struct MissingData {
+    /// Contacts without an email address.
+    pub email: i64,
+    /// Contacts with an email address that was not found valid, because it is unchecked or invalid.
+    pub unverified_email: i64,
+    /// Contacts without a phone number.
+    pub phone_number: i64,
+    /// Contacts without a company.
+    pub company: i64,
+    /// Contacts without recorded consent.
+    pub consent: i64,
+}
+
+/// The data quality of the whole address book.
+#[derive(Serialize)]
+pub struct QualitySummary {
+    /// How many contacts there are.
+    pub contacts: i64,
+    /// The average score, from 0 to 100.
+    pub average_score: f64,
+    /// How many contacts are in each score band, from poor to excellent.
+    pub bands: Vec<ScoreBand>,
+    /// How many contacts lack each part of the score.
+    pub missing: MissingData,
+}
+
+/// Handles summarizing the data quality of the address book.
+///
+/// This endpoint is protected and requires a valid JWT. Every contact counts, also archived ones.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the average score, the contacts per score band and what the contacts lack.
+/// * `Err(ApiError)` if there is a database error.
+pub async fn read_quality_summary(_principal: Principal) -> Result<HttpResponse, ApiError> {
+    let mut conn = establish_connection()?;
+    // SUM of no rows is NULL, so every sum falls back to 0
+    let counts = diesel::sql_query(
+        "SELECT COUNT(*) AS contacts,
+                COALESCE(AVG(quality_score), 0.0) AS average_score,
+                COALESCE(SUM(quality_score < 25), 0) AS poor,
+                COALESCE(SUM(quality_score BETWEEN 25 AND 49), 0) AS fair,
+                COALESCE(SUM(quality_score BETWEEN 50 AND 74), 0) AS good,
+                COALESCE(SUM(quality_score >= 75), 0) AS excellent,
+                COALESCE(SUM(trim(email) = ''), 0) AS no_email,
+                COALESCE(SUM(trim(email) <> '' AND email_status <> 'valid'), 0) AS unverified_email,
+                COALESCE(SUM(trim(phone_number) = ''), 0) AS no_phone_number,
+                COALESCE(SUM(company_id IS NULL), 0) AS no_company,
+                COALESCE(SUM(consent_timestamp IS NULL), 0) AS no_consent
+         FROM contacts",
+    )
+    .get_result::<QualityCounts>(&mut conn)?;
+
+    let band = |band, min, max, contacts| ScoreBand {
+        band,
+        min,
+        max,
+        contacts,
+    };
+    Ok(HttpResponse::Ok().json(QualitySummary {
+        contacts: counts.contacts,
+        // Two decimals are enough to follow the trend
+        average_score: (counts.average_score * 100.0).round() / 100.0,
+        bands: vec![
+            band("poor", 0, 24, counts.poor),
+            band("fair", 25, 49, counts.fair),
+            band("good", 50, 74, counts.good),
+            band("excellent", 75, 100, counts.excellent),
+        ],
+        missing: MissingData {
+            email: counts.no_email,
+            unverified_email: counts.unverified_email,
+            phone_number: counts.no_phone_number,
+            company: counts.no_company,
+            consent: counts.no_consent,
+        },
+    }))
+}
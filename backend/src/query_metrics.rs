@@ -0,0 +1,247 @@
+// backend/src/query_metrics.rs
+// This file times every database query, logs the slow ones and keeps histograms of query durations.
+// It exists so we can find the queries that need indexes as the address book grows.
+// RELEVANT FILES: backend/src/main.rs, backend/src/indexes.rs, backend/src/circuit_breaker.rs
+
+use crate::auth::TokenValidator;
+use crate::ip_filter::IpFilter;
+use actix_web::{web, HttpResponse};
+use diesel::connection::{Connection, InstrumentationEvent};
+use diesel::sqlite::SqliteConnection;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// The slow-query threshold when `SLOW_QUERY_MS` is not set, in milliseconds.
+const DEFAULT_SLOW_QUERY_MS: u64 = 100;
+
+/// The upper bounds of the histogram buckets, in seconds.
+const BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0];
+
+/// Diesel appends the bound values to the query text after this marker.
+const BINDS_MARKER: &str = " -- binds: ";
+
+/// How many distinct queries are kept for `seen_queries`, so the set cannot grow without end.
+const MAX_SEEN_QUERIES: usize = 1000;
+
+/// The slow-query threshold, read once from the environment.
+static SLOW_QUERY_THRESHOLD: OnceLock<Option<Duration>> = OnceLock::new();
+
+/// The query durations, by operation and table.
+static HISTOGRAMS: Mutex<BTreeMap<(String, String), Histogram>> = Mutex::new(BTreeMap::new());
+
+/// The distinct queries that read or change contacts and other data, without their bound values.
+static SEEN_QUERIES: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// The durations of one kind of query.
+#[derive(Default)]
+struct Histogram {
+    /// How many queries took at most the matching bound in `BUCKETS`. Not cumulative.
+    buckets: [u64; BUCKETS.len()],
+    /// How many queries took longer than the last bound.
+    overflow: u64,
+    /// The total time of all queries, in seconds.
+    sum: f64,
+}
+
+impl Histogram {
+    /// Adds one query duration.
+    fn observe(&mut self, seconds: f64) {
+        match BUCKETS.iter().position(|bound| seconds <= *bound) {
+            Some(i) => self.buckets[i] += 1,
+            None => self.overflow += 1,
+        }
+        self.sum += seconds;
+    }
+
+    /// Returns the number of queries.
+    fn count(&self) -> u64 {
+        self.buckets.iter().sum::<u64>() + self.overflow
+    }
+}
+
+/// Returns the slow-query threshold from `SLOW_QUERY_MS`, or `None` when it is `0`.
+fn slow_query_threshold() -> Option<Duration> {
+    *SLOW_QUERY_THRESHOLD.get_or_init(|| {
+        let ms = std::env::var("SLOW_QUERY_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_SLOW_QUERY_MS);
+        (ms > 0).then(|| Duration::from_millis(ms))
+    })
+}
+
+/// Removes the bound values from a query, so personal data does not end up in the logs.
+fn redact(query: &str) -> String {
+    match query.split_once(BINDS_MARKER) {
+        Some((sql, _)) => format!("{}{}[redacted]", sql, BINDS_MARKER),
+        None => query.to_string(),
+    }
+}
+
+/// Finds the kind of a query and the table it works on, e.g. `("select", "contacts")`.
+///
+/// Statements other than SELECT, INSERT, UPDATE and DELETE (e.g. BEGIN or PRAGMA) count as `other`.
+fn classify(sql: &str) -> (String, String) {
+    let sql = sql.trim_start();
+    // ASCII case changes keep byte positions the same, so an index into `upper` fits `sql`
+    let upper = sql.to_ascii_uppercase();
+    let operation = upper.split_whitespace().next().unwrap_or_default();
+    let table_keyword = match operation {
+        "SELECT" | "DELETE" => " FROM ",
+        "INSERT" => " INTO ",
+        "UPDATE" => "UPDATE ",
+        _ => return ("other".to_string(), String::new()),
+    };
+    // Diesel quotes table names with backticks
+    let table = upper
+        .find(table_keyword)
+        .map(|i| &sql[i + table_keyword.len()..])
+        .and_then(|rest| rest.split_whitespace().next())
+        .map(|name| name.trim_matches(|c: char| c == '`' || c == '"' || c == '(' || c == ')'))
+        .unwrap_or_default();
+    (operation.to_lowercase(), table.to_string())
+}
+
+/// Records one finished query: adds it to the histograms and logs it when it was slow.
+fn record(query: &str, elapsed: Duration) {
+    let sql = query.split_once(BINDS_MARKER).map_or(query, |(sql, _)| sql);
+    let key = classify(sql);
+    if key.0 != "other" && !key.1.starts_with("__") {
+        let mut seen = SEEN_QUERIES.lock().unwrap_or_else(|e| e.into_inner());
+        if seen.len() < MAX_SEEN_QUERIES {
+            seen.insert(sql.to_string());
+        }
+    }
+    HISTOGRAMS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(key)
+        .or_default()
+        .observe(elapsed.as_secs_f64());
+
+    if slow_query_threshold().is_some_and(|threshold| elapsed >= threshold) {
+        log::warn!("Slow query ({} ms): {}", elapsed.as_millis(), redact(query));
+    }
+}
+
+/// Returns the distinct queries run since the server started, with `?` in place of bound values.
+///
+/// Internal tables such as `__diesel_schema_migrations` are left out.
+pub fn seen_queries() -> Vec<String> {
+    SEEN_QUERIES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .cloned()
+        .collect()
+}
+
+/// Times every query on a connection.
+///
+/// # Arguments
+///
+/// * `conn` - The connection to time.
+pub fn instrument(conn: &mut SqliteConnection) {
+    let mut started = None;
+    conn.set_instrumentation(move |event: InstrumentationEvent<'_>| match event {
+        InstrumentationEvent::StartQuery { .. } => started = Some(Instant::now()),
+        InstrumentationEvent::FinishQuery { query, .. } => {
+            if let Some(start) = started.take() {
+                record(&query.to_string(), start.elapsed());
+            }
+        }
+        _ => {}
+    });
+}
+
+/// Handles exporting the query durations in the Prometheus text format.
+///
+/// This endpoint does not require a JWT, so scrapers can call it, but only from the networks
+/// allowed to reach the admin routes. It holds only counts and timings per table, no data,
+/// the state of the circuit breaker in front of the identity provider and the decisions of the network check.
+///
+/// # Arguments
+///
+/// * `validator` - The token validator, whose circuit breaker state is exported.
+/// * `ip_filter` - The network check, whose decisions are exported.
+///
+/// # Returns
+///
+/// * `HttpResponse` with one histogram series per operation and table, the circuit breaker series and the network check series.
+pub async fn read_metrics(
+    validator: web::Data<TokenValidator>,
+    ip_filter: web::Data<IpFilter>,
+) -> HttpResponse {
+    let histograms = HISTOGRAMS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut body = String::from(
+        "# HELP db_query_duration_seconds Time spent in database queries.\n\
+         # TYPE db_query_duration_seconds histogram\n",
+    );
+    for ((operation, table), histogram) in histograms.iter() {
+        let labels = format!("operation=\"{}\",table=\"{}\"", operation, table);
+        // Prometheus buckets count every query up to their bound
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                body,
+                "db_query_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            body,
+            "db_query_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+            labels,
+            histogram.count()
+        );
+        let _ = writeln!(
+            body,
+            "db_query_duration_seconds_sum{{{}}} {}",
+            labels, histogram.sum
+        );
+        let _ = writeln!(
+            body,
+            "db_query_duration_seconds_count{{{}}} {}",
+            labels,
+            histogram.count()
+        );
+    }
+
+    let circuit = validator.circuit();
+    body.push_str(
+        "# HELP idp_circuit_state The state of the circuit breaker for the identity provider (1 for the current state).\n\
+         # TYPE idp_circuit_state gauge\n",
+    );
+    for state in ["closed", "open", "half_open"] {
+        let _ = writeln!(
+            body,
+            "idp_circuit_state{{state=\"{}\"}} {}",
+            state,
+            u8::from(circuit.state() == state)
+        );
+    }
+    let _ = writeln!(
+        body,
+        "# HELP idp_circuit_trips_total Times the circuit for the identity provider has opened.\n\
+         # TYPE idp_circuit_trips_total counter\n\
+         idp_circuit_trips_total {}",
+        circuit.trips()
+    );
+
+    let (allowed, denied) = ip_filter.decisions();
+    let _ = writeln!(
+        body,
+        "# HELP admin_ip_filter_decisions_total Requests to the admin routes and metrics let in or kept out by address.\n\
+         # TYPE admin_ip_filter_decisions_total counter\n\
+         admin_ip_filter_decisions_total{{decision=\"allow\"}} {}\n\
+         admin_ip_filter_decisions_total{{decision=\"deny\"}} {}",
+        allowed, denied
+    );
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body)
+}
@@ -0,0 +1,234 @@
+// backend/src/relationships.rs
+// This file contains the handlers for relationships between contacts.
+// It exists to model spouses, colleagues, assistants and reporting lines in the address book.
+// RELEVANT FILES: backend/src/models.rs, backend/contacts-types/src/schema.rs, backend/src/handlers.rs
+
+use crate::auth::Principal;
+use crate::error::ApiError;
+use crate::establish_connection;
+use crate::field_permissions::FieldRules;
+use crate::identifiers::ContactKey;
+use crate::models::{Contact, NewRelationship, Relationship};
+use crate::schema::{contact_relationships, contacts};
+use crate::transaction::Transaction;
+use actix_web::{web, HttpResponse};
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The kinds of relationship a contact can have.
+const KINDS: [&str; 4] = ["spouse", "colleague", "manager", "assistant"];
+
+/// A relationship together with the contact on the other side of it.
+#[derive(Serialize)]
+pub struct RelatedContact {
+    /// The relationship itself.
+    #[serde(flatten)]
+    pub relationship: Relationship,
+    /// The other contact in the relationship.
+    pub related_contact: Contact,
+}
+
+/// Checks that a relationship is well-formed before it is written.
+///
+/// # Arguments
+///
+/// * `relationship` - The relationship from the request body.
+///
+/// # Returns
+///
+/// * `Ok(())` if the relationship is valid.
+/// * `Err(ApiError::BadRequest)` if the kind is unknown or a contact is related to itself.
+fn validate(relationship: &NewRelationship) -> Result<(), ApiError> {
+    if !KINDS.contains(&relationship.kind.as_str()) {
+        return Err(ApiError::BadRequest(format!(
+            "Kind must be one of: {}",
+            KINDS.join(", ")
+        )));
+    }
+    if relationship.contact_a == relationship.contact_b {
+        return Err(ApiError::BadRequest(
+            "A contact cannot be related to itself".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Handles the creation of a new relationship.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `relationship` - The new relationship data from the request body.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the created relationship.
+/// * `Err(ApiError)` if the data is invalid or there is a database error.
+pub async fn create_relationship(
+    _principal: Principal,
+    relationship: web::Json<NewRelationship>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    validate(&relationship)?;
+    let mut conn = transaction.conn()?;
+
+    let created = diesel::insert_into(contact_relationships::table)
+        .values(&relationship.into_inner())
+        .get_result::<Relationship>(&mut *conn)?;
+
+    Ok(HttpResponse::Ok().json(created))
+}
+
+/// Handles reading a specific relationship by its ID.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `id` - The ID of the relationship to read, from the URL path.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the relationship.
+/// * `Err(ApiError)` if the relationship is not found or there is a database error.
+pub async fn read_relationship(
+    _principal: Principal,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = establish_connection()?;
+
+    let relationship = contact_relationships::table
+        .find(id.into_inner())
+        .first::<Relationship>(&mut conn)?;
+
+    Ok(HttpResponse::Ok().json(relationship))
+}
+
+/// Handles updating an existing relationship by its ID.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `id` - The ID of the relationship to update, from the URL path.
+/// * `relationship` - The updated relationship data from the request body.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the JSON data for the updated relationship.
+/// * `Err(ApiError)` if the relationship is not found, the data is invalid or there is a database error.
+pub async fn update_relationship(
+    _principal: Principal,
+    id: web::Path<i32>,
+    relationship: web::Json<NewRelationship>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    validate(&relationship)?;
+    let mut conn = transaction.conn()?;
+
+    let updated = diesel::update(contact_relationships::table.find(id.into_inner()))
+        .set(relationship.into_inner())
+        .get_result::<Relationship>(&mut *conn)?;
+
+    Ok(HttpResponse::Ok().json(updated))
+}
+
+/// Handles deleting a relationship by its ID.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `id` - The ID of the relationship to delete, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a success message if the relationship is deleted.
+/// * `Err(ApiError)` if there is a database error.
+pub async fn delete_relationship(
+    _principal: Principal,
+    id: web::Path<i32>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = transaction.conn()?;
+
+    diesel::delete(contact_relationships::table.find(id.into_inner())).execute(&mut *conn)?;
+
+    Ok(HttpResponse::Ok().body("Relationship deleted successfully"))
+}
+
+/// Handles listing all relationships of a contact, with the related contacts inline.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Relationships are found in both directions.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `id` - The ID or UUID of the contact, from the URL path.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a JSON array of relationships and related contacts.
+/// * `Err(ApiError)` if the contact is not found or there is a database error.
+pub async fn read_contact_relationships(
+    field_rules: FieldRules,
+    id: web::Path<ContactKey>,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = establish_connection()?;
+    let contact_id = id.resolve(&mut conn)?;
+
+    // Return 404 for unknown contacts instead of an empty list
+    contacts::table
+        .find(contact_id)
+        .select(contacts::id)
+        .first::<i32>(&mut conn)?;
+
+    let relationships = contact_relationships::table
+        .filter(
+            contact_relationships::contact_a
+                .eq(contact_id)
+                .or(contact_relationships::contact_b.eq(contact_id)),
+        )
+        .order(contact_relationships::id.asc())
+        .load::<Relationship>(&mut conn)?;
+
+    // Load all related contacts in one query instead of one query per relationship
+    let other_id = |r: &Relationship| {
+        if r.contact_a == contact_id {
+            r.contact_b
+        } else {
+            r.contact_a
+        }
+    };
+    let other_ids: Vec<i32> = relationships.iter().map(other_id).collect();
+    let related: HashMap<i32, Contact> = contacts::table
+        .filter(contacts::id.eq_any(&other_ids))
+        .load::<Contact>(&mut conn)?
+        .into_iter()
+        .map(|c| (c.id, field_rules.masked(c)))
+        .collect();
+
+    let result: Vec<RelatedContact> = relationships
+        .into_iter()
+        .filter_map(|relationship| {
+            // A contact can appear in several relationships, so it is cloned
+            let related_contact = related.get(&other_id(&relationship))?.clone();
+            Some(RelatedContact {
+                relationship,
+                related_contact,
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(result))
+}
@@ -0,0 +1,274 @@
+// backend/src/reports.rs
+// This file computes the canned aggregate reports over the address book, as JSON or CSV, and caches them for a while.
+// It exists so managers can see how contacts are spread over companies and sources without exporting everything.
+// RELEVANT FILES: backend/src/handlers.rs, backend/src/formats.rs, backend/src/query_metrics.rs
+
+use crate::error::ApiError;
+use crate::establish_connection;
+use crate::field_permissions::FieldRules;
+use crate::schema::{companies, contacts};
+use actix_web::http::header::{HeaderValue, ACCEPT, CONTENT_DISPOSITION, VARY};
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a report is cached when not configured, in seconds.
+const DEFAULT_CACHE_SECS: u64 = 300;
+
+/// The content type of CSV reports.
+const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+/// A computed report: a table with named columns.
+pub struct Report {
+    /// The names of the columns, in order.
+    pub(crate) columns: Vec<&'static str>,
+    /// The rows, with one value per column.
+    pub(crate) rows: Vec<Vec<Value>>,
+}
+
+impl Report {
+    /// Builds the JSON form of the report: an array with one object per row.
+    fn to_json(&self) -> Value {
+        Value::Array(
+            self.rows
+                .iter()
+                .map(|row| {
+                    let object: Map<String, Value> = self
+                        .columns
+                        .iter()
+                        .map(|column| column.to_string())
+                        .zip(row.iter().cloned())
+                        .collect();
+                    Value::Object(object)
+                })
+                .collect(),
+        )
+    }
+
+    /// Builds the CSV form of the report (RFC 4180), with a header line.
+    fn to_csv(&self) -> String {
+        let mut csv = self.columns.join(",");
+        csv.push_str("\r\n");
+        for row in &self.rows {
+            let fields: Vec<String> = row.iter().map(csv_field).collect();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+}
+
+/// Writes one value as a CSV field. `null` is empty, and text with commas, quotes or line breaks is quoted.
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// The reports computed lately, shared by all workers.
+///
+/// Reports read the whole contacts table, so they are computed at most once per cache period.
+/// Changes to contacts show up in a report when its cached copy expires.
+pub struct ReportCache {
+    /// How long a report is kept, in seconds. Zero switches the cache off. It can be reloaded at runtime.
+    ttl_secs: AtomicU64,
+    /// The reports by name, with when they were computed.
+    reports: Mutex<HashMap<&'static str, (Instant, Arc<Report>)>>,
+}
+
+impl ReportCache {
+    /// Reads how long reports are cached from the `REPORT_CACHE_SECS` environment variable.
+    ///
+    /// # Returns
+    ///
+    /// * An empty `ReportCache`. A period of `0` switches the cache off.
+    pub fn from_env() -> Self {
+        let seconds = std::env::var("REPORT_CACHE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CACHE_SECS);
+        Self {
+            ttl_secs: AtomicU64::new(seconds),
+            reports: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns how long a report is kept, in seconds.
+    pub fn ttl_secs(&self) -> u64 {
+        self.ttl_secs.load(Ordering::Relaxed)
+    }
+
+    /// Changes how long a report is kept. Cached reports expire by the new period.
+    pub fn set_ttl_secs(&self, seconds: u64) {
+        self.ttl_secs.store(seconds, Ordering::Relaxed);
+    }
+
+    /// Returns a cached report, or computes it when it is missing or expired.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the report, e.g. `contacts-by-source`.
+    /// * `compute` - Computes the report from the database.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Report)` with the report.
+    /// * `Err(ApiError)` if it had to be computed and there is a database error.
+    fn get_or_compute(
+        &self,
+        name: &'static str,
+        compute: impl FnOnce(&mut SqliteConnection) -> Result<Report, ApiError>,
+    ) -> Result<Arc<Report>, ApiError> {
+        if let Some((computed_at, report)) = self.lock().get(name)
+            && computed_at.elapsed() < Duration::from_secs(self.ttl_secs())
+        {
+            return Ok(report.clone());
+        }
+        // Computed without the lock, so one slow report does not hold up the others
+        let report = Arc::new(compute(&mut establish_connection()?)?);
+        self.lock().insert(name, (Instant::now(), report.clone()));
+        Ok(report)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<&'static str, (Instant, Arc<Report>)>> {
+        self.reports.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Builds the response for a report, as CSV when the client accepts `text/csv` and as JSON otherwise.
+///
+/// # Arguments
+///
+/// * `req` - The HTTP request, whose `Accept` header picks the format.
+/// * `name` - The name of the report, used as the file name of CSV downloads.
+/// * `report` - The report.
+///
+/// # Returns
+///
+/// * The `HttpResponse`.
+pub(crate) fn respond(req: &HttpRequest, name: &str, report: &Report) -> HttpResponse {
+    let wants_csv = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/csv"));
+    if !wants_csv {
+        return HttpResponse::Ok().json(report.to_json());
+    }
+    HttpResponse::Ok()
+        .content_type(CSV_CONTENT_TYPE)
+        .insert_header((
+            CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.csv\"", name),
+        ))
+        // The format middleware only adds it to JSON responses
+        .insert_header((VARY, HeaderValue::from_static("accept")))
+        .body(report.to_csv())
+}
+
+/// Counts the contacts of every company, the companies with the most contacts first.
+fn contacts_by_company(conn: &mut SqliteConnection) -> Result<Report, ApiError> {
+    let counts: Vec<(Option<i32>, i64)> = contacts::table
+        .group_by(contacts::company_id)
+        .select((contacts::company_id, diesel::dsl::count_star()))
+        .order((diesel::dsl::count_star().desc(), contacts::company_id.asc()))
+        .load(conn)?;
+    let ids: Vec<i32> = counts.iter().filter_map(|(id, _)| *id).collect();
+    let names: HashMap<i32, String> = companies::table
+        .filter(companies::id.eq_any(&ids))
+        .select((companies::id, companies::name))
+        .load::<(i32, String)>(conn)?
+        .into_iter()
+        .collect();
+    Ok(Report {
+        columns: vec!["company_id", "company_name", "contacts"],
+        rows: counts
+            .into_iter()
+            .map(|(id, count)| {
+                let name = id.and_then(|id| names.get(&id).cloned());
+                vec![id.into(), name.into(), count.into()]
+            })
+            .collect(),
+    })
+}
+
+/// Counts the contacts of every source, the sources with the most contacts first.
+fn contacts_by_source(conn: &mut SqliteConnection) -> Result<Report, ApiError> {
+    let counts: Vec<(Option<String>, i64)> = contacts::table
+        .group_by(contacts::source)
+        .select((contacts::source, diesel::dsl::count_star()))
+        .order((diesel::dsl::count_star().desc(), contacts::source.asc()))
+        .load(conn)?;
+    Ok(Report {
+        columns: vec!["source", "contacts"],
+        rows: counts
+            .into_iter()
+            .map(|(source, count)| vec![source.into(), count.into()])
+            .collect(),
+    })
+}
+
+/// Handles reporting how many contacts each company has.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Every contact counts, also archived ones. Contacts without a company are counted in a row
+/// with an empty `company_id`.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the caller may not read. The report reveals `company_id`.
+/// * `req` - The HTTP request. `Accept: text/csv` asks for CSV.
+/// * `cache` - The reports computed lately.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with `company_id`, `company_name` and `contacts` per company, as JSON or CSV.
+/// * `Err(ApiError)` if `company_id` is hidden from the caller or there is a database error.
+pub async fn report_contacts_by_company(
+    field_rules: FieldRules,
+    req: HttpRequest,
+    cache: web::Data<ReportCache>,
+) -> Result<HttpResponse, ApiError> {
+    field_rules.check_readable("company_id")?;
+    let name = "contacts-by-company";
+    let report = cache.get_or_compute(name, contacts_by_company)?;
+    Ok(respond(&req, name, &report))
+}
+
+/// Handles reporting where contacts came from, e.g. `website`, `scim` or an import.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Every contact counts, also archived ones. Contacts without a source are counted in a row
+/// with an empty `source`.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the caller may not read. The report reveals `source`.
+/// * `req` - The HTTP request. `Accept: text/csv` asks for CSV.
+/// * `cache` - The reports computed lately.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with `source` and `contacts` per source, as JSON or CSV.
+/// * `Err(ApiError)` if `source` is hidden from the caller or there is a database error.
+pub async fn report_contacts_by_source(
+    field_rules: FieldRules,
+    req: HttpRequest,
+    cache: web::Data<ReportCache>,
+) -> Result<HttpResponse, ApiError> {
+    field_rules.check_readable("source")?;
+    let name = "contacts-by-source";
+    let report = cache.get_or_compute(name, contacts_by_source)?;
+    Ok(respond(&req, name, &report))
+}
@@ -0,0 +1,547 @@
+// backend/src/scim.rs
+// This file exposes contacts as SCIM 2.0 Users under `/scim/v2`.
+// It exists so identity systems that speak SCIM can push their directory entries into the address book.
+// RELEVANT FILES: backend/src/models.rs, backend/src/handlers.rs, backend/src/main.rs
+
+use crate::auth::{Principal, ADMIN_ROLE};
+use crate::companies::{apply_rules, CompanyRules};
+use crate::error::ApiError;
+use crate::establish_connection;
+use crate::feature_flags::FeatureFlags;
+use crate::field_permissions::FieldRules;
+use crate::handlers::{ContactEditor, Edit};
+use crate::identifiers::new_uuid;
+use crate::models::{Contact, NewContact};
+use crate::normalization::normalize_contact;
+use crate::pending_changes::ChangeApproval;
+use crate::schema::contacts;
+use crate::transaction::Transaction;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The content type of every SCIM response.
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+
+/// The schema URN of a SCIM user.
+const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+
+/// The schema URN of a SCIM list response.
+const LIST_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+
+/// The schema URN of a SCIM error.
+const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+/// The largest page a client can ask for.
+const MAX_PAGE_SIZE: i64 = 200;
+
+/// The value `source` is set to for contacts created through SCIM.
+pub(crate) const SCIM_SOURCE: &str = "scim";
+
+/// An `ApiError` answered in the SCIM error format, which SCIM clients expect.
+#[derive(Debug)]
+pub struct ScimError(ApiError);
+
+impl fmt::Display for ScimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<ApiError> for ScimError {
+    fn from(e: ApiError) -> Self {
+        ScimError(e)
+    }
+}
+
+impl From<diesel::result::Error> for ScimError {
+    fn from(e: diesel::result::Error) -> Self {
+        ScimError(e.into())
+    }
+}
+
+/// The body of a SCIM error response.
+#[derive(Serialize)]
+struct ScimErrorBody {
+    /// The schema of the message.
+    schemas: [&'static str; 1],
+    /// The HTTP status code, as a string like the spec asks.
+    status: String,
+    /// A message for the user.
+    detail: String,
+}
+
+impl ResponseError for ScimError {
+    fn status_code(&self) -> StatusCode {
+        self.0.error_response().status()
+    }
+
+    /// Maps the error to the SCIM error format, with the same status as the `ApiError`.
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let detail = match &self.0 {
+            ApiError::BadRequest(message) | ApiError::Forbidden(message) => message.clone(),
+            ApiError::NotFound => "Resource not found".to_string(),
+            _ => status.canonical_reason().unwrap_or("Error").to_string(),
+        };
+        HttpResponse::build(status)
+            .content_type(SCIM_CONTENT_TYPE)
+            .json(ScimErrorBody {
+                schemas: [ERROR_SCHEMA],
+                status: status.as_str().to_string(),
+                detail,
+            })
+    }
+}
+
+/// A SCIM multi-valued attribute, like an email address or phone number.
+#[derive(Deserialize, Serialize)]
+pub struct ScimValue {
+    /// The value itself.
+    pub value: String,
+    /// Whether this is the preferred value.
+    #[serde(default)]
+    pub primary: bool,
+}
+
+/// The name of a SCIM user.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimName {
+    /// The first name.
+    #[serde(default)]
+    pub given_name: String,
+    /// The last name.
+    #[serde(default)]
+    pub family_name: String,
+}
+
+/// The metadata of a SCIM resource.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimMeta {
+    /// Always "User".
+    pub resource_type: &'static str,
+    /// The path of the resource.
+    pub location: String,
+}
+
+/// A contact in the shape of a SCIM user.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUser {
+    /// The schemas of the resource.
+    pub schemas: [&'static str; 1],
+    /// The contact ID, as a string.
+    pub id: String,
+    /// The email address, which is the unique name in the directory.
+    pub user_name: String,
+    /// The name of the contact.
+    pub name: ScimName,
+    /// The email address, as a list.
+    pub emails: Vec<ScimValue>,
+    /// The phone number, as a list. Empty if the contact has none.
+    pub phone_numbers: Vec<ScimValue>,
+    /// Whether the contact is active, i.e. its status is not "inactive".
+    pub active: bool,
+    /// The metadata of the resource.
+    pub meta: ScimMeta,
+}
+
+impl From<Contact> for ScimUser {
+    fn from(contact: Contact) -> Self {
+        let phone_numbers = if contact.phone_number.is_empty() {
+            Vec::new()
+        } else {
+            vec![ScimValue {
+                value: contact.phone_number,
+                primary: true,
+            }]
+        };
+        ScimUser {
+            schemas: [USER_SCHEMA],
+            id: contact.id.to_string(),
+            user_name: contact.email.clone(),
+            name: ScimName {
+                given_name: contact.first_name,
+                family_name: contact.last_name,
+            },
+            emails: vec![ScimValue {
+                value: contact.email,
+                primary: true,
+            }],
+            phone_numbers,
+            active: contact.status != "inactive",
+            meta: ScimMeta {
+                resource_type: "User",
+                location: format!("/scim/v2/Users/{}", contact.id),
+            },
+        }
+    }
+}
+
+/// A SCIM user sent by the client when creating or replacing a user.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUserRequest {
+    /// The unique name. Used as the email when no email is given.
+    pub user_name: String,
+    /// The name of the user.
+    #[serde(default)]
+    pub name: ScimName,
+    /// The email addresses. The primary one, or else the first one, is used.
+    #[serde(default)]
+    pub emails: Vec<ScimValue>,
+    /// The phone numbers. The primary one, or else the first one, is used.
+    #[serde(default)]
+    pub phone_numbers: Vec<ScimValue>,
+    /// Whether the user is active. Inactive users get the "inactive" status.
+    pub active: Option<bool>,
+}
+
+/// Picks the primary value of a multi-valued attribute, or else the first one.
+fn preferred(values: Vec<ScimValue>) -> Option<String> {
+    let index = values.iter().position(|v| v.primary).unwrap_or(0);
+    values.into_iter().nth(index).map(|v| v.value)
+}
+
+impl ScimUserRequest {
+    /// Maps the SCIM user onto contact data.
+    ///
+    /// # Returns
+    ///
+    /// * The contact data. The status is only set for inactive users, so active ones keep theirs.
+    fn into_contact(self) -> NewContact {
+        let status = (self.active == Some(false)).then(|| "inactive".to_string());
+        NewContact {
+            first_name: self.name.given_name,
+            last_name: self.name.family_name,
+            email: preferred(self.emails).unwrap_or(self.user_name),
+            phone_number: preferred(self.phone_numbers).unwrap_or_default(),
+            company_id: None,
+            status,
+            source: Some(SCIM_SOURCE.to_string()),
+            owner: None,
+            uuid: None,
+            external_id: None,
+        }
+    }
+}
+
+/// The query parameters of the list endpoint.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListQuery {
+    /// A filter. Only `userName eq "value"` is supported.
+    pub filter: Option<String>,
+    /// The 1-based index of the first result.
+    pub start_index: Option<i64>,
+    /// The page size.
+    pub count: Option<i64>,
+}
+
+/// A page of SCIM users.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListResponse {
+    /// The schema of the message.
+    pub schemas: [&'static str; 1],
+    /// The number of users that match the filter, on all pages.
+    pub total_results: i64,
+    /// The 1-based index of the first result on this page.
+    pub start_index: i64,
+    /// The number of users on this page.
+    pub items_per_page: usize,
+    /// The users on this page.
+    #[serde(rename = "Resources")]
+    pub resources: Vec<ScimUser>,
+}
+
+/// Reads the user name from a `userName eq "value"` filter.
+///
+/// # Arguments
+///
+/// * `filter` - The filter from the query string.
+///
+/// # Returns
+///
+/// * `Ok(value)` with the user name to look for.
+/// * `Err(ApiError::BadRequest)` for any other filter.
+fn user_name_filter(filter: &str) -> Result<String, ApiError> {
+    let mut parts = filter.trim().splitn(3, ' ');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(attribute), Some(operator), Some(value))
+            if attribute.eq_ignore_ascii_case("userName")
+                && operator.eq_ignore_ascii_case("eq") =>
+        {
+            Ok(value.trim().trim_matches('"').to_string())
+        }
+        _ => Err(ApiError::BadRequest(
+            "Only filters of the form userName eq \"value\" are supported".to_string(),
+        )),
+    }
+}
+
+/// Builds a SCIM response with the SCIM content type.
+fn scim_response(status: StatusCode, body: impl Serialize) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(SCIM_CONTENT_TYPE)
+        .json(body)
+}
+
+/// Handles listing contacts as SCIM users.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read, from the roles in the JWT.
+/// * `query` - The filter and paging parameters.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a SCIM list response.
+/// * `Err(ScimError)` if the filter is not supported or there is a database error.
+pub async fn list_users(
+    field_rules: FieldRules,
+    query: web::Query<ScimListQuery>,
+) -> Result<HttpResponse, ScimError> {
+    let start_index = query.start_index.unwrap_or(1).max(1);
+    let count = query.count.unwrap_or(100).clamp(0, MAX_PAGE_SIZE);
+    let user_name = query.filter.as_deref().map(user_name_filter).transpose()?;
+
+    let mut conn = establish_connection()?;
+    let filtered = || {
+        let mut q = contacts::table.into_boxed();
+        if let Some(user_name) = &user_name {
+            q = q.filter(contacts::email.eq(user_name));
+        }
+        q
+    };
+
+    let total_results = filtered().count().get_result::<i64>(&mut conn)?;
+    let resources: Vec<ScimUser> = filtered()
+        .order(contacts::id.asc())
+        .offset(start_index - 1)
+        .limit(count)
+        .load::<Contact>(&mut conn)?
+        .into_iter()
+        .map(|contact| ScimUser::from(field_rules.masked(contact)))
+        .collect();
+
+    Ok(scim_response(
+        StatusCode::OK,
+        ScimListResponse {
+            schemas: [LIST_SCHEMA],
+            total_results,
+            start_index,
+            items_per_page: resources.len(),
+            resources,
+        },
+    ))
+}
+
+/// Handles reading a contact as a SCIM user.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read, from the roles in the JWT.
+/// * `id` - The ID of the contact, from the URL path.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the SCIM user.
+/// * `Err(ScimError)` if the contact is not found or there is a database error.
+pub async fn read_user(
+    field_rules: FieldRules,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, ScimError> {
+    let mut conn = establish_connection()?;
+
+    let contact = contacts::table
+        .find(id.into_inner())
+        .first::<Contact>(&mut conn)?;
+
+    Ok(scim_response(
+        StatusCode::OK,
+        ScimUser::from(field_rules.masked(contact)),
+    ))
+}
+
+/// Answers a create with a `userName` that is taken, with the 409 SCIM clients rely on to detect existing users.
+fn user_name_taken() -> HttpResponse {
+    scim_response(
+        StatusCode::CONFLICT,
+        ScimErrorBody {
+            schemas: [ERROR_SCHEMA],
+            status: "409".to_string(),
+            detail: "A user with this userName already exists".to_string(),
+        },
+    )
+}
+
+/// Handles creating a contact from a SCIM user.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// The new contact gets the source "scim".
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+/// * `field_rules` - The fields the user may not read, from the roles in the JWT.
+/// * `flags` - The feature flags of the caller's workspace, which switch the normalization rules.
+/// * `user` - The SCIM user from the request body.
+/// * `rules` - The company rules, used to link the contact to a company.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with status 201 and the created SCIM user, or 409 if a contact with the same email exists.
+/// * `Err(ScimError)` if the user is not an admin or there is a database error.
+pub async fn create_user(
+    principal: Principal,
+    field_rules: FieldRules,
+    flags: FeatureFlags,
+    user: web::Json<ScimUserRequest>,
+    rules: web::Data<CompanyRules>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ScimError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+    let mut contact = user.into_inner().into_contact();
+    // Normalized first, so the email is compared as it would be stored
+    normalize_contact(&flags, &mut contact);
+
+    // userName is unique in SCIM, and clients rely on a conflict to detect existing users
+    let existing = contacts::table
+        .filter(contacts::email.eq(&contact.email))
+        .select(contacts::id)
+        .first::<i32>(conn)
+        .optional()?;
+    if existing.is_some() {
+        return Ok(user_name_taken());
+    }
+
+    apply_rules(conn, &rules, &mut contact)?;
+    contact.uuid = Some(new_uuid());
+    // The unique index on the email of SCIM users is the last word, should the check above ever race
+    let created = match diesel::insert_into(contacts::table)
+        .values(&contact)
+        .get_result::<Contact>(conn)
+    {
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            return Ok(user_name_taken());
+        }
+        created => created?,
+    };
+
+    let user = ScimUser::from(field_rules.masked(created));
+    Ok(HttpResponse::Created()
+        .content_type(SCIM_CONTENT_TYPE)
+        .insert_header(("Location", user.meta.location.clone()))
+        .json(user))
+}
+
+/// Handles replacing a contact with a SCIM user.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+/// Fields that SCIM does not know about, like the company and owner, are kept.
+/// Setting `active` to false marks the contact inactive, setting it to true makes an inactive contact a lead again.
+///
+/// The edit is checked like one through `PUT /contacts/{id}`, and may wait for approval.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role and to decide if the edit needs approval.
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `flags` - The feature flags of the caller's workspace, which switch the normalization rules.
+/// * `id` - The ID of the contact, from the URL path.
+/// * `user` - The SCIM user from the request body.
+/// * `approval` - The setting that decides if edits need approval.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the updated SCIM user, or 202 Accepted with the pending change
+///   if the edit waits for a reviewer.
+/// * `Err(ScimError)` if the user is not an admin, the contact is not found, a read-only field is changed
+///   or there is a database error.
+// Actix handlers take everything they need as arguments
+#[allow(clippy::too_many_arguments)]
+pub async fn replace_user(
+    principal: Principal,
+    field_rules: FieldRules,
+    flags: FeatureFlags,
+    id: web::Path<i32>,
+    user: web::Json<ScimUserRequest>,
+    approval: web::Data<ChangeApproval>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ScimError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let mut conn = transaction.conn()?;
+    let conn = &mut *conn;
+    let id = id.into_inner();
+    let reactivate = user.active == Some(true);
+    let mut contact = user.into_inner().into_contact();
+    // Keep the original source of contacts that existed before the sync
+    contact.source = None;
+
+    let current = contacts::table.find(id).first::<Contact>(conn)?;
+    let editor = ContactEditor {
+        principal: &principal,
+        field_rules: &field_rules,
+        approval: &approval,
+        flags: &flags,
+    };
+    let mut updated = match editor.apply(conn, &current, contact)? {
+        Edit::Updated(updated) => *updated,
+        Edit::Proposed(change) => return Ok(HttpResponse::Accepted().json(change)),
+    };
+
+    // An inactive contact that is active again starts over as a lead
+    if reactivate && updated.status == "inactive" {
+        updated = diesel::update(contacts::table.find(id))
+            .set(contacts::status.eq("lead"))
+            .get_result::<Contact>(conn)?;
+    }
+
+    Ok(scim_response(
+        StatusCode::OK,
+        ScimUser::from(field_rules.masked(updated)),
+    ))
+}
+
+/// Handles deleting a contact through SCIM.
+///
+/// This endpoint is protected and requires a valid JWT with the `admin` role.
+///
+/// # Arguments
+///
+/// * `principal` - The authenticated caller, used to check the role.
+/// * `id` - The ID of the contact, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with status 204.
+/// * `Err(ScimError)` if the user is not an admin, the contact is not found or there is a database error.
+pub async fn delete_user(
+    principal: Principal,
+    id: web::Path<i32>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ScimError> {
+    principal.require_role(ADMIN_ROLE)?;
+    let deleted =
+        diesel::delete(contacts::table.find(id.into_inner())).execute(&mut *transaction.conn()?)?;
+    if deleted == 0 {
+        return Err(ApiError::NotFound.into());
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
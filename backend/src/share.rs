@@ -0,0 +1,306 @@
+// backend/src/share.rs
+// This file contains public share links for single contacts.
+// It exists so a user can share a read-only JSON or vCard view of a contact with people who have no account.
+// RELEVANT FILES: backend/src/models.rs, backend/src/vcard.rs, backend/src/main.rs
+
+use crate::auth::Principal;
+use crate::error::ApiError;
+use crate::feature_flags::FeatureFlags;
+use crate::field_permissions::FieldRules;
+use crate::identifiers::ContactKey;
+use crate::links::ExternalBaseUrl;
+use crate::models::NewShareLink;
+use crate::transaction::Transaction;
+use crate::vcard::{to_vcard, VCARD_CONTENT_TYPE};
+use crate::{establish_connection, unix_now};
+use actix_web::{web, HttpRequest, HttpResponse};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use diesel::prelude::*;
+use ring::hmac;
+use ring::rand::SystemRandom;
+use serde::{Deserialize, Serialize};
+
+/// The fields a share link serves, the same ones as its vCard.
+const SHARED_FIELDS: [&str; 4] = ["first_name", "last_name", "email", "phone_number"];
+
+/// How long a share link is valid when the caller does not say.
+const DEFAULT_TTL_HOURS: i64 = 24 * 7;
+
+/// The longest lifetime a share link may have (90 days).
+const MAX_TTL_HOURS: i64 = 24 * 90;
+
+/// Signs and verifies share link tokens.
+///
+/// A token has the form `<link id>.<expires at>.<signature>`.
+/// The signature is an HMAC-SHA256 of the first two parts.
+pub struct ShareLinkSigner {
+    key: hmac::Key,
+}
+
+impl ShareLinkSigner {
+    /// Creates a signer from the `SHARE_LINK_SECRET` environment variable.
+    ///
+    /// If the variable is not set, a random key is generated.
+    /// Links then stop working when the server restarts.
+    ///
+    /// # Returns
+    ///
+    /// * A new `ShareLinkSigner`.
+    pub fn from_env() -> Self {
+        let key = match std::env::var("SHARE_LINK_SECRET") {
+            Ok(secret) => hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+            Err(_) => {
+                log::warn!("SHARE_LINK_SECRET is not set, share links will not survive a restart.");
+                hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+                    .expect("Failed to generate a share link key")
+            }
+        };
+        Self { key }
+    }
+
+    /// Builds a signed token for a share link.
+    ///
+    /// # Arguments
+    ///
+    /// * `link_id` - The ID of the share link.
+    /// * `expires_at` - When the link expires, as a Unix timestamp.
+    ///
+    /// # Returns
+    ///
+    /// * The signed token.
+    fn sign(&self, link_id: i32, expires_at: i64) -> String {
+        let payload = format!("{}.{}", link_id, expires_at);
+        let signature = hmac::sign(&self.key, payload.as_bytes());
+        format!("{}.{}", payload, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+    }
+
+    /// Checks a token's signature and expiry time.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The token from the URL.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(link_id)` if the token is genuine and not expired.
+    /// * `None` otherwise.
+    fn verify(&self, token: &str) -> Option<i32> {
+        let (payload, signature) = token.rsplit_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        hmac::verify(&self.key, payload.as_bytes(), &signature).ok()?;
+
+        let (link_id, expires_at) = payload.split_once('.')?;
+        let expires_at: i64 = expires_at.parse().ok()?;
+        if expires_at <= unix_now() {
+            return None;
+        }
+        link_id.parse().ok()
+    }
+}
+
+/// Query parameters for creating a share link.
+#[derive(Deserialize)]
+pub struct ShareLinkQuery {
+    /// How many hours the link should stay valid.
+    pub ttl_hours: Option<i64>,
+}
+
+/// Query parameters for reading a shared contact.
+#[derive(Deserialize)]
+pub struct SharedContactQuery {
+    /// The output format: `json` (default) or `vcard`.
+    pub format: Option<String>,
+}
+
+/// The public view of a shared contact: the name, email and phone number, like the vCard.
+///
+/// Owners, statuses and the other fields are for users of the address book only.
+#[derive(Queryable, Serialize)]
+pub struct SharedContact {
+    /// The first name of the contact.
+    pub first_name: String,
+    /// The last name of the contact.
+    pub last_name: String,
+    /// The email address of the contact.
+    pub email: String,
+    /// The phone number of the contact.
+    pub phone_number: String,
+}
+
+/// The response body returned when a share link is created.
+#[derive(Serialize)]
+pub struct ShareLinkResponse {
+    /// The ID of the share link, used to revoke it.
+    pub id: i32,
+    /// The signed token.
+    pub token: String,
+    /// The public URL that serves the contact.
+    pub url: String,
+    /// When the link expires, as a Unix timestamp in seconds.
+    pub expires_at: i64,
+}
+
+/// Handles the creation of a share link for a contact.
+///
+/// This endpoint is protected and requires a valid JWT.
+/// Links that exist stop working while share links are switched off for the workspace they were made in.
+/// A link serves the fields of `SharedContact` to anyone, so only users who may read all of them can create one.
+///
+/// # Arguments
+///
+/// * `field_rules` - The fields the user may not read or write, from the roles in the JWT.
+/// * `flags` - The feature flags of the caller's workspace, which can switch share links off.
+/// * `req` - The HTTP request, used to build the public URL.
+/// * `id` - The ID or UUID of the contact to share, from the URL path.
+/// * `query` - The optional lifetime of the link in hours.
+/// * `signer` - The signer for share link tokens.
+/// * `base_url` - The external base URL for the public URL.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the link ID, token, URL and expiry time.
+/// * `Err(ApiError::Forbidden)` if share links are switched off or the user may not read a shared field.
+/// * `Err(ApiError)` if the contact is not found or there is a database error.
+// Actix handlers take everything they need as arguments
+#[allow(clippy::too_many_arguments)]
+pub async fn create_share_link(
+    field_rules: FieldRules,
+    flags: FeatureFlags,
+    req: HttpRequest,
+    id: web::Path<ContactKey>,
+    query: web::Query<ShareLinkQuery>,
+    signer: web::Data<ShareLinkSigner>,
+    base_url: web::Data<ExternalBaseUrl>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    use crate::schema::{contacts, share_links};
+
+    flags.require("share_links")?;
+    for field in SHARED_FIELDS {
+        field_rules.check_readable(field)?;
+    }
+    let mut conn = transaction.conn()?;
+    let contact_id = id.resolve(&mut conn)?;
+
+    // Make sure the contact exists before handing out a link to it
+    contacts::table
+        .find(contact_id)
+        .select(contacts::id)
+        .first::<i32>(&mut *conn)?;
+
+    let ttl_hours = query
+        .ttl_hours
+        .unwrap_or(DEFAULT_TTL_HOURS)
+        .clamp(1, MAX_TTL_HOURS);
+    let expires_at = unix_now() + ttl_hours * 3600;
+
+    let link_id = diesel::insert_into(share_links::table)
+        .values(&NewShareLink {
+            contact_id,
+            expires_at,
+            workspace: flags.workspace().to_string(),
+        })
+        .returning(share_links::id)
+        .get_result::<i32>(&mut *conn)?;
+
+    let token = signer.sign(link_id, expires_at);
+    let url = format!("{}/api/public/share/{}", base_url.resolve(&req), token);
+
+    Ok(HttpResponse::Ok().json(ShareLinkResponse {
+        id: link_id,
+        token,
+        url,
+        expires_at,
+    }))
+}
+
+/// Handles revoking a share link by its ID.
+///
+/// This endpoint is protected and requires a valid JWT.
+///
+/// # Arguments
+///
+/// * `_principal` - The authenticated caller.
+/// * `id` - The ID of the share link to revoke, from the URL path.
+/// * `transaction` - The transaction of the request, committed when the response is a success.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with a success message if the link is revoked.
+/// * `Err(ApiError)` if the link is not found or there is a database error.
+pub async fn revoke_share_link(
+    _principal: Principal,
+    id: web::Path<i32>,
+    transaction: Transaction,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = transaction.conn()?;
+
+    let deleted = diesel::delete(crate::schema::share_links::table.find(id.into_inner()))
+        .execute(&mut *conn)?;
+    if deleted == 0 {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(HttpResponse::Ok().body("Share link revoked successfully"))
+}
+
+/// Handles reading a shared contact through its public token.
+///
+/// This endpoint is public and does not require a JWT.
+/// Invalid, expired and revoked tokens all return 404, so nothing is leaked, and so do links
+/// of a workspace where share links are switched off.
+///
+/// # Arguments
+///
+/// * `token` - The signed share link token, from the URL path.
+/// * `query` - The requested output format.
+/// * `signer` - The signer for share link tokens.
+///
+/// # Returns
+///
+/// * `Ok(HttpResponse)` with the `SharedContact` as JSON or as a vCard.
+/// * `Err(ApiError)` if the token is not valid or there is a database error.
+pub async fn read_shared_contact(
+    token: web::Path<String>,
+    query: web::Query<SharedContactQuery>,
+    signer: web::Data<ShareLinkSigner>,
+) -> Result<HttpResponse, ApiError> {
+    use crate::schema::{contacts, share_links};
+
+    let link_id = signer.verify(&token).ok_or(ApiError::NotFound)?;
+
+    let mut conn = establish_connection()?;
+    // A missing row means the link was revoked
+    let (contact_id, workspace) = share_links::table
+        .find(link_id)
+        .filter(share_links::expires_at.gt(unix_now()))
+        .select((share_links::contact_id, share_links::workspace))
+        .first::<(i32, String)>(&mut conn)?;
+    // The flag is checked on every read, so switching it off stops the links that exist too
+    if !FeatureFlags::load(&mut conn, Some(&workspace))?.enabled("share_links") {
+        return Err(ApiError::NotFound);
+    }
+    let contact = contacts::table
+        .find(contact_id)
+        .select((
+            contacts::first_name,
+            contacts::last_name,
+            contacts::email,
+            contacts::phone_number,
+        ))
+        .first::<SharedContact>(&mut conn)?;
+
+    match query.format.as_deref() {
+        Some("vcard") => Ok(HttpResponse::Ok()
+            .content_type(VCARD_CONTENT_TYPE)
+            .body(to_vcard(
+                &contact.first_name,
+                &contact.last_name,
+                &contact.email,
+                &contact.phone_number,
+            ))),
+        _ => Ok(HttpResponse::Ok().json(contact)),
+    }
+}
@@ -0,0 +1,132 @@
+// backend/src/timeouts.rs
+// This file gives each request a time limit, shorter for reads than for writes and bulk operations.
+// It exists so a hung call, e.g. fetching the IDP's signing keys, cannot hold a worker forever.
+// RELEVANT FILES: backend/src/main.rs, backend/src/error.rs, backend/src/auth.rs
+
+use crate::error::Problem;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{web, Error as ActixWebError};
+use std::time::Duration;
+
+/// How long reads may take when not configured, in seconds.
+const DEFAULT_READ_TIMEOUT: u64 = 10;
+
+/// How long writes may take when not configured, in seconds.
+const DEFAULT_WRITE_TIMEOUT: u64 = 30;
+
+/// How long bulk operations may take when not configured, in seconds.
+const DEFAULT_BULK_TIMEOUT: u64 = 120;
+
+/// The paths of bulk operations, which work on many contacts in one request.
+const BULK_PATHS: [&str; 2] = ["/contacts/verify-email", "/contacts/batch-get"];
+
+/// The time limits of requests, shared as app data. `None` means no limit.
+#[derive(Clone, Copy)]
+pub struct RequestTimeouts {
+    /// The limit for GET and HEAD requests.
+    pub read: Option<Duration>,
+    /// The limit for requests that change data.
+    pub write: Option<Duration>,
+    /// The limit for bulk operations, whatever their method.
+    pub bulk: Option<Duration>,
+}
+
+/// Reads a limit in seconds from an environment variable, where `0` means no limit.
+fn limit_from_env(variable: &str, default: u64) -> Option<Duration> {
+    let seconds = std::env::var(variable)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default);
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+impl RequestTimeouts {
+    /// Reads the limits from the `READ_TIMEOUT_SECS`, `WRITE_TIMEOUT_SECS`
+    /// and `BULK_TIMEOUT_SECS` environment variables.
+    ///
+    /// # Returns
+    ///
+    /// * The `RequestTimeouts`. A limit of `0` switches the limit off.
+    pub fn from_env() -> Self {
+        Self {
+            read: limit_from_env("READ_TIMEOUT_SECS", DEFAULT_READ_TIMEOUT),
+            write: limit_from_env("WRITE_TIMEOUT_SECS", DEFAULT_WRITE_TIMEOUT),
+            bulk: limit_from_env("BULK_TIMEOUT_SECS", DEFAULT_BULK_TIMEOUT),
+        }
+    }
+
+    /// Picks the limit for a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method of the request.
+    /// * `path` - The path of the request.
+    ///
+    /// # Returns
+    ///
+    /// * The limit, or `None` if the request has none.
+    fn for_request(&self, method: &Method, path: &str) -> Option<Duration> {
+        if BULK_PATHS.iter().any(|bulk| path.ends_with(bulk)) {
+            self.bulk
+        } else if matches!(*method, Method::GET | Method::HEAD) {
+            self.read
+        } else {
+            self.write
+        }
+    }
+}
+
+/// Middleware that stops requests that take longer than their limit and answers them with 504.
+///
+/// When the limit is reached the handler is dropped at its next `.await`, and a transaction
+/// it had open is rolled back when its connection closes. A database call that is running
+/// cannot be interrupted, so the 504 is sent when it returns.
+/// Streamed bodies (NDJSON) are not limited once they have started.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service if it came in time.
+/// * An error with a 504 problem body otherwise.
+pub async fn enforce_timeout(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    let limit = req
+        .app_data::<web::Data<RequestTimeouts>>()
+        .and_then(|timeouts| timeouts.for_request(req.method(), req.path()));
+    let Some(limit) = limit else {
+        return next.call(req).await;
+    };
+
+    let method = req.method().clone();
+    let path = req.path().to_string();
+    match actix_web::rt::time::timeout(limit, next.call(req)).await {
+        Ok(res) => res,
+        Err(_) => {
+            log::warn!(
+                "{} {} took longer than {:?} and was stopped",
+                method,
+                path,
+                limit
+            );
+            let problem = Problem::new(
+                StatusCode::GATEWAY_TIMEOUT,
+                format!(
+                    "The request took longer than {} seconds. Try again later.",
+                    limit.as_secs()
+                ),
+                path,
+            );
+            Err(InternalError::from_response("request timed out", problem.response()).into())
+        }
+    }
+}
@@ -0,0 +1,185 @@
+// backend/src/transaction.rs
+// This file gives a request one database transaction, committed when the handler succeeds and rolled back otherwise.
+// It exists so handlers that make several database calls are atomic without writing their own transaction code.
+// RELEVANT FILES: backend/src/main.rs, backend/src/handlers.rs, backend/src/error.rs
+
+use crate::error::ApiError;
+use crate::establish_connection;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{Error as ActixWebError, FromRequest, HttpMessage, HttpRequest};
+use diesel::connection::{AnsiTransactionManager, TransactionManager};
+use diesel::sqlite::SqliteConnection;
+use std::cell::{RefCell, RefMut};
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+/// The place where the extractor leaves the transaction of a request for the middleware.
+///
+/// Its presence also marks a request that runs inside the transaction middleware.
+#[derive(Clone, Default)]
+struct TransactionSlot(Rc<RefCell<Option<Transaction>>>);
+
+/// A database connection with an open transaction, shared by everything that handles one request.
+///
+/// Handlers take it instead of calling `establish_connection`. The connection is opened and the
+/// transaction begun on the first call to `conn`, so a handler can validate its input or wait
+/// for another service first without holding a lock. When the response is a success the
+/// middleware commits, otherwise it rolls back. If a handler panics, the connection is closed
+/// without a commit, and SQLite rolls the transaction back.
+#[derive(Clone)]
+pub struct Transaction(Rc<TransactionState>);
+
+/// The connection of a `Transaction`, once it is opened.
+struct TransactionState {
+    /// Whether the request changes data, which decides how the transaction begins.
+    write: bool,
+    /// The connection with the open transaction, or `None` before the first `conn`.
+    conn: RefCell<Option<SqliteConnection>>,
+    /// What to run once the transaction is committed, in the order it was added.
+    after_commit: RefCell<Vec<Box<dyn FnOnce()>>>,
+}
+
+impl Transaction {
+    /// Opens a connection and begins a transaction.
+    ///
+    /// Requests that write take the write lock at once (`BEGIN IMMEDIATE`), so two writes
+    /// cannot both read and then fail when they try to write. Reads do not block each other.
+    ///
+    /// # Arguments
+    ///
+    /// * `write` - Whether the request changes data.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(SqliteConnection)` with the open transaction.
+    /// * `Err(ApiError)` if the database cannot be reached or stays locked.
+    fn begin(write: bool) -> Result<SqliteConnection, ApiError> {
+        let mut conn = establish_connection()?;
+        if write {
+            AnsiTransactionManager::begin_transaction_sql(&mut conn, "BEGIN IMMEDIATE")?;
+        } else {
+            AnsiTransactionManager::begin_transaction(&mut conn)?;
+        }
+        Ok(conn)
+    }
+
+    /// Returns the connection, and begins the transaction on first use.
+    ///
+    /// The borrow must not be held across an `.await`. Use `conn.transaction(...)` for a
+    /// nested transaction; `immediate_transaction` fails, because a transaction is already open.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(RefMut<SqliteConnection>)` with the connection.
+    /// * `Err(ApiError)` if the transaction cannot begin.
+    pub fn conn(&self) -> Result<RefMut<'_, SqliteConnection>, ApiError> {
+        let mut conn = self.0.conn.borrow_mut();
+        if conn.is_none() {
+            *conn = Some(Self::begin(self.0.write)?);
+        }
+        Ok(RefMut::map(conn, |conn| {
+            conn.as_mut().expect("the connection was opened above")
+        }))
+    }
+
+    /// Runs something once the transaction is committed, e.g. a background job that reads what
+    /// the request wrote. It is dropped if the transaction is rolled back.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - What to run after the commit.
+    pub fn after_commit(&self, hook: impl FnOnce() + 'static) {
+        self.0.after_commit.borrow_mut().push(Box::new(hook));
+    }
+
+    /// Takes the connection out, if the transaction was begun, to commit or roll it back.
+    fn take(&self) -> Option<SqliteConnection> {
+        self.0.conn.borrow_mut().take()
+    }
+}
+
+impl FromRequest for Transaction {
+    type Error = ActixWebError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    /// Returns the transaction of the request. Its connection is opened by `Transaction::conn`.
+    ///
+    /// Without the middleware nothing would commit, so that is reported as a server error.
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let Some(slot) = req.extensions().get::<TransactionSlot>().cloned() else {
+            log::error!(
+                "{} takes a Transaction but has no transaction middleware",
+                req.path()
+            );
+            return ready(Err(ErrorInternalServerError(
+                "The request has no transaction",
+            )));
+        };
+        let mut slot = slot.0.borrow_mut();
+        let transaction = slot.get_or_insert_with(|| {
+            let write = !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+            Transaction(Rc::new(TransactionState {
+                write,
+                conn: RefCell::new(None),
+                after_commit: RefCell::new(Vec::new()),
+            }))
+        });
+        ready(Ok(transaction.clone()))
+    }
+}
+
+/// Middleware that commits the transaction of a request when the response is a success (2xx)
+/// and rolls it back otherwise.
+///
+/// After the commit, the hooks the handler added with `Transaction::after_commit` run.
+/// Requests whose handler does not take a `Transaction`, or never used it, are passed on unchanged.
+///
+/// # Arguments
+///
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service.
+/// * `Err(ApiError)` if the commit fails, so the client does not see a success that was not saved.
+pub async fn transaction_per_request(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    // Errors from inner middleware come without the request, so keep a handle to the slot
+    let slot = TransactionSlot::default();
+    req.extensions_mut().insert(slot.clone());
+    let path = req.path().to_string();
+    let res = next.call(req).await;
+
+    let Some(transaction) = slot.0.borrow_mut().take() else {
+        return res;
+    };
+    let succeeded = res.as_ref().is_ok_and(|r| r.status().is_success());
+    if let Some(mut conn) = transaction.take() {
+        if succeeded {
+            if let Err(e) = AnsiTransactionManager::commit_transaction(&mut conn) {
+                log::error!("Could not commit the transaction of {}: {}", path, e);
+                // Leave no half-open transaction behind on a failed commit
+                let _ = AnsiTransactionManager::rollback_transaction(&mut conn);
+                return Err(ApiError::from(e).into());
+            }
+        } else if let Err(e) = AnsiTransactionManager::rollback_transaction(&mut conn) {
+            // The connection is closed next, which rolls back anyway
+            log::warn!("Could not roll back the transaction of {}: {}", path, e);
+        }
+    }
+    // The connection is closed by now, so the hooks see what was committed
+    let hooks = transaction.0.after_commit.take();
+    if succeeded {
+        for hook in hooks {
+            hook();
+        }
+    }
+    res
+}
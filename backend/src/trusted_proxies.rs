@@ -0,0 +1,404 @@
+// backend/src/trusted_proxies.rs
+// This file finds the address, scheme and host of the client behind the reverse proxies we trust, and parses the CIDR ranges used to name them.
+// It exists so address checks, rate limits and links use what the client sees, without letting anyone fake it with forwarding headers.
+// RELEVANT FILES: backend/src/ip_filter.rs, backend/src/links.rs, backend/src/base_path.rs
+
+use actix_web::http::header::{FORWARDED, HOST};
+use actix_web::HttpRequest;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// A range of addresses, e.g. `10.0.0.0/8` or `2001:db8::/32`. A bare address is a range of one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cidr {
+    /// The first address of the range.
+    network: IpAddr,
+    /// How many leading bits of an address must match the network.
+    prefix: u8,
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let invalid = || format!("'{}' is not an IP address or CIDR range", text);
+        let (address, prefix) = match text.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (text, None),
+        };
+        let network = IpAddr::from_str(address)
+            .map_err(|_| invalid())?
+            .to_canonical();
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(Self { network, prefix })
+    }
+}
+
+impl Cidr {
+    /// Tells whether an address is in the range. IPv4 addresses written as IPv6 (`::ffff:10.0.0.1`) count as IPv4.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Parses a comma-separated list of ranges, as given in an environment variable.
+///
+/// # Arguments
+///
+/// * `name` - The environment variable the list comes from, e.g. `ADMIN_ALLOW_CIDRS`, to name in the panic.
+/// * `list` - The ranges, e.g. `10.0.0.0/8, 192.168.1.1`.
+///
+/// # Returns
+///
+/// * The ranges. Empty if the list is.
+///
+/// # Panics
+///
+/// If a range is invalid, so a typo does not silently open or close access.
+pub fn cidrs_from_list(name: &str, list: &str) -> Vec<Cidr> {
+    list.split(',')
+        .filter(|cidr| !cidr.trim().is_empty())
+        .map(|cidr| {
+            cidr.parse()
+                .unwrap_or_else(|e| panic!("{} is invalid: {}", name, e))
+        })
+        .collect()
+}
+
+/// The entry of `TRUSTED_PROXIES` that trusts the proxy connected over the Unix socket.
+const UNIX_PEER: &str = "unix";
+
+/// The reverse proxies in front of the server, whose forwarding headers are believed.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<Cidr>,
+    /// Whether the peer of a Unix socket is a proxy. Such a peer has no address to check.
+    unix: bool,
+}
+
+impl TrustedProxies {
+    /// Reads the proxies from the `TRUSTED_PROXIES` environment variable, a comma-separated list of addresses and CIDR ranges.
+    /// The entry `unix` trusts the proxy that connects over the Unix socket, see `UNIX_SOCKET`.
+    ///
+    /// # Returns
+    ///
+    /// * The `TrustedProxies`. When the variable is not set, no proxy is trusted.
+    ///
+    /// # Panics
+    ///
+    /// If a range is invalid.
+    pub fn from_env() -> Self {
+        Self::parse(&std::env::var("TRUSTED_PROXIES").unwrap_or_default())
+            .unwrap_or_else(|e| panic!("TRUSTED_PROXIES is invalid: {}", e))
+    }
+
+    /// Parses a list of proxies like `unix, 10.0.0.0/8`.
+    fn parse(list: &str) -> Result<Self, String> {
+        let mut proxies = Self::default();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            if entry.eq_ignore_ascii_case(UNIX_PEER) {
+                proxies.unix = true;
+            } else {
+                proxies.ranges.push(entry.parse()?);
+            }
+        }
+        Ok(proxies)
+    }
+
+    /// Tells whether an address is one of the trusted proxies.
+    pub fn trusts(&self, ip: IpAddr) -> bool {
+        self.ranges.iter().any(|range| range.contains(ip))
+    }
+
+    /// Tells whether a request came through a trusted proxy, so its forwarding headers are believed.
+    ///
+    /// A request without a peer address came over the Unix socket, which only the `unix` entry trusts.
+    fn is_trusted(&self, req: &HttpRequest) -> bool {
+        match req.peer_addr() {
+            Some(addr) => self.trusts(addr.ip()),
+            None => self.unix,
+        }
+    }
+
+    /// Finds the address of the client that sent a request.
+    ///
+    /// Without trusted proxies this is the connecting peer. When the peer is a trusted proxy, the
+    /// addresses in `Forwarded` (or `X-Forwarded-For` without it) are read from the right, skipping
+    /// the trusted proxies, and the first other address is the client. Addresses further left were
+    /// added by the client and could be made up.
+    /// Over the Unix socket there is no peer address, so the client is the one the proxy forwarded,
+    /// when `unix` is trusted.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The incoming request.
+    ///
+    /// # Returns
+    ///
+    /// * The address of the client, or `None` if it is not known: over a Unix socket that is not
+    ///   trusted or that forwarded no address, and in tests.
+    pub fn client_ip(&self, req: &HttpRequest) -> Option<IpAddr> {
+        let peer = match req.peer_addr() {
+            Some(addr) => addr.ip().to_canonical(),
+            None if self.unix => return self.forwarded_client(req),
+            None => return None,
+        };
+        if !self.trusts(peer) {
+            return Some(peer);
+        }
+        self.forwarded_client(req).or(Some(peer))
+    }
+
+    /// Finds the client among the addresses trusted proxies forwarded: the first one from the right that is not a proxy.
+    fn forwarded_client(&self, req: &HttpRequest) -> Option<IpAddr> {
+        let forwarded = forwarded_for(req);
+        // If every hop is a trusted proxy, the leftmost one is as close to the client as we know
+        forwarded
+            .iter()
+            .rev()
+            .find(|ip| !self.trusts(**ip))
+            .or(forwarded.first())
+            .copied()
+    }
+
+    /// Finds the scheme and host clients used to reach the server, e.g. `https://contacts.example.com`.
+    ///
+    /// Behind a trusted proxy they come from `Forwarded` (`proto` and `host`) or from
+    /// `X-Forwarded-Proto` and `X-Forwarded-Host`, as the first proxy set them. Otherwise they are
+    /// the scheme of the connection and the `Host` header, so clients cannot change the links with headers.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - The incoming request.
+    ///
+    /// # Returns
+    ///
+    /// * The scheme and host, without a trailing slash.
+    pub fn origin(&self, req: &HttpRequest) -> String {
+        let trusted = self.is_trusted(req);
+        let scheme = trusted
+            .then(|| forwarded_value(req, "proto", "x-forwarded-proto"))
+            .flatten()
+            .filter(|scheme| scheme == "http" || scheme == "https")
+            .unwrap_or_else(|| {
+                let secure = req.app_config().secure();
+                if secure { "https" } else { "http" }.to_string()
+            });
+        let host = trusted
+            .then(|| forwarded_value(req, "host", "x-forwarded-host"))
+            .flatten()
+            .or_else(|| {
+                req.headers()
+                    .get(HOST)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string)
+            })
+            .filter(|host| is_host(host))
+            .unwrap_or_else(|| req.app_config().host().to_string());
+        format!("{}://{}", scheme, host)
+    }
+}
+
+/// Tells whether a text can be a host with an optional port, so a header cannot put anything else in a link.
+fn is_host(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '[' | ']'))
+}
+
+/// Reads the `Forwarded` headers (RFC 7239): one list of parameters per proxy, in the order the proxies added them.
+/// Names are in lowercase and quotes are removed, e.g. `[("for", "192.0.2.60"), ("proto", "https")]`.
+fn forwarded_elements(req: &HttpRequest) -> Vec<Vec<(String, String)>> {
+    req.headers()
+        .get_all(FORWARDED)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|element| {
+            element
+                .split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(name, value)| {
+                    (
+                        name.trim().to_ascii_lowercase(),
+                        value.trim().trim_matches('"').to_string(),
+                    )
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Reads the address of a `for` node of `Forwarded`, e.g. `192.0.2.43:47011` or `[2001:db8::1]:4711`.
+/// Hidden nodes like `unknown` or `_proxy1` have no address.
+fn node_address(node: &str) -> Option<IpAddr> {
+    let address = match node.strip_prefix('[') {
+        Some(rest) => rest.split(']').next()?,
+        // An IPv4 address may have a port, an IPv6 address without brackets may not
+        None if node.matches(':').count() == 1 => node.split(':').next()?,
+        None => node,
+    };
+    IpAddr::from_str(address).ok().map(|ip| ip.to_canonical())
+}
+
+/// Reads the addresses the proxies forwarded for, from `Forwarded`, or from `X-Forwarded-For` when there is no `Forwarded`.
+fn forwarded_for(req: &HttpRequest) -> Vec<IpAddr> {
+    let elements = forwarded_elements(req);
+    if !elements.is_empty() {
+        return elements
+            .iter()
+            .flat_map(|element| element.iter())
+            .filter(|(name, _)| name == "for")
+            .filter_map(|(_, node)| node_address(node))
+            .collect();
+    }
+    req.headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|address| IpAddr::from_str(address.trim()).ok())
+        .map(|ip| ip.to_canonical())
+        .collect()
+}
+
+/// Reads a value the first proxy forwarded, from a parameter of `Forwarded` or else from an `X-Forwarded-*` header.
+fn forwarded_value(req: &HttpRequest, parameter: &str, header: &str) -> Option<String> {
+    let from_forwarded = forwarded_elements(req)
+        .into_iter()
+        .flatten()
+        .find(|(name, _)| name == parameter)
+        .map(|(_, value)| value);
+    from_forwarded
+        .or_else(|| {
+            req.headers()
+                .get(header)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split(',').next())
+                .map(|value| value.trim().to_string())
+        })
+        .map(|value| value.to_ascii_lowercase())
+        .filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cidr, TrustedProxies};
+    use actix_web::test::TestRequest;
+    use std::net::IpAddr;
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn ranges_match_their_addresses() {
+        let range: Cidr = "10.1.0.0/16".parse().unwrap();
+        assert!(range.contains(ip("10.1.200.3")));
+        assert!(!range.contains(ip("10.2.0.1")));
+        // IPv4 written as IPv6, as dual-stack sockets report it
+        assert!(range.contains(ip("::ffff:10.1.0.9")));
+
+        let single: Cidr = "192.168.1.5".parse().unwrap();
+        assert!(single.contains(ip("192.168.1.5")));
+        assert!(!single.contains(ip("192.168.1.6")));
+
+        let v6: Cidr = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(ip("2001:db8:1::1")));
+        assert!(!v6.contains(ip("2001:db9::1")));
+        assert!("0.0.0.0/0".parse::<Cidr>().unwrap().contains(ip("8.8.8.8")));
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("intranet".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn forwarded_addresses_count_only_from_trusted_proxies() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+
+        // Anyone can send the header, so it is ignored from an untrusted peer
+        let req = TestRequest::default()
+            .peer_addr("203.0.113.7:5000".parse().unwrap())
+            .insert_header(("X-Forwarded-For", "10.0.0.1"))
+            .to_http_request();
+        assert_eq!(proxies.client_ip(&req), Some(ip("203.0.113.7")));
+
+        // The client made up the leftmost address; the proxies added the rest
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.2:5000".parse().unwrap())
+            .insert_header(("X-Forwarded-For", "10.9.9.9, 198.51.100.4, 10.0.0.3"))
+            .to_http_request();
+        assert_eq!(proxies.client_ip(&req), Some(ip("198.51.100.4")));
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.2:5000".parse().unwrap())
+            .to_http_request();
+        assert_eq!(proxies.client_ip(&req), Some(ip("10.0.0.2")));
+    }
+
+    #[test]
+    fn links_use_the_forwarded_origin_only_from_trusted_proxies() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.2:5000".parse().unwrap())
+            .insert_header(("Host", "10.0.0.9:8081"))
+            .insert_header((
+                "Forwarded",
+                "for=\"[2001:db8::1]:4711\";proto=https;host=contacts.example.com, for=10.0.0.3",
+            ))
+            .to_http_request();
+        assert_eq!(proxies.origin(&req), "https://contacts.example.com");
+        assert_eq!(proxies.client_ip(&req), Some(ip("2001:db8::1")));
+
+        let req = TestRequest::default()
+            .peer_addr("203.0.113.7:5000".parse().unwrap())
+            .insert_header(("Host", "api.example.com"))
+            .insert_header(("X-Forwarded-Proto", "https"))
+            .insert_header(("X-Forwarded-Host", "evil.example.com"))
+            .to_http_request();
+        assert_eq!(proxies.origin(&req), "http://api.example.com");
+    }
+
+    #[test]
+    fn the_proxy_on_the_unix_socket_is_trusted_only_when_listed() {
+        // Requests over a Unix socket have no peer address
+        let req = TestRequest::default()
+            .insert_header(("Host", "localhost"))
+            .insert_header(("X-Forwarded-For", "198.51.100.4"))
+            .insert_header(("X-Forwarded-Proto", "https"))
+            .insert_header(("X-Forwarded-Host", "contacts.example.com"))
+            .to_http_request();
+
+        let proxies = TrustedProxies::parse("unix, 10.0.0.0/8").unwrap();
+        assert_eq!(proxies.client_ip(&req), Some(ip("198.51.100.4")));
+        assert_eq!(proxies.origin(&req), "https://contacts.example.com");
+
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+        assert_eq!(proxies.client_ip(&req), None);
+        assert_eq!(proxies.origin(&req), "http://localhost");
+
+        assert!(TrustedProxies::parse("unix-socket").is_err());
+    }
+}
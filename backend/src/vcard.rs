@@ -0,0 +1,55 @@
+// backend/src/vcard.rs
+// This file turns contacts into vCard 4.0 text (RFC 6350).
+// It exists so contacts can be shared with address book apps that understand .vcf files.
+// RELEVANT FILES: backend/src/share.rs, backend/src/me.rs
+
+/// The content type used for vCard responses.
+pub const VCARD_CONTENT_TYPE: &str = "text/vcard; charset=utf-8";
+
+/// Escapes a value so it can be placed inside a vCard property.
+///
+/// Backslashes, commas, semicolons and newlines have a special meaning in vCards.
+///
+/// # Arguments
+///
+/// * `value` - The raw text value.
+///
+/// # Returns
+///
+/// * The escaped text.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace('\n', "\\n")
+}
+
+/// Renders a person as a vCard 4.0 document.
+///
+/// # Arguments
+///
+/// * `first_name` - The first name of the person.
+/// * `last_name` - The last name of the person.
+/// * `email` - The email address of the person.
+/// * `phone_number` - The phone number of the person.
+///
+/// # Returns
+///
+/// * The vCard text, with CRLF line endings as the standard requires.
+pub fn to_vcard(first_name: &str, last_name: &str, email: &str, phone_number: &str) -> String {
+    let first_name = escape(first_name);
+    let last_name = escape(last_name);
+    [
+        "BEGIN:VCARD".to_string(),
+        "VERSION:4.0".to_string(),
+        // N is "family;given;additional;prefixes;suffixes"
+        format!("N:{};{};;;", last_name, first_name),
+        format!("FN:{} {}", first_name, last_name),
+        format!("EMAIL:{}", escape(email)),
+        format!("TEL:{}", escape(phone_number)),
+        "END:VCARD".to_string(),
+        String::new(),
+    ]
+    .join("\r\n")
+}
@@ -0,0 +1,96 @@
+// backend/src/versioning.rs
+// This file negotiates the API version from the URL or the `Accept` header.
+// It exists so the current payloads stay stable under `/api/v1` while breaking changes can land in a later version.
+// RELEVANT FILES: backend/src/main.rs, backend/src/error.rs, backend/src/handlers.rs
+
+use crate::error::ApiError;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue, ACCEPT};
+use actix_web::middleware::Next;
+use actix_web::Error as ActixWebError;
+
+/// The API versions this server can serve.
+///
+/// When breaking model changes land (e.g. contacts with many email addresses),
+/// they get a new `/api/v2` scope in `main.rs` and their version is added here.
+pub const SUPPORTED_VERSIONS: [u32; 1] = [1];
+
+/// The vendor media type prefix clients use to ask for a version, e.g. `application/vnd.contacts.v1+json`.
+const MEDIA_TYPE_PREFIX: &str = "application/vnd.contacts.v";
+
+/// The response header that tells the client which version answered.
+const VERSION_HEADER: HeaderName = HeaderName::from_static("api-version");
+
+/// Finds the version a client asked for in its `Accept` header.
+///
+/// # Arguments
+///
+/// * `accept` - The value of the `Accept` header.
+///
+/// # Returns
+///
+/// * `Some(Ok(version))` if a vendor media type with a number was found.
+/// * `Some(Err(()))` if a vendor media type was found, but its version is not a number.
+/// * `None` if the header does not ask for a version.
+fn requested_version(accept: &str) -> Option<Result<u32, ()>> {
+    accept
+        .split(',')
+        // Drop parameters like `;q=0.9`
+        .map(|media_type| media_type.split(';').next().unwrap_or("").trim())
+        .find_map(|media_type| media_type.strip_prefix(MEDIA_TYPE_PREFIX))
+        .map(|rest| {
+            rest.strip_suffix("+json")
+                .unwrap_or(rest)
+                .parse::<u32>()
+                .map_err(|_| ())
+        })
+}
+
+/// Middleware that serves a fixed API version and checks the `Accept` header against it.
+///
+/// Requests without a vendor media type get the version of the scope.
+/// A request for another version is rejected, so clients never get a payload they did not ask for.
+///
+/// # Arguments
+///
+/// * `version` - The version served by the scope this middleware wraps.
+/// * `req` - The incoming request.
+/// * `next` - The rest of the middleware chain.
+///
+/// # Returns
+///
+/// * The response of the next service, with an `API-Version` header.
+/// * `Err(ApiError::NotAcceptable)` if the client asked for another version.
+pub async fn negotiate_version(
+    version: u32,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, ActixWebError> {
+    let accept = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("");
+
+    match requested_version(accept) {
+        None => {}
+        Some(Ok(requested)) if requested == version => {}
+        Some(_) => {
+            let supported: Vec<String> = SUPPORTED_VERSIONS
+                .iter()
+                .map(|v| format!("{}{}+json", MEDIA_TYPE_PREFIX, v))
+                .collect();
+            return Err(ApiError::NotAcceptable(format!(
+                "This API version is not available here. Supported media types: {}",
+                supported.join(", ")
+            ))
+            .into());
+        }
+    }
+
+    let mut res = next.call(req).await?;
+    res.headers_mut()
+        .insert(VERSION_HEADER, HeaderValue::from(version));
+    Ok(res)
+}
@@ -0,0 +1,111 @@
+// backend/src/xml.rs
+// This file writes JSON response bodies as XML, with element names that follow a fixed set of rules.
+// It exists for legacy consumers that can only ingest XML.
+// RELEVANT FILES: backend/src/formats.rs, backend/src/models.rs
+
+use serde_json::Value;
+
+/// Returns the singular of a collection name, used for the elements of a list.
+///
+/// `contacts` gives `contact`, `companies` gives `company`. Names without a plural `s`,
+/// like `missing` or `status`, give `item`.
+pub fn singular(name: &str) -> String {
+    if name.ends_with("ss") || name.ends_with("us") {
+        "item".to_string()
+    } else if let Some(stem) = name.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if let Some(stem) = name.strip_suffix('s').filter(|s| !s.is_empty()) {
+        stem.to_string()
+    } else {
+        "item".to_string()
+    }
+}
+
+/// Turns a JSON key into a valid XML element name.
+///
+/// Characters that XML does not allow in names become `_`, and names that would start with a digit,
+/// `-` or `.` get a leading `_`.
+fn element_name(key: &str) -> String {
+    let mut name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Escapes the characters that have a meaning in XML text.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes one value as an element.
+///
+/// Objects become child elements in key order, lists become one child per item named by
+/// `singular`, `null` becomes an empty element and other values become text.
+fn write_element(out: &mut String, name: &str, value: &Value) {
+    match value {
+        Value::Null => {
+            out.push_str(&format!("<{}/>", name));
+        }
+        Value::Object(object) => {
+            out.push_str(&format!("<{}>", name));
+            for (key, child) in object {
+                write_element(out, &element_name(key), child);
+            }
+            out.push_str(&format!("</{}>", name));
+        }
+        Value::Array(items) => {
+            out.push_str(&format!("<{}>", name));
+            let item_name = singular(name);
+            for item in items {
+                write_element(out, &item_name, item);
+            }
+            out.push_str(&format!("</{}>", name));
+        }
+        Value::String(text) => {
+            out.push_str(&format!("<{}>{}</{}>", name, escape(text), name));
+        }
+        Value::Bool(_) | Value::Number(_) => {
+            out.push_str(&format!("<{}>{}</{}>", name, value, name));
+        }
+    }
+}
+
+/// Writes a response body as an XML document.
+///
+/// Child elements are in alphabetical order of their keys, so the schema does not change
+/// when fields are reordered in the code.
+///
+/// # Arguments
+///
+/// * `root` - The name of the root element, e.g. `contacts` for a list or `contact` for one contact.
+/// * `value` - The JSON body.
+///
+/// # Returns
+///
+/// * The XML document.
+pub fn to_xml(root: &str, value: &Value) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    write_element(&mut out, &element_name(root), value);
+    out
+}
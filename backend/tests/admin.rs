@@ -0,0 +1,318 @@
+// backend/tests/admin.rs
+// This file tests the admin endpoints that change or inspect a running server: feature flags, maintenance mode, the log filter, reloaded settings, migrations and diagnostics.
+// It exists so these endpoints are checked for the admin role and for taking effect without a restart.
+// RELEVANT FILES: backend/src/feature_flags.rs, backend/src/maintenance.rs, backend/src/log_level.rs, backend/src/ip_filter.rs
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use contacts_core::test_support::{self, bearer, TestApi, TestDatabase};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// The admin routes of this file, with a method and a body that would change something.
+fn admin_requests() -> Vec<test::TestRequest> {
+    vec![
+        test::TestRequest::get().uri("/api/v1/admin/feature-flags/default"),
+        test::TestRequest::put()
+            .uri("/api/v1/admin/feature-flags/default/share_links")
+            .set_json(json!({ "enabled": false })),
+        test::TestRequest::delete().uri("/api/v1/admin/feature-flags/default/share_links"),
+        test::TestRequest::get().uri("/api/v1/admin/diagnostics"),
+        test::TestRequest::get().uri("/api/v1/admin/schema-version"),
+        test::TestRequest::put()
+            .uri("/api/v1/admin/maintenance")
+            .set_json(json!({ "read_only": true })),
+        test::TestRequest::post().uri("/api/v1/admin/config/reload"),
+        test::TestRequest::get().uri("/api/v1/admin/log-level"),
+        test::TestRequest::put()
+            .uri("/api/v1/admin/log-level")
+            .set_json(json!({ "directives": "trace" })),
+        test::TestRequest::delete().uri("/api/v1/admin/log-level"),
+    ]
+}
+
+#[actix_web::test]
+async fn only_admins_change_the_running_server() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    for req in admin_requests() {
+        let req = req.insert_header(bearer("alice", &[])).to_request();
+        let path = req.path().to_string();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", path);
+    }
+}
+
+#[actix_web::test]
+async fn a_flag_only_switches_its_own_workspace() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(json!({
+            "first_name": "Ada",
+            "last_name": "Lovelace",
+            "email": "ada@example.com",
+            "phone_number": "+44 20 7946 0000",
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let share = || {
+        test::TestRequest::post()
+            .uri("/api/v1/contacts/1/share-link")
+            .insert_header(bearer("alice", &[]))
+            .to_request()
+    };
+    let set_flag = |workspace: &str, enabled: bool| {
+        test::TestRequest::put()
+            .uri(&format!(
+                "/api/v1/admin/feature-flags/{}/share_links",
+                workspace
+            ))
+            .insert_header(bearer("root", &["admin"]))
+            .set_json(json!({ "enabled": enabled }))
+            .to_request()
+    };
+
+    // alice has no workspace claim, so the default workspace applies to her
+    let flags: Value = test::call_and_read_body_json(&app, set_flag("acme", false)).await;
+    let share_links = flags
+        .as_array()
+        .and_then(|flags| flags.iter().find(|f| f["flag"] == "share_links"))
+        .expect("the share_links flag");
+    assert_eq!(share_links["enabled"], false);
+    assert_eq!(share_links["updated_by"], "root");
+    let link: Value = test::call_and_read_body_json(&app, share()).await;
+    let read_link = || {
+        test::TestRequest::get()
+            .uri(&format!(
+                "/api/v1/public/share/{}",
+                link["token"].as_str().unwrap()
+            ))
+            .to_request()
+    };
+
+    assert_eq!(
+        test::call_service(&app, set_flag("default", false))
+            .await
+            .status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        test::call_service(&app, share()).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    // Links made before stop working too
+    assert_eq!(
+        test::call_service(&app, read_link()).await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    // Resetting brings the default back, which is on
+    let req = test::TestRequest::delete()
+        .uri("/api/v1/admin/feature-flags/default/share_links")
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert_eq!(
+        test::call_service(&app, share()).await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        test::call_service(&app, read_link()).await.status(),
+        StatusCode::OK
+    );
+
+    let req = test::TestRequest::put()
+        .uri("/api/v1/admin/feature-flags/default/webhooks")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(json!({ "enabled": true }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[actix_web::test]
+async fn a_failed_check_is_part_of_the_diagnostics() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    // A failed check is part of the answer, not an error
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/diagnostics")
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    let diagnostics: Value = test::call_and_read_body_json(&app, req).await;
+    let checks = diagnostics["checks"].as_array().expect("a list of checks");
+    let names: Vec<&str> = checks.iter().filter_map(|c| c["name"].as_str()).collect();
+    assert!(names.contains(&"database"), "{:?}", names);
+    assert!(names.contains(&"identity_provider"), "{:?}", names);
+    let failed = checks.iter().any(|c| c["status"] == "fail");
+    assert_eq!(diagnostics["status"], if failed { "fail" } else { "pass" });
+}
+
+#[actix_web::test]
+async fn the_schema_version_lists_every_migration_as_applied() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/schema-version")
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    let version: Value = test::call_and_read_body_json(&app, req).await;
+    // The test database is migrated when it is made, so nothing is left to run
+    assert_eq!(version["pending"], json!([]));
+    let applied = version["applied"].as_array().expect("a list of versions");
+    assert!(!applied.is_empty());
+    assert_eq!(version["current"], applied[applied.len() - 1]);
+}
+
+#[actix_web::test]
+async fn maintenance_mode_turns_away_changes_until_switched_off() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    let maintenance = |change: Value| {
+        test::TestRequest::put()
+            .uri("/api/v1/admin/maintenance")
+            .insert_header(bearer("root", &["admin"]))
+            .set_json(change)
+            .to_request()
+    };
+    let create = || {
+        test::TestRequest::post()
+            .uri("/api/v1/contacts")
+            .insert_header(bearer("alice", &[]))
+            .set_json(json!({
+                "first_name": "Ada",
+                "last_name": "Lovelace",
+                "email": "ada@example.com",
+                "phone_number": "+44 20 7946 0000",
+            }))
+            .to_request()
+    };
+
+    let change = json!({ "read_only": true, "banner": "Backup running", "retry_after": 600 });
+    let state: Value = test::call_and_read_body_json(&app, maintenance(change)).await;
+    assert_eq!(state["read_only"], true);
+    let res = test::try_call_service(&app, create()).await;
+    let res = res.map_or_else(|e| e.error_response(), |r| r.into_parts().1);
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.headers().get("retry-after").unwrap(), "600");
+
+    // Reads go on, and the readiness check shows the state
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get().uri("/readyz").to_request();
+    let ready: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(ready["maintenance"]["banner"], "Backup running");
+
+    // Only the fields that are sent change
+    let state: Value =
+        test::call_and_read_body_json(&app, maintenance(json!({ "banner": null }))).await;
+    assert_eq!(state["read_only"], true);
+    assert!(state["banner"].is_null());
+    let state: Value =
+        test::call_and_read_body_json(&app, maintenance(json!({ "read_only": false }))).await;
+    assert_eq!(state["read_only"], false);
+    assert_eq!(state["retry_after"], 600);
+    assert_eq!(
+        test::call_service(&app, create()).await.status(),
+        StatusCode::OK
+    );
+}
+
+#[actix_web::test]
+async fn a_reload_without_changes_applies_nothing() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/config/reload")
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    let reload: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(reload["changed"], json!([]));
+    assert_eq!(
+        reload["reloadable"],
+        json!(["CONTACT_REQUEST_LIMIT", "REPORT_CACHE_SECS"])
+    );
+}
+
+#[actix_web::test]
+async fn a_changed_log_filter_reverts_by_itself() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    let set = |directives: &str| {
+        test::TestRequest::put()
+            .uri("/api/v1/admin/log-level")
+            .insert_header(bearer("root", &["admin"]))
+            .set_json(json!({ "directives": directives, "revert_after_secs": 1 }))
+            .to_request()
+    };
+
+    let res = test::call_service(&app, set("contacts_core::auth=verbose")).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let state: Value =
+        test::call_and_read_body_json(&app, set("contacts_core::auth=debug,warn")).await;
+    assert_eq!(state["directives"], "contacts_core::auth=debug,warn");
+    assert_eq!(state["changed_by"], "root");
+    assert!(state["revert_at"].is_i64());
+
+    actix_web::rt::time::sleep(Duration::from_millis(1500)).await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/log-level")
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    let state: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(state["directives"], state["default_directives"]);
+    assert!(state["changed_by"].is_null());
+}
+
+#[actix_web::test]
+async fn admin_routes_are_only_reached_from_allowed_networks() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| {
+        TestApi::new()
+            .with_admin_networks("10.0.0.0/8", "10.6.6.0/24")
+            .configure(cfg)
+    }))
+    .await;
+    let from = |peer: &str, path: &str| {
+        test::TestRequest::get()
+            .uri(path)
+            .peer_addr(peer.parse().unwrap())
+            .insert_header(bearer("root", &["admin"]))
+            .to_request()
+    };
+
+    for (peer, status) in [
+        ("10.1.2.3:4000", StatusCode::OK),
+        ("10.6.6.6:4000", StatusCode::FORBIDDEN),
+        ("192.0.2.1:4000", StatusCode::FORBIDDEN),
+    ] {
+        for path in [
+            "/api/v1/admin/feature-flags/default",
+            "/api/admin/feature-flags/default",
+            // An encoded letter routes to the same handler, so it must be kept out the same way
+            "/api/v1/%61dmin/feature-flags/default",
+            "/metrics",
+        ] {
+            let res = test::try_call_service(&app, from(peer, path)).await;
+            let got = res.map_or_else(|e| e.error_response().status(), |r| r.status());
+            assert_eq!(got, status, "{} {}", peer, path);
+        }
+    }
+
+    // Other routes are open to every network
+    let res = test::call_service(&app, from("192.0.2.1:4000", "/api/v1/contacts")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
@@ -0,0 +1,70 @@
+// backend/tests/companies.rs
+// This file tests the company endpoints and the address checks end to end, with the built-in address provider.
+// It exists so the standardization of addresses on write and in the form check is checked the way clients see it.
+// RELEVANT FILES: backend/src/companies.rs, backend/src/addresses.rs, backend/src/test_support.rs
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use contacts_core::test_support::{self, bearer, TestDatabase};
+use serde_json::{json, Value};
+
+#[actix_web::test]
+async fn company_addresses_are_standardized_and_checked_when_saved() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/companies")
+        .insert_header(bearer("alice", &[]))
+        .set_json(json!({ "name": "Acme", "address": "12 main street ,  london  sw1a 1aa" }))
+        .to_request();
+    let company: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(company["address"], "12 Main St, London SW1A 1AA");
+    // The built-in provider only looks at the form, so it cannot tell a real address
+    assert_eq!(company["address_status"], "unknown");
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/v1/companies/{}", company["id"]))
+        .insert_header(bearer("alice", &[]))
+        .set_json(json!({ "name": "Acme", "address": "main street" }))
+        .to_request();
+    let company: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(company["address"], "Main St");
+    assert_eq!(company["address_status"], "undeliverable");
+
+    // The status is set by the server only
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/v1/companies/{}", company["id"]))
+        .insert_header(bearer("alice", &[]))
+        .set_json(json!({ "name": "Acme", "address_status": "deliverable" }))
+        .to_request();
+    let company: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(company["address"].is_null());
+    assert!(company["address_status"].is_null());
+}
+
+#[actix_web::test]
+async fn a_form_can_check_an_address_before_saving_it() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    let validate = |address: &str| {
+        test::TestRequest::post()
+            .uri("/api/v1/addresses/validate")
+            .insert_header(bearer("alice", &[]))
+            .set_json(json!({ "address": address }))
+            .to_request()
+    };
+
+    let check: Value =
+        test::call_and_read_body_json(&app, validate("1 infinite loop\ncupertino ca 95014")).await;
+    assert_eq!(check["standardized"], "1 Infinite Loop, Cupertino Ca 95014");
+    assert_eq!(check["deliverability"], "unknown");
+    assert_eq!(check["messages"], json!([]));
+
+    let check: Value = test::call_and_read_body_json(&app, validate("somewhere")).await;
+    assert_eq!(check["deliverability"], "undeliverable");
+    assert_eq!(check["messages"].as_array().map(Vec::len), Some(2));
+
+    let res = test::call_service(&app, validate("   ")).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,101 @@
+// backend/tests/contact_requests.rs
+// This file tests the public "contact me" form end to end, from a submission to an approved contact.
+// It exists so the secret, the limit per IP address and the admin review are checked the way clients see them.
+// RELEVANT FILES: backend/src/contact_requests.rs, backend/src/trusted_proxies.rs, backend/src/test_support.rs
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use contacts_core::test_support::{bearer, TestApi, TestDatabase};
+use serde_json::{json, Value};
+
+/// A submission of the public form from an IP address, with an optional secret.
+fn submit(token: Option<&str>, peer: &str) -> test::TestRequest {
+    let req = test::TestRequest::post()
+        .uri("/api/v1/public/contact-requests")
+        .peer_addr(peer.parse().unwrap())
+        .set_json(json!({
+            "first_name": "Ada",
+            "last_name": "Lovelace",
+            "email": "ada@example.com",
+            "phone_number": "+44 20 7946 0000",
+            "message": "Please call me back",
+        }));
+    match token {
+        Some(token) => req.insert_header(("X-Contact-Request-Token", token)),
+        None => req,
+    }
+}
+
+#[actix_web::test]
+async fn the_form_needs_the_secret_and_is_limited_per_address() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(
+        App::new().configure(|cfg| TestApi::new().with_contact_form("s3cret", 2).configure(cfg)),
+    )
+    .await;
+
+    for token in [None, Some("wrong")] {
+        let res = test::call_service(&app, submit(token, "10.0.0.1:4000").to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{:?}", token);
+    }
+
+    for _ in 0..2 {
+        let res =
+            test::call_service(&app, submit(Some("s3cret"), "10.0.0.1:4000").to_request()).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        // Nothing of the stored request is sent back
+        let receipt: Value = test::read_body_json(res).await;
+        assert_eq!(receipt, json!({ "status": "pending" }));
+    }
+    let res = test::call_service(&app, submit(Some("s3cret"), "10.0.0.1:4000").to_request()).await;
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // The limit is per address
+    let res = test::call_service(&app, submit(Some("s3cret"), "10.0.0.2:4000").to_request()).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+}
+
+#[actix_web::test]
+async fn an_admin_approves_a_request_into_a_contact() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(
+        App::new().configure(|cfg| TestApi::new().with_contact_form("s3cret", 5).configure(cfg)),
+    )
+    .await;
+    let res = test::call_service(&app, submit(Some("s3cret"), "10.0.0.1:4000").to_request()).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+
+    let list = |user: &str, roles: &[&str]| {
+        test::TestRequest::get()
+            .uri("/api/v1/contact-requests?status=pending")
+            .insert_header(bearer(user, roles))
+            .to_request()
+    };
+    let res = test::call_service(&app, list("alice", &[])).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let requests: Value = test::call_and_read_body_json(&app, list("root", &["admin"])).await;
+    assert_eq!(requests.as_array().map(Vec::len), Some(1));
+    assert_eq!(requests[0]["message"], "Please call me back");
+
+    let req = test::TestRequest::post()
+        .uri(&format!(
+            "/api/v1/contact-requests/{}/approve",
+            requests[0]["id"]
+        ))
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    let approved: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(approved["status"], "approved");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/contacts/{}", approved["contact_id"]))
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    let contact: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(contact["email"], "ada@example.com");
+    assert_eq!(contact["phone_number"], "+442079460000");
+    assert_eq!(contact["source"], "contact-request");
+
+    let requests: Value = test::call_and_read_body_json(&app, list("root", &["admin"])).await;
+    assert_eq!(requests, json!([]));
+}
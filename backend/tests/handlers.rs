@@ -0,0 +1,1216 @@
+// backend/tests/handlers.rs
+// This file tests the contact handlers end to end, from the HTTP request to the database and back.
+// It exists so changes to handlers, middleware and routing are checked together, the way clients see them.
+// RELEVANT FILES: backend/src/test_support.rs, backend/src/handlers.rs, backend/src/lib.rs
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App, HttpResponse};
+use contacts_core::cors::{self, CorsConfig};
+use contacts_core::schema::my_cards;
+use contacts_core::test_support::{self, bearer, TestApi, TestDatabase};
+use contacts_core::{API_V1_PATH, API_V1_ROUTES, SCIM_PATH, SCIM_ROUTES};
+use diesel::prelude::*;
+use serde_json::{json, Value};
+use std::rc::Rc;
+
+/// A contact body that passes validation.
+fn new_contact(email: &str) -> Value {
+    json!({
+        "first_name": "Ada",
+        "last_name": "Lovelace",
+        "email": email,
+        "phone_number": "+44 20 7946 0000",
+    })
+}
+
+#[actix_web::test]
+async fn reading_contacts_needs_a_token() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn a_created_contact_can_be_read_back() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(new_contact("ada@example.com"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let contacts: Value = test::call_and_read_body_json(&app, req).await;
+    let contacts = contacts.as_array().expect("a list of contacts");
+    assert_eq!(contacts.len(), 1);
+    assert_eq!(contacts[0]["email"], "ada@example.com");
+    assert_eq!(contacts[0]["status"], "lead");
+
+    let id = contacts[0]["id"].as_i64().expect("an id");
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/v1/contacts/{}", id))
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let contact: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(contact["first_name"], "Ada");
+}
+
+#[actix_web::test]
+async fn every_test_gets_an_empty_database() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts/count")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let count: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(count["count"], 0);
+}
+
+#[actix_web::test]
+async fn an_unknown_status_is_rejected() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let mut contact = new_contact("ada@example.com");
+    contact["status"] = json!("dormant");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(contact)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn a_missing_contact_is_not_found() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts/42")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn admin_routes_need_the_admin_role() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/schema-version")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/schema-version")
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn a_wrong_method_lists_the_allowed_ones() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::patch()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(res.headers().get("allow").unwrap(), "GET, POST");
+}
+
+#[actix_web::test]
+async fn errors_are_in_the_language_of_accept_language() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let mut contact = new_contact("ada@example.com");
+    contact["status"] = json!("dormant");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .insert_header(("Accept-Language", "sv-SE, en;q=0.5"))
+        .set_json(contact)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(res.headers().get("content-language").unwrap(), "sv");
+    let message: Value = test::read_body_json(res).await;
+    assert!(message
+        .as_str()
+        .expect("a message")
+        .starts_with("Status måste vara en av: "));
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/nowhere")
+        .insert_header(("Accept-Language", "de"))
+        .to_request();
+    let problem: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(problem["title"], "Nicht gefunden");
+    assert_eq!(problem["detail"], "Keine Route passt zu /api/v1/nowhere");
+}
+
+#[actix_web::test]
+async fn incoming_contacts_are_normalized_unless_the_rule_is_off() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::put()
+        .uri("/api/v1/admin/feature-flags/default/normalize_phones")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(json!({"enabled": false}))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let mut contact = new_contact("  Ada@Example.COM ");
+    contact["first_name"] = json!("ADA");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(contact)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let contacts: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(contacts[0]["first_name"], "Ada");
+    assert_eq!(contacts[0]["email"], "ada@example.com");
+    // Switched off above, so the number is kept as typed
+    assert_eq!(contacts[0]["phone_number"], "+44 20 7946 0000");
+}
+
+/// Adds a company and asks to count the companies after the commit; fails with `?fail=true`.
+async fn add_company_then_count(
+    transaction: contacts_core::transaction::Transaction,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    counted: web::Data<std::sync::Mutex<Vec<i64>>>,
+) -> Result<HttpResponse, contacts_core::error::ApiError> {
+    use contacts_core::schema::companies;
+
+    let mut conn = transaction.conn()?;
+    diesel::insert_into(companies::table)
+        .values(companies::name.eq("Acme"))
+        .execute(&mut *conn)?;
+    transaction.after_commit(move || {
+        let mut conn = contacts_core::establish_connection().unwrap();
+        let count = companies::table
+            .count()
+            .get_result::<i64>(&mut conn)
+            .unwrap();
+        counted.lock().unwrap().push(count);
+    });
+    Ok(if query.contains_key("fail") {
+        HttpResponse::BadRequest().finish()
+    } else {
+        HttpResponse::Ok().finish()
+    })
+}
+
+#[actix_web::test]
+async fn after_commit_hooks_only_run_once_the_data_is_committed() {
+    let _db = TestDatabase::new();
+    let counted = web::Data::new(std::sync::Mutex::new(Vec::<i64>::new()));
+    let app = test::init_service(
+        App::new()
+            .app_data(counted.clone())
+            .wrap(actix_web::middleware::from_fn(
+                contacts_core::transaction::transaction_per_request,
+            ))
+            .route("/companies", web::post().to(add_company_then_count)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/companies?fail=true")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+    assert!(counted.lock().unwrap().is_empty());
+
+    // A new connection sees the company, so the hook ran after the commit
+    let req = test::TestRequest::post().uri("/companies").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert_eq!(*counted.lock().unwrap(), vec![1]);
+}
+
+#[actix_web::test]
+async fn a_debug_capture_records_one_user_without_credentials() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/debug-capture")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(json!({"user_id": "alice", "duration_secs": 60}))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let mut contact = new_contact("ada@example.com");
+    contact["status"] = json!("dormant");
+    contact["password"] = json!("hunter2");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(contact)
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_REQUEST
+    );
+    // Someone else, so not captured
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("bob", &[]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // A stream is passed on as it comes, and its body is left out
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts/stream")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/debug-capture")
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    let exchanges = report["exchanges"].as_array().expect("a list");
+    assert_eq!(exchanges.len(), 2);
+    assert_eq!(exchanges[1]["path"], "/api/v1/contacts/stream");
+    assert_eq!(exchanges[1]["response_body"], "[not captured: streamed]");
+    assert_eq!(exchanges[0]["status"], 400);
+    assert_eq!(
+        exchanges[0]["request_headers"]["authorization"],
+        "[redacted]"
+    );
+    assert_eq!(exchanges[0]["request_body"]["password"], "[redacted]");
+    assert_eq!(exchanges[0]["request_body"]["email"], "ada@example.com");
+    assert!(exchanges[0]["response_body"]
+        .as_str()
+        .expect("a message")
+        .starts_with("Status must be one of: "));
+}
+
+#[actix_web::test]
+async fn public_routes_allow_any_origin_and_admin_routes_do_not() {
+    let _db = TestDatabase::new();
+    let config = Rc::new(CorsConfig::from_env());
+    let app = test::init_service(
+        App::new()
+            .wrap(actix_web::middleware::from_fn(move |req, next| {
+                cors::apply_cors(config.clone(), req, next)
+            }))
+            .configure(test_support::configure),
+    )
+    .await;
+
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/api/v1/public/contact-requests")
+        .insert_header(("Origin", "https://shop.example.com"))
+        .insert_header(("Access-Control-Request-Method", "POST"))
+        .insert_header(("Access-Control-Request-Headers", "content-type"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        res.headers().get("access-control-allow-origin").unwrap(),
+        "*"
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/admin/maintenance")
+        .insert_header(("Origin", "https://shop.example.com"))
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    // The middleware rejects it, and the server turns the error into the response
+    let error = test::try_call_service(&app, req).await.unwrap_err();
+    assert_eq!(error.error_response().status(), StatusCode::FORBIDDEN);
+
+    // The frontend may call the other routes, and read the pagination links
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts")
+        .insert_header(("Origin", "http://localhost:5173"))
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get("access-control-allow-origin").unwrap(),
+        "http://localhost:5173"
+    );
+    assert!(res
+        .headers()
+        .get("access-control-expose-headers")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .contains("Link"));
+}
+
+/// Sends `count` new contacts from a thread of its own, with an app of its own, and returns the statuses.
+fn create_contacts_on_a_thread(
+    database_url: String,
+    prefix: &'static str,
+    count: usize,
+    start: std::sync::Arc<std::sync::Barrier>,
+) -> std::thread::JoinHandle<Vec<StatusCode>> {
+    std::thread::spawn(move || {
+        test_support::use_database(&database_url);
+        actix_web::rt::System::new().block_on(async move {
+            let app = test::init_service(App::new().configure(test_support::configure)).await;
+            start.wait();
+            let mut statuses = Vec::new();
+            for i in 0..count {
+                let req = test::TestRequest::post()
+                    .uri("/api/v1/contacts")
+                    .insert_header(bearer("alice", &[]))
+                    .set_json(new_contact(&format!("{}{}@example.com", prefix, i)))
+                    .to_request();
+                statuses.push(test::call_service(&app, req).await.status());
+            }
+            statuses
+        })
+    })
+}
+
+#[actix_web::test]
+async fn the_memory_database_takes_concurrent_requests() {
+    test_support::use_database(contacts_core::MEMORY_DATABASE_URL);
+    contacts_core::migrations::run_migrations(
+        &mut contacts_core::establish_connection().expect("the in-memory database"),
+    )
+    .expect("migrated");
+
+    let start = std::sync::Arc::new(std::sync::Barrier::new(2));
+    let threads = [
+        create_contacts_on_a_thread(":memory:".into(), "left", 20, start.clone()),
+        create_contacts_on_a_thread(":memory:".into(), "right", 20, start),
+    ];
+    for thread in threads {
+        let statuses = thread.join().expect("the thread finished");
+        assert!(statuses.iter().all(|status| *status == StatusCode::OK));
+    }
+
+    // Both threads wrote to the one database of the process
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts/count")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let count: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(count["count"], 40);
+}
+
+#[actix_web::test]
+async fn a_write_waits_for_the_write_lock_instead_of_failing() {
+    let db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    // Another connection takes the write lock and keeps it for a moment
+    let (locked, is_locked) = std::sync::mpsc::channel();
+    let url = db.url().to_string();
+    let writer = std::thread::spawn(move || {
+        use diesel::connection::SimpleConnection;
+        test_support::use_database(&url);
+        let mut conn = contacts_core::establish_connection().expect("a connection");
+        conn.batch_execute("BEGIN IMMEDIATE")
+            .expect("the write lock");
+        locked.send(()).expect("the test is waiting");
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        conn.batch_execute("COMMIT").expect("committed");
+    });
+    is_locked.recv().expect("the lock was taken");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(new_contact("ada@example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    writer.join().expect("the writer finished");
+}
+
+#[actix_web::test]
+async fn every_edit_of_a_stored_contact_waits_for_approval() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(
+        App::new().configure(|cfg| TestApi::new().with_change_approval().configure(cfg)),
+    )
+    .await;
+
+    let mut contact = new_contact("ada@example.com");
+    contact["uuid"] = json!("5f0c1d9e-7a4b-4c2e-9d3f-1a2b3c4d5e6f");
+    contact["source"] = json!("crm");
+    contact["external_id"] = json!("42");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(&contact)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    contact["first_name"] = json!("Augusta");
+    // Sending the UUID again, and syncing by the external ID, are edits too
+    for req in [
+        test::TestRequest::post().uri("/api/v1/contacts"),
+        test::TestRequest::put().uri("/api/v1/contacts/by-external-id/crm/42"),
+    ] {
+        let req = req
+            .insert_header(bearer("alice", &[]))
+            .set_json(&contact)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts/5f0c1d9e-7a4b-4c2e-9d3f-1a2b3c4d5e6f")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let stored: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(stored["first_name"], "Ada");
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/pending-changes")
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    let changes: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(changes.as_array().map(Vec::len), Some(2));
+}
+
+#[actix_web::test]
+async fn routes_below_a_contact_take_its_uuid() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    let uuid = "5f0c1d9e-7a4b-4c2e-9d3f-1a2b3c4d5e6f";
+    let mut contact = new_contact("ada@example.com");
+    contact["uuid"] = json!(uuid);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(&contact)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    for req in [
+        test::TestRequest::post().uri(&format!("/api/v1/contacts/{}/archive", uuid)),
+        test::TestRequest::post().uri(&format!("/api/v1/contacts/{}/unarchive", uuid)),
+        test::TestRequest::post()
+            .uri(&format!("/api/v1/contacts/{}/consent", uuid))
+            .set_json(json!({ "email": true, "source": "signup form" })),
+        test::TestRequest::get().uri(&format!("/api/v1/contacts/{}/relationships", uuid)),
+        test::TestRequest::get().uri(&format!("/api/v1/contacts/{}/enrichments", uuid)),
+        test::TestRequest::post().uri(&format!("/api/v1/contacts/{}/share-link", uuid)),
+        test::TestRequest::delete().uri(&format!("/api/v1/contacts/{}/company", uuid)),
+    ] {
+        let req = req.insert_header(bearer("alice", &[])).to_request();
+        let path = req.path().to_string();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", path);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts/1")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let stored: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(stored["email_opt_in"], true);
+
+    // A UUID that no contact has is not found
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts/00000000-0000-4000-8000-000000000000/archive")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[actix_web::test]
+async fn the_demo_never_serves_stored_data() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    let mut contact = new_contact("stored.person@example.org");
+    contact["last_name"] = json!("Storedsson");
+    contact["source"] = json!("crm");
+    contact["owner"] = json!("alice");
+    for (uri, body) in [
+        ("/api/v1/contacts", contact),
+        ("/api/v1/companies", json!({ "name": "Storedsson AB" })),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .insert_header(bearer("root", &["admin"]))
+            .set_json(body)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+
+    let demo =
+        test::init_service(App::new().configure(|cfg| TestApi::new().with_demo().configure(cfg)))
+            .await;
+    let routes = [(API_V1_PATH, API_V1_ROUTES), (SCIM_PATH, SCIM_ROUTES)]
+        .into_iter()
+        .flat_map(|(scope, routes)| routes.iter().map(move |route| (scope, route)));
+    for (scope, route) in routes.filter(|(_, route)| route.method == "GET") {
+        // Every path parameter names the stored contact, company or workspace
+        let path = route
+            .path
+            .split('/')
+            .map(|segment| {
+                if segment.starts_with('{') {
+                    "1"
+                } else {
+                    segment
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let uri = format!("{}{}?q=Sto&dimension=source", scope, path);
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(bearer("root", &["admin"]))
+            .to_request();
+        // The demo answers what it does not serve from middleware, with an error
+        let res = match test::try_call_service(&demo, req).await {
+            Ok(res) => res,
+            Err(e) => {
+                assert_eq!(
+                    e.error_response().status(),
+                    StatusCode::FORBIDDEN,
+                    "{}",
+                    uri
+                );
+                continue;
+            }
+        };
+        let status = res.status();
+        let body = String::from_utf8_lossy(&test::read_body(res).await).to_string();
+
+        assert!(
+            !body.contains("Storedsson") && !body.contains("stored.person"),
+            "{} served stored data in demo mode: {} {}",
+            uri,
+            status,
+            body
+        );
+    }
+}
+
+#[actix_web::test]
+async fn hidden_fields_are_not_searched_reported_or_proposed() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| {
+        TestApi::new()
+            .with_hidden_fields("intern:email,last_name,source")
+            .with_change_approval()
+            .configure(cfg)
+    }))
+    .await;
+    let mut contact = new_contact("ada@example.com");
+    contact["source"] = json!("crm");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(&contact)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let autocomplete = |q: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/v1/contacts/autocomplete?q={}", q))
+            .insert_header(bearer("ivan", &["intern"]))
+            .to_request()
+    };
+    // The last name and the email are hidden, so they do not match
+    for q in ["love", "ada%40"] {
+        let suggestions: Value = test::call_and_read_body_json(&app, autocomplete(q)).await;
+        assert_eq!(suggestions, json!([]), "{}", q);
+    }
+    let suggestions: Value = test::call_and_read_body_json(&app, autocomplete("ad")).await;
+    assert_eq!(suggestions[0]["display_name"], "Ada ***");
+    assert_eq!(suggestions[0]["email"], "***");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/reports/custom")
+        .insert_header(bearer("ivan", &["intern"]))
+        .set_json(json!({
+            "name": "By source",
+            "definition": { "dimensions": ["status"], "measures": ["contacts"],
+                            "filters": [{ "field": "source", "op": "eq", "value": "crm" }] },
+        }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    let req = test::TestRequest::get()
+        .uri("/api/v1/reports/contacts-by-source")
+        .insert_header(bearer("ivan", &["intern"]))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::FORBIDDEN
+    );
+
+    // The edit keeps the stored email, which the pending change must not show
+    let mut edit = new_contact("***");
+    edit["first_name"] = json!("Augusta");
+    edit["last_name"] = json!("***");
+    let req = test::TestRequest::put()
+        .uri("/api/v1/contacts/1")
+        .insert_header(bearer("ivan", &["intern"]))
+        .set_json(edit)
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    let change: Value = test::read_body_json(res).await;
+    assert_eq!(change["changes"]["first_name"], "Augusta");
+    assert_eq!(change["changes"]["email"], "***");
+    assert_eq!(change["changes"]["last_name"], "***");
+}
+
+#[actix_web::test]
+async fn lists_cannot_be_filtered_on_hidden_fields() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| {
+        TestApi::new()
+            .with_hidden_fields("intern:status,phone_number")
+            .configure(cfg)
+    }))
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(new_contact("ada@example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let get = |uri: &str| {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header(bearer("ivan", &["intern"]))
+            .to_request()
+    };
+
+    for uri in [
+        "/api/v1/contacts?status=lead",
+        "/api/v1/contacts/count?status=lead",
+        "/api/v1/contacts/sample?status=lead",
+        "/api/v1/contacts/stream?status=lead",
+        // The score counts the hidden phone number
+        "/api/v1/contacts?min_quality=50",
+        "/api/v1/contacts/stats/by-status",
+    ] {
+        let res = test::call_service(&app, get(uri)).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{}", uri);
+    }
+
+    let contacts: Value =
+        test::call_and_read_body_json(&app, get("/api/v1/contacts?email_prefix=ada")).await;
+    assert_eq!(contacts[0]["status"], "***");
+    assert_eq!(contacts[0]["quality_score"], 0);
+}
+
+#[actix_web::test]
+async fn consent_is_only_changed_by_users_who_may_write_it() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| {
+        TestApi::new()
+            .with_read_only_fields("intern:sms_opt_in;auditor:consent_source")
+            .configure(cfg)
+    }))
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(new_contact("ada@example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let consent = |role: &str, change: Value| {
+        test::TestRequest::post()
+            .uri("/api/v1/contacts/1/consent")
+            .insert_header(bearer("ivan", &[role]))
+            .set_json(change)
+            .to_request()
+    };
+
+    for (role, change) in [
+        ("intern", json!({ "sms": true, "source": "phone call" })),
+        ("auditor", json!({ "email": true, "source": "signup form" })),
+    ] {
+        let res = test::call_service(&app, consent(role, change.clone())).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{} {}", role, change);
+    }
+
+    let change = json!({ "email": true, "source": "signup form" });
+    let contact: Value = test::call_and_read_body_json(&app, consent("intern", change)).await;
+    assert_eq!(contact["email_opt_in"], true);
+    assert_eq!(contact["sms_opt_in"], false);
+}
+
+#[actix_web::test]
+async fn an_email_check_shows_only_what_the_user_may_read() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| {
+        TestApi::new()
+            .with_hidden_fields("intern:email;auditor:email_status")
+            .configure(cfg)
+    }))
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(new_contact("ada@example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let check = |role: &str, body: Value| {
+        test::TestRequest::post()
+            .uri("/api/v1/contacts/verify-email")
+            .insert_header(bearer("ivan", &[role]))
+            .set_json(body)
+            .to_request()
+    };
+
+    // The check stores a status the auditor may not change
+    let res = test::call_service(&app, check("auditor", json!({}))).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let ids: Vec<i32> = (1..=501).collect();
+    let res = test::call_service(&app, check("intern", json!({ "ids": ids }))).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let report: Value = test::call_and_read_body_json(&app, check("intern", json!({}))).await;
+    assert_eq!(report["checked"], 1);
+    assert_eq!(report["results"][0]["email"], "***");
+    assert!(report["next_after_id"].is_null());
+}
+
+#[actix_web::test]
+async fn names_and_emails_outside_ascii_are_found() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    let mut contact = new_contact("Åsa@example.com");
+    contact["first_name"] = json!("Åsa");
+    contact["last_name"] = json!("Öberg");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(&contact)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // "Å" and "Öb", as typed
+    for q in ["%C3%85", "%C3%96b"] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/v1/contacts/autocomplete?q={}", q))
+            .insert_header(bearer("alice", &[]))
+            .to_request();
+        let suggestions: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(suggestions[0]["display_name"], "Åsa Öberg", "{}", q);
+    }
+
+    // "ÅSA@EXAMPLE.COM"
+    let req = test::TestRequest::get()
+        .uri("/api/v1/lookup?email=%C3%85SA%40EXAMPLE.COM")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let found: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(found["contact"]["last_name"], "Öberg");
+}
+
+#[actix_web::test]
+async fn reading_my_card_stores_nothing_until_it_is_saved() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    let stored_cards = || {
+        let mut conn = contacts_core::establish_connection().unwrap();
+        my_cards::table
+            .count()
+            .get_result::<i64>(&mut conn)
+            .unwrap()
+    };
+
+    // A read during maintenance must not write
+    let req = test::TestRequest::put()
+        .uri("/api/v1/admin/maintenance")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(json!({ "read_only": true }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri("/api/v1/me/card")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let card: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(card["email"], "alice@example.com");
+    assert_eq!(stored_cards(), 0);
+
+    let req = test::TestRequest::put()
+        .uri("/api/v1/admin/maintenance")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(json!({ "read_only": false }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::put()
+        .uri("/api/v1/me/card")
+        .insert_header(bearer("alice", &[]))
+        .set_json(json!({
+            "first_name": "Alice",
+            "last_name": "Liddell",
+            "email": "alice@example.org",
+            "phone_number": "654321",
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert_eq!(stored_cards(), 1);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/me/card")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let card: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(card["email"], "alice@example.org");
+}
+
+#[actix_web::test]
+async fn unsaved_contacts_are_scored_like_stored_ones() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    // Names, an unchecked email and a phone number, like every demo contact
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(new_contact("ada@example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts/1")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let stored: Value = test::call_and_read_body_json(&app, req).await;
+
+    let demo =
+        test::init_service(App::new().configure(|cfg| TestApi::new().with_demo().configure(cfg)))
+            .await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let demo_contacts: Value = test::call_and_read_body_json(&demo, req).await;
+    assert_eq!(demo_contacts[0]["email_status"], stored["email_status"]);
+    assert_eq!(demo_contacts[0]["quality_score"], stored["quality_score"]);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/reports/data-quality")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let summary: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        summary["average_score"].as_f64(),
+        stored["quality_score"].as_f64()
+    );
+    assert_eq!(summary["missing"]["email"], 0);
+    assert_eq!(summary["missing"]["unverified_email"], 1);
+}
+
+#[actix_web::test]
+async fn the_score_in_rust_matches_the_generated_column() {
+    use contacts_core::models::Contact;
+    use contacts_core::schema::contacts;
+
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/companies")
+        .insert_header(bearer("alice", &[]))
+        .set_json(json!({ "name": "Acme" }))
+        .to_request();
+    let company: Value = test::call_and_read_body_json(&app, req).await;
+    let company_id = company["id"].as_i64().map(|id| id as i32);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(new_contact("ada@example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // Every way each part of the score can go, written straight to the stored contact
+    let mut conn = contacts_core::establish_connection().unwrap();
+    for first_name in ["", "Ada"] {
+        for (email, email_status) in [
+            ("", "unknown"),
+            ("ada@example.com", "unknown"),
+            ("ada@example.com", "valid"),
+            ("ada@example.com", "invalid"),
+        ] {
+            for phone_number in ["", "+442079460000"] {
+                for company in [None, company_id] {
+                    for consent in [None, Some(1_700_000_000_i64)] {
+                        diesel::update(contacts::table)
+                            .set((
+                                contacts::first_name.eq(first_name),
+                                contacts::email.eq(email),
+                                contacts::email_status.eq(email_status),
+                                contacts::phone_number.eq(phone_number),
+                                contacts::company_id.eq(company),
+                                contacts::consent_timestamp.eq(consent),
+                            ))
+                            .execute(&mut conn)
+                            .unwrap();
+                        let stored = contacts::table.first::<Contact>(&mut conn).unwrap();
+                        assert_eq!(
+                            contacts_core::quality::score(&stored),
+                            stored.quality_score,
+                            "{:?}",
+                            stored
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[actix_web::test]
+async fn archived_contacts_are_left_out_until_asked_for() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| {
+        TestApi::new()
+            .with_hidden_fields("intern:archived_at")
+            .configure(cfg)
+    }))
+    .await;
+    for email in ["ada@example.com", "adele@example.com"] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/contacts")
+            .insert_header(bearer("alice", &[]))
+            .set_json(new_contact(email))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+    // Archiving sets archived_at, which interns may not change
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts/1/archive")
+        .insert_header(bearer("ivan", &["intern"]))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::FORBIDDEN
+    );
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts/1/archive")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let archived: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(archived["archived_at"].is_i64());
+
+    let emails = |uri: &'static str| {
+        let app = &app;
+        async move {
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header(bearer("alice", &[]))
+                .to_request();
+            let contacts: Value = test::call_and_read_body_json(app, req).await;
+            let mut emails: Vec<String> = contacts
+                .as_array()
+                .expect("a list of contacts")
+                .iter()
+                .map(|c| c["email"].as_str().unwrap_or_default().to_string())
+                .collect();
+            emails.sort();
+            emails
+        }
+    };
+    assert_eq!(emails("/api/v1/contacts").await, ["adele@example.com"]);
+    assert_eq!(
+        emails("/api/v1/contacts/autocomplete?q=ad").await,
+        ["adele@example.com"]
+    );
+    assert_eq!(
+        emails("/api/v1/contacts?include_archived=true").await,
+        ["ada@example.com", "adele@example.com"]
+    );
+    assert_eq!(
+        emails("/api/v1/contacts/autocomplete?q=ad&include_archived=true").await,
+        ["ada@example.com", "adele@example.com"]
+    );
+    // Still readable by its ID
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts/1")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts/1/unarchive")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let unarchived: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(unarchived["archived_at"].is_null());
+    assert_eq!(emails("/api/v1/contacts").await.len(), 2);
+}
+
+#[actix_web::test]
+async fn a_draft_finds_the_contacts_it_may_duplicate() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| {
+        TestApi::new()
+            .with_hidden_fields("intern:email")
+            .configure(cfg)
+    }))
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(new_contact("ada@example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let check_as = |roles: &[&str], draft: Value| {
+        test::TestRequest::post()
+            .uri("/api/v1/contacts/check-duplicate")
+            .insert_header(bearer("alice", roles))
+            .set_json(draft)
+            .to_request()
+    };
+    let check = |draft: Value| check_as(&[], draft);
+    // The same address in capitals, the number without the country code and a typo in the name
+    let draft = json!({
+        "first_name": "Ada",
+        "last_name": "Lovelaze",
+        "email": "ADA@EXAMPLE.COM",
+        "phone_number": "020 7946 0000",
+    });
+    let matches: Value = test::call_and_read_body_json(&app, check(draft)).await;
+    assert_eq!(matches[0]["contact"]["email"], "ada@example.com");
+    assert_eq!(
+        matches[0]["matched_on"],
+        json!(["email", "phone_number", "name"])
+    );
+
+    let draft = json!({ "first_name": "Grace", "last_name": "Hopper" });
+    let matches: Value = test::call_and_read_body_json(&app, check(draft)).await;
+    assert_eq!(matches, json!([]));
+
+    let res = test::call_service(&app, check(json!({}))).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // The email is hidden from interns, so it is not compared for them
+    let draft = json!({ "first_name": "Ada", "last_name": "Lovelace", "email": "ada@example.com" });
+    let matches: Value = test::call_and_read_body_json(&app, check_as(&["intern"], draft)).await;
+    assert_eq!(matches[0]["matched_on"], json!(["name"]));
+    assert_eq!(matches[0]["contact"]["email"], "***");
+    let draft = json!({ "email": "ada@example.com" });
+    let res = test::call_service(&app, check_as(&["intern"], draft)).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn a_company_set_by_hand_is_kept_on_edits() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(
+        App::new().configure(|cfg| TestApi::new().with_company_auto_link().configure(cfg)),
+    )
+    .await;
+    for company in [
+        json!({ "name": "Acme", "domain": "acme.com" }),
+        json!({ "name": "Initech" }),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/companies")
+            .insert_header(bearer("alice", &[]))
+            .set_json(company)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+    let company_of_contact = || async {
+        let req = test::TestRequest::get()
+            .uri("/api/v1/contacts/1")
+            .insert_header(bearer("alice", &[]))
+            .to_request();
+        let contact: Value = test::call_and_read_body_json(&app, req).await;
+        contact["company_id"].clone()
+    };
+    let edit = || async {
+        // Without `company_id`, which keeps the company
+        let req = test::TestRequest::put()
+            .uri("/api/v1/contacts/1")
+            .insert_header(bearer("alice", &[]))
+            .set_json(new_contact("john@acme.com"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    };
+
+    let mut contact = new_contact("john@acme.com");
+    contact["company_id"] = json!(2);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(contact)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    edit().await;
+    assert_eq!(company_of_contact().await, json!(2));
+
+    let req = test::TestRequest::delete()
+        .uri("/api/v1/contacts/1/company")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    edit().await;
+    assert_eq!(company_of_contact().await, Value::Null);
+
+    // New contacts are still linked by their domain
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(new_contact("jane@acme.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri("/api/v1/contacts/2")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let contact: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(contact["company_id"], 1);
+}
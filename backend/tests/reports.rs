@@ -0,0 +1,157 @@
+// backend/tests/reports.rs
+// This file tests the canned and the custom reports end to end, from stored contacts to JSON and CSV.
+// It exists so the SQL the reports are built from is run against the real schema, which unit tests cannot do.
+// RELEVANT FILES: backend/src/reports.rs, backend/src/custom_reports.rs, backend/src/test_support.rs
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use contacts_core::test_support::{self, bearer, TestDatabase};
+use serde_json::{json, Value};
+
+/// A request that creates a contact with a source and a status.
+fn create_contact(email: &str, source: &str, status: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("alice", &[]))
+        .set_json(json!({
+            "first_name": "Ada",
+            "last_name": "Lovelace",
+            "email": email,
+            "phone_number": "+44 20 7946 0000",
+            "source": source,
+            "status": status,
+        }))
+}
+
+/// The contacts every test starts with: two from the website and one referral.
+const CONTACTS: [(&str, &str, &str); 3] = [
+    ("ada@example.com", "website", "lead"),
+    ("grace@example.com", "website", "customer"),
+    ("alan@example.com", "referral", "lead"),
+];
+
+#[actix_web::test]
+async fn canned_reports_count_contacts_as_json_and_csv() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    for (email, source, status) in CONTACTS {
+        let res =
+            test::call_service(&app, create_contact(email, source, status).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/reports/contacts-by-source")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let rows: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        rows,
+        json!([
+            { "source": "website", "contacts": 2 },
+            { "source": "referral", "contacts": 1 },
+        ])
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/reports/contacts-by-company")
+        .insert_header(bearer("alice", &[]))
+        .insert_header(("Accept", "text/csv"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res
+        .headers()
+        .get("content-type")
+        .is_some_and(|value| value.as_bytes().starts_with(b"text/csv")));
+    let csv = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("company_id,company_name,contacts"));
+    assert_eq!(lines.next(), Some(",,3"));
+}
+
+#[actix_web::test]
+async fn a_saved_report_groups_and_filters_the_current_data() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+    for (email, source, status) in CONTACTS {
+        let res =
+            test::call_service(&app, create_contact(email, source, status).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/reports/custom")
+        .insert_header(bearer("alice", &[]))
+        .set_json(json!({
+            "name": "Web leads by status",
+            "definition": {
+                "dimensions": ["status"],
+                "measures": ["contacts", "with_company"],
+                "filters": [{ "field": "source", "op": "eq", "value": "website" }],
+            },
+        }))
+        .to_request();
+    let saved: Value = test::call_and_read_body_json(&app, req).await;
+    let run = format!("/api/v1/reports/custom/{}/run", saved["id"]);
+
+    let req = test::TestRequest::get()
+        .uri(&run)
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let mut rows: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    rows.sort_by_key(|row| row["status"].as_str().unwrap_or_default().to_string());
+    assert_eq!(
+        rows,
+        [
+            json!({ "status": "customer", "contacts": 1, "with_company": 0 }),
+            json!({ "status": "lead", "contacts": 1, "with_company": 0 }),
+        ]
+    );
+
+    // Run again, a report sees the data as it is now
+    let res = test::call_service(
+        &app,
+        create_contact("ida@example.com", "website", "lead").to_request(),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri(&run)
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let rows: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        rows[0],
+        json!({ "status": "lead", "contacts": 2, "with_company": 0 })
+    );
+}
+
+#[actix_web::test]
+async fn a_report_only_uses_the_allowed_fields() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(test_support::configure)).await;
+
+    for definition in [
+        // Not a dimension, and not a way into the SQL
+        json!({ "dimensions": ["email; DROP TABLE contacts"], "measures": ["contacts"] }),
+        json!({ "dimensions": ["status"], "measures": ["SUM(id)"] }),
+        json!({ "measures": ["contacts"], "filters": [{ "field": "status", "op": "like", "value": "%" }] }),
+        json!({ "dimensions": ["status", "source", "owner", "company_id"], "measures": ["contacts"] }),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/reports/custom")
+            .insert_header(bearer("alice", &[]))
+            .set_json(json!({ "name": "Not allowed", "definition": definition }))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{}", definition);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/reports/custom")
+        .insert_header(bearer("alice", &[]))
+        .to_request();
+    let saved: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(saved, json!([]));
+}
@@ -0,0 +1,144 @@
+// backend/tests/scim.rs
+// This file tests the SCIM endpoints end to end, the way an identity system calls them.
+// It exists so the roles, the field rules and the uniqueness of userName are checked on the SCIM routes too.
+// RELEVANT FILES: backend/src/scim.rs, backend/src/test_support.rs, backend/src/field_permissions.rs
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use contacts_core::test_support::{bearer, TestApi, TestDatabase};
+use serde_json::{json, Value};
+
+/// A SCIM user body.
+fn scim_user(user_name: &str) -> Value {
+    json!({
+        "userName": user_name,
+        "name": { "givenName": "Jane", "familyName": "Doe" },
+    })
+}
+
+#[actix_web::test]
+async fn only_admins_provision_and_deprovision_users() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| TestApi::new().configure(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/scim/v2/Users")
+        .insert_header(bearer("alice", &[]))
+        .set_json(scim_user("jane.doe@example.com"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::FORBIDDEN
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/scim/v2/Users")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(scim_user("jane.doe@example.com"))
+        .to_request();
+    let user: Value = test::call_and_read_body_json(&app, req).await;
+    let uri = format!("/scim/v2/Users/{}", user["id"].as_str().expect("an id"));
+
+    for req in [
+        test::TestRequest::put().set_json(scim_user("jane@example.com")),
+        test::TestRequest::delete(),
+    ] {
+        let req = req
+            .uri(&uri)
+            .insert_header(bearer("alice", &[]))
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, req).await.status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    let req = test::TestRequest::delete()
+        .uri(&uri)
+        .insert_header(bearer("root", &["admin"]))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NO_CONTENT
+    );
+}
+
+#[actix_web::test]
+async fn a_user_name_is_provisioned_once() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| TestApi::new().configure(cfg))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/scim/v2/Users")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(scim_user("jane.doe@example.com"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::CREATED
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/scim/v2/Users")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(scim_user("jane.doe@example.com"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::CONFLICT
+    );
+
+    // The index holds on every route, not only on the check of the SCIM create
+    let req = test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(json!({
+            "first_name": "Jane",
+            "last_name": "Doe",
+            "email": "jane.doe@example.com",
+            "phone_number": "+46 8 123 456 78",
+            "source": "scim",
+        }))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::CONFLICT
+    );
+}
+
+#[actix_web::test]
+async fn hidden_fields_are_masked_in_scim_users() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| {
+        TestApi::new()
+            .with_hidden_fields("intern:email,last_name")
+            .configure(cfg)
+    }))
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/scim/v2/Users")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(scim_user("jane.doe@example.com"))
+        .to_request();
+    let user: Value = test::call_and_read_body_json(&app, req).await;
+    let id = user["id"].as_str().expect("an id").to_string();
+
+    let req = test::TestRequest::get()
+        .uri("/scim/v2/Users")
+        .insert_header(bearer("ivan", &["intern"]))
+        .to_request();
+    let list: Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::get()
+        .uri(&format!("/scim/v2/Users/{}", id))
+        .insert_header(bearer("ivan", &["intern"]))
+        .to_request();
+    let user: Value = test::call_and_read_body_json(&app, req).await;
+
+    for user in [&list["Resources"][0], &user] {
+        assert_eq!(user["userName"], "***");
+        assert_eq!(user["emails"][0]["value"], "***");
+        assert_eq!(user["name"]["familyName"], "***");
+        assert_eq!(user["name"]["givenName"], "Jane");
+    }
+}
@@ -0,0 +1,65 @@
+// backend/tests/share.rs
+// This file tests the public share links end to end, from creating a link to reading it without a login.
+// It exists so what a link hands to people without an account is checked the way they see it.
+// RELEVANT FILES: backend/src/share.rs, backend/src/field_permissions.rs, backend/src/test_support.rs
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use contacts_core::test_support::{bearer, TestApi, TestDatabase};
+use serde_json::{json, Value};
+
+/// A request that creates a contact with an owner and a status, which a link must not show.
+fn create_contact() -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/v1/contacts")
+        .insert_header(bearer("root", &["admin"]))
+        .set_json(json!({
+            "first_name": "Ada",
+            "last_name": "Lovelace",
+            "email": "ada@example.com",
+            "phone_number": "+44 20 7946 0000",
+            "owner": "alice",
+            "status": "customer",
+        }))
+}
+
+#[actix_web::test]
+async fn a_share_link_only_shows_the_card_fields() {
+    let _db = TestDatabase::new();
+    let app = test::init_service(App::new().configure(|cfg| {
+        TestApi::new()
+            .with_hidden_fields("intern:phone_number")
+            .configure(cfg)
+    }))
+    .await;
+    let res = test::call_service(&app, create_contact().to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let share = |user: &str, roles: &[&str]| {
+        test::TestRequest::post()
+            .uri("/api/v1/contacts/1/share-link")
+            .insert_header(bearer(user, roles))
+            .to_request()
+    };
+    // The link would show the phone number the intern may not read
+    let res = test::call_service(&app, share("ivan", &["intern"])).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let link: Value = test::call_and_read_body_json(&app, share("alice", &[])).await;
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/api/v1/public/share/{}",
+            link["token"].as_str().unwrap()
+        ))
+        .to_request();
+    let shared: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        shared,
+        json!({
+            "first_name": "Ada",
+            "last_name": "Lovelace",
+            "email": "ada@example.com",
+            "phone_number": "+442079460000",
+        })
+    );
+}